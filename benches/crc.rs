@@ -0,0 +1,63 @@
+//! Compares codec decode throughput of 4 KB packets with the default
+//! single table CRC against a shared slice-by-16 table.
+//!
+//! Run with `cargo bench --bench crc`.
+use std::sync::Arc;
+
+use asynchronous_codec::{BytesMut, Decoder};
+use crc::{Crc, Table, CRC_16_IBM_3740};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use spacepacket::{codec::SpacePacketCodec, GroupingFlag, PacketType, SpacePacket};
+
+const PAYLOAD_LEN: usize = 4096;
+const PACKETS: usize = 256;
+
+const SYNC_MARKER: [u8; 4] = [0x1A, 0xCF, 0xFC, 0x1D];
+
+static SLICE16: Crc<u16, Table<16>> = Crc::<u16, Table<16>>::new(&CRC_16_IBM_3740);
+
+/// Decode every packet of the `stream` with a fresh clone of the `codec`.
+fn decode_stream(stream: &[u8], codec: &SpacePacketCodec) {
+    let mut codec = codec.clone();
+    let mut buffer = BytesMut::from(stream);
+    let mut decoded = 0;
+    while let Some(packet) = codec.decode(&mut buffer).unwrap() {
+        black_box(packet);
+        decoded += 1;
+    }
+    assert_eq!(PACKETS, decoded);
+}
+
+fn crc_decode(c: &mut Criterion) {
+    let crc = Crc::<u16>::new(&CRC_16_IBM_3740);
+    let packet = SpacePacket::new(
+        0,
+        PacketType::Telemetry,
+        0x42,
+        GroupingFlag::Unsegm,
+        0,
+        false,
+        (0..PAYLOAD_LEN).map(|val| (val * 31) as u8).collect(),
+    );
+    let encoded = packet.encode_crc(&crc).unwrap();
+    let stream: Vec<u8> = (0..PACKETS)
+        .flat_map(|_| SYNC_MARKER.iter().chain(encoded.iter()).copied())
+        .collect();
+
+    let mut group = c.benchmark_group("crc decode");
+    group.throughput(Throughput::Bytes(stream.len() as u64));
+    for (name, codec) in [
+        ("table", SpacePacketCodec::new(SYNC_MARKER).with_crc(crc)),
+        (
+            "slice16",
+            SpacePacketCodec::new(SYNC_MARKER).with_shared_crc(Arc::new(&SLICE16)),
+        ),
+    ] {
+        group.bench_function(name, |b| b.iter(|| decode_stream(&stream, &codec)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, crc_decode);
+criterion_main!(benches);
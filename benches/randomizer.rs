@@ -0,0 +1,42 @@
+//! Compares the word-at-a-time randomizer against a byte-at-a-time reference
+//! on a full period of the 131071 byte TM sequence.
+//!
+//! Run with `cargo bench --bench randomizer`.
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use spacepacket::tctm::randomizer::{apply_randomization_chunks, Randomization};
+
+const LEN: usize = 131071;
+
+/// The byte-at-a-time implementation the randomizer previously used.
+fn bytewise(bytes: &[u8], sequence: &[u8]) -> Vec<u8> {
+    bytes
+        .iter()
+        .zip(sequence.iter().cycle())
+        .map(|(val, rand)| val ^ rand)
+        .collect()
+}
+
+fn randomizer(c: &mut Criterion) {
+    let bytes: Vec<u8> = (0..LEN).map(|val| (val * 31) as u8).collect();
+    // randomizing zeros yields the sequence itself
+    let sequence = apply_randomization_chunks([vec![0_u8; LEN]], Randomization::Tm131071);
+
+    assert_eq!(
+        bytewise(&bytes, &sequence),
+        apply_randomization_chunks([&bytes], Randomization::Tm131071)
+    );
+
+    let mut group = c.benchmark_group("randomizer");
+    group.throughput(Throughput::Bytes(LEN as u64));
+    group.bench_function("bytewise", |b| {
+        b.iter(|| bytewise(black_box(&bytes), &sequence))
+    });
+    group.bench_function("words", |b| {
+        b.iter(|| apply_randomization_chunks([black_box(&bytes)], Randomization::Tm131071))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, randomizer);
+criterion_main!(benches);
@@ -0,0 +1,139 @@
+//! Throughput of the hot paths of a telemetry processing chain, the baseline for
+//! spotting performance regressions between releases.
+//!
+//! Run with `cargo bench --bench throughput`. Save a baseline before a change with
+//! `cargo bench --bench throughput -- --save-baseline main` and compare against it with
+//! `cargo bench --bench throughput -- --baseline main`, criterion reports every
+//! benchmark which regressed beyond the noise threshold.
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use asynchronous_codec::{BytesMut, Decoder};
+use crc::{Crc, CRC_16_IBM_3740};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use spacepacket::{
+    codec::SpacePacketCodec,
+    consts::ASM,
+    tctm::{
+        cltu::{generate_ctlu, EncodingScheme},
+        randomizer::{apply_randomization_chunks, Randomization},
+        tm::{TMPrimaryHeader, TMRandomization, TMTransferFrame},
+    },
+};
+
+use common::packet;
+
+const CRC: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+
+/// The length of a TM Transfer Frame, the common Reed-Solomon interleave 5 frame length.
+const FRAME_LEN: usize = 1115;
+
+/// Decode every packet of the `stream`, returning the number decoded.
+fn decode_stream(stream: &[u8]) -> usize {
+    let mut codec = SpacePacketCodec::new(ASM);
+    let mut buffer = BytesMut::from(stream);
+    let mut decoded = 0;
+    while let Some(packet) = codec.decode(&mut buffer).unwrap() {
+        black_box(packet);
+        decoded += 1;
+    }
+    decoded
+}
+
+fn codec_decode(c: &mut Criterion) {
+    let encoded = packet(0x42, 0, 1024).encode();
+    let clean: Vec<u8> = (0..256)
+        .flat_map(|_| ASM.iter().chain(encoded.iter()).copied())
+        .collect();
+    assert_eq!(256, decode_stream(&clean));
+
+    // every packet is preceded by as many bytes of noise as it is long
+    let noise = vec![0x55_u8; ASM.len() + encoded.len()];
+    let noisy: Vec<u8> = (0..256)
+        .flat_map(|_| noise.iter().chain(&ASM).chain(encoded.iter()).copied())
+        .collect();
+    assert_eq!(256, decode_stream(&noisy));
+
+    let mut group = c.benchmark_group("codec decode");
+    for (name, stream) in [("clean", &clean), ("50% noise", &noisy)] {
+        group.throughput(Throughput::Bytes(stream.len() as u64));
+        group.bench_function(name, |b| b.iter(|| decode_stream(stream)));
+    }
+    group.finish();
+}
+
+fn packet_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("packet encode");
+    for payload_len in [64, 4096] {
+        let packet = packet(0x42, 0, payload_len);
+        group.throughput(Throughput::Bytes(payload_len as u64));
+        group.bench_function(format!("encode {payload_len} B"), |b| {
+            b.iter(|| black_box(&packet).encode())
+        });
+        group.bench_function(format!("encode_crc {payload_len} B"), |b| {
+            b.iter(|| black_box(&packet).encode_crc(&CRC).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn frame_randomization(c: &mut Criterion) {
+    let frame: Vec<u8> = (0..FRAME_LEN).map(|val| (val * 31) as u8).collect();
+    let mut group = c.benchmark_group("randomization");
+    group.throughput(Throughput::Bytes(FRAME_LEN as u64));
+    group.bench_function("randomize frame", |b| {
+        b.iter(|| apply_randomization_chunks([black_box(&frame)], Randomization::Tm255))
+    });
+    group.finish();
+}
+
+fn cltu_generation(c: &mut Criterion) {
+    let frame: Vec<u8> = (0..1024).map(|val| (val * 31) as u8).collect();
+    let mut group = c.benchmark_group("cltu");
+    group.throughput(Throughput::Bytes(frame.len() as u64));
+    group.bench_function("BCH CLTU 1 KB", |b| {
+        b.iter(|| generate_ctlu(black_box(&frame), EncodingScheme::BCH))
+    });
+    group.finish();
+}
+
+fn frame_batch_decode(c: &mut Criterion) {
+    let header = TMPrimaryHeader::builder().scid(758).build().unwrap();
+    let stream: Vec<u8> = (0..64_u8)
+        .flat_map(|index| {
+            TMTransferFrame {
+                primary_header: header,
+                secondary_header: None,
+                data_field: vec![index; FRAME_LEN - 6],
+            }
+            .encode(TMRandomization::Tm255)
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("tm frame");
+    group.throughput(Throughput::Bytes(stream.len() as u64));
+    group.bench_function("batch decode", |b| {
+        b.iter(|| {
+            stream
+                .chunks(FRAME_LEN)
+                .map(|frame| TMTransferFrame::decode(frame, FRAME_LEN, TMRandomization::Tm255))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+/// Report changes beyond 5% as regressions or improvements, the default of 1% is
+/// within the noise of shared CI runners.
+fn config() -> Criterion {
+    Criterion::default().noise_threshold(0.05)
+}
+
+criterion_group! {
+    name = benches;
+    config = config();
+    targets = codec_decode, packet_encode, frame_randomization, cltu_generation, frame_batch_decode
+}
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+use std::{path::Path, process::Command};
+
+/// Record the `git describe` of the checkout as `SPACEPACKET_GIT_DESCRIBE` for
+/// `capabilities::capabilities()`, unless it is already set by the environment.
+///
+/// Nothing is recorded outside a git checkout of the crate, e.g. when built from crates.io,
+/// so the crate never reports the describe of an enclosing repository.
+fn main() {
+    println!("cargo:rerun-if-env-changed=SPACEPACKET_GIT_DESCRIBE");
+    if std::env::var_os("SPACEPACKET_GIT_DESCRIBE").is_some() || !Path::new(".git").exists() {
+        return;
+    }
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    let describe = Command::new("git")
+        .args(["describe", "--tags", "--always"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(describe) = describe.as_deref().map(str::trim) {
+        if !describe.is_empty() {
+            println!("cargo:rustc-env=SPACEPACKET_GIT_DESCRIBE={describe}");
+        }
+    }
+}
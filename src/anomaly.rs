@@ -0,0 +1,402 @@
+//! A structured record of everything that went wrong while decoding a session.
+//!
+//! Decoders report damaged input by skipping it, which leaves operators reconstructing
+//! "what went wrong during this pass?" from scattered logs. Every component given an
+//! [AnomalyLog] handle also appends a typed [Anomaly] to it. The handle is cheap to clone
+//! and all clones share one log, so the codec, the packet extractor and the continuity
+//! checker of a session can report into the same log.
+//!
+//! ```
+//! # use spacepacket::{anomaly::{AnomalyKind, AnomalyLog, Layer}, continuity::ContinuityChecker, GroupingFlag, PacketType, SpacePacket};
+//! let log = AnomalyLog::new();
+//! let mut checker = ContinuityChecker::new().with_anomaly_log(log.clone());
+//!
+//! for (offset, count) in [(0, 7), (12, 8), (24, 11)] {
+//!     let packet = SpacePacket::new(0, PacketType::Telemetry, 0x42, GroupingFlag::Unsegm, count, false, vec![0; 6]);
+//!     checker.observe(&packet.primary_header, offset);
+//! }
+//!
+//! assert_eq!(
+//!     AnomalyKind::SequenceGap { apid: 0x42, expected: 9, received: 11 },
+//!     log.events()[0].kind
+//! );
+//! assert_eq!(1, log.summary().sequence_gaps);
+//! ```
+
+use std::{
+    fmt::Display,
+    io::Write,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::limits::ResourceLimit;
+
+/// The component which observed an [Anomaly].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Layer {
+    /// The packet codec decoding a synchronized byte stream.
+    Codec,
+    /// The packet extractor reassembling packets from frames.
+    Extractor,
+    /// The [ContinuityChecker](crate::continuity::ContinuityChecker) following the
+    /// sequence count of every APID.
+    Continuity,
+}
+impl Display for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Codec => write!(f, "codec"),
+            Self::Extractor => write!(f, "extractor"),
+            Self::Continuity => write!(f, "continuity"),
+        }
+    }
+}
+
+/// What went wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AnomalyKind {
+    /// Bytes were skipped while searching for the synchronization marker.
+    Unsynchronized {
+        /// The number of bytes skipped.
+        skipped: usize,
+    },
+    /// The CRC sent with a packet did not match the CRC computed over it.
+    CrcMismatch {
+        /// The CRC sent with the packet.
+        sent: u16,
+        /// The CRC computed over the received packet.
+        computed: u16,
+    },
+    /// The header CRC sent with a packet did not match the CRC computed over its header.
+    HeaderCrcMismatch {
+        /// The header CRC sent with the packet.
+        sent: u16,
+        /// The header CRC computed over the received header.
+        computed: u16,
+    },
+    /// A packet declared a length shorter than the minimum packet length.
+    TooShort {
+        /// The declared length of the packet, including the primary header.
+        packet_length: usize,
+    },
+    /// Input was discarded to stay within the [DecodeLimits](crate::limits::DecodeLimits).
+    ResourceLimit(ResourceLimit),
+    /// Frames were missing from the sequence of frames of a virtual channel.
+    FramesLost {
+        /// The number of frames missing.
+        count: u64,
+    },
+    /// A packet did not carry the sequence count following the previous packet of its APID.
+    SequenceGap {
+        /// The APID of the packet.
+        apid: u16,
+        /// The sequence count following the previous packet.
+        expected: u16,
+        /// The sequence count of the packet.
+        received: u16,
+    },
+}
+impl Display for AnomalyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsynchronized { skipped } => write!(f, "{skipped} bytes unsynchronized"),
+            Self::CrcMismatch { sent, computed } => {
+                write!(f, "CRC sent {sent:#06X} computed {computed:#06X}")
+            }
+            Self::HeaderCrcMismatch { sent, computed } => {
+                write!(f, "header CRC sent {sent:#06X} computed {computed:#06X}")
+            }
+            Self::TooShort { packet_length } => {
+                write!(f, "packet length {packet_length} too short")
+            }
+            Self::ResourceLimit(limit) => write!(f, "{limit}"),
+            Self::FramesLost { count } => write!(f, "{count} frames lost"),
+            Self::SequenceGap {
+                apid,
+                expected,
+                received,
+            } => write!(
+                f,
+                "APID {apid:#05X} expected sequence count {expected} received {received}"
+            ),
+        }
+    }
+}
+
+/// A single event of an [AnomalyLog].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Anomaly {
+    /// The byte offset of the anomaly in the input of its layer, never decreasing
+    /// between the events of one component. The codec counts the bytes of its stream,
+    /// the extractor the bytes of the packet zones it received and the continuity checker
+    /// uses the offset of the packet given to it.
+    pub offset: u64,
+    /// The component which observed the anomaly.
+    pub layer: Layer,
+    /// What went wrong.
+    pub kind: AnomalyKind,
+}
+impl Display for Anomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte {}: {}", self.layer, self.offset, self.kind)
+    }
+}
+
+/// The totals of the events of an [AnomalyLog].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnomalySummary {
+    /// The number of times synchronization was searched for.
+    pub resyncs: u64,
+    /// The number of bytes skipped while searching for synchronization.
+    pub unsynchronized_bytes: u64,
+    /// The number of packets failing their CRC.
+    pub crc_mismatches: u64,
+    /// The number of packets failing their header CRC.
+    pub header_crc_mismatches: u64,
+    /// The number of packets declaring a length shorter than the minimum.
+    pub too_short: u64,
+    /// The number of times input was discarded to stay within a resource limit.
+    pub limit_violations: u64,
+    /// The number of frames lost.
+    pub lost_frames: u64,
+    /// The number of discontinuities in the sequence counts of packets.
+    pub sequence_gaps: u64,
+}
+impl Display for AnomalySummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} resyncs skipping {} bytes, {} CRC mismatches, {} header CRC mismatches, \
+             {} short packets, {} limit violations, {} frames lost, {} sequence gaps",
+            self.resyncs,
+            self.unsynchronized_bytes,
+            self.crc_mismatches,
+            self.header_crc_mismatches,
+            self.too_short,
+            self.limit_violations,
+            self.lost_frames,
+            self.sequence_gaps
+        )
+    }
+}
+
+/// A shared, append only log of [Anomaly] events.
+///
+/// Clones share the same events, a poisoned lock is recovered since events are only appended.
+#[derive(Debug, Clone, Default)]
+pub struct AnomalyLog {
+    events: Arc<Mutex<Vec<Anomaly>>>,
+}
+impl AnomalyLog {
+    /// Create an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Anomaly>> {
+        self.events
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Append an event observed by the `layer` at the byte `offset`.
+    pub fn record(&self, offset: u64, layer: Layer, kind: AnomalyKind) {
+        self.lock().push(Anomaly {
+            offset,
+            layer,
+            kind,
+        });
+    }
+
+    /// A copy of every event in the order they were recorded.
+    pub fn events(&self) -> Vec<Anomaly> {
+        self.lock().clone()
+    }
+
+    /// The number of events recorded.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no event was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Remove every event, e.g. at the start of a new pass.
+    pub fn clear(&self) {
+        self.lock().clear()
+    }
+
+    /// The totals of every event.
+    pub fn summary(&self) -> AnomalySummary {
+        self.lock()
+            .iter()
+            .fold(AnomalySummary::default(), |mut summary, event| {
+                match event.kind {
+                    AnomalyKind::Unsynchronized { skipped } => {
+                        summary.resyncs += 1;
+                        summary.unsynchronized_bytes += skipped as u64;
+                    }
+                    AnomalyKind::CrcMismatch { .. } => summary.crc_mismatches += 1,
+                    AnomalyKind::HeaderCrcMismatch { .. } => summary.header_crc_mismatches += 1,
+                    AnomalyKind::TooShort { .. } => summary.too_short += 1,
+                    AnomalyKind::ResourceLimit(_) => summary.limit_violations += 1,
+                    AnomalyKind::FramesLost { count } => summary.lost_frames += count,
+                    AnomalyKind::SequenceGap { .. } => summary.sequence_gaps += 1,
+                }
+                summary
+            })
+    }
+
+    /// Export every event as comma separated values with an `offset,layer,anomaly` header line.
+    ///
+    /// # Errors
+    ///
+    /// Errors if writing to the `writer` fails.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "offset,layer,anomaly")?;
+        for event in self.lock().iter() {
+            writeln!(writer, "{},{},{}", event.offset, event.layer, event.kind)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::limits::Limit;
+
+    #[test]
+    fn anomaly_log_shared() {
+        let log = AnomalyLog::new();
+        let clone = log.clone();
+        assert!(log.is_empty());
+
+        log.record(0, Layer::Codec, AnomalyKind::Unsynchronized { skipped: 5 });
+        clone.record(
+            17,
+            Layer::Codec,
+            AnomalyKind::CrcMismatch {
+                sent: 0x1234,
+                computed: 0xABCD,
+            },
+        );
+        clone.record(
+            40,
+            Layer::Extractor,
+            AnomalyKind::ResourceLimit(ResourceLimit {
+                which: Limit::ItemLen,
+                limit: 100,
+            }),
+        );
+        log.record(40, Layer::Extractor, AnomalyKind::FramesLost { count: 3 });
+        log.record(90, Layer::Codec, AnomalyKind::Unsynchronized { skipped: 2 });
+
+        assert_eq!(5, clone.len());
+        assert_eq!(
+            AnomalySummary {
+                resyncs: 2,
+                unsynchronized_bytes: 7,
+                crc_mismatches: 1,
+                limit_violations: 1,
+                lost_frames: 3,
+                ..Default::default()
+            },
+            log.summary()
+        );
+
+        clone.clear();
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn anomaly_log_csv() {
+        let log = AnomalyLog::new();
+        log.record(
+            17,
+            Layer::Codec,
+            AnomalyKind::CrcMismatch {
+                sent: 0x1234,
+                computed: 0xABCD,
+            },
+        );
+        log.record(
+            30,
+            Layer::Continuity,
+            AnomalyKind::SequenceGap {
+                apid: 0x42,
+                expected: 9,
+                received: 11,
+            },
+        );
+
+        let mut csv = vec![];
+        log.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            "offset,layer,anomaly\n\
+             17,codec,CRC sent 0x1234 computed 0xABCD\n\
+             30,continuity,APID 0x042 expected sequence count 9 received 11\n",
+            String::from_utf8(csv).unwrap()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn anomaly_log_serde() {
+        let events = vec![
+            Anomaly {
+                offset: 0,
+                layer: Layer::Codec,
+                kind: AnomalyKind::Unsynchronized { skipped: 5 },
+            },
+            Anomaly {
+                offset: 17,
+                layer: Layer::Codec,
+                kind: AnomalyKind::HeaderCrcMismatch {
+                    sent: 0x1234,
+                    computed: 0xABCD,
+                },
+            },
+            Anomaly {
+                offset: 40,
+                layer: Layer::Extractor,
+                kind: AnomalyKind::ResourceLimit(ResourceLimit {
+                    which: Limit::BufferedBytes,
+                    limit: 100,
+                }),
+            },
+            Anomaly {
+                offset: 40,
+                layer: Layer::Extractor,
+                kind: AnomalyKind::FramesLost { count: 3 },
+            },
+            Anomaly {
+                offset: 90,
+                layer: Layer::Continuity,
+                kind: AnomalyKind::SequenceGap {
+                    apid: 0x42,
+                    expected: 9,
+                    received: 11,
+                },
+            },
+        ];
+        let log = AnomalyLog::new();
+        events
+            .iter()
+            .for_each(|event| log.record(event.offset, event.layer, event.kind));
+
+        let json = serde_json::to_string(&log.events()).unwrap();
+        assert_eq!(events, serde_json::from_str::<Vec<Anomaly>>(&json).unwrap());
+
+        let summary = log.summary();
+        let json = serde_json::to_string(&summary).unwrap();
+        assert_eq!(summary, serde_json::from_str(&json).unwrap());
+    }
+}
@@ -0,0 +1,365 @@
+//! Merge overlapping recordings of the same pass into one time-ordered stream of [SpacePacket]s.
+//!
+//! Copies of a packet are matched across inputs by APID and sequence count, but only within a
+//! time window so the 14-bit sequence count of a later orbit is not mistaken for a duplicate.
+//! Of every set of copies the [MergePolicy] picks the one to keep.
+//!
+//! ```
+//! # use std::time::Duration;
+//! # use spacepacket::{archive::{merge, MergePolicy, ReceiveInfo}, GroupingFlag, PacketType, SpacePacket};
+//! let packet = |count| SpacePacket::new(0, PacketType::Telemetry, 0x42, GroupingFlag::Unsegm, count, false, vec![count as u8]);
+//! let at = |secs, crc_valid| ReceiveInfo { timestamp: Duration::from_secs(secs), crc_valid };
+//!
+//! let north = vec![(packet(0), at(0, true)), (packet(1), at(1, false))];
+//! let south = vec![(packet(1), at(1, true)), (packet(2), at(2, true))];
+//!
+//! let merged: Vec<_> = merge(
+//!     vec![north.into_iter(), south.into_iter()],
+//!     MergePolicy::new(Duration::from_secs(60)),
+//! )
+//! .map(|packet| packet.primary_header.sequence_count)
+//! .collect();
+//! assert_eq!(vec![0, 1, 2], merged);
+//! ```
+use std::{
+    collections::{HashMap, VecDeque},
+    iter::Peekable,
+    time::Duration,
+};
+
+use crate::SpacePacket;
+
+/// How a packet was received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReceiveInfo {
+    /// The reception time, measured from an epoch shared by all inputs of a merge.
+    pub timestamp: Duration,
+    /// Whether the CRC of the packet was valid.
+    pub crc_valid: bool,
+}
+
+/// Which copy of a packet received by several inputs is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preference {
+    /// Prefer a copy with a valid CRC, then the earliest copy.
+    #[default]
+    ValidCrcFirst,
+    /// Prefer the earliest copy, then a copy with a valid CRC.
+    EarliestFirst,
+}
+
+/// The rules of a [merge].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergePolicy {
+    preference: Preference,
+    window: Duration,
+}
+impl MergePolicy {
+    /// Match copies of a packet received at most `window` after its first copy,
+    /// preferring copies with a valid CRC.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            preference: Preference::default(),
+            window,
+        }
+    }
+
+    /// Choose which copy of a packet is kept.
+    pub fn with_preference(mut self, preference: Preference) -> Self {
+        self.preference = preference;
+        self
+    }
+
+    /// Whether the `candidate` copy is preferred over the `current` copy.
+    fn prefers(&self, candidate: &ReceiveInfo, current: &ReceiveInfo) -> bool {
+        let earlier = candidate.timestamp.cmp(&current.timestamp).reverse();
+        let valid = candidate.crc_valid.cmp(&current.crc_valid);
+        match self.preference {
+            Preference::ValidCrcFirst => valid.then(earlier).is_gt(),
+            Preference::EarliestFirst => earlier.then(valid).is_gt(),
+        }
+    }
+}
+
+/// The copies of one packet received within the window.
+struct Copies {
+    key: (u16, u16),
+    first: Duration,
+    inputs: Vec<usize>,
+    best: (SpacePacket, ReceiveInfo),
+}
+
+/// Merge the `inputs` into one stream of unique packets ordered by their first reception.
+///
+/// Every input must yield its packets in reception order. Copies from different inputs with
+/// the same APID and sequence count received within the window of the [MergePolicy] are
+/// reduced to the preferred copy, ties go to the input listed first.
+/// The output only depends on the order and content of the inputs.
+pub fn merge<I>(inputs: Vec<I>, policy: MergePolicy) -> Merge<I>
+where
+    I: Iterator<Item = (SpacePacket, ReceiveInfo)>,
+{
+    Merge {
+        inputs: inputs.into_iter().map(Iterator::peekable).collect(),
+        policy,
+        pending: VecDeque::new(),
+        emitted: 0,
+        latest: HashMap::new(),
+    }
+}
+
+/// Iterator returned by [merge].
+pub struct Merge<I: Iterator<Item = (SpacePacket, ReceiveInfo)>> {
+    inputs: Vec<Peekable<I>>,
+    policy: MergePolicy,
+    /// Packets not yet emitted, ordered by their first reception.
+    pending: VecDeque<Copies>,
+    /// The number of groups popped from the front of pending.
+    emitted: usize,
+    /// The index of the latest group of every key, counting emitted groups.
+    latest: HashMap<(u16, u16), usize>,
+}
+
+impl<I: Iterator<Item = (SpacePacket, ReceiveInfo)>> Merge<I> {
+    /// The input whose next packet was received first and its reception time.
+    fn next_input(&mut self) -> Option<(usize, Duration)> {
+        self.inputs
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, input)| input.peek().map(|(_, info)| (index, info.timestamp)))
+            .min_by_key(|(index, timestamp)| (*timestamp, *index))
+    }
+
+    fn insert(&mut self, input: usize, packet: SpacePacket, info: ReceiveInfo) {
+        let key = (
+            packet.primary_header.apid,
+            packet.primary_header.sequence_count,
+        );
+        let group = self
+            .latest
+            .get(&key)
+            .and_then(|index| index.checked_sub(self.emitted))
+            .and_then(|index| self.pending.get_mut(index))
+            .filter(|group| {
+                info.timestamp.saturating_sub(group.first) <= self.policy.window
+                    && !group.inputs.contains(&input)
+            });
+
+        match group {
+            Some(group) => {
+                group.inputs.push(input);
+                if self.policy.prefers(&info, &group.best.1) {
+                    group.best = (packet, info);
+                }
+            }
+            None => {
+                self.latest.insert(key, self.emitted + self.pending.len());
+                self.pending.push_back(Copies {
+                    key,
+                    first: info.timestamp,
+                    inputs: vec![input],
+                    best: (packet, info),
+                });
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Option<SpacePacket> {
+        let group = self.pending.pop_front()?;
+        if self.latest.get(&group.key) == Some(&self.emitted) {
+            self.latest.remove(&group.key);
+        }
+        self.emitted += 1;
+        Some(group.best.0)
+    }
+}
+
+impl<I: Iterator<Item = (SpacePacket, ReceiveInfo)>> Iterator for Merge<I> {
+    type Item = SpacePacket;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let next = self.next_input();
+            match (next, self.pending.front()) {
+                // no later copy can join the oldest packet
+                (Some((_, timestamp)), Some(front))
+                    if timestamp.saturating_sub(front.first) > self.policy.window =>
+                {
+                    return self.pop()
+                }
+                (Some((input, _)), _) => {
+                    let (packet, info) = self.inputs[input].next()?;
+                    self.insert(input, packet, info);
+                }
+                (None, _) => return self.pop(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_util::packet_with_payload;
+
+    use rstest::rstest;
+
+    fn at(millis: u64, crc_valid: bool) -> ReceiveInfo {
+        ReceiveInfo {
+            timestamp: Duration::from_millis(millis),
+            crc_valid,
+        }
+    }
+
+    type Input = std::vec::IntoIter<(SpacePacket, ReceiveInfo)>;
+
+    fn run(inputs: Vec<Vec<(SpacePacket, ReceiveInfo)>>, policy: MergePolicy) -> Vec<(u16, u8)> {
+        merge(
+            inputs
+                .into_iter()
+                .map(Vec::into_iter)
+                .collect::<Vec<Input>>(),
+            policy,
+        )
+        .map(|packet| (packet.primary_header.sequence_count, packet.payload[0]))
+        .collect()
+    }
+
+    #[test]
+    fn merge_overlap() {
+        // antenna 1 loses the end of the pass, antenna 2 the start
+        let first = (0..6)
+            .map(|count| {
+                (
+                    packet_with_payload(7, count, vec![1]),
+                    at(100 * u64::from(count), true),
+                )
+            })
+            .collect();
+        let second = (3..10)
+            .map(|count| {
+                (
+                    packet_with_payload(7, count, vec![2]),
+                    at(100 * u64::from(count) + 5, true),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                (0, 1),
+                (1, 1),
+                (2, 1),
+                (3, 1),
+                (4, 1),
+                (5, 1),
+                (6, 2),
+                (7, 2),
+                (8, 2),
+                (9, 2)
+            ],
+            run(
+                vec![first, second],
+                MergePolicy::new(Duration::from_secs(1))
+            )
+        );
+    }
+
+    #[rstest]
+    #[case(Preference::ValidCrcFirst, vec![(0, 1), (1, 2), (2, 1)])]
+    #[case(Preference::EarliestFirst, vec![(0, 1), (1, 1), (2, 1)])]
+    fn merge_preference(#[case] preference: Preference, #[case] expected: Vec<(u16, u8)>) {
+        let first = vec![
+            (packet_with_payload(7, 0, vec![1]), at(0, true)),
+            (packet_with_payload(7, 1, vec![1]), at(100, false)),
+            (packet_with_payload(7, 2, vec![1]), at(200, true)),
+        ];
+        let second = vec![
+            (packet_with_payload(7, 0, vec![2]), at(10, false)),
+            (packet_with_payload(7, 1, vec![2]), at(110, true)),
+            (packet_with_payload(7, 2, vec![2]), at(210, true)),
+        ];
+
+        assert_eq!(
+            expected,
+            run(
+                vec![first, second],
+                MergePolicy::new(Duration::from_secs(1)).with_preference(preference)
+            )
+        );
+    }
+
+    #[test]
+    fn merge_window_aliasing() {
+        // the sequence count wrapped between the passes, the copies are distinct packets
+        let first = vec![(packet_with_payload(7, 5, vec![1]), at(0, true))];
+        let second = vec![(packet_with_payload(7, 5, vec![2]), at(90_000, true))];
+
+        assert_eq!(
+            vec![(5, 1), (5, 2)],
+            run(
+                vec![first, second],
+                MergePolicy::new(Duration::from_secs(60))
+            )
+        );
+    }
+
+    #[test]
+    fn merge_same_input_repeats() {
+        // a packet repeated by one input is never a copy of itself
+        let first = vec![
+            (packet_with_payload(7, 5, vec![1]), at(0, true)),
+            (packet_with_payload(7, 5, vec![2]), at(1, true)),
+        ];
+        let second = vec![(packet_with_payload(7, 5, vec![3]), at(2, false))];
+
+        assert_eq!(
+            vec![(5, 1), (5, 2)],
+            run(
+                vec![first, second],
+                MergePolicy::new(Duration::from_secs(60))
+            )
+        );
+    }
+
+    #[test]
+    fn merge_apids() {
+        let first = vec![
+            (packet_with_payload(7, 5, vec![1]), at(0, true)),
+            (packet_with_payload(8, 5, vec![1]), at(0, true)),
+        ];
+        let second = vec![
+            (packet_with_payload(8, 5, vec![2]), at(0, true)),
+            (packet_with_payload(9, 5, vec![2]), at(0, true)),
+        ];
+
+        assert_eq!(
+            vec![(5, 1), (5, 1), (5, 2)],
+            run(vec![first, second], MergePolicy::new(Duration::ZERO))
+        );
+    }
+
+    #[test]
+    fn merge_deterministic() {
+        let inputs: Vec<Vec<_>> = (0..3_u8)
+            .map(|input| {
+                (0..200_u16)
+                    .filter(|count| (count + u16::from(input)) % 3 != 0)
+                    .map(|count| {
+                        (
+                            packet_with_payload(7, count % 16, vec![input]),
+                            at(u64::from(count) * 10, count % (2 + u16::from(input)) != 0),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        let policy = MergePolicy::new(Duration::from_millis(50));
+
+        let merged = run(inputs.clone(), policy);
+        assert_eq!(merged, run(inputs, policy));
+        // every count is received by at least one input and every copy is matched
+        assert_eq!(200, merged.len());
+    }
+}
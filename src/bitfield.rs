@@ -0,0 +1,403 @@
+//! Extraction of telemetry bit fields from [SpacePacket] payloads.
+//!
+//! # Bit numbering
+//!
+//! Following CCSDS conventions bits are numbered from the most significant bit
+//! of the first byte of the payload. Bit `0` is the MSB of `payload[0]`,
+//! bit `7` is the LSB of `payload[0]` and bit `8` is the MSB of `payload[1]`.
+//!
+//! A field of `bit_length` bits starting at `bit_offset` is read with its first bit as
+//! the most significant bit of the value ([Endianness::Big]). Fields may start and end anywhere
+//! inside a byte and may cross any number of byte boundaries.
+//!
+//! [Endianness::Little] fields must be a whole number of bytes long,
+//! the bytes of the field are then interpreted least significant byte first.
+//!
+//! ```
+//! use spacepacket::bitfield::{read_i, read_u};
+//!
+//! let payload = [0x12_u8, 0x34, 0x56];
+//! // the 12 bits starting from the low nibble of the first byte
+//! assert_eq!(0x234, read_u(&payload, 4, 12).unwrap());
+//! // the top nibble of the last byte as a two's complement value
+//! assert_eq!(5, read_i(&payload, 16, 4).unwrap());
+//! ```
+
+use std::{
+    fmt::Display,
+    io::{Error, ErrorKind},
+};
+
+use crate::SpacePacket;
+
+/// Errors encountered while reading a bit field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitFieldError {
+    /// Field lengths must be in the range 1..=64 bits,
+    /// and a multiple of 8 for [Endianness::Little] fields.
+    InvalidLength(usize),
+    /// The payload does not contain all bits of the field.
+    PayloadTooShort {
+        /// The index of the bit following the last bit of the field.
+        required_bits: usize,
+        /// The number of bits in the payload.
+        available_bits: usize,
+    },
+}
+impl Display for BitFieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BitFieldError::InvalidLength(len) => write!(f, "Invalid bit field length {len}."),
+            BitFieldError::PayloadTooShort {
+                required_bits,
+                available_bits,
+            } => write!(
+                f,
+                "Bit field requires {required_bits} bits but payload has only {available_bits}."
+            ),
+        }
+    }
+}
+impl std::error::Error for BitFieldError {}
+impl From<BitFieldError> for Error {
+    fn from(error: BitFieldError) -> Self {
+        Error::new(ErrorKind::InvalidData, error)
+    }
+}
+
+/// How the bits of a field are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signedness {
+    /// An unsigned integer.
+    Unsigned,
+    /// A two's complement integer.
+    Signed,
+}
+
+/// The byte order of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endianness {
+    /// Most significant byte (and bit) first. The CCSDS default.
+    #[default]
+    Big,
+    /// Least significant byte first. Only valid for fields which are a whole number of bytes.
+    Little,
+}
+
+/// A value read from a bit field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldValue {
+    /// A [Signedness::Unsigned] field value.
+    Unsigned(u64),
+    /// A [Signedness::Signed] field value.
+    Signed(i64),
+}
+
+fn read_bits(payload: &[u8], bit_offset: usize, bit_length: usize) -> Result<u64, BitFieldError> {
+    if bit_length == 0 || bit_length > 64 {
+        return Err(BitFieldError::InvalidLength(bit_length));
+    }
+
+    let available_bits = payload.len() * 8;
+    let required_bits = bit_offset.saturating_add(bit_length);
+    if required_bits > available_bits {
+        return Err(BitFieldError::PayloadTooShort {
+            required_bits,
+            available_bits,
+        });
+    }
+
+    Ok((bit_offset..required_bits).fold(0_u64, |acc, bit| {
+        acc << 1 | u64::from((payload[bit / 8] >> (7 - bit % 8)) & 1)
+    }))
+}
+
+fn read_field(
+    payload: &[u8],
+    bit_offset: usize,
+    bit_length: usize,
+    endianness: Endianness,
+) -> Result<u64, BitFieldError> {
+    match endianness {
+        Endianness::Big => read_bits(payload, bit_offset, bit_length),
+        Endianness::Little => {
+            if bit_length % 8 != 0 {
+                return Err(BitFieldError::InvalidLength(bit_length));
+            }
+            read_bits(payload, bit_offset, bit_length).map(|value| {
+                let n_bytes = bit_length / 8;
+                (0..n_bytes).fold(0_u64, |acc, index| {
+                    acc << 8 | ((value >> (8 * index)) & 0xFF)
+                })
+            })
+        }
+    }
+}
+
+fn sign_extend(value: u64, bit_length: usize) -> i64 {
+    let shift = 64 - bit_length;
+    ((value << shift) as i64) >> shift
+}
+
+/// Read an unsigned big-endian field of `bit_length` bits starting at `bit_offset`.
+///
+/// # Errors
+///
+/// This function errors under the following circumstances
+///  - `bit_length` is 0 or > 64
+///  - `payload` is too short to contain the field
+pub fn read_u(payload: &[u8], bit_offset: usize, bit_length: usize) -> Result<u64, BitFieldError> {
+    read_bits(payload, bit_offset, bit_length)
+}
+
+/// Read a two's complement big-endian field of `bit_length` bits starting at `bit_offset`.
+///
+/// # Errors
+///
+/// This function errors under the following circumstances
+///  - `bit_length` is 0 or > 64
+///  - `payload` is too short to contain the field
+pub fn read_i(payload: &[u8], bit_offset: usize, bit_length: usize) -> Result<i64, BitFieldError> {
+    read_bits(payload, bit_offset, bit_length).map(|value| sign_extend(value, bit_length))
+}
+
+/// Definition of a single named bit field inside a payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// Name of the telemetry point.
+    pub name: String,
+    /// Offset of the first bit of the field from the MSB of the first payload byte.
+    pub bit_offset: usize,
+    /// Number of bits in the field. Must be in 1..=64.
+    pub bit_length: usize,
+    /// Whether the field is read as a two's complement value.
+    pub signedness: Signedness,
+    /// Byte order of the field.
+    pub endianness: Endianness,
+}
+impl Field {
+    /// Read this field from the input payload.
+    pub fn read(&self, payload: &[u8]) -> Result<FieldValue, BitFieldError> {
+        let value = read_field(payload, self.bit_offset, self.bit_length, self.endianness)?;
+        Ok(match self.signedness {
+            Signedness::Unsigned => FieldValue::Unsigned(value),
+            Signedness::Signed => FieldValue::Signed(sign_extend(value, self.bit_length)),
+        })
+    }
+}
+
+/// A collection of [Field]s read together from a [SpacePacket] payload.
+///
+/// ```
+/// use spacepacket::{
+///     bitfield::{Endianness, Extractor, FieldValue, Signedness},
+///     GroupingFlag, PacketType, SpacePacket,
+/// };
+///
+/// let extractor = Extractor::new()
+///     .field("mode", 0, 3, Signedness::Unsigned, Endianness::Big)
+///     .field("temperature", 3, 13, Signedness::Signed, Endianness::Big);
+///
+/// let packet = SpacePacket::new(
+///     0,
+///     PacketType::Telemetry,
+///     0x42,
+///     GroupingFlag::Unsegm,
+///     0,
+///     false,
+///     vec![0b101_11111, 0xFE],
+/// );
+///
+/// assert_eq!(
+///     vec![
+///         ("mode", FieldValue::Unsigned(5)),
+///         ("temperature", FieldValue::Signed(-2)),
+///     ],
+///     extractor.extract(&packet).unwrap()
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Extractor {
+    fields: Vec<Field>,
+}
+impl Extractor {
+    /// Create an Extractor with no fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field to this Extractor.
+    pub fn field<S: Into<String>>(
+        mut self,
+        name: S,
+        bit_offset: usize,
+        bit_length: usize,
+        signedness: Signedness,
+        endianness: Endianness,
+    ) -> Self {
+        self.fields.push(Field {
+            name: name.into(),
+            bit_offset,
+            bit_length,
+            signedness,
+            endianness,
+        });
+        self
+    }
+
+    /// The fields of this Extractor in the order they were added.
+    pub fn fields(&self) -> &[Field] {
+        self.fields.as_slice()
+    }
+
+    /// Read all fields from the payload of a [SpacePacket].
+    pub fn extract(&self, packet: &SpacePacket) -> Result<Vec<(&str, FieldValue)>, BitFieldError> {
+        self.extract_payload(&packet.payload)
+    }
+
+    /// Read all fields from a raw payload.
+    pub fn extract_payload(
+        &self,
+        payload: &[u8],
+    ) -> Result<Vec<(&str, FieldValue)>, BitFieldError> {
+        self.fields
+            .iter()
+            .map(|field| Ok((field.name.as_str(), field.read(payload)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&[0x12, 0x34], 0, 16, 0x1234)]
+    #[case(&[0x12, 0x34, 0x56], 4, 12, 0x234)]
+    #[case(&[0x12, 0x34, 0x56], 4, 16, 0x2345)]
+    #[case(&[0b0000_0111, 0b1100_0000], 5, 5, 0b11111)]
+    #[case(&[0b1000_0000], 0, 1, 1)]
+    #[case(&[0b0000_0001], 7, 1, 1)]
+    #[case(&[0b0000_0001], 6, 1, 0)]
+    #[case(&[0xFF; 8], 0, 64, u64::MAX)]
+    #[case(&[0x0F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xF0], 4, 64, u64::MAX)]
+    fn unsigned_fields(
+        #[case] payload: &[u8],
+        #[case] bit_offset: usize,
+        #[case] bit_length: usize,
+        #[case] expected: u64,
+    ) {
+        assert_eq!(expected, read_u(payload, bit_offset, bit_length).unwrap())
+    }
+
+    #[rstest]
+    #[case(&[0xF0], 0, 4, -1)]
+    #[case(&[0x70], 0, 4, 7)]
+    #[case(&[0x80], 0, 4, -8)]
+    #[case(&[0x80], 0, 1, -1)]
+    #[case(&[0x1F, 0xFE], 3, 13, -2)]
+    #[case(&[0xFF, 0xFE], 0, 16, -2)]
+    #[case(&[0x80, 0, 0, 0, 0, 0, 0, 0], 0, 64, i64::MIN)]
+    fn signed_fields(
+        #[case] payload: &[u8],
+        #[case] bit_offset: usize,
+        #[case] bit_length: usize,
+        #[case] expected: i64,
+    ) {
+        assert_eq!(expected, read_i(payload, bit_offset, bit_length).unwrap())
+    }
+
+    #[rstest]
+    #[case(&[0x12, 0x34], 0, 16, Signedness::Unsigned, FieldValue::Unsigned(0x3412))]
+    #[case(&[0x01, 0x23, 0x45], 4, 16, Signedness::Unsigned, FieldValue::Unsigned(0x3412))]
+    #[case(&[0xFE, 0xFF], 0, 16, Signedness::Signed, FieldValue::Signed(-2))]
+    fn little_endian_fields(
+        #[case] payload: &[u8],
+        #[case] bit_offset: usize,
+        #[case] bit_length: usize,
+        #[case] signedness: Signedness,
+        #[case] expected: FieldValue,
+    ) {
+        let field = Field {
+            name: "field".into(),
+            bit_offset,
+            bit_length,
+            signedness,
+            endianness: Endianness::Little,
+        };
+        assert_eq!(expected, field.read(payload).unwrap())
+    }
+
+    #[rstest]
+    #[case(0, 0, Endianness::Big, BitFieldError::InvalidLength(0))]
+    #[case(0, 65, Endianness::Big, BitFieldError::InvalidLength(65))]
+    #[case(0, 12, Endianness::Little, BitFieldError::InvalidLength(12))]
+    #[case(
+        10,
+        8,
+        Endianness::Big,
+        BitFieldError::PayloadTooShort { required_bits: 18, available_bits: 16 }
+    )]
+    #[case(
+        usize::MAX,
+        8,
+        Endianness::Big,
+        BitFieldError::PayloadTooShort { required_bits: usize::MAX, available_bits: 16 }
+    )]
+    fn field_errors(
+        #[case] bit_offset: usize,
+        #[case] bit_length: usize,
+        #[case] endianness: Endianness,
+        #[case] expected: BitFieldError,
+    ) {
+        let field = Field {
+            name: "field".into(),
+            bit_offset,
+            bit_length,
+            signedness: Signedness::Unsigned,
+            endianness,
+        };
+        assert_eq!(Err(expected), field.read(&[0x12, 0x34]))
+    }
+
+    #[test]
+    fn extract_packet() {
+        let extractor = Extractor::new()
+            .field("flag", 0, 1, Signedness::Unsigned, Endianness::Big)
+            .field("counter", 1, 15, Signedness::Unsigned, Endianness::Big)
+            .field("current", 16, 16, Signedness::Signed, Endianness::Little);
+
+        let packet = SpacePacket::new(
+            0,
+            crate::PacketType::Telemetry,
+            0x42,
+            crate::GroupingFlag::Unsegm,
+            0,
+            false,
+            vec![0x80, 0x05, 0xFF, 0xFF],
+        );
+
+        assert_eq!(
+            vec![
+                ("flag", FieldValue::Unsigned(1)),
+                ("counter", FieldValue::Unsigned(5)),
+                ("current", FieldValue::Signed(-1)),
+            ],
+            extractor.extract(&packet).unwrap()
+        );
+
+        let short_packet = SpacePacket {
+            payload: vec![0x80, 0x05],
+            ..packet
+        };
+        assert_eq!(
+            Err(BitFieldError::PayloadTooShort {
+                required_bits: 32,
+                available_bits: 16
+            }),
+            extractor.extract(&short_packet)
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! A report of the features this build of the crate was compiled with.
+//!
+//! Applications configured at runtime, e.g. from mission files, can check the
+//! report up front and fail with a clear message instead of misbehaving later.
+//!
+//! ```
+//! let capabilities = spacepacket::capabilities::capabilities();
+//! println!("{capabilities}");
+//!
+//! if let Err(err) = capabilities.require("crc") {
+//!     eprintln!("{err}");
+//! }
+//! ```
+
+use std::{
+    fmt::Display,
+    io::{Error, ErrorKind},
+};
+
+use crate::PACKET_VERSION_NUMBER;
+
+/// The cargo features of the crate, in the order reported by [Capabilities].
+pub const FEATURES: [&str; 4] = ["crc", "async-codec", "tokio-codec", "tctm"];
+
+/// The features, versions and formats supported by this build of the crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// The version of the crate.
+    pub version: &'static str,
+    /// The output of `git describe` for the build, recorded when built from a git checkout
+    /// or taken from the `SPACEPACKET_GIT_DESCRIBE` environment variable at compile time.
+    pub git_describe: Option<&'static str>,
+    /// The Space Packet version number encoded and expected by the crate.
+    pub packet_version: u8,
+    /// Whether packet CRCs are supported, feature `crc`.
+    pub crc: bool,
+    /// The widths in bits of the supported packet CRCs.
+    pub crc_widths: &'static [u32],
+    /// Whether the asynchronous-codec codec is available, feature `async-codec`.
+    pub async_codec: bool,
+    /// Whether the tokio-util codec is available, feature `tokio-codec`.
+    pub tokio_codec: bool,
+    /// Whether TC and TM Transfer Frames are supported, feature `tctm`.
+    pub tctm: bool,
+}
+impl Capabilities {
+    /// Whether the cargo `feature` was enabled for this build.
+    pub fn has_feature(&self, feature: &str) -> bool {
+        match feature {
+            "crc" => self.crc,
+            "async-codec" => self.async_codec,
+            "tokio-codec" => self.tokio_codec,
+            "tctm" => self.tctm,
+            _ => false,
+        }
+    }
+
+    /// The names of the cargo features enabled for this build.
+    pub fn features(&self) -> impl Iterator<Item = &'static str> + '_ {
+        FEATURES
+            .into_iter()
+            .filter(|feature| self.has_feature(feature))
+    }
+
+    /// Check the cargo `feature` was enabled for this build.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [ErrorKind::Unsupported] naming the feature if it was not enabled
+    /// or is not a feature of the crate.
+    pub fn require(&self, feature: &str) -> Result<(), Error> {
+        if self.has_feature(feature) {
+            return Ok(());
+        }
+        let message = match FEATURES.contains(&feature) {
+            true => format!(
+                "This build of spacepacket {} lacks feature `{feature}`, rebuild with it enabled",
+                self.version
+            ),
+            false => format!(
+                "Unknown spacepacket feature `{feature}`, expected one of {}",
+                FEATURES.join(", ")
+            ),
+        };
+        Err(Error::new(ErrorKind::Unsupported, message))
+    }
+}
+impl Display for Capabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "spacepacket {}", self.version)?;
+        if let Some(describe) = self.git_describe {
+            write!(f, " ({describe})")?;
+        }
+        write!(
+            f,
+            " packet version {} features [{}]",
+            self.packet_version,
+            self.features().collect::<Vec<_>>().join(", ")
+        )
+    }
+}
+
+/// The capabilities of this build of the crate.
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        git_describe: option_env!("SPACEPACKET_GIT_DESCRIBE"),
+        packet_version: PACKET_VERSION_NUMBER,
+        crc: cfg!(feature = "crc"),
+        crc_widths: match cfg!(feature = "crc") {
+            true => &[16],
+            false => &[],
+        },
+        async_codec: cfg!(feature = "async-codec"),
+        tokio_codec: cfg!(feature = "tokio-codec"),
+        tctm: cfg!(feature = "tctm"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn capabilities_match_features() {
+        let capabilities = capabilities();
+
+        assert_eq!(env!("CARGO_PKG_VERSION"), capabilities.version);
+        assert_eq!(0, capabilities.packet_version);
+        // the dev-dependencies enable these features
+        for feature in ["crc", "async-codec", "tctm"] {
+            assert!(capabilities.require(feature).is_ok());
+        }
+        assert_eq!(&[16], capabilities.crc_widths);
+        assert_eq!(
+            cfg!(feature = "tokio-codec"),
+            capabilities
+                .features()
+                .any(|feature| feature == "tokio-codec")
+        );
+        assert!(capabilities
+            .to_string()
+            .starts_with(&format!("spacepacket {}", capabilities.version)));
+        if let Some(describe) = capabilities.git_describe {
+            assert!(!describe.is_empty());
+            assert!(capabilities.to_string().contains(&format!("({describe})")));
+        }
+    }
+
+    #[test]
+    fn capabilities_require_missing() {
+        let capabilities = Capabilities {
+            crc: false,
+            crc_widths: &[],
+            ..capabilities()
+        };
+
+        let error = capabilities.require("crc").unwrap_err();
+        assert_eq!(ErrorKind::Unsupported, error.kind());
+        assert!(error.to_string().contains("lacks feature `crc`"));
+
+        let error = capabilities.require("fecf").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Unknown spacepacket feature `fecf`"));
+    }
+}
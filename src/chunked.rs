@@ -0,0 +1,111 @@
+//! Reading from scattered input without first copying it into a contiguous buffer.
+//!
+//! ```
+//! # use spacepacket::{chunked::ChunkedReader, SpacePacket};
+//! let chunks: [&[u8]; 3] = [&[0x18, 0x11], &[0xC0, 0x00, 0x00], &[0x00, 0x42]];
+//!
+//! let packet = SpacePacket::decode(&mut ChunkedReader::new(chunks)).unwrap();
+//! assert_eq!(17, packet.primary_header.apid);
+//! assert_eq!(vec![0x42], packet.payload);
+//! ```
+
+use std::io::{BufRead, Read};
+
+/// An adapter implementing [Read] and [BufRead] over an iterator of byte slices.
+///
+/// The slices are read in order as if they were a single contiguous buffer,
+/// empty slices are skipped.
+#[derive(Debug, Clone)]
+pub struct ChunkedReader<'a, I> {
+    chunks: I,
+    current: &'a [u8],
+}
+impl<'a, I: Iterator<Item = &'a [u8]>> ChunkedReader<'a, I> {
+    /// Create a reader over the `chunks`.
+    pub fn new<C: IntoIterator<IntoIter = I>>(chunks: C) -> Self {
+        Self {
+            chunks: chunks.into_iter(),
+            current: &[],
+        }
+    }
+
+    /// Recover the remainder of the current chunk and the chunks not yet read.
+    pub fn into_inner(self) -> (&'a [u8], I) {
+        (self.current, self.chunks)
+    }
+}
+impl<'a, I: Iterator<Item = &'a [u8]>> Read for ChunkedReader<'a, I> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut available = self.fill_buf()?;
+        let read = available.read(buf)?;
+        self.consume(read);
+        Ok(read)
+    }
+}
+impl<'a, I: Iterator<Item = &'a [u8]>> BufRead for ChunkedReader<'a, I> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        while self.current.is_empty() {
+            match self.chunks.next() {
+                Some(chunk) => self.current = chunk,
+                None => break,
+            }
+        }
+        Ok(self.current)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.current = &self.current[amt.min(self.current.len())..];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{GroupingFlag, PacketType, SpacePacket};
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(1)]
+    #[case(3)]
+    #[case(7)]
+    #[case(64)]
+    fn chunked_decode(#[case] chunk_len: usize) {
+        let expected = SpacePacket::new(
+            0,
+            PacketType::Command,
+            17,
+            GroupingFlag::Unsegm,
+            5,
+            false,
+            (0..40).collect(),
+        );
+        let encoded = [expected.encode(), expected.encode()].concat();
+
+        // interleave empty chunks which must be skipped
+        let chunks = encoded.chunks(chunk_len).flat_map(|chunk| [chunk, &[][..]]);
+        let mut reader = ChunkedReader::new(chunks);
+
+        assert_eq!(expected, SpacePacket::decode(&mut reader).unwrap());
+        assert_eq!(expected, SpacePacket::decode(&mut reader).unwrap());
+        assert!(SpacePacket::decode(&mut reader).is_err());
+    }
+
+    #[test]
+    fn chunked_read_empty() {
+        let mut reader = ChunkedReader::new(Vec::<&[u8]>::new());
+        assert_eq!(0, reader.read(&mut [0_u8; 4]).unwrap());
+
+        let mut reader = ChunkedReader::new([&[][..], &[1, 2, 3][..], &[4][..]]);
+        assert_eq!(0, reader.read(&mut []).unwrap());
+
+        let mut buf = [0_u8; 2];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!([1, 2], buf);
+
+        let (current, mut rest) = reader.into_inner();
+        assert_eq!(&[3], current);
+        assert_eq!(Some(&[4][..]), rest.next());
+    }
+}
@@ -0,0 +1,146 @@
+//! Consistent Overhead Byte Stuffing (COBS) for links which cannot carry arbitrary binary.
+//!
+//! [encode] removes every occurrence of a delimiter byte from a frame at a cost of at most
+//! one byte per 254 bytes, so the delimiter unambiguously marks frame boundaries.
+//! A stuffed frame damaged in transit is rejected by [decode] and the receiver
+//! resynchronizes on the next delimiter.
+//!
+//! ```
+//! # use spacepacket::cobs;
+//! let frame = [0x11, 0xFF, 0xFF, 0x22];
+//! let stuffed = cobs::encode(&frame, 0xFF);
+//! assert!(!stuffed.contains(&0xFF));
+//! assert_eq!(frame.to_vec(), cobs::decode(&stuffed, 0xFF).unwrap());
+//! ```
+
+use std::io::{Error, ErrorKind};
+
+/// The longest run of bytes described by a single code byte.
+const MAX_CODE: u8 = 0xFF;
+
+/// How packets are protected from bytes the link cannot carry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Transparency {
+    /// Packets are sent as is.
+    #[default]
+    None,
+    /// Every packet is COBS encoded without the `delimiter` and followed by it.
+    Cobs {
+        /// The byte marking the end of every frame.
+        delimiter: u8,
+    },
+}
+
+/// Stuff the `frame` so it no longer contains the `delimiter`.
+///
+/// The delimiter itself is not appended.
+pub fn encode(frame: &[u8], delimiter: u8) -> Vec<u8> {
+    let mut stuffed = Vec::with_capacity(frame.len() + frame.len() / 254 + 1);
+    let mut code_index = 0;
+    let mut code = 1;
+    stuffed.push(0);
+
+    for byte in frame {
+        if *byte != 0 {
+            stuffed.push(*byte);
+            code += 1;
+        }
+        if *byte == 0 || code == MAX_CODE {
+            stuffed[code_index] = code;
+            code_index = stuffed.len();
+            stuffed.push(0);
+            code = 1;
+        }
+    }
+    stuffed[code_index] = code;
+
+    // zero no longer occurs, exchanging it with the delimiter removes the delimiter instead
+    stuffed.iter_mut().for_each(|byte| *byte ^= delimiter);
+    stuffed
+}
+
+/// Recover a frame stuffed by [encode], without its trailing `delimiter`.
+///
+/// # Errors
+///
+/// Errors with [ErrorKind::InvalidData] if the `stuffed` bytes are not a valid COBS encoding,
+/// i.e. they contain the delimiter or a code byte runs past their end.
+pub fn decode(stuffed: &[u8], delimiter: u8) -> Result<Vec<u8>, Error> {
+    let invalid = |index: usize| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Invalid COBS encoding at byte {index}"),
+        )
+    };
+
+    let mut frame = Vec::with_capacity(stuffed.len());
+    let mut index = 0;
+    while index < stuffed.len() {
+        let code = stuffed[index] ^ delimiter;
+        let end = index + usize::from(code);
+        if code == 0 || end > stuffed.len() {
+            return Err(invalid(index));
+        }
+        for (offset, byte) in stuffed[index + 1..end].iter().enumerate() {
+            match byte ^ delimiter {
+                0 => return Err(invalid(index + 1 + offset)),
+                byte => frame.push(byte),
+            }
+        }
+        index = end;
+        if code != MAX_CODE && index < stuffed.len() {
+            frame.push(0);
+        }
+    }
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(vec![], vec![0x01])]
+    #[case(vec![0x00], vec![0x01, 0x01])]
+    #[case(vec![0x00, 0x00], vec![0x01, 0x01, 0x01])]
+    #[case(vec![0x11, 0x22, 0x00, 0x33], vec![0x03, 0x11, 0x22, 0x02, 0x33])]
+    #[case(vec![0x11, 0x00, 0x00, 0x00], vec![0x02, 0x11, 0x01, 0x01, 0x01])]
+    fn cobs_reference_vectors(#[case] frame: Vec<u8>, #[case] stuffed: Vec<u8>) {
+        assert_eq!(stuffed, encode(&frame, 0x00));
+        assert_eq!(frame, decode(&stuffed, 0x00).unwrap());
+    }
+
+    #[rstest]
+    fn cobs_roundtrip(
+        #[values(0, 1, 253, 254, 255, 508, 1000)] len: usize,
+        #[values(0x00, 0xFF, 0x7E)] delimiter: u8,
+        #[values(0x00, 0x01, 0xFF)] fill: u8,
+    ) {
+        let frame: Vec<u8> = (0..len)
+            .map(|index| match index % 7 {
+                0 => fill,
+                _ => index as u8,
+            })
+            .collect();
+
+        let stuffed = encode(&frame, delimiter);
+        assert!(!stuffed.contains(&delimiter));
+        assert!(stuffed.len() <= frame.len() + frame.len() / 254 + 1);
+        assert_eq!(frame, decode(&stuffed, delimiter).unwrap());
+    }
+
+    #[rstest]
+    // a code byte running past the end
+    #[case(vec![0x05, 0x11])]
+    // the delimiter inside a frame
+    #[case(vec![0x03, 0x11, 0x00])]
+    #[case(vec![0x00])]
+    fn cobs_invalid(#[case] stuffed: Vec<u8>) {
+        assert_eq!(
+            ErrorKind::InvalidData,
+            decode(&stuffed, 0x00).unwrap_err().kind()
+        );
+    }
+}
@@ -1,14 +1,22 @@
-use crate::SpacePacket;
+use crate::{
+    anomaly::{AnomalyKind, AnomalyLog, Layer},
+    framer::{DiscardReason, FramerEvent, PacketFramer},
+    PrimaryHeader, SpacePacket,
+};
 use bytes::{Buf, BytesMut};
 
-#[cfg(feature = "crc")]
-use {crate::CompletePacket, crc::Crc};
+#[cfg(feature = "cobs")]
+use crate::cobs::{self, Transparency};
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum CodecState {
-    Sync,
-    Data,
-}
+#[cfg(feature = "crc")]
+use {
+    crate::{
+        trailer::{Crc16, TrailerCheck},
+        CompletePacket,
+    },
+    crc::Crc,
+    std::sync::Arc,
+};
 
 #[cfg_attr(
     docsrs,
@@ -19,100 +27,547 @@ type PacketReturn = CompletePacket;
 #[cfg(not(feature = "crc"))]
 type PacketReturn = SpacePacket;
 
+/// The [SpacePacketCodec] found no packet within the bytes allowed by
+/// [SpacePacketCodec::with_sync_watchdog], the sync marker is likely wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncAcquisitionFailed {
+    /// The number of bytes consumed since the last packet, or since the watchdog last fired.
+    pub bytes_examined: u64,
+}
+impl std::fmt::Display for SyncAcquisitionFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "No packet found in {} bytes, check the synchronization marker",
+            self.bytes_examined
+        )
+    }
+}
+impl std::error::Error for SyncAcquisitionFailed {}
+impl From<SyncAcquisitionFailed> for std::io::Error {
+    fn from(err: SyncAcquisitionFailed) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
 /// A Codec used to Encode/Decode [SpacePacket]s from Streams and Sinks.
 /// This Codec can be useful when designing programs that must listen for
 /// a packet on an I/O device.
+///
+/// Decoding is delegated to a [PacketFramer], which takes every byte handed to
+/// the decoder and frames the packets itself. The bytes of a partial packet are
+/// left in the read buffer until the packet is complete, so a stream ending
+/// inside a packet yields an [std::io::ErrorKind::UnexpectedEof] error.
+///
+/// A codec without a CRC decodes CRC protected packets with the CRC left
+/// as the last two bytes of the payload. A codec with a CRC decodes packets without
+/// a CRC as `CompletePacket::InvalidCRC`, or returns an
+/// error when the packet is too short to contain a CRC.
+///
+/// The codec is [Send] and [Sync]. A configured codec can be used as a template
+/// and cloned cheaply for every new connection, clones always start searching for
+/// the synchronization marker regardless of the state of the original.
+///
+/// # Cancellation safety
+///
+/// Decoding is synchronous, bytes move from the read buffer into the framer and a
+/// packet is returned within the same call to decode, so a Framed stream polled in
+/// `select!` loses no packets and yields none twice when a pending read is dropped.
+/// [Self::last_packet_offset] and [Self::last_gap] describe the last packet actually
+/// returned by decode and are not changed by calls which return no packet.
 pub struct SpacePacketCodec {
-    sync_marker: Box<[u8]>,
-    state: CodecState,
-    #[cfg(feature = "crc")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
-    crc: Option<Crc<u16>>,
+    framer: PacketFramer,
+    /// Bytes at the start of the read buffer already pushed to the framer.
+    pushed: usize,
+    /// Stream offset of the last packet returned by decode.
+    last_packet_offset: Option<u64>,
+    /// Gap bytes following the last packet returned by decode.
+    last_gap: Vec<u8>,
+    anomaly_log: Option<AnomalyLog>,
+    /// The bytes consumed without a packet before the watchdog fires.
+    max_unsynchronized: Option<u64>,
+    /// Stream offset since which no packet was found.
+    acquisition_start: u64,
+    /// The configured marker followed by the fallback markers, empty without fallbacks.
+    sync_markers: Vec<Box<[u8]>>,
+    /// Index of the marker in use within `sync_markers`.
+    marker_index: usize,
+    #[cfg(feature = "cobs")]
+    transparency: Transparency,
+    /// Number of delimited frames discarded as corrupted.
+    #[cfg(feature = "cobs")]
+    corrupted_frames: u64,
+}
+impl Clone for SpacePacketCodec {
+    fn clone(&self) -> Self {
+        let mut framer = self.framer.clone();
+        framer.reset();
+        if let Some(marker) = self.sync_markers.first() {
+            framer.set_sync_marker(marker);
+        }
+        let mut codec = Self::from_framer(framer);
+        codec.anomaly_log = self.anomaly_log.clone();
+        codec.max_unsynchronized = self.max_unsynchronized;
+        codec.sync_markers = self.sync_markers.clone();
+        #[cfg(feature = "cobs")]
+        {
+            codec.transparency = self.transparency;
+        }
+        codec
+    }
 }
 impl SpacePacketCodec {
     /// Create a new SpacePacketCodec with the input synchronization
     /// marker. This codec with sweep through the input byte stream
     /// until the synchronization marker is found, then parse a [SpacePacket].
     ///
-    /// crc agrument only valid on feature `crcs`
-    pub fn new<T: AsRef<[u8]>>(
-        sync_marker: T,
-        #[cfg(feature = "crc")] crc: Option<Crc<u16>>,
-    ) -> Self {
-        Self {
-            sync_marker: sync_marker.as_ref().to_owned().into_boxed_slice(),
-            state: CodecState::Sync,
-            #[cfg(feature = "crc")]
-            crc,
+    /// The signature is the same with and without the `crc` feature,
+    /// a CRC is added with [Self::with_crc].
+    ///
+    /// The marker should be at most [PacketFramer::MAX_SYNC_MARKER_LEN] bytes,
+    /// use [Self::try_new] to check it.
+    pub fn new<T: AsRef<[u8]>>(sync_marker: T) -> Self {
+        Self::from_framer(PacketFramer::new(sync_marker))
+    }
+
+    /// Create a new SpacePacketCodec as [Self::new], checking the synchronization marker length.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [std::io::ErrorKind::InvalidInput] if the marker is longer than
+    /// [PacketFramer::MAX_SYNC_MARKER_LEN] bytes.
+    pub fn try_new<T: AsRef<[u8]>>(sync_marker: T) -> std::io::Result<Self> {
+        PacketFramer::try_new(sync_marker).map(Self::from_framer)
+    }
+
+    /// Create a new SpacePacketCodec with an optional CRC, the constructor signature
+    /// used to depend on the `crc` feature.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    #[deprecated(note = "use `SpacePacketCodec::new(sync_marker).with_crc(crc)` instead")]
+    pub fn new_with_optional_crc<T: AsRef<[u8]>>(sync_marker: T, crc: Option<Crc<u16>>) -> Self {
+        let codec = Self::new(sync_marker);
+        match crc {
+            Some(crc) => codec.with_crc(crc),
+            None => codec,
         }
     }
 
-    fn find_sync<B: AsRef<[u8]>>(&mut self, source: &B) -> Option<usize> {
-        if self.sync_marker.is_empty() {
-            return Some(0);
+    fn from_framer(framer: PacketFramer) -> Self {
+        Self {
+            framer,
+            pushed: 0,
+            last_packet_offset: None,
+            last_gap: vec![],
+            anomaly_log: None,
+            max_unsynchronized: None,
+            acquisition_start: 0,
+            sync_markers: vec![],
+            marker_index: 0,
+            #[cfg(feature = "cobs")]
+            transparency: Transparency::None,
+            #[cfg(feature = "cobs")]
+            corrupted_frames: 0,
         }
-        source
-            .as_ref()
-            .windows(self.sync_marker.len())
-            .position(|window| window == &*self.sync_marker)
     }
 
-    fn decode_helper(&mut self, buffer: &mut BytesMut) -> std::io::Result<Option<PacketReturn>> {
-        if self.state == CodecState::Sync {
-            if let Some(index) = self.find_sync(buffer) {
-                buffer.advance(index + self.sync_marker.len());
-                self.state = CodecState::Data;
-            } else {
-                // There is no sync marker in the current buffer
-                // but keep SYNC_MARKERS.len() - 1 bytes
-                // in case syncs cross buffer boundaries
-                let len = buffer.remaining();
-                if len > self.sync_marker.len() - 1 {
-                    buffer.advance(len - (self.sync_marker.len() - 1));
+    /// Validate and append a CRC-16 value to every packet using the provided [Crc].
+    ///
+    /// Decoded packets are returned as a [CompletePacket] either way.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub fn with_crc(mut self, crc: Crc<u16>) -> Self {
+        self.framer = self.framer.with_crc(crc);
+        self
+    }
+
+    /// Protect the [PrimaryHeader] with a CRC-16 value using the provided [Crc].
+    ///
+    /// The 2 byte header CRC is inserted between the primary header and the payload
+    /// and is not counted in the packet length. The decoder verifies it before waiting
+    /// for the payload, rejecting damaged headers without buffering up to 64KB of
+    /// payload implied by a damaged length field.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub fn with_header_crc(mut self, crc: Crc<u16>) -> Self {
+        self.framer = self.framer.with_header_crc(crc);
+        self
+    }
+
+    /// Validate and append a CRC-16 to every packet using a shared implementation of any table size,
+    /// replacing any CRC set by [Self::with_crc].
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub fn with_shared_crc(mut self, crc: Arc<dyn Crc16>) -> Self {
+        self.framer = self.framer.with_shared_crc(crc);
+        self
+    }
+
+    /// As [Self::with_header_crc] using a shared implementation of any table size.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub fn with_shared_header_crc(mut self, crc: Arc<dyn Crc16>) -> Self {
+        self.framer = self.framer.with_shared_header_crc(crc);
+        self
+    }
+
+    /// Silently discard decoded Idle Packets with the given APID,
+    /// usually [IDLE_APID](crate::IDLE_APID).
+    pub fn skip_idle(mut self, idle_apid: u16) -> Self {
+        self.framer = self.framer.skip_idle(idle_apid);
+        self
+    }
+
+    /// Count skipped Idle Packets which do not follow the fill `pattern`,
+    /// see [PacketFramer::verify_idle].
+    pub fn verify_idle(mut self, pattern: crate::FillPattern) -> Self {
+        self.framer = self.framer.verify_idle(pattern);
+        self
+    }
+
+    /// The number of skipped Idle Packets which did not follow the fill pattern.
+    pub fn corrupted_idle_count(&self) -> u64 {
+        self.framer.corrupted_idle_count()
+    }
+
+    /// Skip exactly `gap` bytes following every decoded packet,
+    /// see [PacketFramer::with_inter_packet_gap].
+    ///
+    /// The encoder does not write any gap.
+    pub fn with_inter_packet_gap(mut self, gap: usize) -> Self {
+        self.framer = self.framer.with_inter_packet_gap(gap);
+        self
+    }
+
+    /// Cap the resources spent decoding hostile input, see [PacketFramer::with_limits].
+    ///
+    /// Exceeding a limit returns the [ResourceLimit](crate::limits::ResourceLimit) as the error of the decoder.
+    pub fn with_limits(mut self, limits: crate::limits::DecodeLimits) -> Self {
+        self.framer = self.framer.with_limits(limits);
+        self
+    }
+
+    /// Give up on the synchronization marker once `max_bytes` bytes were consumed without
+    /// a packet, instead of discarding a stream framed with another marker forever.
+    ///
+    /// The decoder then searches for the next marker given to [Self::with_fallback_markers],
+    /// or returns a [SyncAcquisitionFailed] error once every marker was tried. The count
+    /// restarts after every packet, including packets with an invalid CRC and skipped
+    /// Idle Packets, and after the watchdog fires.
+    pub fn with_sync_watchdog(mut self, max_bytes: u64) -> Self {
+        self.max_unsynchronized = Some(max_bytes);
+        self
+    }
+
+    /// Markers tried in order when [Self::with_sync_watchdog] fires,
+    /// a decoder keeps the marker of the first packet found.
+    pub fn with_fallback_markers<I, T>(mut self, markers: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        self.sync_markers = std::iter::once(self.framer.sync_marker().into())
+            .chain(markers.into_iter().map(|marker| marker.as_ref().into()))
+            .collect();
+        self.marker_index = 0;
+        self
+    }
+
+    /// Restart the watchdog after a packet was found.
+    fn acquired(&mut self) {
+        self.acquisition_start = self.framer.stream_offset();
+    }
+
+    /// Switch to the next fallback marker or fail once the watchdog expired.
+    fn check_acquisition(&mut self) -> std::io::Result<()> {
+        let bytes_examined = self.framer.stream_offset() - self.acquisition_start;
+        match self.max_unsynchronized {
+            Some(max_bytes) if bytes_examined > max_bytes => {
+                self.acquired();
+                match self.sync_markers.get(self.marker_index + 1) {
+                    Some(marker) => {
+                        self.marker_index += 1;
+                        self.framer.set_sync_marker(marker);
+                        Ok(())
+                    }
+                    None => Err(SyncAcquisitionFailed { bytes_examined }.into()),
                 }
-                // Return None to indiciate more data is needed
-                return Ok(None);
             }
+            _ => Ok(()),
         }
+    }
 
-        // 7 is the minimum length of a ccsds packet
-        // header: 6 bytes
-        // payload:  1 byte
-        if buffer.remaining() < 7 {
-            // Not enough bytes for a packet
-            return Ok(None);
-        }
+    /// Protect every packet from bytes the link cannot carry.
+    ///
+    /// With [Transparency::Cobs] the encoder stuffs the synchronization marker, header and
+    /// payload including any CRC and writes the delimiter after them. The decoder splits
+    /// the stream at every delimiter and frames the packet of each un-stuffed frame,
+    /// a frame which is not a valid encoding or holds an incomplete packet is discarded
+    /// and counted by [Self::corrupted_frame_count].
+    ///
+    /// Stream offsets count the un-stuffed bytes.
+    #[cfg(feature = "cobs")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cobs")))]
+    pub fn with_transparency(mut self, transparency: Transparency) -> Self {
+        self.transparency = transparency;
+        self
+    }
 
-        // check the length marker
-        // bytes 4 and 5 (0 index) are CCSDS length - 1
-        // add 6 for the header
-        let packet_length =
-            u16::from_be_bytes(buffer.as_ref()[4..6].try_into().unwrap()) as usize + 1 + 6;
+    /// The number of delimited frames discarded since they were corrupted,
+    /// see [Self::with_transparency].
+    #[cfg(feature = "cobs")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cobs")))]
+    pub fn corrupted_frame_count(&self) -> u64 {
+        self.corrupted_frames
+    }
 
-        if buffer.remaining() < packet_length {
-            // full packet has not yet arrived
-            // reserve enough bytes so we can fit it in the buffer
-            buffer.reserve(packet_length - buffer.remaining());
+    /// Record every skipped byte and damaged packet in the `log`, as [Layer::Codec] events
+    /// at their stream offset. Clones of the codec report into the same log.
+    pub fn with_anomaly_log(mut self, log: AnomalyLog) -> Self {
+        self.anomaly_log = Some(log);
+        self
+    }
 
-            // Tell the frame we need more bytes
-            return Ok(None);
+    fn record(&self, offset: u64, kind: AnomalyKind) {
+        if let Some(log) = &self.anomaly_log {
+            log.record(offset, Layer::Codec, kind);
         }
+    }
 
-        let data = buffer.as_ref()[..packet_length].to_vec();
-        buffer.advance(packet_length);
-        // We know there is a packet's length of data whether or not it is valid
-        // Rever to check for sync
-        self.state = CodecState::Sync;
+    /// Record the anomaly reported by a framer `event`, if any, which
+    /// started processing the stream at `offset`.
+    fn record_event(&self, offset: u64, event: &FramerEvent) {
+        match event {
+            FramerEvent::Discarded(DiscardReason::Unsynchronized(skipped)) => {
+                self.record(offset, AnomalyKind::Unsynchronized { skipped: *skipped })
+            }
+            FramerEvent::Discarded(DiscardReason::TooShort { packet_length, .. }) => self.record(
+                offset,
+                AnomalyKind::TooShort {
+                    packet_length: *packet_length,
+                },
+            ),
+            #[cfg(feature = "crc")]
+            FramerEvent::Discarded(DiscardReason::HeaderCrc { sent, computed }) => self.record(
+                offset,
+                AnomalyKind::HeaderCrcMismatch {
+                    sent: *sent,
+                    computed: *computed,
+                },
+            ),
+            FramerEvent::Discarded(DiscardReason::ResourceLimit(limit)) => {
+                self.record(offset, AnomalyKind::ResourceLimit(*limit))
+            }
+            #[cfg(feature = "crc")]
+            FramerEvent::CrcError(sent, computed, _) => self.record(
+                self.framer.last_packet_offset().unwrap_or(offset),
+                AnomalyKind::CrcMismatch {
+                    sent: *sent,
+                    computed: *computed,
+                },
+            ),
+            _ => (),
+        }
+    }
+
+    /// The gap bytes which followed the last packet returned by decode.
+    pub fn last_gap(&self) -> &[u8] {
+        &self.last_gap
+    }
+
+    /// The framing core used by the decoder.
+    pub fn framer(&self) -> &PacketFramer {
+        &self.framer
+    }
+
+    /// The number of bytes of the stream consumed by the decoder,
+    /// see [PacketFramer::stream_offset].
+    pub fn stream_offset(&self) -> u64 {
+        self.framer.stream_offset()
+    }
 
+    /// The stream offset of the primary header of the last packet returned by decode.
+    pub fn last_packet_offset(&self) -> Option<u64> {
+        self.last_packet_offset
+    }
+
+    /// Write the synchronization marker followed by the encoded packet,
+    /// shared by the Encoder implementations of all codec crates.
+    ///
+    /// A packet which cannot be encoded is returned inside the error as a [RejectedPacket](crate::RejectedPacket).
+    fn encode_helper(&self, item: SpacePacket, dst: &mut BytesMut) -> std::io::Result<()> {
+        #[cfg(feature = "cobs")]
+        let start = dst.len();
         #[cfg(feature = "crc")]
-        match &self.crc {
-            Some(crc) => SpacePacket::decode_crc(&mut data.as_slice(), crc).map(Some),
-            None => SpacePacket::decode(&mut data.as_slice())
-                .map(|packet| Some(CompletePacket::Valid(packet))),
-        }
+        let trailer_len = self.framer.crc().map_or(0, TrailerCheck::width);
+        #[cfg(not(feature = "crc"))]
+        let trailer_len = 0;
+        let item = item
+            .reject_header()?
+            .reject_payload_len(SpacePacket::MAX_PAYLOAD_LEN - trailer_len)?;
 
+        #[cfg(feature = "crc")]
+        let bytes = match self.framer.crc() {
+            Some(crc) => item.encode_with_trailer(crc)?,
+            None => item.encode(),
+        };
         #[cfg(not(feature = "crc"))]
-        SpacePacket::decode(&mut data.as_slice()).map(Some)
+        let bytes = item.encode();
+
+        let (header, payload) = bytes.split_at(PrimaryHeader::WIRE_LEN);
+        let sync_marker = self.framer.sync_marker();
+
+        dst.reserve(bytes.len() + sync_marker.len() + self.framer.header_crc_len());
+        dst.extend_from_slice(sync_marker);
+        dst.extend_from_slice(header);
+        #[cfg(feature = "crc")]
+        if let Some(crc) = self.framer.header_crc() {
+            dst.extend_from_slice(&crc.checksum(header).to_be_bytes());
+        }
+        dst.extend_from_slice(payload);
+
+        #[cfg(feature = "cobs")]
+        if let Transparency::Cobs { delimiter } = self.transparency {
+            let stuffed = cobs::encode(&dst.split_off(start), delimiter);
+            dst.extend_from_slice(&stuffed);
+            dst.extend_from_slice(&[delimiter]);
+        }
+        Ok(())
+    }
+
+    /// Record the details of the packet framed last, as it is about to be returned.
+    /// Skipped Idle Packets are framed too, so the framer's details are not used directly.
+    fn yielded(&mut self) {
+        self.last_packet_offset = self.framer.last_packet_offset();
+        self.last_gap.clear();
+        self.last_gap.extend_from_slice(self.framer.last_gap());
+    }
+
+    /// Hand the buffered bytes to the framer and translate its events,
+    /// shared by the Decoder implementations of all codec crates.
+    fn decode_helper(&mut self, buffer: &mut BytesMut) -> std::io::Result<Option<PacketReturn>> {
+        #[cfg(feature = "cobs")]
+        if let Transparency::Cobs { delimiter } = self.transparency {
+            return self.decode_delimited(buffer, delimiter);
+        }
+
+        self.framer.push(&buffer[self.pushed.min(buffer.len())..]);
+        let packet = self.next_packet();
+
+        // the pending bytes are a suffix of the stream, keep them until framed
+        self.pushed = self.framer.pending_len().min(buffer.len());
+        buffer.advance(buffer.len() - self.pushed);
+        packet
+    }
+
+    /// Decode the bytes left when the stream ends, erroring when they end inside a packet
+    /// instead of dropping the partial packet held by the framer silently.
+    fn decode_eof_helper(
+        &mut self,
+        buffer: &mut BytesMut,
+    ) -> std::io::Result<Option<PacketReturn>> {
+        if let Some(packet) = self.decode_helper(buffer)? {
+            return Ok(Some(packet));
+        }
+
+        // bytes searched for the synchronization marker are not part of a packet,
+        // bytes not yet pushed are a partial delimited frame
+        let partial = match self.framer.is_synchronized() {
+            true => self.framer.pending_len(),
+            false => 0,
+        } + (buffer.len() - self.pushed);
+        self.framer.discard_pending();
+        self.pushed = 0;
+        buffer.clear();
+        match partial {
+            0 => Ok(None),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("Stream ended with {partial} bytes of a partial packet"),
+            )),
+        }
+    }
+
+    /// Frame the packet of every complete delimited frame in the buffer, leaving any
+    /// partial frame in the buffer until its delimiter arrives.
+    #[cfg(feature = "cobs")]
+    fn decode_delimited(
+        &mut self,
+        buffer: &mut BytesMut,
+        delimiter: u8,
+    ) -> std::io::Result<Option<PacketReturn>> {
+        loop {
+            if let Some(packet) = self.next_packet()? {
+                return Ok(Some(packet));
+            }
+            let end = match buffer.iter().position(|byte| *byte == delimiter) {
+                Some(end) => end,
+                None => return Ok(None),
+            };
+            let frame = buffer.split_to(end + 1);
+
+            // the previous frame ended inside a packet
+            if self.framer.discard_pending() > 0 {
+                self.corrupted_frames += 1;
+            }
+            match cobs::decode(&frame[..end], delimiter) {
+                Ok(bytes) => self.framer.push(&bytes),
+                Err(_) => self.corrupted_frames += 1,
+            }
+        }
+    }
+
+    /// Translate the events of the framer until a packet is framed or more bytes are needed.
+    fn next_packet(&mut self) -> std::io::Result<Option<PacketReturn>> {
+        loop {
+            let offset = self.framer.stream_offset();
+            let event = self.framer.next_event();
+            if let Some(FramerEvent::Packet(_)) = &event {
+                self.yielded();
+            }
+            #[cfg(feature = "crc")]
+            if let Some(FramerEvent::CrcError(..)) = &event {
+                self.yielded();
+            }
+            if let Some(event) = &event {
+                self.record_event(offset, event);
+            }
+            match &event {
+                Some(FramerEvent::Packet(_) | FramerEvent::Discarded(DiscardReason::Idle(_))) => {
+                    self.acquired()
+                }
+                #[cfg(feature = "crc")]
+                Some(FramerEvent::CrcError(..)) => self.acquired(),
+                _ => self.check_acquisition()?,
+            }
+
+            match event {
+                None | Some(FramerEvent::NeedMore) => return Ok(None),
+                // keep decoding in case another packet is already buffered
+                Some(FramerEvent::Discarded(
+                    DiscardReason::Unsynchronized(_) | DiscardReason::Idle(_),
+                )) => continue,
+                Some(FramerEvent::Discarded(DiscardReason::ResourceLimit(limit))) => {
+                    return Err(limit.into())
+                }
+                Some(FramerEvent::Discarded(reason)) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        reason.to_string(),
+                    ))
+                }
+                #[cfg(feature = "crc")]
+                Some(FramerEvent::CrcError(sent, computed, data)) => {
+                    return Ok(Some(CompletePacket::InvalidCRC(sent, computed, data)))
+                }
+                #[cfg(feature = "crc")]
+                Some(FramerEvent::Packet(packet)) => {
+                    return Ok(Some(CompletePacket::Valid(packet)))
+                }
+                #[cfg(not(feature = "crc"))]
+                Some(FramerEvent::Packet(packet)) => return Ok(Some(packet)),
+            }
+        }
     }
 }
 
@@ -133,6 +588,10 @@ mod non_tokio {
         fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
             self.decode_helper(src)
         }
+
+        fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+            self.decode_eof_helper(src)
+        }
     }
 
     impl Encoder for SpacePacketCodec {
@@ -145,20 +604,7 @@ mod non_tokio {
             item: Self::Item,
             dst: &mut asynchronous_codec::BytesMut,
         ) -> Result<(), Self::Error> {
-            let bytes = {
-                #[cfg(feature = "crc")]
-                match &self.crc {
-                    Some(crc) => item.encode_crc(crc),
-                    None => item.encode(),
-                }
-                #[cfg(not(feature = "crc"))]
-                item.encode()
-            };
-
-            dst.reserve(bytes.len() + self.sync_marker.len());
-            dst.extend(&*self.sync_marker);
-            dst.extend(bytes);
-            Ok(())
+            self.encode_helper(item, dst)
         }
     }
 }
@@ -180,6 +626,13 @@ mod tokio_codec {
         fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
             self.decode_helper(src)
         }
+
+        fn decode_eof(
+            &mut self,
+            src: &mut bytes::BytesMut,
+        ) -> Result<Option<Self::Item>, Self::Error> {
+            self.decode_eof_helper(src)
+        }
     }
 
     impl Encoder<SpacePacket> for SpacePacketCodec {
@@ -190,20 +643,7 @@ mod tokio_codec {
             item: SpacePacket,
             dst: &mut bytes::BytesMut,
         ) -> Result<(), Self::Error> {
-            let bytes = {
-                #[cfg(feature = "crc")]
-                match &self.crc {
-                    Some(crc) => item.encode_crc(crc),
-                    None => item.encode(),
-                }
-                #[cfg(not(feature = "crc"))]
-                item.encode()
-            };
-
-            dst.reserve(bytes.len() + self.sync_marker.len());
-            dst.extend(&*self.sync_marker);
-            dst.extend(bytes);
-            Ok(())
+            self.encode_helper(item, dst)
         }
     }
 }
@@ -222,6 +662,444 @@ mod test {
     #[cfg(feature = "crc")]
     const CRC_CCITT_FALSE: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
 
+    #[cfg(feature = "crc")]
+    fn codec_with(sync_marker: &[u8], crc: Option<Crc<u16>>) -> SpacePacketCodec {
+        let codec = SpacePacketCodec::new(sync_marker);
+        match crc {
+            Some(crc) => codec.with_crc(crc),
+            None => codec,
+        }
+    }
+
+    #[test]
+    fn codec_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SpacePacketCodec>();
+    }
+
+    #[rstest]
+    #[case(0, true)]
+    #[case(4, true)]
+    #[case(PacketFramer::MAX_SYNC_MARKER_LEN, true)]
+    #[case(PacketFramer::MAX_SYNC_MARKER_LEN + 1, false)]
+    fn codec_try_new(#[case] marker_len: usize, #[case] valid: bool) {
+        let marker = vec![0x1A; marker_len];
+        let codec = SpacePacketCodec::try_new(&marker);
+
+        match codec {
+            Ok(codec) => {
+                assert!(valid);
+                assert_eq!(marker, codec.framer.sync_marker());
+            }
+            Err(error) => {
+                assert!(!valid);
+                assert_eq!(std::io::ErrorKind::InvalidInput, error.kind());
+            }
+        }
+    }
+
+    #[test]
+    fn codec_clone_resets_state() {
+        let codec = SpacePacketCodec::new([0xAA, 0xBB]);
+        #[cfg(feature = "crc")]
+        let codec = codec.with_crc(CRC_CCITT_FALSE);
+        let mut codec = codec;
+
+        // find the sync marker but leave the packet incomplete
+        let mut buffer = BytesMut::from(&[0xAA_u8, 0xBB, 0x00][..]);
+        assert!(codec.decode_helper(&mut buffer).unwrap().is_none());
+        assert!(codec.framer.is_synchronized());
+
+        let cloned = codec.clone();
+        assert!(!cloned.framer.is_synchronized());
+        assert_eq!(0, cloned.framer.pending_len());
+        assert_eq!(codec.framer.sync_marker(), cloned.framer.sync_marker());
+    }
+
+    /// Deterministic noise which never contains the 0xAA 0xBB or 0xCC 0xDD markers.
+    fn noise(len: usize) -> Vec<u8> {
+        (0..len).map(|index| (index * 7 % 0x80) as u8).collect()
+    }
+
+    fn framed_packet(marker: &[u8], count: u16) -> Vec<u8> {
+        let mut packet = SpacePacket::idle(3);
+        packet.primary_header.apid = 17;
+        packet.primary_header.sequence_count = count;
+        #[cfg(feature = "crc")]
+        let encoded = packet.encode_crc(&CRC_CCITT_FALSE).unwrap();
+        #[cfg(not(feature = "crc"))]
+        let encoded = packet.encode();
+        [marker, &encoded].concat()
+    }
+
+    fn watchdog_codec() -> SpacePacketCodec {
+        let codec = SpacePacketCodec::new([0xAA, 0xBB]).with_sync_watchdog(1000);
+        #[cfg(feature = "crc")]
+        let codec = codec.with_crc(CRC_CCITT_FALSE);
+        codec
+    }
+
+    /// Decode the stream in chunks of 100 bytes, returning the number of packets
+    /// and the errors of the decoder.
+    fn decode_chunks(codec: &mut SpacePacketCodec, stream: &[u8]) -> (usize, Vec<std::io::Error>) {
+        let mut packets = 0;
+        let mut errors = vec![];
+        let mut buffer = BytesMut::new();
+        for chunk in stream.chunks(100) {
+            buffer.extend_from_slice(chunk);
+            loop {
+                match codec.decode_helper(&mut buffer) {
+                    Ok(Some(_)) => packets += 1,
+                    Ok(None) => break,
+                    Err(err) => errors.push(err),
+                }
+            }
+        }
+        (packets, errors)
+    }
+
+    #[test]
+    fn codec_sync_watchdog_noise() {
+        let mut codec = watchdog_codec();
+        let (packets, errors) = decode_chunks(&mut codec, &noise(2500));
+        assert_eq!(0, packets);
+        assert_eq!(2, errors.len());
+
+        let failed = errors[0]
+            .get_ref()
+            .and_then(|err| err.downcast_ref::<SyncAcquisitionFailed>())
+            .unwrap();
+        assert!(failed.bytes_examined > 1000 && failed.bytes_examined <= 1100);
+    }
+
+    #[test]
+    fn codec_sync_watchdog_sparse_stream() {
+        // 900 bytes of noise between packets, 9000 bytes in total
+        let stream: Vec<u8> = (0..10)
+            .flat_map(|count| [noise(900), framed_packet(&[0xAA, 0xBB], count)].concat())
+            .collect();
+
+        let mut codec = watchdog_codec();
+        let (packets, errors) = decode_chunks(&mut codec, &stream);
+        assert_eq!(10, packets);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn codec_sync_watchdog_fallback() {
+        let stream: Vec<u8> = (0..20)
+            .flat_map(|count| [noise(300), framed_packet(&[0xCC, 0xDD], count)].concat())
+            .collect();
+
+        let mut codec = watchdog_codec().with_fallback_markers([[0xEE, 0xFF], [0xCC, 0xDD]]);
+        let (packets, errors) = decode_chunks(&mut codec, &stream);
+        // the first packets are lost while the markers are tried
+        assert!((10..20).contains(&packets));
+        assert!(errors.is_empty());
+        assert_eq!(&[0xCC, 0xDD], codec.framer.sync_marker());
+
+        // clones start from the configured marker
+        assert_eq!(&[0xAA, 0xBB], codec.clone().framer.sync_marker());
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(SpacePacket::MAX_PAYLOAD_LEN + 1)]
+    fn codec_rejects_packet(#[case] payload_len: usize) {
+        let codec = SpacePacketCodec::new([0xAA, 0xBB]);
+
+        let mut packet = SpacePacket::idle(1);
+        packet.payload = vec![0x55; payload_len];
+        let payload_ptr = packet.payload.as_ptr();
+
+        let mut encoded = BytesMut::new();
+        let error = codec.encode_helper(packet, &mut encoded).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidInput, error.kind());
+        assert!(encoded.is_empty());
+
+        let rejected = crate::RejectedPacket::from_io_error(error).unwrap();
+        assert_eq!(
+            crate::RejectReason::PayloadLength {
+                len: payload_len,
+                max_len: SpacePacket::MAX_PAYLOAD_LEN
+            },
+            rejected.reason
+        );
+        // the payload is handed back without being copied
+        assert_eq!(payload_ptr, rejected.into_packet().payload.as_ptr());
+
+        let other = std::io::Error::new(std::io::ErrorKind::InvalidInput, "other");
+        assert!(crate::RejectedPacket::from_io_error(other).is_err());
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn codec_rejects_header_field(#[case] with_crc: bool) {
+        let codec = SpacePacketCodec::new([0xAA, 0xBB]);
+        // the CRC path masked the fields instead of rejecting them
+        let codec = match with_crc {
+            true => codec.with_crc(CRC_CCITT_FALSE),
+            false => codec,
+        };
+
+        let mut packet = SpacePacket::idle(4);
+        packet.primary_header.apid = 0x800;
+
+        let mut encoded = BytesMut::new();
+        let error = codec
+            .encode_helper(packet.clone(), &mut encoded)
+            .unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidInput, error.kind());
+        assert!(encoded.is_empty());
+
+        let rejected = crate::RejectedPacket::from_io_error(error).unwrap();
+        assert_eq!(
+            crate::RejectReason::HeaderField(crate::FieldOutOfRange {
+                field: "apid",
+                value: 0x800,
+                max: crate::consts::APID_MASK,
+            }),
+            rejected.reason
+        );
+        assert_eq!(packet, rejected.into_packet());
+    }
+
+    #[test]
+    #[cfg(feature = "crc")]
+    fn codec_too_short_for_crc() {
+        let mut codec = SpacePacketCodec::new([0xAA, 0xBB]).with_crc(CRC_CCITT_FALSE);
+
+        // a packet with a 1 byte payload has no room for a CRC
+        // it is followed by the start of the next packet
+        let mut buffer = BytesMut::from(
+            &[
+                0xAA_u8, 0xBB, 0x18, 0x11, 0xC0, 0x00, 0x00, 0x00, 0x42, 0xAA, 0xBB,
+            ][..],
+        );
+        let error = codec.decode_helper(&mut buffer).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+        // the start of the next packet is kept by the framer
+        assert_eq!(2, codec.framer.pending_len());
+        assert!(!codec.framer.is_synchronized());
+
+        // a CRC packet which has not fully arrived waits for more data
+        let packet = SpacePacket::new(
+            0,
+            crate::PacketType::Command,
+            17,
+            crate::GroupingFlag::Unsegm,
+            0,
+            false,
+            vec![0x42],
+        );
+        let mut buffer = BytesMut::from(&packet.encode_crc(&CRC_CCITT_FALSE).unwrap()[..8]);
+        assert!(codec.decode_helper(&mut buffer).unwrap().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "crc")]
+    fn codec_header_crc() {
+        let mut codec = SpacePacketCodec::new([0xAA, 0xBB])
+            .with_crc(CRC_CCITT_FALSE)
+            .with_header_crc(CRC_CCITT_FALSE);
+        let expected = SpacePacket::new(
+            0,
+            crate::PacketType::Command,
+            17,
+            crate::GroupingFlag::Unsegm,
+            0,
+            false,
+            vec![0x42; 10],
+        );
+
+        let mut encoded = BytesMut::new();
+        codec.encode_helper(expected.clone(), &mut encoded).unwrap();
+        // marker, header, header CRC, payload and CRC
+        assert_eq!(2 + 6 + 2 + 10 + 2, encoded.len());
+        assert_eq!(
+            CRC_CCITT_FALSE
+                .checksum(&expected.encode_crc(&CRC_CCITT_FALSE).unwrap()[..6])
+                .to_be_bytes(),
+            encoded[8..10]
+        );
+
+        // a damaged header claiming a huge payload is rejected immediately
+        let mut damaged = encoded.clone();
+        damaged[6] = 0xFF;
+        damaged[7] = 0xFF;
+        let mut buffer = BytesMut::new();
+        buffer.extend_from_slice(&damaged[..10]);
+        buffer.extend_from_slice(&encoded);
+
+        let error = codec.decode_helper(&mut buffer).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+        assert!(!codec.framer.is_synchronized());
+
+        // resynchronizes on the following packet
+        assert_eq!(
+            Some(CompletePacket::Valid(expected)),
+            codec.decode_helper(&mut buffer).unwrap()
+        );
+        assert_eq!(0, codec.framer.pending_len());
+    }
+
+    #[test]
+    fn codec_offsets() {
+        let mut codec = SpacePacketCodec::new([0xAA, 0xBB]).skip_idle(0x7F0);
+
+        let packet = SpacePacket::new(
+            0,
+            crate::PacketType::Command,
+            17,
+            crate::GroupingFlag::Unsegm,
+            0,
+            false,
+            vec![0x42; 5],
+        );
+
+        // garbage, an idle packet, a packet split across reads and a truncated packet
+        let mut stream = vec![0x01, 0xAA, 0x02];
+        stream.extend([0xAA, 0xBB]);
+        stream.extend(SpacePacket::idle_with_apid(0x7F0, 3).encode());
+        stream.extend([0xAA, 0xBB]);
+        let first = stream.len() as u64;
+        stream.extend(packet.encode());
+        stream.extend([0xAA, 0xBB]);
+        stream.extend(&packet.encode()[..6]);
+        stream.extend([0x00; 6]);
+        stream.extend([0xAA, 0xBB]);
+        let second = stream.len() as u64;
+        stream.extend(packet.encode());
+
+        let mut offsets = vec![];
+        let mut buffer = BytesMut::new();
+        for chunk in stream.chunks(5) {
+            buffer.extend_from_slice(chunk);
+            if let Some(recovered) = codec.decode_helper(&mut buffer).unwrap() {
+                #[cfg(feature = "crc")]
+                let recovered = match recovered {
+                    CompletePacket::Valid(packet) => packet,
+                    CompletePacket::InvalidCRC(..) => panic!("Unexpected CRC"),
+                };
+                if recovered == packet {
+                    offsets.push(codec.last_packet_offset().unwrap());
+                }
+            }
+        }
+
+        assert_eq!(vec![first, second], offsets);
+        assert_eq!(stream.len() as u64, codec.stream_offset());
+        // clones start a new stream
+        assert_eq!(0, codec.clone().stream_offset());
+    }
+
+    #[test]
+    fn codec_offset_ignores_skipped_idle() {
+        let mut codec = SpacePacketCodec::new([0xAA, 0xBB]).skip_idle(0x7F0);
+
+        let mut buffer = BytesMut::new();
+        buffer.extend_from_slice(&[0xAA, 0xBB]);
+        buffer.extend_from_slice(&SpacePacket::idle_with_apid(0x42, 3).encode());
+        assert!(codec.decode_helper(&mut buffer).unwrap().is_some());
+        assert_eq!(Some(2), codec.last_packet_offset());
+
+        // an idle packet skipped by a later call does not move the offset
+        buffer.extend_from_slice(&[0xAA, 0xBB]);
+        buffer.extend_from_slice(&SpacePacket::idle_with_apid(0x7F0, 3).encode());
+        assert!(codec.decode_helper(&mut buffer).unwrap().is_none());
+        assert_eq!(Some(2), codec.last_packet_offset());
+        assert_eq!(Some(13), codec.framer().last_packet_offset());
+    }
+
+    #[rstest]
+    #[case::partial_packet(&[0xAA, 0xBB, 0x08, 0x11, 0xC0, 0x00], Some(4))]
+    #[case::partial_marker(&[0x01, 0xAA], None)]
+    #[case::nothing(&[], None)]
+    fn codec_decode_eof(#[case] trailer: &[u8], #[case] partial: Option<usize>) {
+        let packet = SpacePacket::new(
+            0,
+            crate::PacketType::Command,
+            17,
+            crate::GroupingFlag::Unsegm,
+            0,
+            false,
+            vec![0x42; 5],
+        );
+        let mut stream = vec![0xAA, 0xBB];
+        stream.extend(packet.encode());
+        stream.extend(trailer);
+
+        let mut framed = Framed::new(Cursor::new(stream), SpacePacketCodec::new([0xAA, 0xBB]));
+        assert!(executor::block_on(framed.try_next()).unwrap().is_some());
+        match (executor::block_on(framed.try_next()), partial) {
+            (Ok(None), None) => {}
+            (Err(err), Some(partial)) => {
+                assert_eq!(std::io::ErrorKind::UnexpectedEof, err.kind());
+                assert!(
+                    err.to_string().contains(&format!("{partial} bytes")),
+                    "{err}"
+                );
+            }
+            (result, _) => panic!("Unexpected end of stream {result:?}"),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "crc")]
+    fn codec_min_packet_len() {
+        assert_eq!(7, SpacePacketCodec::new([]).framer().min_packet_len());
+        assert_eq!(
+            9,
+            SpacePacketCodec::new([])
+                .with_crc(CRC_CCITT_FALSE)
+                .framer()
+                .min_packet_len()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "crc")]
+    #[allow(deprecated)]
+    fn codec_new_with_optional_crc() {
+        let codec = SpacePacketCodec::new_with_optional_crc([0xAA], None);
+        assert_eq!(7, codec.framer().min_packet_len());
+        let codec = SpacePacketCodec::new_with_optional_crc([0xAA], Some(CRC_CCITT_FALSE));
+        assert_eq!(9, codec.framer().min_packet_len());
+    }
+
+    #[test]
+    fn codec_skip_idle() {
+        let mut codec = SpacePacketCodec::new([0xAA, 0xBB]).skip_idle(0x7F0);
+
+        let expected = SpacePacket::new(
+            0,
+            crate::PacketType::Command,
+            17,
+            crate::GroupingFlag::Unsegm,
+            0,
+            false,
+            vec![0x42],
+        );
+
+        let mut buffer = BytesMut::new();
+        for packet in [
+            SpacePacket::idle_with_apid(0x7F0, 10),
+            SpacePacket::idle_with_apid(0x7F0, 3),
+            expected.clone(),
+        ] {
+            buffer.extend_from_slice(&[0xAA, 0xBB]);
+            buffer.extend_from_slice(&packet.encode());
+        }
+
+        let recovered = codec.decode_helper(&mut buffer).unwrap();
+        #[cfg(feature = "crc")]
+        assert_eq!(Some(CompletePacket::Valid(expected)), recovered);
+        #[cfg(not(feature = "crc"))]
+        assert_eq!(Some(expected), recovered);
+        assert_eq!(0, codec.framer.pending_len());
+    }
+
     #[rstest]
     #[cfg(not(feature = "crc"))]
     fn codec_no_sync() {
@@ -341,7 +1219,7 @@ mod test {
         let buffer: Cursor<&mut Vec<u8>> = Cursor::new(&mut buf);
 
         let (crc, crc2) = crc;
-        let mut framed = Framed::new(buffer, SpacePacketCodec::new([], crc));
+        let mut framed = Framed::new(buffer, codec_with(&[], crc));
 
         executor::block_on(framed.send(expected.clone())).unwrap();
 
@@ -349,7 +1227,7 @@ mod test {
         let mut cursor = framed.into_inner();
         cursor.set_position(0);
 
-        let mut framed = Framed::new(cursor, SpacePacketCodec::new([], crc2));
+        let mut framed = Framed::new(cursor, codec_with(&[], crc2));
 
         let recovered = executor::block_on(framed.try_next()).unwrap().unwrap();
 
@@ -378,7 +1256,7 @@ mod test {
         let buffer: Cursor<&mut Vec<u8>> = Cursor::new(&mut buf);
 
         let (crc, crc2) = crc;
-        let mut framed = Framed::new(buffer, SpacePacketCodec::new([0xAA, 0xBB], crc));
+        let mut framed = Framed::new(buffer, codec_with(&[0xAA, 0xBB], crc));
 
         executor::block_on(framed.send(expected.clone())).unwrap();
 
@@ -386,7 +1264,7 @@ mod test {
         let mut cursor = framed.into_inner();
         cursor.set_position(0);
 
-        let mut framed = Framed::new(cursor, SpacePacketCodec::new([0xAA, 0xBB], crc2));
+        let mut framed = Framed::new(cursor, codec_with(&[0xAA, 0xBB], crc2));
 
         let recovered = executor::block_on(framed.try_next()).unwrap().unwrap();
 
@@ -416,7 +1294,7 @@ mod test {
         buffer.set_position(20);
 
         let (crc, crc2) = crc;
-        let mut framed = Framed::new(buffer, SpacePacketCodec::new([0xAA, 0xBB], crc));
+        let mut framed = Framed::new(buffer, codec_with(&[0xAA, 0xBB], crc));
 
         executor::block_on(framed.send(expected.clone())).unwrap();
 
@@ -427,10 +1305,129 @@ mod test {
         cursor.get_mut()[..20].copy_from_slice((0_u8..20).collect::<Vec<u8>>().as_slice());
         cursor.set_position(0);
 
-        let mut framed = Framed::new(cursor, SpacePacketCodec::new([0xAA, 0xBB], crc2));
+        let mut framed = Framed::new(cursor, codec_with(&[0xAA, 0xBB], crc2));
 
         let recovered = executor::block_on(framed.try_next()).unwrap().unwrap();
 
         assert_eq!(CompletePacket::Valid(expected), recovered)
     }
+
+    #[cfg(all(feature = "cobs", feature = "crc"))]
+    fn cobs_codec() -> SpacePacketCodec {
+        SpacePacketCodec::new([0xAA, 0xBB])
+            .with_crc(CRC_CCITT_FALSE)
+            .with_transparency(Transparency::Cobs { delimiter: 0xFF })
+    }
+
+    #[cfg(all(feature = "cobs", feature = "crc"))]
+    fn cobs_packets() -> Vec<SpacePacket> {
+        (0..4_u16)
+            .map(|count| {
+                SpacePacket::new(
+                    0,
+                    crate::PacketType::Telemetry,
+                    0x7FF,
+                    crate::GroupingFlag::Unsegm,
+                    count,
+                    false,
+                    vec![0xFF; 10 + 100 * usize::from(count)],
+                )
+            })
+            .collect()
+    }
+
+    /// Decode the `stream` in chunks, collecting every packet.
+    #[cfg(all(feature = "cobs", feature = "crc"))]
+    fn cobs_decode(codec: &mut SpacePacketCodec, stream: &[u8]) -> Vec<CompletePacket> {
+        let mut packets = vec![];
+        let mut buffer = BytesMut::new();
+        for chunk in stream.chunks(7) {
+            buffer.extend_from_slice(chunk);
+            while let Some(packet) = codec.decode_helper(&mut buffer).unwrap() {
+                packets.push(packet);
+            }
+        }
+        packets
+    }
+
+    #[test]
+    #[cfg(all(feature = "cobs", feature = "crc"))]
+    fn codec_cobs_roundtrip() {
+        let packets = cobs_packets();
+        let codec = cobs_codec();
+
+        let mut stream = BytesMut::new();
+        for packet in &packets {
+            codec.encode_helper(packet.clone(), &mut stream).unwrap();
+        }
+        // only the delimiters remain
+        assert_eq!(
+            packets.len(),
+            stream.iter().filter(|byte| **byte == 0xFF).count()
+        );
+
+        let mut codec = codec.clone();
+        assert_eq!(
+            packets
+                .into_iter()
+                .map(CompletePacket::Valid)
+                .collect::<Vec<_>>(),
+            cobs_decode(&mut codec, &stream)
+        );
+        assert_eq!(0, codec.corrupted_frame_count());
+    }
+
+    #[rstest]
+    // a code byte no longer matches the group lengths
+    #[case::stuffing(|frame: &mut Vec<u8>| frame[0] ^= 0x10)]
+    // the frame loses bytes in transit
+    #[case::truncated(|frame: &mut Vec<u8>| frame.drain(20..30).for_each(drop))]
+    #[cfg(all(feature = "cobs", feature = "crc"))]
+    fn codec_cobs_corruption(#[case] corrupt: fn(&mut Vec<u8>)) {
+        let packets = cobs_packets();
+        let mut codec = cobs_codec();
+
+        let mut stream = vec![];
+        for (index, packet) in packets.iter().enumerate() {
+            let mut frame = BytesMut::new();
+            codec.encode_helper(packet.clone(), &mut frame).unwrap();
+            let mut frame = frame.to_vec();
+            if index == 1 {
+                corrupt(&mut frame);
+            }
+            stream.extend(frame);
+        }
+
+        // the decoder resynchronizes on the delimiter following the corrupted frame
+        let expected: Vec<_> = [&packets[0], &packets[2], &packets[3]]
+            .into_iter()
+            .cloned()
+            .map(CompletePacket::Valid)
+            .collect();
+        assert_eq!(expected, cobs_decode(&mut codec, &stream));
+        assert_eq!(1, codec.corrupted_frame_count());
+    }
+
+    #[test]
+    #[cfg(all(feature = "cobs", feature = "crc"))]
+    fn codec_cobs_decode_eof() {
+        let packets = cobs_packets();
+        let mut codec = cobs_codec();
+
+        let mut buffer = BytesMut::new();
+        codec
+            .encode_helper(packets[0].clone(), &mut buffer)
+            .unwrap();
+        codec
+            .encode_helper(packets[1].clone(), &mut buffer)
+            .unwrap();
+        // the stream ends before the delimiter of the second frame
+        buffer.truncate(buffer.len() - 1);
+
+        assert!(codec.decode_eof_helper(&mut buffer).unwrap().is_some());
+        let err = codec.decode_eof_helper(&mut buffer).unwrap_err();
+        assert_eq!(std::io::ErrorKind::UnexpectedEof, err.kind());
+        assert!(buffer.is_empty());
+        assert!(codec.decode_eof_helper(&mut buffer).unwrap().is_none());
+    }
 }
@@ -0,0 +1,42 @@
+//! Protocol identifiers and reserved values defined by the CCSDS Blue Books.
+//!
+//! The values are plain data and available regardless of enabled features.
+
+/// The Packet Version Number of the Space Packets defined by CCSDS 133.0-B-2 section 4.1.3.2.
+pub const PACKET_VERSION_NUMBER: u8 = 0;
+
+/// Mask of the 11-bit Application Process Identifier, CCSDS 133.0-B-2 section 4.1.3.3.4.
+pub const APID_MASK: u16 = 0x7FF;
+
+/// The Application Process Identifier reserved for Idle Packets by CCSDS 133.0-B-2
+/// section 4.1.3.3.4.4, all ones.
+///
+/// Missions which designate a different APID for fill data can use
+/// [SpacePacket::idle_with_apid](crate::SpacePacket::idle_with_apid) and
+/// [SpacePacket::is_idle_with_apid](crate::SpacePacket::is_idle_with_apid).
+pub const IDLE_APID: u16 = APID_MASK;
+
+/// Mask of the 14-bit Packet Sequence Count, CCSDS 133.0-B-2 section 4.1.3.4.3.
+pub const SEQUENCE_COUNT_MASK: u16 = 0x3FFF;
+
+/// First Header Pointer of a TM Transfer Frame whose data field holds only idle data,
+/// CCSDS 132.0-B-3 section 4.1.2.7.3.
+pub const FHP_ONLY_IDLE_DATA: u16 = 0b111_1111_1110;
+
+/// First Header Pointer of a TM Transfer Frame in which no packet starts,
+/// CCSDS 132.0-B-3 section 4.1.2.7.3.
+pub const FHP_NO_PACKET_START: u16 = 0b111_1111_1111;
+
+/// The Attached Sync Marker preceding uncoded, convolutional, Reed-Solomon and concatenated
+/// coded TM Transfer Frames, CCSDS 131.0-B-5 section 9.3.
+pub const ASM: [u8; 4] = [0x1A, 0xCF, 0xFC, 0x1D];
+
+/// The Start Sequence of a BCH coded CLTU, CCSDS 231.0-B-4 section 5.2.2.
+pub const CLTU_START_SEQUENCE: [u8; 2] = [0xEB, 0x90];
+
+/// The Tail Sequence of a BCH coded CLTU, CCSDS 231.0-B-4 section 5.2.4.
+pub const CLTU_TAIL_SEQUENCE: [u8; 8] = [0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0x79];
+
+// The reserved values must fit in their 11-bit fields and stay distinct.
+const _: () = assert!(IDLE_APID & APID_MASK == IDLE_APID);
+const _: () = assert!(FHP_ONLY_IDLE_DATA < FHP_NO_PACKET_START && FHP_NO_PACKET_START <= 0x7FF);
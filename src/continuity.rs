@@ -0,0 +1,115 @@
+//! Detection of lost, repeated and reordered packets from their sequence counts.
+
+use std::collections::HashMap;
+
+use crate::{
+    anomaly::{AnomalyKind, AnomalyLog, Layer},
+    consts::SEQUENCE_COUNT_MASK,
+    seq_distance, PrimaryHeader,
+};
+
+/// Follows the sequence count of every APID and reports packets which do not
+/// carry the count following the previous packet of their APID.
+///
+/// Idle Packets carry no meaningful sequence count and should not be observed.
+#[derive(Debug, Clone, Default)]
+pub struct ContinuityChecker {
+    /// The sequence count of the last packet of every APID.
+    last: HashMap<u16, u16>,
+    log: Option<AnomalyLog>,
+}
+impl ContinuityChecker {
+    /// Create a checker which accepts any sequence count for the first packet of every APID.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record every discontinuity as an [AnomalyKind::SequenceGap] in the `log`.
+    pub fn with_anomaly_log(mut self, log: AnomalyLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    /// Check the sequence count of the next packet, received at the byte `offset` of the stream.
+    ///
+    /// Returns the sequence count which was expected if the packet does not follow the previous
+    /// packet of its APID, see [seq_distance] to tell lost packets from repeated ones.
+    pub fn observe(&mut self, header: &PrimaryHeader, offset: u64) -> Option<u16> {
+        let received = header.sequence_count & SEQUENCE_COUNT_MASK;
+        let expected = self
+            .last
+            .insert(header.apid, received)
+            .map(|last| (last + 1) & SEQUENCE_COUNT_MASK)
+            .filter(|expected| seq_distance(*expected, received) != 0)?;
+
+        if let Some(log) = &self.log {
+            log.record(
+                offset,
+                Layer::Continuity,
+                AnomalyKind::SequenceGap {
+                    apid: header.apid,
+                    expected,
+                    received,
+                },
+            );
+        }
+        Some(expected)
+    }
+
+    /// Forget the sequence count of the `apid`, e.g. after a commanded counter reset.
+    pub fn reset(&mut self, apid: u16) {
+        self.last.remove(&apid);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{GroupingFlag, PacketType, SpacePacket};
+
+    fn header(apid: u16, count: u16) -> PrimaryHeader {
+        SpacePacket::new(
+            0,
+            PacketType::Telemetry,
+            apid,
+            GroupingFlag::Unsegm,
+            count,
+            false,
+            vec![0],
+        )
+        .primary_header
+    }
+
+    #[test]
+    fn continuity_gaps() {
+        let log = AnomalyLog::new();
+        let mut checker = ContinuityChecker::new().with_anomaly_log(log.clone());
+
+        let expected: Vec<Option<u16>> = [
+            (1, 16382),
+            (1, 16383),
+            // the count wraps
+            (1, 0),
+            (2, 40),
+            (1, 3),
+            // repeated
+            (1, 3),
+            (2, 41),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(offset, (apid, count))| checker.observe(&header(apid, count), offset as u64))
+        .collect();
+
+        assert_eq!(
+            vec![None, None, None, None, Some(1), Some(4), None],
+            expected
+        );
+        assert_eq!(2, log.summary().sequence_gaps);
+        assert_eq!(4, log.events()[0].offset);
+
+        checker.reset(1);
+        assert_eq!(None, checker.observe(&header(1, 100), 7));
+    }
+}
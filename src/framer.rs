@@ -0,0 +1,1064 @@
+//! A sans-io core which frames [SpacePacket]s from a stream of bytes.
+//!
+//! The [PacketFramer] owns all synchronization marker, length and CRC logic
+//! without depending on any I/O or async runtime. Bytes are pushed into the
+//! framer as they arrive and events are pulled out until it needs more data.
+//! The [codec](crate::codec) is a thin adapter over this core.
+//!
+//! ```
+//! # use spacepacket::{framer::{FramerEvent, PacketFramer}, GroupingFlag, PacketType, SpacePacket};
+//! let packet = SpacePacket::new(0, PacketType::Command, 17, GroupingFlag::Unsegm, 0, false, vec![0x42]);
+//! let mut framer = PacketFramer::new([0xAA, 0xBB]);
+//!
+//! framer.push(&[0xAA, 0xBB]);
+//! assert_eq!(Some(FramerEvent::NeedMore), framer.next_event());
+//!
+//! framer.push(&packet.encode());
+//! assert_eq!(Some(FramerEvent::Packet(packet)), framer.next_event());
+//! assert_eq!(None, framer.next_event());
+//! ```
+
+use std::{borrow::Cow, fmt::Display, sync::Arc};
+
+#[cfg(feature = "crc")]
+use crc::Crc;
+
+#[cfg(feature = "crc")]
+use crate::trailer::{CheckedPacket, Crc16};
+use crate::{
+    limits::{DecodeLimits, Limit, ResourceLimit},
+    FillPattern, PrimaryHeader, SpacePacket,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FramerState {
+    Sync,
+    Data,
+}
+
+/// The reason bytes were discarded by the [PacketFramer].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscardReason {
+    /// The given number of bytes were skipped while searching for the synchronization marker.
+    Unsynchronized(usize),
+    /// A packet declared a length shorter than the minimum packet length.
+    TooShort {
+        /// The declared length of the packet, including the primary header.
+        packet_length: usize,
+        /// The shortest packet the framer can decode.
+        min_packet_length: usize,
+    },
+    /// The header CRC sent with the packet did not match the CRC computed over the header.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    HeaderCrc {
+        /// The header CRC sent with the packet.
+        sent: u16,
+        /// The header CRC computed over the received header.
+        computed: u16,
+    },
+    /// An Idle Packet was skipped.
+    Idle(SpacePacket),
+    /// Bytes were dropped to stay within the [DecodeLimits] of the framer.
+    ResourceLimit(ResourceLimit),
+}
+impl Display for DiscardReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsynchronized(len) => {
+                write!(f, "{len} bytes skipped searching for the sync marker.")
+            }
+            Self::TooShort {
+                packet_length,
+                min_packet_length,
+            } => write!(
+                f,
+                "Packet length {packet_length} is shorter than the minimum {min_packet_length}."
+            ),
+            #[cfg(feature = "crc")]
+            Self::HeaderCrc { sent, computed } => write!(
+                f,
+                "Invalid header CRC. Expected {sent:>#06X} Received {computed:>#06X}"
+            ),
+            Self::Idle(packet) => write!(
+                f,
+                "Idle Packet with APID {:#05X} skipped.",
+                packet.primary_header.apid
+            ),
+            Self::ResourceLimit(limit) => write!(f, "{limit}."),
+        }
+    }
+}
+
+/// The result of pulling the next event from a [PacketFramer].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FramerEvent {
+    /// More bytes must be pushed before the next packet can be framed.
+    NeedMore,
+    /// A complete packet, with any CRC validated and removed.
+    Packet(SpacePacket),
+    /// Bytes were dropped from the stream.
+    Discarded(DiscardReason),
+    /// A complete packet was framed but its CRC is invalid,
+    /// contains the sent and computed CRC values and the received packet without the CRC.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    CrcError(u16, u16, Vec<u8>),
+}
+
+/// Frames [SpacePacket]s following a synchronization marker from pushed bytes.
+///
+/// The framer sweeps through the pushed bytes until the synchronization marker is found,
+/// then waits for the complete packet and returns to searching for the next marker.
+#[derive(Clone)]
+pub struct PacketFramer {
+    sync_marker: Box<[u8]>,
+    state: FramerState,
+    buffer: Vec<u8>,
+    /// Number of bytes at the start of the buffer already processed.
+    consumed: usize,
+    /// Stream offset of the start of the buffer.
+    buffer_offset: u64,
+    last_packet_offset: Option<u64>,
+    #[cfg(feature = "crc")]
+    crc: Option<Arc<dyn Crc16>>,
+    #[cfg(feature = "crc")]
+    header_crc: Option<Arc<dyn Crc16>>,
+    idle_apid: Option<u16>,
+    idle_pattern: Option<Arc<FillPattern>>,
+    corrupted_idle: u64,
+    inter_packet_gap: usize,
+    /// The gap bytes which followed the last packet framed.
+    last_gap: Vec<u8>,
+    limits: DecodeLimits,
+    /// Number of times synchronization was lost since the last push.
+    resyncs: usize,
+    /// Whether bytes were dropped for exceeding [DecodeLimits::max_buffered] and not yet reported.
+    overflowed: bool,
+}
+impl PacketFramer {
+    /// The longest synchronization marker accepted by [Self::try_new].
+    /// The CCSDS Attached Sync Marker is 4 bytes, longer markers usually mean
+    /// the marker and the data were confused.
+    pub const MAX_SYNC_MARKER_LEN: usize = 64;
+
+    /// Create a new framer searching for the given synchronization marker.
+    /// An empty marker expects packets back to back.
+    ///
+    /// The marker should be at most [Self::MAX_SYNC_MARKER_LEN] bytes,
+    /// use [Self::try_new] to check it.
+    pub fn new<T: AsRef<[u8]>>(sync_marker: T) -> Self {
+        Self {
+            sync_marker: sync_marker.as_ref().to_owned().into_boxed_slice(),
+            state: FramerState::Sync,
+            buffer: vec![],
+            consumed: 0,
+            buffer_offset: 0,
+            last_packet_offset: None,
+            #[cfg(feature = "crc")]
+            crc: None,
+            #[cfg(feature = "crc")]
+            header_crc: None,
+            idle_apid: None,
+            idle_pattern: None,
+            corrupted_idle: 0,
+            inter_packet_gap: 0,
+            last_gap: vec![],
+            limits: DecodeLimits::default(),
+            resyncs: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Create a new framer as [Self::new], checking the synchronization marker length.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [std::io::ErrorKind::InvalidInput] if the marker is longer than
+    /// [Self::MAX_SYNC_MARKER_LEN] bytes.
+    pub fn try_new<T: AsRef<[u8]>>(sync_marker: T) -> std::io::Result<Self> {
+        let len = sync_marker.as_ref().len();
+        if len > Self::MAX_SYNC_MARKER_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Synchronization marker must be at most {} bytes but found {len}",
+                    Self::MAX_SYNC_MARKER_LEN
+                ),
+            ));
+        }
+        Ok(Self::new(sync_marker))
+    }
+
+    /// Validate and remove a CRC-16 value appended to every packet using the provided [Crc].
+    /// The CRC is included in the packet length, see [SpacePacket::decode_crc].
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub fn with_crc(self, crc: Crc<u16>) -> Self {
+        self.with_shared_crc(Arc::new(crc))
+    }
+
+    /// As [Self::with_crc] using a shared CRC implementation of any table size.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub fn with_shared_crc(mut self, crc: Arc<dyn Crc16>) -> Self {
+        self.crc = Some(crc);
+        self
+    }
+
+    /// Validate a CRC-16 value inserted between the primary header and the payload
+    /// before waiting for the payload.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub fn with_header_crc(self, crc: Crc<u16>) -> Self {
+        self.with_shared_header_crc(Arc::new(crc))
+    }
+
+    /// As [Self::with_header_crc] using a shared CRC implementation of any table size.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub fn with_shared_header_crc(mut self, crc: Arc<dyn Crc16>) -> Self {
+        self.header_crc = Some(crc);
+        self
+    }
+
+    /// Discard Idle Packets with the given APID,
+    /// usually [IDLE_APID](crate::IDLE_APID).
+    pub fn skip_idle(mut self, idle_apid: u16) -> Self {
+        self.idle_apid = Some(idle_apid);
+        self
+    }
+
+    /// Verify skipped Idle Packets follow the fill `pattern`,
+    /// counting those which do not in [Self::corrupted_idle_count].
+    ///
+    /// Only has an effect together with [Self::skip_idle].
+    pub fn verify_idle(mut self, pattern: FillPattern) -> Self {
+        self.idle_pattern = Some(Arc::new(pattern));
+        self
+    }
+
+    /// The number of skipped Idle Packets which did not follow the fill pattern
+    /// given to [Self::verify_idle].
+    pub fn corrupted_idle_count(&self) -> u64 {
+        self.corrupted_idle
+    }
+
+    /// Skip exactly `gap` bytes following every framed packet, outside of its
+    /// Packet Data Length, e.g. a proprietary tag appended by another system.
+    ///
+    /// A packet is only framed once its gap bytes have arrived, they are available from
+    /// [Self::last_gap] alongside the packet. Gap bytes are never searched for the
+    /// synchronization marker, even if they contain it; the search resumes after the gap.
+    /// No gap is skipped after packets discarded for their length or header CRC, since
+    /// their end is not trusted.
+    pub fn with_inter_packet_gap(mut self, gap: usize) -> Self {
+        self.inter_packet_gap = gap;
+        self
+    }
+
+    /// The gap bytes following the last packet framed, see [Self::with_inter_packet_gap].
+    pub fn last_gap(&self) -> &[u8] {
+        &self.last_gap
+    }
+
+    /// The synchronization marker preceding every packet.
+    pub fn sync_marker(&self) -> &[u8] {
+        &self.sync_marker
+    }
+
+    /// Search for the `sync_marker` instead from now on, keeping the pending bytes,
+    /// e.g. when the marker of a link is not known for sure.
+    pub fn set_sync_marker<T: AsRef<[u8]>>(&mut self, sync_marker: T) {
+        self.sync_marker = sync_marker.as_ref().to_owned().into_boxed_slice();
+        self.state = FramerState::Sync;
+    }
+
+    /// The CRC appended to every packet, if any.
+    #[cfg(all(feature = "crc", any(feature = "async-codec", feature = "tokio-codec")))]
+    pub(crate) fn crc(&self) -> Option<&dyn Crc16> {
+        self.crc.as_deref()
+    }
+
+    /// The CRC inserted after every primary header, if any.
+    #[cfg(all(feature = "crc", any(feature = "async-codec", feature = "tokio-codec")))]
+    pub(crate) fn header_crc(&self) -> Option<&dyn Crc16> {
+        self.header_crc.as_deref()
+    }
+
+    /// The number of bytes of header CRC following the primary header.
+    pub fn header_crc_len(&self) -> usize {
+        #[cfg(feature = "crc")]
+        if self.header_crc.is_some() {
+            return std::mem::size_of::<u16>();
+        }
+        0
+    }
+
+    /// The shortest packet this framer can decode, including any CRC.
+    pub fn min_packet_len(&self) -> usize {
+        #[cfg(feature = "crc")]
+        if self.crc.is_some() {
+            return SpacePacket::MIN_WIRE_LEN + std::mem::size_of::<u16>();
+        }
+        SpacePacket::MIN_WIRE_LEN
+    }
+
+    /// Whether the synchronization marker of the next packet has been found.
+    pub fn is_synchronized(&self) -> bool {
+        self.state == FramerState::Data
+    }
+
+    /// The number of pushed bytes not yet consumed by an event.
+    pub fn pending_len(&self) -> usize {
+        self.buffer.len() - self.consumed
+    }
+
+    /// The number of bytes consumed from the stream since the framer was created or reset,
+    /// including bytes discarded while searching for the synchronization marker.
+    pub fn stream_offset(&self) -> u64 {
+        self.buffer_offset + self.consumed as u64
+    }
+
+    /// The stream offset of the primary header of the last packet framed,
+    /// reported by a [FramerEvent::Packet], [FramerEvent::CrcError] or [DiscardReason::Idle].
+    ///
+    /// The offset remains valid until the next such event and is the position of the
+    /// packet in a raw recording of the stream, after its synchronization marker.
+    pub fn last_packet_offset(&self) -> Option<u64> {
+        self.last_packet_offset
+    }
+
+    /// Cap the resources spent on hostile input, see [DecodeLimits].
+    ///
+    /// Packets longer than [DecodeLimits::max_item_len] are discarded after their header,
+    /// the oldest pending bytes beyond [DecodeLimits::max_buffered] are dropped on push, and
+    /// once synchronization was lost more than [DecodeLimits::max_resyncs] times the rest
+    /// of the pushed bytes are dropped. Each is reported as [DiscardReason::ResourceLimit].
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The resource limits of this framer.
+    pub fn limits(&self) -> &DecodeLimits {
+        &self.limits
+    }
+
+    /// Drop all pending bytes and return to searching for the synchronization marker
+    /// at the start of a new stream.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.consumed = 0;
+        self.buffer_offset = 0;
+        self.last_packet_offset = None;
+        self.corrupted_idle = 0;
+        self.last_gap.clear();
+        self.state = FramerState::Sync;
+        self.resyncs = 0;
+        self.overflowed = false;
+    }
+
+    /// Drop the pending bytes and search for the synchronization marker again,
+    /// keeping the stream offsets, e.g. at a frame boundary known from an outer layer.
+    ///
+    /// Returns the number of bytes dropped.
+    pub fn discard_pending(&mut self) -> usize {
+        let discarded = self.pending_len();
+        self.consumed = self.buffer.len();
+        self.state = FramerState::Sync;
+        discarded
+    }
+
+    /// Append received bytes to the stream.
+    pub fn push(&mut self, bytes: &[u8]) {
+        // reclaim the space of consumed bytes before growing, an empty push does not grow
+        // so the pending bytes are not moved, e.g. while a decoder drains a large buffer
+        if self.consumed > 0 && !bytes.is_empty() {
+            self.compact();
+        }
+        if !bytes.is_empty() {
+            self.resyncs = 0;
+        }
+        self.buffer.extend_from_slice(bytes);
+
+        let excess = self
+            .pending_len()
+            .saturating_sub(self.limits.max_buffered());
+        if excess > 0 {
+            self.consumed += excess;
+            self.state = FramerState::Sync;
+            self.overflowed = true;
+            self.compact();
+            self.buffer.shrink_to(self.limits.max_buffered());
+        }
+    }
+
+    fn compact(&mut self) {
+        self.buffer.drain(..self.consumed);
+        self.buffer_offset += self.consumed as u64;
+        self.consumed = 0;
+    }
+
+    /// Return to searching for the synchronization marker after a damaged packet.
+    fn lose_sync(&mut self) {
+        self.state = FramerState::Sync;
+        self.resyncs += 1;
+    }
+
+    fn find_sync(&self) -> Option<usize> {
+        if self.sync_marker.is_empty() {
+            return Some(0);
+        }
+        // scan for the first byte of the marker before comparing the whole marker,
+        // noise rarely matches the first byte so most positions are rejected quickly
+        let pending = &self.buffer[self.consumed..];
+        let first = self.sync_marker[0];
+        let mut start = 0;
+        while let Some(index) = pending[start..].iter().position(|byte| *byte == first) {
+            let index = start + index;
+            if pending.len() - index < self.sync_marker.len() {
+                return None;
+            }
+            if pending[index..].starts_with(&self.sync_marker) {
+                return Some(index);
+            }
+            start = index + 1;
+        }
+        None
+    }
+
+    /// Process the pending bytes up to the next event.
+    ///
+    /// Returns `None` once every pushed byte has been consumed and
+    /// [FramerEvent::NeedMore] while a partial marker or packet is pending.
+    pub fn next_event(&mut self) -> Option<FramerEvent> {
+        if self.overflowed {
+            self.overflowed = false;
+            return Some(FramerEvent::Discarded(DiscardReason::ResourceLimit(
+                ResourceLimit {
+                    which: Limit::BufferedBytes,
+                    limit: self.limits.max_buffered(),
+                },
+            )));
+        }
+        if self.pending_len() == 0 {
+            return None;
+        }
+        Some(self.framer_event())
+    }
+
+    fn framer_event(&mut self) -> FramerEvent {
+        if self.state == FramerState::Sync && self.resyncs > self.limits.max_resyncs() {
+            // drop the rest of the pushed bytes instead of sweeping them for more false markers
+            self.consumed = self.buffer.len();
+            return FramerEvent::Discarded(DiscardReason::ResourceLimit(ResourceLimit {
+                which: Limit::ResyncAttempts,
+                limit: self.limits.max_resyncs(),
+            }));
+        }
+        if self.state == FramerState::Sync {
+            match self.find_sync() {
+                Some(index) => {
+                    self.consumed += index + self.sync_marker.len();
+                    self.state = FramerState::Data;
+                    if index > 0 {
+                        return FramerEvent::Discarded(DiscardReason::Unsynchronized(index));
+                    }
+                }
+                None => {
+                    // There is no sync marker in the pending bytes
+                    // but keep sync_marker.len() - 1 bytes
+                    // in case syncs cross push boundaries
+                    let skipped = self
+                        .pending_len()
+                        .saturating_sub(self.sync_marker.len() - 1);
+                    self.consumed += skipped;
+                    return match skipped {
+                        0 => FramerEvent::NeedMore,
+                        skipped => FramerEvent::Discarded(DiscardReason::Unsynchronized(skipped)),
+                    };
+                }
+            }
+        }
+
+        let pending = &self.buffer[self.consumed..];
+        let min_packet_length = self.min_packet_len();
+        let header_crc_len = self.header_crc_len();
+        if pending.len() < min_packet_length + header_crc_len {
+            // Not enough bytes for a packet
+            return FramerEvent::NeedMore;
+        }
+
+        #[cfg(feature = "crc")]
+        if let Some(crc) = &self.header_crc {
+            let (header, rest) = pending.split_at(PrimaryHeader::WIRE_LEN);
+            let sent = u16::from_be_bytes([rest[0], rest[1]]);
+            let computed = crc.checksum(header);
+            if sent != computed {
+                // reject the header before waiting for its payload
+                // the header bytes are kept in case they contain the next sync marker
+                self.lose_sync();
+                return FramerEvent::Discarded(DiscardReason::HeaderCrc { sent, computed });
+            }
+        }
+
+        // check the length marker
+        // the length field is CCSDS length - 1
+        // add the header length as well
+        // at most SpacePacket::MAX_PAYLOAD_LEN + 6 which does not fit in a u16
+        let packet_length = usize::from(u16::from_be_bytes(
+            pending[PrimaryHeader::LENGTH_FIELD_RANGE]
+                .try_into()
+                .unwrap(),
+        )) + 1
+            + PrimaryHeader::WIRE_LEN;
+
+        if packet_length < min_packet_length {
+            // the declared length cannot hold the payload and CRC
+            // discard the packet and return to searching for sync
+            self.consumed += packet_length + header_crc_len;
+            self.lose_sync();
+            return FramerEvent::Discarded(DiscardReason::TooShort {
+                packet_length,
+                min_packet_length,
+            });
+        }
+
+        if let Err(limit) = self.limits.check_item_len(packet_length) {
+            // reject the packet before buffering its payload
+            // the header bytes are kept in case they contain the next sync marker
+            self.lose_sync();
+            return FramerEvent::Discarded(DiscardReason::ResourceLimit(limit));
+        }
+
+        let wire_length = packet_length + header_crc_len;
+        if pending.len() < wire_length + self.inter_packet_gap {
+            // full packet and its gap have not yet arrived
+            self.buffer
+                .reserve(wire_length + self.inter_packet_gap - pending.len());
+            return FramerEvent::NeedMore;
+        }
+
+        // drop the header CRC between the header and payload,
+        // without one the packet is decoded straight from the buffer
+        let data = match header_crc_len {
+            0 => Cow::Borrowed(&pending[..wire_length]),
+            _ => Cow::Owned(
+                [
+                    &pending[..PrimaryHeader::WIRE_LEN],
+                    &pending[PrimaryHeader::WIRE_LEN + header_crc_len..wire_length],
+                ]
+                .concat(),
+            ),
+        };
+        self.last_gap.clear();
+        self.last_gap
+            .extend_from_slice(&pending[wire_length..wire_length + self.inter_packet_gap]);
+        self.last_packet_offset = Some(self.stream_offset());
+        self.consumed += wire_length + self.inter_packet_gap;
+        // We know there is a packet's length of data whether or not it is valid
+        // Return to check for sync
+        self.state = FramerState::Sync;
+
+        // unwraping is safe here because data holds the complete packet
+        #[cfg(feature = "crc")]
+        let packet = match &self.crc {
+            Some(crc) => {
+                match SpacePacket::decode_with_trailer(&mut &data[..], crc.as_ref()).unwrap() {
+                    CheckedPacket::Valid(packet) => packet,
+                    CheckedPacket::Invalid {
+                        sent,
+                        computed,
+                        data,
+                    } => {
+                        return FramerEvent::CrcError(
+                            u16::from_be_bytes([sent[0], sent[1]]),
+                            u16::from_be_bytes([computed[0], computed[1]]),
+                            data,
+                        )
+                    }
+                }
+            }
+            None => SpacePacket::decode(&mut &data[..]).unwrap(),
+        };
+        #[cfg(not(feature = "crc"))]
+        let packet = SpacePacket::decode(&mut &data[..]).unwrap();
+
+        match self.idle_apid {
+            Some(apid) if packet.is_idle_with_apid(apid) => {
+                if let Some(pattern) = &self.idle_pattern {
+                    if packet.verify_idle_pattern(pattern).is_err() {
+                        self.corrupted_idle += 1;
+                    }
+                }
+                FramerEvent::Discarded(DiscardReason::Idle(packet))
+            }
+            _ => FramerEvent::Packet(packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_util::packet;
+
+    #[cfg(feature = "crc")]
+    use crc::CRC_16_IBM_3740;
+    use rstest::rstest;
+
+    #[cfg(feature = "crc")]
+    const CRC_CCITT_FALSE: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+
+    const SYNC_MARKER: [u8; 4] = crate::consts::ASM;
+
+    /// A stream of packets mixed with garbage, damaged packets and idle packets.
+    fn stream(sync_marker: &[u8]) -> Vec<u8> {
+        let mut stream = vec![0x00, 0x1A, 0xCF, 0xFF];
+        for packet in [packet(17, 3, 10), packet(18, 3, 1), packet(0x7FF, 3, 300)] {
+            stream.extend(sync_marker);
+            stream.extend(packet.encode());
+        }
+        // a packet which ends in the next marker
+        stream.extend(sync_marker);
+        stream.extend(&packet(19, 3, 20).encode()[..10]);
+        for packet in [packet(20, 3, 40), packet(21, 3, 6)] {
+            stream.extend(sync_marker);
+            stream.extend(packet.encode());
+        }
+        stream.extend([0x1A, 0xCF]);
+        stream
+    }
+
+    /// Pull events until the framer needs more data.
+    fn drain(framer: &mut PacketFramer, events: &mut Vec<FramerEvent>) {
+        while let Some(event) = framer.next_event() {
+            match event {
+                FramerEvent::NeedMore => return,
+                event => events.push(event),
+            }
+        }
+    }
+
+    /// Events pushing the stream in chunks of `chunk_len`, with the total
+    /// unsynchronized bytes, which are reported in chunks, summed up.
+    fn events(
+        mut framer: PacketFramer,
+        stream: &[u8],
+        chunk_len: usize,
+    ) -> (Vec<FramerEvent>, usize) {
+        let mut events = vec![];
+        for chunk in stream.chunks(chunk_len) {
+            framer.push(chunk);
+            drain(&mut framer, &mut events);
+        }
+
+        let mut unsynchronized = 0;
+        events.retain(|event| match event {
+            FramerEvent::Discarded(DiscardReason::Unsynchronized(len)) => {
+                unsynchronized += len;
+                false
+            }
+            _ => true,
+        });
+        (events, unsynchronized)
+    }
+
+    #[rstest]
+    fn framer_byte_at_a_time(
+        #[values(&[][..], &SYNC_MARKER[..])] sync_marker: &[u8],
+        #[values(false, true)] skip_idle: bool,
+    ) {
+        let mut framer = PacketFramer::new(sync_marker);
+        if skip_idle {
+            framer = framer.skip_idle(0x7FF);
+        }
+
+        let stream = stream(sync_marker);
+        let bulk = events(framer.clone(), &stream, stream.len());
+        for chunk_len in [1, 2, 3, 7, 64] {
+            assert_eq!(bulk, events(framer.clone(), &stream, chunk_len));
+        }
+    }
+
+    #[test]
+    fn framer_repeated_marker_start() {
+        let mut framer = PacketFramer::new(SYNC_MARKER);
+        framer.push(&[0x1A, 0x1A, 0xCF, 0x1A, 0x1A, 0xCF, 0xFC, 0x1D]);
+        framer.push(&packet(17, 3, 3).encode());
+
+        assert_eq!(
+            Some(FramerEvent::Discarded(DiscardReason::Unsynchronized(4))),
+            framer.next_event()
+        );
+        // empty pushes between events leave the pending bytes in place
+        framer.push(&[]);
+        assert_eq!(8, framer.stream_offset());
+        assert_eq!(
+            Some(FramerEvent::Packet(packet(17, 3, 3))),
+            framer.next_event()
+        );
+        assert_eq!(Some(8), framer.last_packet_offset());
+        assert_eq!(None, framer.next_event());
+    }
+
+    #[test]
+    fn framer_bulk_drain() {
+        // noise full of the first marker byte between packets, pushed as one chunk
+        let mut stream = vec![];
+        for apid in 0..50 {
+            stream.extend([0x1A; 7]);
+            stream.extend(SYNC_MARKER);
+            stream.extend(packet(apid, 3, 20 + usize::from(apid)).encode());
+        }
+        let mut framer = PacketFramer::new(SYNC_MARKER);
+        framer.push(&stream);
+
+        let mut packets = vec![];
+        loop {
+            // the empty pushes of a draining decoder
+            framer.push(&[]);
+            match framer.next_event() {
+                Some(FramerEvent::Packet(packet)) => packets.push(packet),
+                Some(FramerEvent::Discarded(DiscardReason::Unsynchronized(7))) => {}
+                other => {
+                    assert_eq!(None, other);
+                    break;
+                }
+            }
+        }
+        assert_eq!(
+            (0..50)
+                .map(|apid| packet(apid, 3, 20 + usize::from(apid)))
+                .collect::<Vec<_>>(),
+            packets
+        );
+        assert_eq!(0, framer.pending_len());
+    }
+
+    #[test]
+    #[cfg(feature = "crc")]
+    fn framer_header_crc_removed() {
+        let encoded = packet(17, 3, 5).encode();
+        let header_crc = CRC_CCITT_FALSE.checksum(&encoded[..PrimaryHeader::WIRE_LEN]);
+
+        let mut framer = PacketFramer::new(SYNC_MARKER).with_header_crc(CRC_CCITT_FALSE);
+        framer.push(&SYNC_MARKER);
+        framer.push(&encoded[..PrimaryHeader::WIRE_LEN]);
+        framer.push(&header_crc.to_be_bytes());
+        framer.push(&encoded[PrimaryHeader::WIRE_LEN..]);
+        assert_eq!(
+            Some(FramerEvent::Packet(packet(17, 3, 5))),
+            framer.next_event()
+        );
+    }
+
+    #[test]
+    fn framer_events() {
+        let framer = PacketFramer::new(SYNC_MARKER).skip_idle(0x7FF);
+        let stream = stream(&SYNC_MARKER);
+        let (events, unsynchronized) = events(framer, &stream, stream.len());
+
+        // the truncated packet swallows 16 bytes of the following marker and packet
+        // the rest of which is skipped along with the leading garbage
+        // the 2 trailing bytes may start the next marker
+        assert_eq!(4 + (4 + 46 - 16), unsynchronized);
+        assert_eq!(5, events.len());
+        assert_eq!(
+            [
+                FramerEvent::Packet(packet(17, 3, 10)),
+                FramerEvent::Packet(packet(18, 3, 1)),
+                FramerEvent::Discarded(DiscardReason::Idle(packet(0x7FF, 3, 300))),
+            ],
+            events[..3]
+        );
+        assert!(
+            matches!(&events[3], FramerEvent::Packet(packet) if packet.primary_header.apid == 19)
+        );
+        assert_eq!(FramerEvent::Packet(packet(21, 3, 6)), events[4]);
+    }
+
+    #[rstest]
+    fn framer_offsets(#[values(1, 5, 1000)] chunk_len: usize) {
+        let mut framer = PacketFramer::new(SYNC_MARKER);
+
+        // garbage, a partial marker, a good packet, a partial packet, garbage and a good packet
+        let mut stream = vec![0x00, 0x01, 0x1A, 0xCF, 0x02];
+        let mut expected = vec![];
+        stream.extend(SYNC_MARKER);
+        expected.push((stream.len() as u64, packet(17, 3, 10)));
+        stream.extend(packet(17, 3, 10).encode());
+        stream.extend(SYNC_MARKER);
+        stream.extend(&packet(18, 3, 40).encode()[..6]);
+        stream.extend([0x1A; 60]);
+        stream.extend(SYNC_MARKER);
+        expected.push((stream.len() as u64, packet(19, 3, 3)));
+        stream.extend(packet(19, 3, 3).encode());
+
+        let mut recovered = vec![];
+        for chunk in stream.chunks(chunk_len) {
+            framer.push(chunk);
+            while let Some(event) = framer.next_event() {
+                match event {
+                    FramerEvent::NeedMore => break,
+                    // the partial packet swallows the start of the garbage
+                    FramerEvent::Packet(packet) if packet.primary_header.apid == 18 => continue,
+                    FramerEvent::Packet(packet) => {
+                        recovered.push((framer.last_packet_offset().unwrap(), packet))
+                    }
+                    _ => continue,
+                }
+            }
+        }
+
+        assert_eq!(expected, recovered);
+        for (offset, packet) in recovered {
+            let start = offset as usize;
+            assert_eq!(
+                packet.encode(),
+                stream[start..start + packet.encode().len()]
+            );
+        }
+        assert_eq!(stream.len() as u64, framer.stream_offset());
+
+        framer.reset();
+        assert_eq!(0, framer.stream_offset());
+        assert_eq!(None, framer.last_packet_offset());
+    }
+
+    #[test]
+    fn framer_verify_idle() {
+        let pattern = FillPattern::Counting { start: 0 };
+        let mut framer = PacketFramer::new(SYNC_MARKER)
+            .skip_idle(0x7FF)
+            .verify_idle(FillPattern::Counting { start: 0 });
+
+        let mut corrupted = SpacePacket::idle_with_pattern(0x7FF, 20, &pattern).encode();
+        corrupted[15] ^= 0x10;
+        for bytes in [
+            SpacePacket::idle_with_pattern(0x7FF, 20, &pattern).encode(),
+            corrupted,
+            packet(0x7FF, 3, 20).encode(),
+            SpacePacket::idle(20).encode(),
+        ] {
+            framer.push(&SYNC_MARKER);
+            framer.push(&bytes);
+        }
+
+        let mut events = vec![];
+        drain(&mut framer, &mut events);
+        assert_eq!(4, events.len());
+        // the counting packet matches, the 0x55 fill does not
+        assert_eq!(2, framer.corrupted_idle_count());
+
+        framer.reset();
+        assert_eq!(0, framer.corrupted_idle_count());
+    }
+
+    #[rstest]
+    fn framer_inter_packet_gap(#[values(1, 3, 1000)] chunk_len: usize) {
+        let framer = PacketFramer::new(SYNC_MARKER).with_inter_packet_gap(4);
+
+        // the gap after the second packet contains a sync marker followed by garbage
+        // which would alias as a packet if the gap was searched
+        let tags: [[u8; 4]; 3] = [[0xDE, 0xAD, 0xBE, 0xEF], SYNC_MARKER, [0x00; 4]];
+        let packets = [packet(17, 3, 10), packet(18, 3, 1), packet(19, 3, 30)];
+        let mut stream = vec![];
+        for (packet, tag) in packets.iter().zip(tags) {
+            stream.extend(SYNC_MARKER);
+            stream.extend(packet.encode());
+            stream.extend(tag);
+            if tag == SYNC_MARKER {
+                stream.extend([0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x42]);
+            }
+        }
+
+        let mut framer = framer;
+        let mut recovered = vec![];
+        for chunk in stream.chunks(chunk_len) {
+            framer.push(chunk);
+            while let Some(event) = framer.next_event() {
+                match event {
+                    FramerEvent::NeedMore => break,
+                    FramerEvent::Packet(packet) => {
+                        recovered.push((packet, framer.last_gap().to_vec()))
+                    }
+                    _ => continue,
+                }
+            }
+        }
+
+        assert_eq!(
+            packets
+                .into_iter()
+                .zip(tags.map(|tag| tag.to_vec()))
+                .collect::<Vec<_>>(),
+            recovered
+        );
+        assert_eq!(stream.len() as u64, framer.stream_offset());
+    }
+
+    #[test]
+    fn framer_reset() {
+        let mut framer = PacketFramer::new(SYNC_MARKER);
+        framer.push(&SYNC_MARKER);
+        framer.push(&[0x00]);
+        assert_eq!(Some(FramerEvent::NeedMore), framer.next_event());
+        assert!(framer.is_synchronized());
+        assert_eq!(1, framer.pending_len());
+
+        framer.reset();
+        assert!(!framer.is_synchronized());
+        assert_eq!(0, framer.pending_len());
+    }
+
+    #[test]
+    fn framer_discard_pending() {
+        let mut framer = PacketFramer::new(SYNC_MARKER);
+        framer.push(&SYNC_MARKER);
+        framer.push(&[0x00, 0x01]);
+        assert_eq!(Some(FramerEvent::NeedMore), framer.next_event());
+
+        assert_eq!(2, framer.discard_pending());
+        assert!(!framer.is_synchronized());
+        assert_eq!(0, framer.pending_len());
+        assert_eq!((SYNC_MARKER.len() + 2) as u64, framer.stream_offset());
+    }
+
+    #[test]
+    #[cfg(feature = "crc")]
+    fn framer_crc() {
+        let mut framer = PacketFramer::new([]).with_crc(CRC_CCITT_FALSE);
+        assert_eq!(9, framer.min_packet_len());
+
+        let mut damaged = packet(17, 3, 10).encode_crc(&CRC_CCITT_FALSE).unwrap();
+        damaged[10] ^= 0xFF;
+        framer.push(&damaged);
+        framer.push(&packet(17, 3, 10).encode_crc(&CRC_CCITT_FALSE).unwrap());
+        // the short packet is only judged once a CRC packet's worth of bytes arrived
+        framer.push(&packet(17, 3, 1).encode());
+        framer.push(&[0x00, 0x00]);
+
+        assert!(matches!(
+            framer.next_event(),
+            Some(FramerEvent::CrcError(..))
+        ));
+        assert_eq!(
+            Some(FramerEvent::Packet(packet(17, 3, 10))),
+            framer.next_event()
+        );
+        assert_eq!(
+            Some(FramerEvent::Discarded(DiscardReason::TooShort {
+                packet_length: 7,
+                min_packet_length: 9
+            })),
+            framer.next_event()
+        );
+        assert_eq!(Some(FramerEvent::NeedMore), framer.next_event());
+    }
+
+    fn limit(which: Limit, limit: usize) -> Option<FramerEvent> {
+        Some(FramerEvent::Discarded(DiscardReason::ResourceLimit(
+            ResourceLimit { which, limit },
+        )))
+    }
+
+    #[test]
+    fn framer_limit_item_len() {
+        let mut framer = PacketFramer::new(SYNC_MARKER)
+            .with_limits(DecodeLimits::default().with_max_item_len(20));
+
+        framer.push(&SYNC_MARKER);
+        framer.push(&packet(17, 3, 15).encode());
+        framer.push(&SYNC_MARKER);
+        framer.push(&packet(17, 3, 14).encode());
+
+        assert_eq!(limit(Limit::ItemLen, 20), framer.next_event());
+        // the rejected packet is swept for the next marker
+        assert_eq!(
+            Some(FramerEvent::Discarded(DiscardReason::Unsynchronized(21))),
+            framer.next_event()
+        );
+        assert_eq!(
+            Some(FramerEvent::Packet(packet(17, 3, 14))),
+            framer.next_event()
+        );
+        assert_eq!(None, framer.next_event());
+    }
+
+    #[test]
+    fn framer_limit_buffered() {
+        let mut framer = PacketFramer::new(SYNC_MARKER)
+            .with_limits(DecodeLimits::default().with_max_buffered(64));
+
+        // the oldest bytes are dropped, including the header of the first packet
+        framer.push(&SYNC_MARKER);
+        framer.push(&packet(17, 3, 100).encode());
+        assert_eq!(64, framer.pending_len());
+        assert_eq!(limit(Limit::BufferedBytes, 64), framer.next_event());
+        assert_eq!(
+            Some(FramerEvent::Discarded(DiscardReason::Unsynchronized(61))),
+            framer.next_event()
+        );
+
+        framer.push(&SYNC_MARKER);
+        framer.push(&packet(17, 3, 10).encode());
+        let mut events = vec![];
+        drain(&mut framer, &mut events);
+        assert_eq!(
+            vec![
+                FramerEvent::Discarded(DiscardReason::Unsynchronized(3)),
+                FramerEvent::Packet(packet(17, 3, 10))
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn framer_limit_resyncs() {
+        let mut framer = PacketFramer::new(SYNC_MARKER).with_limits(
+            DecodeLimits::default()
+                .with_max_resyncs(2)
+                .with_max_item_len(64),
+        );
+
+        // false markers followed by headers declaring long packets
+        let mut chunk = vec![];
+        for _ in 0..4 {
+            chunk.extend(SYNC_MARKER);
+            chunk.extend([0x08, 0x42, 0xC0, 0x00, 0x10, 0x00]);
+        }
+        chunk.extend(SYNC_MARKER);
+        chunk.extend(packet(17, 3, 10).encode());
+        framer.push(&chunk);
+
+        let mut events = vec![];
+        drain(&mut framer, &mut events);
+        let limits = events
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    FramerEvent::Discarded(DiscardReason::ResourceLimit(_))
+                )
+            })
+            .count();
+        // two resyncs are allowed, the third drops the rest of the chunk
+        assert_eq!(4, limits);
+        assert_eq!(
+            limit(Limit::ResyncAttempts, 2).unwrap(),
+            events[events.len() - 1]
+        );
+        assert_eq!(0, framer.pending_len());
+
+        // the count starts over with the next chunk
+        framer.push(&SYNC_MARKER);
+        framer.push(&packet(17, 3, 10).encode());
+        assert_eq!(
+            Some(FramerEvent::Packet(packet(17, 3, 10))),
+            framer.next_event()
+        );
+    }
+}
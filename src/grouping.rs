@@ -0,0 +1,671 @@
+//! The packet sequence control rules of segmented user data, per APID.
+//!
+//! A group is a [GroupingFlag::First] packet, any number of [GroupingFlag::Interm] packets and a
+//! [GroupingFlag::Last] packet. [GroupingFlag::Unsegm] packets stand on their own between groups.
+//! The [GroupingValidator] tracks the open group of every APID and reports illegal transitions,
+//! the [PacketReassembler] concatenates the payloads of every group into one message.
+//!
+//! ```
+//! # use spacepacket::{grouping::{GroupState, GroupingValidator, GroupingViolation}, GroupingFlag};
+//! let mut validator = GroupingValidator::new();
+//! validator.observe(17, GroupingFlag::First).unwrap();
+//! validator.observe(17, GroupingFlag::Interm).unwrap();
+//! assert_eq!(GroupState::Open, validator.state(17));
+//!
+//! // groups of different APIDs interleave
+//! assert_eq!(
+//!     Err(GroupingViolation::NoOpenGroup { apid: 18, flag: GroupingFlag::Last }),
+//!     validator.observe(18, GroupingFlag::Last)
+//! );
+//! validator.observe(17, GroupingFlag::Last).unwrap();
+//! assert_eq!(GroupState::Closed, validator.state(17));
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{consts::SEQUENCE_COUNT_MASK, seq_distance, GroupingFlag, SpacePacket};
+
+/// Whether a group of packets is open on an APID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GroupState {
+    /// No group is open, the next packet must be [GroupingFlag::First] or [GroupingFlag::Unsegm].
+    #[default]
+    Closed,
+    /// A [GroupingFlag::First] packet was observed and the group is not yet complete.
+    Open,
+}
+
+/// How a [GroupingFlag::First] packet arriving while a group is open is treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    /// Report a [GroupingViolation::FirstWhileOpen].
+    #[default]
+    Error,
+    /// Silently abort the open group.
+    ImplicitAbort,
+}
+
+/// An illegal grouping flag transition. The observed packet still takes effect,
+/// see [GroupingValidator::observe].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupingViolation {
+    /// A [GroupingFlag::Interm] or [GroupingFlag::Last] packet arrived without an open group.
+    NoOpenGroup {
+        /// The APID of the packet.
+        apid: u16,
+        /// The grouping flag of the packet.
+        flag: GroupingFlag,
+    },
+    /// A [GroupingFlag::First] packet arrived while a group was open.
+    FirstWhileOpen {
+        /// The APID of the packet.
+        apid: u16,
+    },
+    /// A [GroupingFlag::Unsegm] packet arrived while a group was open.
+    UnsegmWhileOpen {
+        /// The APID of the packet.
+        apid: u16,
+    },
+}
+impl Display for GroupingViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoOpenGroup { apid, flag } => {
+                write!(
+                    f,
+                    "{flag:?} packet on APID {apid:#05X} without a First packet"
+                )
+            }
+            Self::FirstWhileOpen { apid } => {
+                write!(f, "First packet on APID {apid:#05X} while a group is open")
+            }
+            Self::UnsegmWhileOpen { apid } => {
+                write!(f, "Unsegm packet on APID {apid:#05X} while a group is open")
+            }
+        }
+    }
+}
+impl std::error::Error for GroupingViolation {}
+impl From<GroupingViolation> for std::io::Error {
+    fn from(err: GroupingViolation) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Tracks the [GroupState] of every APID and checks the grouping flag of each packet against it.
+#[derive(Debug, Clone, Default)]
+pub struct GroupingValidator {
+    /// The APIDs with an open group.
+    open: HashSet<u16>,
+    restart: RestartPolicy,
+}
+impl GroupingValidator {
+    /// Create a validator with every APID [GroupState::Closed].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Choose how a new group starting while a group is open is treated.
+    pub fn with_restart_policy(mut self, restart: RestartPolicy) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    /// Check the `flag` of the next packet of the `apid` and update its state.
+    ///
+    /// The packet takes effect even when the transition is illegal, so the validator
+    /// follows the stream as a receiver discarding damaged groups would:
+    ///  - a [GroupingFlag::First] packet always opens a new group, aborting any open group
+    ///  - a [GroupingFlag::Unsegm] packet aborts any open group
+    ///  - stray [GroupingFlag::Interm] and [GroupingFlag::Last] packets leave the APID closed
+    ///
+    /// # Errors
+    ///
+    /// Errors with the [GroupingViolation] if the transition is illegal.
+    pub fn observe(&mut self, apid: u16, flag: GroupingFlag) -> Result<(), GroupingViolation> {
+        let state = self.state(apid);
+        let (next, result) = match (state, flag) {
+            (GroupState::Closed, GroupingFlag::Unsegm) => (GroupState::Closed, Ok(())),
+            (GroupState::Closed, GroupingFlag::First) => (GroupState::Open, Ok(())),
+            (GroupState::Closed, GroupingFlag::Interm | GroupingFlag::Last) => (
+                GroupState::Closed,
+                Err(GroupingViolation::NoOpenGroup { apid, flag }),
+            ),
+            (GroupState::Open, GroupingFlag::Interm) => (GroupState::Open, Ok(())),
+            (GroupState::Open, GroupingFlag::Last) => (GroupState::Closed, Ok(())),
+            (GroupState::Open, GroupingFlag::First) => (
+                GroupState::Open,
+                match self.restart {
+                    RestartPolicy::Error => Err(GroupingViolation::FirstWhileOpen { apid }),
+                    RestartPolicy::ImplicitAbort => Ok(()),
+                },
+            ),
+            (GroupState::Open, GroupingFlag::Unsegm) => (
+                GroupState::Closed,
+                Err(GroupingViolation::UnsegmWhileOpen { apid }),
+            ),
+        };
+
+        match next {
+            GroupState::Open => self.open.insert(apid),
+            GroupState::Closed => self.open.remove(&apid),
+        };
+        result
+    }
+
+    /// The state of the `apid`.
+    pub fn state(&self, apid: u16) -> GroupState {
+        match self.open.contains(&apid) {
+            true => GroupState::Open,
+            false => GroupState::Closed,
+        }
+    }
+
+    /// Abandon any group open on the `apid`, e.g. after a gap in its sequence count.
+    pub fn reset(&mut self, apid: u16) {
+        self.open.remove(&apid);
+    }
+}
+
+/// A packet which a [PacketReassembler] could not add to a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReassemblyError {
+    /// The grouping flag of the packet is illegal, see [GroupingValidator::observe].
+    Grouping(GroupingViolation),
+    /// A packet of an open group does not carry the sequence count following the previous
+    /// packet of the group, see [seq_distance] to tell lost packets from repeated ones.
+    SequenceGap {
+        /// The APID of the packet.
+        apid: u16,
+        /// The sequence count following the previous packet of the group.
+        expected: u16,
+        /// The sequence count of the packet.
+        received: u16,
+    },
+}
+impl Display for ReassemblyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Grouping(violation) => violation.fmt(f),
+            Self::SequenceGap {
+                apid,
+                expected,
+                received,
+            } => write!(
+                f,
+                "Sequence count {received} on APID {apid:#05X} breaks the group, expected {expected}"
+            ),
+        }
+    }
+}
+impl std::error::Error for ReassemblyError {}
+impl From<GroupingViolation> for ReassemblyError {
+    fn from(violation: GroupingViolation) -> Self {
+        Self::Grouping(violation)
+    }
+}
+impl From<ReassemblyError> for std::io::Error {
+    fn from(err: ReassemblyError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// The open group of an APID.
+#[derive(Debug, Clone)]
+struct Group {
+    /// The payloads of the packets of the group so far.
+    message: Vec<u8>,
+    /// The sequence count of the latest packet of the group.
+    sequence_count: u16,
+    /// The time the latest packet of the group arrived.
+    updated: Duration,
+}
+
+/// The partial message of a group abandoned by [PacketReassembler::flush_stale]
+/// before its [GroupingFlag::Last] packet arrived.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncompleteMessage {
+    /// The APID of the group.
+    pub apid: u16,
+    /// The payloads of the packets received for the group, the end of the message is missing.
+    pub message: Vec<u8>,
+    /// The time since the latest packet of the group arrived.
+    pub age: Duration,
+}
+
+/// The time source of a [PacketReassembler], the time elapsed since any fixed instant.
+type Clock = Arc<dyn Fn() -> Duration + Send + Sync>;
+
+/// Reassembles the user data segmented over a group of packets, per APID.
+///
+/// The payloads of a [GroupingFlag::First] packet, any [GroupingFlag::Interm] packets and the
+/// [GroupingFlag::Last] packet are concatenated into one message, the payload of a
+/// [GroupingFlag::Unsegm] packet is a message on its own. Groups of different APIDs interleave.
+///
+/// A group is abandoned when a packet breaks it, i.e. an illegal grouping flag transition or
+/// a packet not following the previous packet of the group in sequence count.
+/// Idle Packets carry no user data and should not be pushed.
+///
+/// A group whose [GroupingFlag::Last] packet is lost stays open until the next packet of its
+/// APID breaks it. [Self::flush_stale] releases the partial messages of groups waiting for
+/// too long, measured by the clock given to [Self::with_clock].
+///
+/// ```
+/// # use spacepacket::{grouping::PacketReassembler, GroupingFlag, PacketType, SpacePacket};
+/// let segment = |grouping, sequence_count, data: &[u8]| {
+///     SpacePacket::new(0, PacketType::Telemetry, 17, grouping, sequence_count, false, data.to_vec())
+/// };
+///
+/// let mut reassembler = PacketReassembler::new();
+/// assert_eq!(Ok(None), reassembler.push(segment(GroupingFlag::First, 16383, b"he")));
+/// assert_eq!(Ok(None), reassembler.push(segment(GroupingFlag::Interm, 0, b"ll")));
+/// assert_eq!(
+///     Ok(Some(b"hello".to_vec())),
+///     reassembler.push(segment(GroupingFlag::Last, 1, b"o"))
+/// );
+/// ```
+#[derive(Clone)]
+pub struct PacketReassembler {
+    validator: GroupingValidator,
+    groups: HashMap<u16, Group>,
+    clock: Clock,
+}
+impl std::fmt::Debug for PacketReassembler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PacketReassembler")
+            .field("validator", &self.validator)
+            .field("groups", &self.groups)
+            .finish_non_exhaustive()
+    }
+}
+impl Default for PacketReassembler {
+    fn default() -> Self {
+        let start = Instant::now();
+        Self {
+            validator: GroupingValidator::default(),
+            groups: HashMap::new(),
+            clock: Arc::new(move || start.elapsed()),
+        }
+    }
+}
+impl PacketReassembler {
+    /// Create a reassembler without any open group, measuring time with the system clock.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measure the age of groups with the `clock` instead of the system clock,
+    /// e.g. the time of the ground station or a simulated time in tests.
+    ///
+    /// The `clock` returns the time elapsed since any fixed instant and should never decrease.
+    pub fn with_clock<F: Fn() -> Duration + Send + Sync + 'static>(mut self, clock: F) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Choose how a new group starting while a group is open is treated.
+    pub fn with_restart_policy(mut self, restart: RestartPolicy) -> Self {
+        self.validator = self.validator.with_restart_policy(restart);
+        self
+    }
+
+    /// Add the next `packet` of its APID.
+    ///
+    /// Returns the complete message once the `packet` completes a group or stands on its own.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the `packet` breaks the open group of its APID, which is abandoned:
+    ///  - with [ReassemblyError::SequenceGap] if an [GroupingFlag::Interm] or
+    ///    [GroupingFlag::Last] packet does not follow the previous packet of the group
+    ///  - with [ReassemblyError::Grouping] for an illegal grouping flag transition, a
+    ///    [GroupingFlag::First] packet still opens a new group while the payload of any
+    ///    other packet is discarded
+    pub fn push(&mut self, packet: SpacePacket) -> Result<Option<Vec<u8>>, ReassemblyError> {
+        let SpacePacket {
+            primary_header,
+            payload,
+        } = packet;
+        let apid = primary_header.apid;
+        let flag = primary_header.grouping;
+        let sequence_count = primary_header.sequence_count & SEQUENCE_COUNT_MASK;
+
+        if let (Some(group), GroupingFlag::Interm | GroupingFlag::Last) =
+            (self.groups.get(&apid), flag)
+        {
+            if seq_distance(group.sequence_count, sequence_count) != 1 {
+                let expected = group.sequence_count.wrapping_add(1) & SEQUENCE_COUNT_MASK;
+                self.reset(apid);
+                return Err(ReassemblyError::SequenceGap {
+                    apid,
+                    expected,
+                    received: sequence_count,
+                });
+            }
+        }
+
+        let observed = self.validator.observe(apid, flag);
+        match flag {
+            GroupingFlag::First => {
+                self.groups.insert(
+                    apid,
+                    Group {
+                        message: payload,
+                        sequence_count,
+                        updated: (self.clock)(),
+                    },
+                );
+                observed.map(|_| None).map_err(Into::into)
+            }
+            _ if observed.is_err() => {
+                self.groups.remove(&apid);
+                observed.map(|_| None).map_err(Into::into)
+            }
+            GroupingFlag::Unsegm => Ok(Some(payload)),
+            // the validator accepts these only while the group is open
+            GroupingFlag::Interm | GroupingFlag::Last => match self.groups.remove(&apid) {
+                Some(mut group) => {
+                    group.message.extend(payload);
+                    group.sequence_count = sequence_count;
+                    group.updated = (self.clock)();
+                    match flag {
+                        GroupingFlag::Last => Ok(Some(group.message)),
+                        _ => {
+                            self.groups.insert(apid, group);
+                            Ok(None)
+                        }
+                    }
+                }
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// The length of the message reassembled so far on the `apid`, `None` without an open group.
+    pub fn pending_len(&self, apid: u16) -> Option<usize> {
+        self.groups.get(&apid).map(|group| group.message.len())
+    }
+
+    /// Abandon any group open on the `apid`.
+    pub fn reset(&mut self, apid: u16) {
+        self.validator.reset(apid);
+        self.groups.remove(&apid);
+    }
+
+    /// Abandon every group whose latest packet arrived more than `max_age` ago,
+    /// returning their partial messages ordered by APID.
+    ///
+    /// The next packet of an abandoned APID must start a new group.
+    pub fn flush_stale(&mut self, max_age: Duration) -> Vec<IncompleteMessage> {
+        let now = (self.clock)();
+        let mut stale: Vec<u16> = self
+            .groups
+            .iter()
+            .filter(|(_, group)| now.saturating_sub(group.updated) > max_age)
+            .map(|(apid, _)| *apid)
+            .collect();
+        stale.sort_unstable();
+
+        stale
+            .into_iter()
+            .filter_map(|apid| {
+                self.validator.reset(apid);
+                self.groups.remove(&apid).map(|group| IncompleteMessage {
+                    apid,
+                    message: group.message,
+                    age: now.saturating_sub(group.updated),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(GroupState::Closed, GroupingFlag::Unsegm, GroupState::Closed, None)]
+    #[case(GroupState::Closed, GroupingFlag::First, GroupState::Open, None)]
+    #[case(
+        GroupState::Closed,
+        GroupingFlag::Interm,
+        GroupState::Closed,
+        Some(GroupingViolation::NoOpenGroup { apid: 17, flag: GroupingFlag::Interm })
+    )]
+    #[case(
+        GroupState::Closed,
+        GroupingFlag::Last,
+        GroupState::Closed,
+        Some(GroupingViolation::NoOpenGroup { apid: 17, flag: GroupingFlag::Last })
+    )]
+    #[case(
+        GroupState::Open,
+        GroupingFlag::Unsegm,
+        GroupState::Closed,
+        Some(GroupingViolation::UnsegmWhileOpen { apid: 17 })
+    )]
+    #[case(
+        GroupState::Open,
+        GroupingFlag::First,
+        GroupState::Open,
+        Some(GroupingViolation::FirstWhileOpen { apid: 17 })
+    )]
+    #[case(GroupState::Open, GroupingFlag::Interm, GroupState::Open, None)]
+    #[case(GroupState::Open, GroupingFlag::Last, GroupState::Closed, None)]
+    fn grouping_transitions(
+        #[case] state: GroupState,
+        #[case] flag: GroupingFlag,
+        #[case] next: GroupState,
+        #[case] violation: Option<GroupingViolation>,
+        #[values(RestartPolicy::Error, RestartPolicy::ImplicitAbort)] restart: RestartPolicy,
+    ) {
+        let mut validator = GroupingValidator::new().with_restart_policy(restart);
+        if state == GroupState::Open {
+            validator.observe(17, GroupingFlag::First).unwrap();
+        }
+        assert_eq!(state, validator.state(17));
+
+        let expected = match (restart, violation) {
+            (RestartPolicy::ImplicitAbort, Some(GroupingViolation::FirstWhileOpen { .. })) => {
+                Ok(())
+            }
+            (_, Some(violation)) => Err(violation),
+            (_, None) => Ok(()),
+        };
+        assert_eq!(expected, validator.observe(17, flag));
+        assert_eq!(next, validator.state(17));
+        // other APIDs are unaffected
+        assert_eq!(GroupState::Closed, validator.state(18));
+    }
+
+    #[test]
+    fn grouping_reset() {
+        let mut validator = GroupingValidator::new();
+        validator.observe(17, GroupingFlag::First).unwrap();
+        validator.observe(18, GroupingFlag::First).unwrap();
+
+        validator.reset(17);
+        assert_eq!(GroupState::Closed, validator.state(17));
+        assert_eq!(GroupState::Open, validator.state(18));
+        assert!(validator.observe(17, GroupingFlag::Interm).is_err());
+        assert!(validator.observe(18, GroupingFlag::Last).is_ok());
+    }
+
+    #[test]
+    fn grouping_sequence() {
+        use GroupingFlag::*;
+
+        let mut validator = GroupingValidator::new();
+        let violations = [
+            Unsegm, First, Interm, Interm, Last, Last, First, First, Last, Interm, First, Unsegm,
+        ]
+        .into_iter()
+        .filter(|flag| validator.observe(3, *flag).is_err())
+        .count();
+        // the second Last, the second First, the stray Interm and the Unsegm in a group
+        assert_eq!(4, violations);
+        assert_eq!(GroupState::Closed, validator.state(3));
+    }
+
+    fn segment(apid: u16, grouping: GroupingFlag, sequence_count: u16, data: &[u8]) -> SpacePacket {
+        SpacePacket::new(
+            0,
+            crate::PacketType::Telemetry,
+            apid,
+            grouping,
+            sequence_count,
+            false,
+            data.to_vec(),
+        )
+    }
+
+    #[test]
+    fn reassembler_interleaved() {
+        use GroupingFlag::*;
+
+        let mut reassembler = PacketReassembler::new();
+        assert_eq!(Ok(None), reassembler.push(segment(17, First, 5, b"ab")));
+        assert_eq!(Ok(None), reassembler.push(segment(18, First, 9, b"xy")));
+        assert_eq!(
+            Ok(Some(b"solo".to_vec())),
+            reassembler.push(segment(19, Unsegm, 0, b"solo"))
+        );
+        assert_eq!(Ok(None), reassembler.push(segment(17, Interm, 6, b"cd")));
+        assert_eq!(Some(4), reassembler.pending_len(17));
+        assert_eq!(
+            Ok(Some(b"xyz".to_vec())),
+            reassembler.push(segment(18, Last, 10, b"z"))
+        );
+        assert_eq!(
+            Ok(Some(b"abcde".to_vec())),
+            reassembler.push(segment(17, Last, 7, b"e"))
+        );
+        assert_eq!(None, reassembler.pending_len(17));
+    }
+
+    #[rstest]
+    // a lost Interm packet
+    #[case(8, 7)]
+    // a repeated packet
+    #[case(6, 7)]
+    fn reassembler_sequence_gap(#[case] received: u16, #[case] expected: u16) {
+        use GroupingFlag::*;
+
+        let mut reassembler = PacketReassembler::new();
+        reassembler.push(segment(17, First, 5, b"ab")).unwrap();
+        reassembler.push(segment(17, Interm, 6, b"cd")).unwrap();
+        assert_eq!(
+            Err(ReassemblyError::SequenceGap {
+                apid: 17,
+                expected,
+                received
+            }),
+            reassembler.push(segment(17, Last, received, b"e"))
+        );
+        // the group was abandoned
+        assert_eq!(None, reassembler.pending_len(17));
+        assert!(reassembler
+            .push(segment(17, Last, received + 1, b"f"))
+            .is_err());
+    }
+
+    #[test]
+    fn reassembler_grouping_violations() {
+        use GroupingFlag::*;
+
+        let mut reassembler = PacketReassembler::new();
+        // an Interm packet without a First packet
+        assert_eq!(
+            Err(ReassemblyError::Grouping(GroupingViolation::NoOpenGroup {
+                apid: 17,
+                flag: Interm
+            })),
+            reassembler.push(segment(17, Interm, 0, b"ab"))
+        );
+
+        // a new group aborts the open one
+        reassembler.push(segment(17, First, 1, b"ab")).unwrap();
+        assert_eq!(
+            Err(ReassemblyError::Grouping(
+                GroupingViolation::FirstWhileOpen { apid: 17 }
+            )),
+            reassembler.push(segment(17, First, 2, b"cd"))
+        );
+        assert_eq!(
+            Ok(Some(b"cdef".to_vec())),
+            reassembler.push(segment(17, Last, 3, b"ef"))
+        );
+
+        let mut reassembler =
+            PacketReassembler::new().with_restart_policy(RestartPolicy::ImplicitAbort);
+        reassembler.push(segment(17, First, 1, b"ab")).unwrap();
+        assert_eq!(Ok(None), reassembler.push(segment(17, First, 2, b"cd")));
+        assert_eq!(Some(2), reassembler.pending_len(17));
+
+        // an Unsegm packet inside a group
+        assert!(reassembler.push(segment(17, Unsegm, 3, b"gh")).is_err());
+        assert_eq!(None, reassembler.pending_len(17));
+    }
+
+    #[test]
+    fn reassembler_flush_stale() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use GroupingFlag::*;
+
+        let secs = Arc::new(AtomicU64::new(0));
+        let clock = secs.clone();
+        let mut reassembler = PacketReassembler::new()
+            .with_clock(move || Duration::from_secs(clock.load(Ordering::Relaxed)));
+
+        reassembler.push(segment(18, First, 0, b"xy")).unwrap();
+        secs.store(5, Ordering::Relaxed);
+        reassembler.push(segment(17, First, 5, b"ab")).unwrap();
+        secs.store(10, Ordering::Relaxed);
+        reassembler.push(segment(17, Interm, 6, b"cd")).unwrap();
+        reassembler.push(segment(19, First, 3, b"uv")).unwrap();
+        assert!(reassembler.flush_stale(Duration::from_secs(10)).is_empty());
+
+        // the Last packets of 17 and 18 are lost, the age counts from the latest packet
+        secs.store(16, Ordering::Relaxed);
+        reassembler.push(segment(19, Interm, 4, b"w")).unwrap();
+        assert_eq!(
+            vec![
+                IncompleteMessage {
+                    apid: 17,
+                    message: b"abcd".to_vec(),
+                    age: Duration::from_secs(6)
+                },
+                IncompleteMessage {
+                    apid: 18,
+                    message: b"xy".to_vec(),
+                    age: Duration::from_secs(16)
+                },
+            ],
+            reassembler.flush_stale(Duration::from_secs(5))
+        );
+        assert_eq!(None, reassembler.pending_len(17));
+        assert_eq!(Some(3), reassembler.pending_len(19));
+
+        // the abandoned APIDs start over
+        assert!(reassembler.push(segment(17, Last, 7, b"e")).is_err());
+        reassembler.push(segment(18, First, 1, b"z")).unwrap();
+        assert_eq!(
+            Ok(Some(b"zz".to_vec())),
+            reassembler.push(segment(18, Last, 2, b"z"))
+        );
+    }
+
+    #[test]
+    fn reassembler_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<PacketReassembler>();
+    }
+}
@@ -0,0 +1,121 @@
+//! Chunked ingestion of large recordings, e.g. CADU captures, from a file.
+//!
+//! [process_file] reads the file in large chunks aligned to [CHUNK_LEN] and drives a
+//! [PacketFramer] over them. The returned offset is where a later call resumes without
+//! losing or repeating a packet, even if the recording ended part way through one.
+//!
+//! ```no_run
+//! # use spacepacket::{framer::PacketFramer, ingest::process_file};
+//! # async fn run() -> std::io::Result<()> {
+//! let framer = PacketFramer::new([0x1A, 0xCF, 0xFC, 0x1D]);
+//! let resume_offset = process_file(
+//!     "recording.bin",
+//!     framer,
+//!     0,
+//!     |packet, _offset| println!("{packet:?}"),
+//!     |progress| println!("{} bytes", progress.bytes_read),
+//! )
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{io::SeekFrom, path::Path};
+
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+
+use crate::{
+    framer::{FramerEvent, PacketFramer},
+    SpacePacket,
+};
+
+/// The length of the chunks read from the file, reads after the first are aligned to it.
+pub const CHUNK_LEN: usize = 1 << 20;
+
+/// The progress of [process_file], reported after every chunk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngestProgress {
+    /// The file offset up to which bytes have been read.
+    pub offset: u64,
+    /// The number of bytes read since the start offset.
+    pub bytes_read: u64,
+    /// The number of packets passed to the callback.
+    pub packets: u64,
+    /// The number of discarded packets and byte runs, including CRC errors.
+    pub discarded: u64,
+}
+
+/// Frame the [SpacePacket]s in the file at `path` starting at `start_offset`.
+///
+/// Every packet is passed to `on_packet` along with the file offset just past it,
+/// where a later call resumes without repeating the packet. `progress` is called after
+/// every chunk. The `framer` provides the synchronization marker, CRC and idle handling
+/// and is [reset](PacketFramer::reset) before use.
+///
+/// Returns the offset to resume from once the end of the file is reached.
+/// Bytes of an incomplete packet at the end of the file, including its synchronization
+/// marker, lie after the returned offset and are framed again by the resuming call.
+///
+/// # Errors
+///
+/// Errors if the file cannot be opened, seeked to `start_offset` or read.
+pub async fn process_file<P, F, G>(
+    path: P,
+    mut framer: PacketFramer,
+    start_offset: u64,
+    mut on_packet: F,
+    progress: G,
+) -> std::io::Result<u64>
+where
+    P: AsRef<Path>,
+    F: FnMut(SpacePacket, u64),
+    G: Fn(IngestProgress),
+{
+    let mut file = File::open(path).await?;
+    file.seek(SeekFrom::Start(start_offset)).await?;
+    framer.reset();
+
+    let mut chunk = vec![0_u8; CHUNK_LEN];
+    let mut report = IngestProgress {
+        offset: start_offset,
+        ..Default::default()
+    };
+    loop {
+        // read up to the next chunk boundary of the file
+        let aligned_len = CHUNK_LEN - (report.offset % CHUNK_LEN as u64) as usize;
+        let read = file.read(&mut chunk[..aligned_len]).await?;
+        if read == 0 {
+            break;
+        }
+        report.offset += read as u64;
+        report.bytes_read += read as u64;
+
+        framer.push(&chunk[..read]);
+        while let Some(event) = framer.next_event() {
+            match event {
+                FramerEvent::NeedMore => break,
+                FramerEvent::Packet(packet) => {
+                    report.packets += 1;
+                    on_packet(packet, resume_offset(&framer, start_offset));
+                }
+                _ => report.discarded += 1,
+            }
+        }
+        progress(report);
+    }
+
+    Ok(resume_offset(&framer, start_offset))
+}
+
+/// The file offset a fresh framer searching for the synchronization marker resumes from.
+fn resume_offset(framer: &PacketFramer, start_offset: u64) -> u64 {
+    let offset = start_offset + framer.stream_offset();
+    match framer.is_synchronized() {
+        // the marker of the pending packet was consumed, search for it again
+        true => offset - framer.sync_marker().len() as u64,
+        false => offset,
+    }
+}
@@ -0,0 +1,218 @@
+//! Conversions from and to the header types of the `ccsds_primary_header` crate.
+//!
+//! Its `PrimaryHeader` holds the Packet Data Length field, which a [PrimaryHeader] leaves to
+//! the [SpacePacket](crate::SpacePacket) payload. [RawPrimaryHeader] converts with the field,
+//! the rich [PrimaryHeader] drops it and converts back with a field of 0, a data field of 1 byte.
+//! Both crates store the field as the data field length minus one, the `ccsds_primary_header`
+//! `data_length` method adds the one back as [RawPrimaryHeader::data_length] does not.
+
+use ccsds_primary_header::primary_header as ccsds;
+
+use crate::{raw::RawPrimaryHeader, GroupingFlag, PacketType, PrimaryHeader};
+
+impl From<ccsds::SeqFlag> for GroupingFlag {
+    /// `Unknown` maps to [GroupingFlag::Interm], the flag `ccsds_primary_header` encodes it as.
+    fn from(flag: ccsds::SeqFlag) -> Self {
+        match flag {
+            ccsds::SeqFlag::Continuation | ccsds::SeqFlag::Unknown => Self::Interm,
+            ccsds::SeqFlag::FirstSegment => Self::First,
+            ccsds::SeqFlag::LastSegment => Self::Last,
+            ccsds::SeqFlag::Unsegmented => Self::Unsegm,
+        }
+    }
+}
+impl From<GroupingFlag> for ccsds::SeqFlag {
+    fn from(flag: GroupingFlag) -> Self {
+        match flag {
+            GroupingFlag::Interm => Self::Continuation,
+            GroupingFlag::First => Self::FirstSegment,
+            GroupingFlag::Last => Self::LastSegment,
+            GroupingFlag::Unsegm => Self::Unsegmented,
+        }
+    }
+}
+
+impl From<ccsds::PacketType> for PacketType {
+    /// `Unknown` maps to [PacketType::Telemetry], the type `ccsds_primary_header` encodes it as.
+    fn from(packet_type: ccsds::PacketType) -> Self {
+        match packet_type {
+            ccsds::PacketType::Data | ccsds::PacketType::Unknown => Self::Telemetry,
+            ccsds::PacketType::Command => Self::Command,
+        }
+    }
+}
+impl From<PacketType> for ccsds::PacketType {
+    fn from(packet_type: PacketType) -> Self {
+        match packet_type {
+            PacketType::Telemetry => Self::Data,
+            PacketType::Command => Self::Command,
+        }
+    }
+}
+
+impl From<ccsds::PrimaryHeader> for PrimaryHeader {
+    /// Convert the header fields, dropping the Packet Data Length field.
+    fn from(header: ccsds::PrimaryHeader) -> Self {
+        Self {
+            version: header.control.version() as u8,
+            packet_type: header.control.packet_type().into(),
+            apid: header.control.apid(),
+            secondary_header: header.control.secondary_header_flag()
+                == ccsds::SecondaryHeaderFlag::Present,
+            grouping: header.sequence.sequence_type().into(),
+            sequence_count: header.sequence.sequence_count(),
+        }
+    }
+}
+impl From<PrimaryHeader> for ccsds::PrimaryHeader {
+    /// Convert the header fields with a Packet Data Length field of 0,
+    /// see [RawPrimaryHeader] to convert with the field.
+    ///
+    /// Fields wider than their bits are masked as by [PrimaryHeader::encode].
+    fn from(header: PrimaryHeader) -> Self {
+        let mut converted = Self::default();
+        converted
+            .control
+            .set_version(u16::from(header.version & 0x7));
+        converted.control.set_packet_type(header.packet_type.into());
+        converted
+            .control
+            .set_secondary_header_flag(match header.secondary_header {
+                true => ccsds::SecondaryHeaderFlag::Present,
+                false => ccsds::SecondaryHeaderFlag::NotPresent,
+            });
+        converted.control.set_apid(header.apid);
+        converted.sequence.set_sequence_type(header.grouping.into());
+        converted.sequence.set_sequence_count(header.sequence_count);
+        converted
+    }
+}
+
+impl From<ccsds::PrimaryHeader> for RawPrimaryHeader {
+    fn from(header: ccsds::PrimaryHeader) -> Self {
+        let [b0, b1] = header.control.0;
+        let [b2, b3] = header.sequence.0;
+        let [b4, b5] = header.length.0;
+        Self([b0, b1, b2, b3, b4, b5])
+    }
+}
+impl From<RawPrimaryHeader> for ccsds::PrimaryHeader {
+    fn from(header: RawPrimaryHeader) -> Self {
+        Self::new(header.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_util::packet_with_payload;
+
+    /// The encoded bytes of a `ccsds_primary_header` header.
+    fn bytes(header: ccsds::PrimaryHeader) -> [u8; PrimaryHeader::WIRE_LEN] {
+        RawPrimaryHeader::from(header).0
+    }
+
+    fn headers() -> impl Iterator<Item = PrimaryHeader> {
+        let mut headers = vec![];
+        for version in 0..=7 {
+            for packet_type in [PacketType::Telemetry, PacketType::Command] {
+                for secondary_header in [false, true] {
+                    for apid in [0, 1, 0x42, 0x555, 0x7FF] {
+                        for grouping in [
+                            GroupingFlag::Interm,
+                            GroupingFlag::First,
+                            GroupingFlag::Last,
+                            GroupingFlag::Unsegm,
+                        ] {
+                            for sequence_count in [0, 1, 0x2AAA, 0x3FFF] {
+                                headers.push(PrimaryHeader {
+                                    version,
+                                    packet_type,
+                                    apid,
+                                    secondary_header,
+                                    grouping,
+                                    sequence_count,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        headers.into_iter()
+    }
+
+    #[test]
+    fn interop_ccsds_primary_header_into() {
+        for header in headers() {
+            let converted = ccsds::PrimaryHeader::from(header);
+            assert_eq!(RawPrimaryHeader::new(header, 0).0, bytes(converted));
+
+            for data_length in [0, 1, 0x1234, u16::MAX] {
+                let raw = RawPrimaryHeader::new(header, data_length);
+                let converted = ccsds::PrimaryHeader::from(raw);
+                assert_eq!(raw.0, bytes(converted));
+                assert_eq!(data_length, converted.length.length_field());
+            }
+        }
+    }
+
+    #[test]
+    fn interop_ccsds_primary_header_from() {
+        for header in headers() {
+            let mut other = ccsds::PrimaryHeader::default();
+            other.control.set_version(header.version.into());
+            other.control.set_packet_type(match header.packet_type {
+                PacketType::Telemetry => ccsds::PacketType::Data,
+                PacketType::Command => ccsds::PacketType::Command,
+            });
+            other
+                .control
+                .set_secondary_header_flag(match header.secondary_header {
+                    true => ccsds::SecondaryHeaderFlag::Present,
+                    false => ccsds::SecondaryHeaderFlag::NotPresent,
+                });
+            other.control.set_apid(header.apid);
+            other
+                .sequence
+                .set_sequence_type(ccsds::SeqFlag::from(header.grouping as u8));
+            other.sequence.set_sequence_count(header.sequence_count);
+            other.length.set_length_field(0x1234);
+
+            let converted = PrimaryHeader::from(other);
+            assert_eq!(header, converted);
+            assert_eq!(bytes(other), RawPrimaryHeader::new(converted, 0x1234).0);
+            assert_eq!(bytes(other), RawPrimaryHeader::from(other).0);
+        }
+    }
+
+    #[test]
+    fn interop_ccsds_primary_header_length() {
+        for payload_len in [1, 2, 255, 0x1_0000] {
+            let packet = packet_with_payload(0x42, 7, vec![0xA5; payload_len]);
+            let encoded = packet.encode();
+
+            let other = ccsds::PrimaryHeader::from_slice(&encoded).unwrap();
+            assert_eq!(payload_len, other.data_length() as usize);
+            assert_eq!(encoded.len(), other.packet_length() as usize);
+            assert_eq!(packet.primary_header, PrimaryHeader::from(other));
+
+            let raw = RawPrimaryHeader::from(other);
+            assert_eq!(usize::from(raw.data_length()) + 1, payload_len);
+            assert_eq!(&encoded[..PrimaryHeader::WIRE_LEN], raw.as_bytes());
+        }
+    }
+
+    #[test]
+    fn interop_ccsds_primary_header_unknown() {
+        assert_eq!(
+            GroupingFlag::Interm,
+            GroupingFlag::from(ccsds::SeqFlag::Unknown)
+        );
+        assert_eq!(
+            PacketType::Telemetry,
+            PacketType::from(ccsds::PacketType::Unknown)
+        );
+    }
+}
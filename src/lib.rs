@@ -2,18 +2,61 @@
 #![doc = include_str!("../README.md")]
 /// CCSDS compliant packet definition and implementations
 use byteorder::{BigEndian, ReadBytesExt};
+use consts::{APID_MASK, SEQUENCE_COUNT_MASK};
 #[cfg(feature = "crc")]
 #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
 use crc::Crc;
+use raw::RawPrimaryHeader;
+use trailer::{CheckedPacket, TrailerCheck};
+
+// declared first, its macros are used by the modules below
+#[macro_use]
+pub mod names;
 
 #[cfg(feature = "tctm")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tctm")))]
 pub mod tctm;
 
+pub mod anomaly;
+pub mod archive;
+pub mod bitfield;
+pub mod capabilities;
+pub mod chunked;
+#[cfg(feature = "cobs")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cobs")))]
+pub mod cobs;
+pub mod consts;
+pub mod continuity;
+#[cfg(feature = "framer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "framer")))]
+pub mod framer;
+pub mod grouping;
+#[cfg(feature = "tokio-ingest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio-ingest")))]
+pub mod ingest;
+#[cfg(feature = "interop-ccsds-primary-header")]
+mod interop;
+pub mod limits;
+pub mod raw;
+#[cfg(feature = "crc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+pub mod recover;
+pub mod sequencer;
+pub mod sink;
+pub mod sniff;
+#[cfg(test)]
+mod test_util;
+pub mod trailer;
+pub mod transport;
 #[cfg(feature = "crc")]
-use std::fmt::Display;
+#[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+pub mod valid;
 
-use std::io::Read;
+use std::{
+    fmt::{Debug, Display},
+    io::{IoSlice, Read, Write},
+    ops::Range,
+};
 
 #[cfg(any(feature = "async-codec", feature = "tokio-codec"))]
 #[cfg_attr(
@@ -34,6 +77,29 @@ pub mod codec;
 /// A re-export of the [crc] crate.
 pub use crc;
 
+pub use consts::{IDLE_APID, PACKET_VERSION_NUMBER};
+pub use sniff::sniff;
+
+// Lengths of up to SpacePacket::MAX_PAYLOAD_LEN plus framing overhead must fit in a usize,
+// see "Supported Targets" in the README.
+const _: () = assert!(
+    usize::BITS >= 32,
+    "spacepacket requires a target with at least 32-bit pointers"
+);
+
+/// The signed distance from sequence count `from` to `to` in the 14-bit sequence count space,
+/// taking the shorter way around the wrap, e.g. from 16380 to 3 is 7 and from 3 to 16380 is -7.
+///
+/// Positive values mean `to` follows `from`. Counts exactly half the space apart are reported
+/// as -8192. Bits above the 14-bit sequence count are ignored.
+pub fn seq_distance(from: u16, to: u16) -> i32 {
+    let forward = i32::from(to.wrapping_sub(from) & SEQUENCE_COUNT_MASK);
+    match forward < 0x2000 {
+        true => forward,
+        false => forward - 0x4000,
+    }
+}
+
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// CCSDS grouping flag to determine packet location in a stream.
@@ -60,6 +126,13 @@ impl GroupingFlag {
     }
 }
 
+named_variants!(GroupingFlag {
+    Interm => "Continuation",
+    First => "First",
+    Last => "Last",
+    Unsegm => "Unsegmented",
+});
+
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// Possible CCSDS packet types.
@@ -79,6 +152,10 @@ impl PacketType {
         }
     }
 }
+named_variants!(PacketType {
+    Telemetry => "Telemetry",
+    Command => "Telecommand",
+});
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// CCSCS Primary header defined in 133.0-B-2 June 2020
@@ -101,48 +178,148 @@ pub struct PrimaryHeader {
 }
 
 impl PrimaryHeader {
+    /// Length of the Primary Header on the wire, including the Packet Data Length field.
+    pub const WIRE_LEN: usize = 6;
+
+    /// Location of the big-endian Packet Data Length field within the encoded Primary Header.
+    pub const LENGTH_FIELD_RANGE: Range<usize> = 4..6;
+
     /// Encode to a byte stream for network communication.
     /// This encoding assumed BigEndian-ness
     pub fn encode(&self) -> Vec<u8> {
-        let mut message = vec![];
+        self.to_bytes().to_vec()
+    }
+
+    /// Encode as [Self::encode], rejecting fields wider than their bits instead of masking them.
+    ///
+    /// # Errors
+    ///
+    /// Errors with the first field exceeding its bits, see [Self::validate].
+    pub fn try_encode(&self) -> Result<Vec<u8>, FieldOutOfRange> {
+        self.validate()?;
+        Ok(self.encode())
+    }
+
+    /// Check every field fits in the bits it is encoded with,
+    /// the `version` is 3, the `apid` 11 and the `sequence_count` 14 bits wide.
+    ///
+    /// # Errors
+    ///
+    /// Errors with the first field exceeding its bits.
+    pub fn validate(&self) -> Result<(), FieldOutOfRange> {
+        for (field, value, max) in [
+            ("version", u16::from(self.version), 0x7),
+            ("apid", self.apid, APID_MASK),
+            ("sequence_count", self.sequence_count, SEQUENCE_COUNT_MASK),
+        ] {
+            if value > max {
+                return Err(FieldOutOfRange { field, value, max });
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the bytes of [Self::encode] to `writer`, returning the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Errors if writing to the `writer` fails.
+    pub fn encode_to_writer<W: Write + ?Sized>(&self, writer: &mut W) -> std::io::Result<usize> {
+        let bytes = self.to_bytes();
+        writer.write_all(&bytes)?;
+        Ok(bytes.len())
+    }
+
+    /// Mask every field into the bit-depth it is encoded with, returning exactly
+    /// the header [Self::encode] transmits and [Self::decode] recovers.
+    ///
+    /// [SpacePacket::encode] panics on unmasked fields in debug builds,
+    /// clamp the header first to transmit the masked fields in every build.
+    pub fn clamped(self) -> Self {
+        Self {
+            version: self.version & 0x7,
+            apid: self.apid & APID_MASK,
+            sequence_count: self.sequence_count & SEQUENCE_COUNT_MASK,
+            ..self
+        }
+    }
+
+    /// Encode the header fields preceding the Packet Data Length field.
+    fn to_bytes(self) -> [u8; 4] {
         let header_0 = u16::from(self.version & 0x7) << 13
                     | u16::from(self.packet_type as u8 & 0x1) << 12
                     // Flag for secondary header
                     | (self.secondary_header as u16) << 11
-                    | (self.apid & 0x7FF);
-        let header_1 = (self.grouping as u16) << 14 | (self.sequence_count & 0x3FFF);
-
-        message.extend_from_slice(&header_0.to_be_bytes());
-        message.extend_from_slice(&header_1.to_be_bytes());
+                    | (self.apid & APID_MASK);
+        let header_1 = (self.grouping as u16) << 14 | (self.sequence_count & SEQUENCE_COUNT_MASK);
 
-        message
+        let [b0, b1] = header_0.to_be_bytes();
+        let [b2, b3] = header_1.to_be_bytes();
+        [b0, b1, b2, b3]
     }
     /// Decode from a byte stream for network communication.
     /// This decoding assumes BigEndian-ness
+    ///
+    /// The fields are unpacked by [RawPrimaryHeader::header], the Packet Data Length field
+    /// is left in the `buffer`.
     pub fn decode<R: Read>(buffer: &mut R) -> std::io::Result<Self> {
-        let header0 = buffer.read_u16::<BigEndian>()?;
+        let mut bytes = [0_u8; 4];
+        buffer.read_exact(&mut bytes)?;
+        let [b0, b1, b2, b3] = bytes;
+        Ok(RawPrimaryHeader([b0, b1, b2, b3, 0, 0]).header())
+    }
 
-        let (version, packet_type, secondary_header, apid) = (
-            ((header0 & 0xe000) >> 13) as u8,
-            PacketType::from_1bit(((header0 & 0x1000) >> 12) as u8),
-            ((header0 & 0x800) >> 11) != 0,
-            (header0 & 0x7ff),
-        );
-        let header1 = buffer.read_u16::<BigEndian>()?;
+    /// Read only the packet type and APID from the first 2 bytes of an encoded packet,
+    /// to route or filter packets before committing to a full decode.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [std::io::ErrorKind::UnexpectedEof] if `bytes` is shorter than 2 bytes.
+    #[inline]
+    pub fn classify(bytes: &[u8]) -> std::io::Result<(PacketType, u16)> {
+        match bytes {
+            [b0, b1, ..] => Ok((
+                PacketType::from_1bit(b0 >> 4),
+                u16::from_be_bytes([*b0, *b1]) & APID_MASK,
+            )),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!(
+                    "Classifying a packet requires 2 bytes but found {}",
+                    bytes.len()
+                ),
+            )),
+        }
+    }
 
-        let (grouping, sequence_count) = (
-            GroupingFlag::from_2bits(((header1 & 0xc000) >> 14) as u8),
-            header1 & 0x3fff,
-        );
+    /// Decode the header and the Packet Data Length field from a byte stream,
+    /// leaving the reader positioned at the start of the payload.
+    ///
+    /// The returned length is the Packet Data Length field as encoded,
+    /// per CCSDS this is the payload length minus one.
+    /// The payload is therefore `usize::from(length) + 1` bytes long.
+    /// This decoding assumes BigEndian-ness
+    pub fn decode_with_length<R: Read>(buffer: &mut R) -> std::io::Result<(Self, u16)> {
+        let header = Self::decode(buffer)?;
+        let length = buffer.read_u16::<BigEndian>()?;
+        Ok((header, length))
+    }
 
-        Ok(Self {
-            version,
-            packet_type,
-            apid,
-            secondary_header,
-            grouping,
-            sequence_count,
-        })
+    /// The Packet Data Length field describing a packet data field of `data_len` bytes,
+    /// including any trailer, i.e. `data_len - 1`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `data_len` is not within `1..=`[SpacePacket::MAX_PAYLOAD_LEN].
+    pub fn data_length(data_len: usize) -> Result<u16, LengthOutOfRange> {
+        data_len
+            .checked_sub(1)
+            .and_then(|length| u16::try_from(length).ok())
+            .ok_or(LengthOutOfRange {
+                len: data_len,
+                min_len: 1,
+                max_len: SpacePacket::MAX_PAYLOAD_LEN,
+            })
     }
 }
 
@@ -154,21 +331,513 @@ impl PrimaryHeader {
 pub enum CompletePacket {
     /// The CRC validated packet
     Valid(SpacePacket),
-    /// The expected and computed CRC values associated with this packet.
-    /// The packet was deemed invalid and discarded but is a recoverable error.
-    InvalidCRC(u16, u16),
+    /// The expected and computed CRC values associated with this packet,
+    /// followed by the received primary header and payload without the CRC.
+    /// The packet was deemed invalid but is a recoverable error,
+    /// see [CompletePacket::recover_lossy] to salvage its payload.
+    InvalidCRC(u16, u16, Vec<u8>),
 }
 #[cfg(feature = "crc")]
 impl Display for CompletePacket {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self{
             CompletePacket::Valid(packet) => write!(f, "{:?}", packet),
-            CompletePacket::InvalidCRC(expected, computed) => write!(f, "Invalid CRC encountered in packet decoding. Expected {expected:>#06X} Received {computed:>#06X}"),
+            CompletePacket::InvalidCRC(expected, computed, _) => write!(f, "Invalid CRC encountered in packet decoding. Expected {expected:>#06X} Received {computed:>#06X}"),
+        }
+    }
+}
+
+/// A best-effort packet recovered by [SpacePacket::decode_until_marker].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveredPacket {
+    /// The packet with all bytes up to the next synchronization marker as payload.
+    pub packet: SpacePacket,
+    /// The payload length declared in the Packet Data Length field.
+    pub declared_len: usize,
+    /// Set when the declared length disagrees with the recovered payload length,
+    /// i.e. the Packet Data Length field was ignored.
+    pub length_recovered: bool,
+}
+impl RecoveredPacket {
+    /// The number of bytes of the input buffer used by the packet.
+    pub fn consumed(&self) -> usize {
+        PrimaryHeader::WIRE_LEN + self.packet.payload.len()
+    }
+}
+
+/// A typed packet secondary header, e.g. a mission time code, preceding the user data
+/// in the payload, see [SpacePacket::with_secondary_header].
+pub trait SecondaryHeader {
+    /// The length of the encoded secondary header in bytes.
+    fn encoded_len(&self) -> usize;
+
+    /// Append the encoded secondary header to `out`.
+    fn encode_into(&self, out: &mut Vec<u8>);
+}
+
+/// A secondary header which is already encoded.
+impl SecondaryHeader for [u8] {
+    fn encoded_len(&self) -> usize {
+        self.len()
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+}
+
+/// The location of a field within an encoded packet, see [SpacePacket::decode_annotated].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSpan {
+    /// The name of the field, e.g. `"apid"`.
+    pub name: &'static str,
+    /// The bits of the field, numbered from the most significant bit of the first byte.
+    pub bits: Range<usize>,
+}
+impl FieldSpan {
+    /// The bytes holding any bit of the field.
+    pub fn bytes(&self) -> Range<usize> {
+        self.bits.start / 8..(self.bits.end + 7) / 8
+    }
+}
+
+/// A packet split into the pieces of a scatter list by [SpacePacket::encode_vectored]
+/// or `SpacePacket::encode_vectored_crc`,
+/// written without first copying the payload into a contiguous buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VectoredPacket<'a> {
+    /// The encoded primary header, including the Packet Data Length field.
+    pub header: [u8; PrimaryHeader::WIRE_LEN],
+    /// The borrowed payload of the packet.
+    pub payload: &'a [u8],
+    /// The big-endian CRC-16 trailer, if the packet was encoded with a CRC.
+    pub trailer: Option<[u8; 2]>,
+}
+impl<'a> VectoredPacket<'a> {
+    /// The total number of bytes of the encoded packet.
+    pub fn len(&self) -> usize {
+        self.header.len() + self.payload.len() + self.trailer.map_or(0, |trailer| trailer.len())
+    }
+
+    /// Whether the encoded packet is empty, which is never the case.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The scatter list for [Write::write_vectored], the trailer slice is empty without a CRC.
+    pub fn io_slices(&self) -> [IoSlice<'_>; 3] {
+        [
+            IoSlice::new(&self.header),
+            IoSlice::new(self.payload),
+            IoSlice::new(self.trailer.as_ref().map_or(&[], |trailer| &trailer[..])),
+        ]
+    }
+
+    /// Write the whole packet to `writer` using [Write::write_vectored],
+    /// repeating the write until every byte is accepted.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the writer errors or accepts no bytes.
+    pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> std::io::Result<()> {
+        let trailer = self
+            .trailer
+            .as_ref()
+            .map_or(&[][..], |trailer| &trailer[..]);
+        let pieces: [&[u8]; 3] = [&self.header, self.payload, trailer];
+        let mut written = 0;
+        while written < self.len() {
+            // rebuild the scatter list past the bytes already written
+            let mut skip = written;
+            let slices = pieces.map(|piece| {
+                let start = skip.min(piece.len());
+                skip -= start;
+                IoSlice::new(&piece[start..])
+            });
+            match writer.write_vectored(&slices) {
+                Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+                Ok(len) => written += len,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fill value of every payload byte of an Idle Packet.
+///
+/// CCSDS 133.0-B-2 leaves the idle data pattern to the mission.
+/// A counting pattern lets the receiver detect slipped or corrupted bytes.
+pub enum FillPattern {
+    /// Every byte has the same value.
+    Constant(u8),
+    /// Bytes count up from `start`, wrapping after 0xFF.
+    Counting {
+        /// The value of the first payload byte.
+        start: u8,
+    },
+    /// The value of every byte is computed from its offset in the payload.
+    Custom(Box<dyn Fn(usize) -> u8 + Send + Sync>),
+}
+impl FillPattern {
+    /// The expected value of the payload byte at `offset`.
+    pub fn byte(&self, offset: usize) -> u8 {
+        match self {
+            Self::Constant(value) => *value,
+            Self::Counting { start } => start.wrapping_add(offset as u8),
+            Self::Custom(pattern) => pattern(offset),
+        }
+    }
+}
+impl Debug for FillPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Constant(value) => f.debug_tuple("Constant").field(value).finish(),
+            Self::Counting { start } => f.debug_struct("Counting").field("start", start).finish(),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// The payload of an Idle Packet does not follow the expected [FillPattern],
+/// see [SpacePacket::verify_idle_pattern].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdleCorruption {
+    /// The offset into the payload of the first byte which differs from the pattern.
+    pub first_bad_offset: usize,
+}
+impl Display for IdleCorruption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Idle Packet payload differs from the fill pattern at offset {}",
+            self.first_bad_offset
+        )
+    }
+}
+impl std::error::Error for IdleCorruption {}
+
+/// A length which cannot be described by the length field of a header,
+/// see [PrimaryHeader::data_length].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthOutOfRange {
+    /// The length which was to be encoded.
+    pub len: usize,
+    /// The shortest length the field can describe.
+    pub min_len: usize,
+    /// The longest length the field can describe.
+    pub max_len: usize,
+}
+impl Display for LengthOutOfRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Payload length must be in {}..={} bytes but found {}",
+            self.min_len, self.max_len, self.len
+        )
+    }
+}
+impl std::error::Error for LengthOutOfRange {}
+impl From<LengthOutOfRange> for std::io::Error {
+    fn from(err: LengthOutOfRange) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, err)
+    }
+}
+
+/// A header field value wider than the bits it is encoded with, see [SpacePacket::try_new].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldOutOfRange {
+    /// The name of the field, e.g. `"apid"`.
+    pub field: &'static str,
+    /// The value which was to be encoded.
+    pub value: u16,
+    /// The largest value the field can hold.
+    pub max: u16,
+}
+impl Display for FieldOutOfRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Field {} must be at most {:#X} but found {:#X}",
+            self.field, self.max, self.value
+        )
+    }
+}
+impl std::error::Error for FieldOutOfRange {}
+impl From<FieldOutOfRange> for std::io::Error {
+    fn from(err: FieldOutOfRange) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, err)
+    }
+}
+
+/// A packet which cannot be encoded, see [SpacePacket::try_encode].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeError {
+    /// A primary header field is wider than its bits.
+    Field(FieldOutOfRange),
+    /// The Packet Data Length field cannot describe the payload.
+    Length(LengthOutOfRange),
+}
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Field(err) => Display::fmt(err, f),
+            Self::Length(err) => Display::fmt(err, f),
+        }
+    }
+}
+impl std::error::Error for EncodeError {}
+impl From<FieldOutOfRange> for EncodeError {
+    fn from(err: FieldOutOfRange) -> Self {
+        Self::Field(err)
+    }
+}
+impl From<LengthOutOfRange> for EncodeError {
+    fn from(err: LengthOutOfRange) -> Self {
+        Self::Length(err)
+    }
+}
+impl From<EncodeError> for std::io::Error {
+    fn from(err: EncodeError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, err)
+    }
+}
+
+/// How the Packet Data Length field relates to the length of the packet data field,
+/// see [SpacePacket::encode_with_convention] and [SpacePacket::decode_with_convention].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LengthConvention {
+    /// The field holds the data field length minus one, as defined by CCSDS 133.0-B-2.
+    #[default]
+    CcsdsMinusOne,
+    /// **Non-standard:** the field holds the data field length itself.
+    ///
+    /// Only for interoperating with peers which do not follow CCSDS 133.0-B-2,
+    /// compliant decoders read packets encoded this way as one byte longer.
+    Actual,
+}
+impl LengthConvention {
+    /// The Packet Data Length field describing a packet data field of `data_len` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `data_len` is empty or too long for the 16-bit field under this convention.
+    pub fn data_length(self, data_len: usize) -> Result<u16, LengthOutOfRange> {
+        match self {
+            Self::CcsdsMinusOne => PrimaryHeader::data_length(data_len),
+            Self::Actual => u16::try_from(data_len)
+                .ok()
+                .filter(|length| *length > 0)
+                .ok_or(LengthOutOfRange {
+                    len: data_len,
+                    min_len: 1,
+                    max_len: u16::MAX.into(),
+                }),
+        }
+    }
+
+    /// The length of the packet data field described by the Packet Data Length field `length`.
+    pub fn data_field_len(self, length: u16) -> usize {
+        match self {
+            Self::CcsdsMinusOne => usize::from(length) + 1,
+            Self::Actual => usize::from(length),
+        }
+    }
+}
+
+/// Why a [RejectedPacket] was refused.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    /// The payload is empty or too long to encode.
+    PayloadLength {
+        /// The length of the rejected payload.
+        len: usize,
+        /// The longest payload which could have been encoded.
+        max_len: usize,
+    },
+    /// A primary header field is wider than its bits, see [PrimaryHeader::validate].
+    HeaderField(FieldOutOfRange),
+}
+impl Display for RejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PayloadLength { len, max_len } => write!(
+                f,
+                "Payload length must be in 1..={max_len} bytes but found {len}"
+            ),
+            Self::HeaderField(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+/// A packet refused by an encoder, returned to the caller without copying its payload.
+///
+/// Encoders which consume their packets, such as the Encoder implementations of
+/// [SpacePacketCodec](codec::SpacePacketCodec), wrap it in an [std::io::ErrorKind::InvalidInput]
+/// error, recover it with [RejectedPacket::from_io_error].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedPacket {
+    /// Why the packet was refused.
+    pub reason: RejectReason,
+    /// The refused packet, boxed to keep errors small.
+    pub packet: Box<SpacePacket>,
+}
+impl RejectedPacket {
+    /// Recover the refused packet.
+    pub fn into_packet(self) -> SpacePacket {
+        *self.packet
+    }
+
+    /// Extract the rejection from an [std::io::Error] wrapping one,
+    /// or return the error unchanged.
+    pub fn from_io_error(error: std::io::Error) -> Result<Self, std::io::Error> {
+        if !error.get_ref().map_or(false, |inner| inner.is::<Self>()) {
+            return Err(error);
+        }
+        // checked above that the inner error is present and of this type
+        Ok(*error.into_inner().unwrap().downcast::<Self>().unwrap())
+    }
+}
+impl Display for RejectedPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.reason, f)
+    }
+}
+impl std::error::Error for RejectedPacket {}
+impl From<RejectedPacket> for std::io::Error {
+    fn from(rejected: RejectedPacket) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, rejected)
+    }
+}
+
+/// Number of payload bytes shown by the [Debug] implementations of packets and frames.
+const DEBUG_PAYLOAD_BYTES: usize = 16;
+/// Number of payload bytes shown by the alternate (`{:#?}`) [Debug] implementations.
+const DEBUG_PAYLOAD_BYTES_ALTERNATE: usize = 64;
+
+/// Summarizes a potentially large byte array for [Debug] output.
+///
+/// Formats as `[len=4096, first16=DE AD BE EF ..]`, where the trailing `..`
+/// is only present when bytes were omitted.
+/// The alternate form (`{:#?}`) shows up to 64 bytes instead of 16.
+pub(crate) struct PayloadSummary<'a>(pub(crate) &'a [u8]);
+impl<'a> Debug for PayloadSummary<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bound = match f.alternate() {
+            true => DEBUG_PAYLOAD_BYTES_ALTERNATE,
+            false => DEBUG_PAYLOAD_BYTES,
+        };
+        let shown = &self.0[..self.0.len().min(bound)];
+
+        write!(f, "[len={}, first{}=", self.0.len(), shown.len())?;
+        for (index, byte) in shown.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{byte:02X}")?;
+        }
+        if shown.len() < self.0.len() {
+            write!(f, " ..")?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// A [SpacePacket] which borrows its payload.
+///
+/// Used to en/de-code packets without allocating, see [SpacePacket::decode_into].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SpacePacketRef<'a> {
+    /// Primary header information.
+    pub primary_header: PrimaryHeader,
+    /// Borrowed payload to be decoded by the end user.
+    pub payload: &'a [u8],
+}
+impl<'a> Debug for SpacePacketRef<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpacePacketRef")
+            .field("primary_header", &self.primary_header)
+            .field("payload", &PayloadSummary(self.payload))
+            .finish()
+    }
+}
+impl<'a> SpacePacketRef<'a> {
+    /// Encode the packet into the start of the `out` buffer without allocating.
+    /// This encoding assumed BigEndian-ness
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - a header field is wider than its bits, see [PrimaryHeader::validate]
+    ///  - the payload is empty or longer than [SpacePacket::MAX_PAYLOAD_LEN]
+    ///  - `out` is too short to hold the encoded packet
+    pub fn encode_into(&self, out: &mut [u8]) -> std::io::Result<usize> {
+        self.primary_header.validate()?;
+        let length_field = PrimaryHeader::data_length(self.payload.len())?;
+
+        let encoded_len = PrimaryHeader::WIRE_LEN + self.payload.len();
+        let out_len = out.len();
+        let out = out.get_mut(..encoded_len).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Output buffer of length {out_len} cannot hold packet of length {encoded_len}"
+                ),
+            )
+        })?;
+
+        out[..4].copy_from_slice(&self.primary_header.to_bytes());
+        out[PrimaryHeader::LENGTH_FIELD_RANGE].copy_from_slice(&length_field.to_be_bytes());
+        out[PrimaryHeader::WIRE_LEN..].copy_from_slice(self.payload);
+
+        Ok(encoded_len)
+    }
+
+    /// Decode a packet at the start of the `buffer`, borrowing its payload from the `buffer`.
+    ///
+    /// Returns the packet and the remainder of the `buffer` following it.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [std::io::ErrorKind::UnexpectedEof] if the `buffer` ends within the packet.
+    pub fn decode(buffer: &'a [u8]) -> std::io::Result<(Self, &'a [u8])> {
+        let mut reader = buffer;
+        let (primary_header, length) = PrimaryHeader::decode_with_length(&mut reader)?;
+        let message_len = usize::from(length) + 1;
+
+        if reader.len() < message_len {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let (payload, rest) = reader.split_at(message_len);
+
+        Ok((
+            Self {
+                primary_header,
+                payload,
+            },
+            rest,
+        ))
+    }
+
+    /// Copy the payload into an owned [SpacePacket].
+    pub fn into_owned(self) -> SpacePacket {
+        SpacePacket {
+            primary_header: self.primary_header,
+            payload: self.payload.to_vec(),
         }
     }
 }
+impl<'a> From<SpacePacketRef<'a>> for SpacePacket {
+    fn from(packet: SpacePacketRef<'a>) -> Self {
+        packet.into_owned()
+    }
+}
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 /// CCSCS Space Packet defined in 133.0-B-2 June 2020
 /// Primary header generated automatically when initializing this structue.
 pub struct SpacePacket {
@@ -177,7 +846,27 @@ pub struct SpacePacket {
     /// Flexible payload to be decoded by the end user.
     pub payload: Vec<u8>,
 }
+impl Debug for SpacePacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpacePacket")
+            .field("primary_header", &self.primary_header)
+            .field("payload", &PayloadSummary(&self.payload))
+            .finish()
+    }
+}
 impl SpacePacket {
+    /// Start building a packet with a [SpacePacketBuilder], the recommended way to construct
+    /// packets since every field is named.
+    pub fn builder() -> SpacePacketBuilder {
+        SpacePacketBuilder::default()
+    }
+
+    /// Create a packet from its header fields and payload.
+    ///
+    /// Fields wider than their bits are not checked. Encoding them with [Self::encode] panics
+    /// in debug builds and masks them in release builds, use [Self::try_new] to reject them
+    /// up front, [Self::try_encode] to reject them when encoding or [Self::builder] to name
+    /// every field.
     pub fn new(
         version: u8,
         packet_type: PacketType,
@@ -199,130 +888,1938 @@ impl SpacePacket {
             payload,
         }
     }
-}
-impl SpacePacket {
-    /// Encodes the packet and header to a bytes array.
-    /// This encoding assumed BigEndian-ness
-    /// Adds the payload len -1 to the appropriate location in the encoded header
-    pub fn encode(&self) -> Vec<u8> {
-        let mut message = self.primary_header.encode();
-        // lists the length of the payload minus one as per CCSDS specs
-        let header_2 = (self.payload.len() - 1) as u16;
 
-        message.extend(header_2.to_be_bytes());
-        message.extend(self.payload.clone());
+    /// Create a packet like [Self::new], rejecting header fields wider than their bits
+    /// instead of leaving them to [Self::encode].
+    ///
+    /// # Errors
+    ///
+    /// Errors with the first field exceeding its bits, see [PrimaryHeader::validate].
+    pub fn try_new(
+        version: u8,
+        packet_type: PacketType,
+        apid: u16,
+        grouping: GroupingFlag,
+        sequence_count: u16,
+        secondary_header: bool,
+        payload: Vec<u8>,
+    ) -> Result<Self, FieldOutOfRange> {
+        let packet = Self::new(
+            version,
+            packet_type,
+            apid,
+            grouping,
+            sequence_count,
+            secondary_header,
+            payload,
+        );
+        packet.primary_header.validate()?;
+        Ok(packet)
+    }
 
-        message
+    /// Assign this packet the next sequence count of its APID from the `sequencer`.
+    pub fn with_next_sequence(mut self, sequencer: &mut sequencer::ApidSequencer) -> Self {
+        self.primary_header.sequence_count = sequencer.next(self.primary_header.apid);
+        self
     }
-    /// Decode the header and retrieve the payload
-    /// This decoding assumed BigEndian-ness
-    pub fn decode<R: Read>(buffer: &mut R) -> std::io::Result<Self> {
-        let primary_header = PrimaryHeader::decode(buffer)?;
-        // add one to acount for CCSDS standard subtracting 1
-        let message_len = buffer.read_u16::<BigEndian>()? + 1;
 
-        let payload = {
-            let mut temp = vec![0_u8; message_len as usize];
-            buffer.read_exact(&mut temp)?;
-            temp
-        };
+    /// Construct a packet whose payload is the `secondary_header` followed by the `user_data`,
+    /// setting the secondary header flag of the `primary_header`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the `secondary_header` encodes to no bytes, CCSDS 133.0-B-2 requires
+    /// a present secondary header to be at least one byte long.
+    pub fn with_secondary_header<S: SecondaryHeader + ?Sized>(
+        primary_header: PrimaryHeader,
+        secondary_header: &S,
+        user_data: Vec<u8>,
+    ) -> std::io::Result<Self> {
+        let mut payload = Vec::with_capacity(secondary_header.encoded_len() + user_data.len());
+        secondary_header.encode_into(&mut payload);
+        if payload.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Secondary header must be at least 1 byte",
+            ));
+        }
+        payload.extend(user_data);
 
         Ok(Self {
-            primary_header,
+            primary_header: PrimaryHeader {
+                secondary_header: true,
+                ..primary_header
+            },
             payload,
         })
     }
 
-    #[cfg(feature = "crc")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
-    /// Encode the CCSDS packet and append a CRC-16 value using the provied [Crc].
-    /// This method assumes the length of the CRC should be **included** in the payload length of the CCSDS Packet.
-    pub fn encode_crc(&self, crc: &Crc<u16>) -> Vec<u8> {
-        let mut message = self.primary_header.encode();
-        // lists the length of the payload minus one as per CCSDS specs
-        // add two to account for crc appended to the end
-        let header_2 = (self.payload.len() - 1 + 2) as u16;
+    /// Construct a packet whose payload is only the `user_data`,
+    /// clearing the secondary header flag of the `primary_header`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the `user_data` is empty, CCSDS 133.0-B-2 requires
+    /// at least one byte of user data without a secondary header.
+    pub fn without_secondary_header(
+        primary_header: PrimaryHeader,
+        user_data: Vec<u8>,
+    ) -> std::io::Result<Self> {
+        if user_data.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "User data must be at least 1 byte without a secondary header",
+            ));
+        }
+        Ok(Self {
+            primary_header: PrimaryHeader {
+                secondary_header: false,
+                ..primary_header
+            },
+            payload: user_data,
+        })
+    }
 
-        message.extend(header_2.to_be_bytes());
-        message.extend(self.payload.clone());
-        message.extend(crc.checksum(message.as_slice()).to_be_bytes());
+    /// Check the secondary header flag agrees with the payload, where `secondary_header_len`
+    /// looks up the secondary header length registered for an APID, if any.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [std::io::ErrorKind::InvalidData] if the flag is set but the payload is
+    /// shorter than the secondary header length registered for the APID.
+    pub fn validate_semantics<F: Fn(u16) -> Option<usize>>(
+        &self,
+        secondary_header_len: F,
+    ) -> std::io::Result<()> {
+        if !self.primary_header.secondary_header {
+            return Ok(());
+        }
+        match secondary_header_len(self.primary_header.apid) {
+            Some(len) if self.payload.len() < len => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Secondary header of APID {:#05X} is {len} bytes but the payload is only {}",
+                    self.primary_header.apid,
+                    self.payload.len()
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+/// Builds a [SpacePacket] field by field, see [SpacePacket::builder].
+///
+/// Fields which are not set default to
+///  - `version`: `0`
+///  - `packet_type`: [PacketType::Telemetry]
+///  - `apid`: `0`
+///  - `grouping`: [GroupingFlag::Unsegm]
+///  - `sequence_count`: `0`
+///  - `secondary_header`: `false`
+///
+/// ```
+/// use spacepacket::{GroupingFlag, SpacePacket};
+///
+/// let packet = SpacePacket::builder()
+///     .command()
+///     .apid(0x42)
+///     .sequence_count(3)
+///     .grouping(GroupingFlag::First)
+///     .payload(b"secret payload".to_vec())
+///     .build()
+///     .unwrap();
+/// assert_eq!(0x42, packet.primary_header.apid);
+///
+/// // the APID is only 11 bits wide
+/// assert!(SpacePacket::builder().apid(0x800).payload([1]).build().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpacePacketBuilder {
+    header: PrimaryHeader,
+    payload: Vec<u8>,
+}
+impl Default for SpacePacketBuilder {
+    fn default() -> Self {
+        Self {
+            header: PrimaryHeader {
+                version: 0,
+                packet_type: PacketType::Telemetry,
+                secondary_header: false,
+                apid: 0,
+                grouping: GroupingFlag::Unsegm,
+                sequence_count: 0,
+            },
+            payload: vec![],
+        }
+    }
+}
+impl SpacePacketBuilder {
+    /// Set the 3-bit packet version number.
+    pub fn version(mut self, version: u8) -> Self {
+        self.header.version = version;
+        self
+    }
 
-        message
+    /// Mark the packet as a telecommand.
+    pub fn command(mut self) -> Self {
+        self.header.packet_type = PacketType::Command;
+        self
     }
 
-    #[cfg(feature = "crc")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
-    /// Decode a CCSDS packet with an appended a CRC-16 value using the provied [Crc].
-    /// This method assumes the length of the CRC should be **included** in the payload length of the CCSDS Packet.
-    /// The crc is stripped from the byte stream and not included in the returned packet.
-    /// Error if the packet's CRC is not valid.
-    pub fn decode_crc<R: Read>(buffer: &mut R, crc: &Crc<u16>) -> std::io::Result<CompletePacket> {
-        let full_message = {
+    /// Mark the packet as telemetry.
+    pub fn telemetry(mut self) -> Self {
+        self.header.packet_type = PacketType::Telemetry;
+        self
+    }
+
+    /// Set the 11-bit application process ID.
+    pub fn apid(mut self, apid: u16) -> Self {
+        self.header.apid = apid;
+        self
+    }
+
+    /// Set the grouping of the packet within a sequence of segments.
+    pub fn grouping(mut self, grouping: GroupingFlag) -> Self {
+        self.header.grouping = grouping;
+        self
+    }
+
+    /// Set the 14-bit sequence count.
+    pub fn sequence_count(mut self, sequence_count: u16) -> Self {
+        self.header.sequence_count = sequence_count;
+        self
+    }
+
+    /// Mark a secondary header as present at the start of the payload.
+    pub fn secondary_header(mut self) -> Self {
+        self.header.secondary_header = true;
+        self
+    }
+
+    /// Set the payload, including any secondary header.
+    pub fn payload(mut self, payload: impl Into<Vec<u8>>) -> Self {
+        self.payload = payload.into();
+        self
+    }
+
+    /// Construct the packet.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - a header field fails [PrimaryHeader::validate]
+    ///  - the payload is empty or longer than [SpacePacket::MAX_PAYLOAD_LEN]
+    pub fn build(self) -> std::io::Result<SpacePacket> {
+        self.header.validate()?;
+        PrimaryHeader::data_length(self.payload.len())?;
+        Ok(SpacePacket {
+            primary_header: self.header,
+            payload: self.payload,
+        })
+    }
+}
+
+impl SpacePacket {
+    /// Construct a telemetry Idle Packet with the CCSDS reserved [IDLE_APID]
+    /// and a payload of `payload_len` fill bytes.
+    pub fn idle(payload_len: usize) -> Self {
+        Self::idle_with_apid(IDLE_APID, payload_len)
+    }
+
+    /// Construct a telemetry Idle Packet with a mission specific idle APID
+    /// and a payload of `payload_len` fill bytes.
+    pub fn idle_with_apid(apid: u16, payload_len: usize) -> Self {
+        Self::new(
+            0,
+            PacketType::Telemetry,
+            apid,
+            GroupingFlag::Unsegm,
+            0,
+            false,
+            vec![0x55; payload_len],
+        )
+    }
+
+    /// Construct a telemetry Idle Packet with a mission specific idle APID
+    /// and a payload of `payload_len` bytes following the fill `pattern`.
+    pub fn idle_with_pattern(apid: u16, payload_len: usize, pattern: &FillPattern) -> Self {
+        Self::new(
+            0,
+            PacketType::Telemetry,
+            apid,
+            GroupingFlag::Unsegm,
+            0,
+            false,
+            (0..payload_len)
+                .map(|offset| pattern.byte(offset))
+                .collect(),
+        )
+    }
+
+    /// Check every payload byte follows the fill `pattern`, a cheap check of link
+    /// quality on the Idle Packets which fill a stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns the offset of the first byte differing from the pattern.
+    pub fn verify_idle_pattern(&self, pattern: &FillPattern) -> Result<(), IdleCorruption> {
+        match self
+            .payload
+            .iter()
+            .enumerate()
+            .position(|(offset, byte)| *byte != pattern.byte(offset))
+        {
+            Some(first_bad_offset) => Err(IdleCorruption { first_bad_offset }),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether this packet has the CCSDS reserved [IDLE_APID].
+    pub fn is_idle(&self) -> bool {
+        self.is_idle_with_apid(IDLE_APID)
+    }
+
+    /// Whether this packet has the given mission specific idle APID.
+    pub fn is_idle_with_apid(&self, apid: u16) -> bool {
+        self.primary_header.apid == apid
+    }
+
+    /// Iterate over the packets stored back to back in the `buffer` without allocating.
+    ///
+    /// Iteration ends after the last packet, or after yielding an error
+    /// if the `buffer` ends within a packet.
+    pub fn iter_refs(buffer: &[u8]) -> impl Iterator<Item = std::io::Result<SpacePacketRef<'_>>> {
+        let mut remaining = buffer;
+        std::iter::from_fn(move || {
+            if remaining.is_empty() {
+                return None;
+            }
+            match SpacePacketRef::decode(remaining) {
+                Ok((packet, rest)) => {
+                    remaining = rest;
+                    Some(Ok(packet))
+                }
+                Err(err) => {
+                    remaining = &[];
+                    Some(Err(err))
+                }
+            }
+        })
+    }
+
+    /// Borrow this packet as a [SpacePacketRef].
+    pub fn borrowed(&self) -> SpacePacketRef<'_> {
+        SpacePacketRef {
+            primary_header: self.primary_header,
+            payload: &self.payload,
+        }
+    }
+
+    /// Decode a packet without allocating by reading the payload into the caller provided
+    /// `scratch` buffer. The returned [SpacePacketRef] borrows its payload from `scratch`.
+    /// This decoding assumed BigEndian-ness
+    ///
+    /// # Errors
+    ///
+    /// Errors if `scratch` is shorter than the payload, in which case the
+    /// primary header has already been consumed from the `buffer`.
+    pub fn decode_into<'a, R: Read>(
+        buffer: &mut R,
+        scratch: &'a mut [u8],
+    ) -> std::io::Result<SpacePacketRef<'a>> {
+        let (primary_header, length) = PrimaryHeader::decode_with_length(buffer)?;
+        let message_len = usize::from(length) + 1;
+
+        let scratch_len = scratch.len();
+        let payload = scratch.get_mut(..message_len).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Scratch buffer of length {scratch_len} cannot hold payload of length {message_len}"),
+            )
+        })?;
+        buffer.read_exact(payload)?;
+
+        Ok(SpacePacketRef {
+            primary_header,
+            payload,
+        })
+    }
+
+    /// Decode a packet reading the payload into the caller provided `payload`,
+    /// which is cleared and resized to the payload length, reusing its allocation.
+    /// Only the header is returned, the payload is left in `payload`.
+    /// This decoding assumed BigEndian-ness
+    ///
+    /// # Errors
+    ///
+    /// Errors if the packet cannot be read, in which case the contents of `payload` are unspecified.
+    pub fn decode_into_vec<R: Read>(
+        buffer: &mut R,
+        payload: &mut Vec<u8>,
+    ) -> std::io::Result<PrimaryHeader> {
+        let (primary_header, length) = PrimaryHeader::decode_with_length(buffer)?;
+        let message_len = usize::from(length) + 1;
+
+        payload.clear();
+        payload.resize(message_len, 0);
+        buffer.read_exact(payload)?;
+
+        Ok(primary_header)
+    }
+}
+impl SpacePacket {
+    /// The shortest possible encoded packet, a [PrimaryHeader] with a 1 byte payload.
+    pub const MIN_WIRE_LEN: usize = PrimaryHeader::WIRE_LEN + 1;
+
+    /// The longest payload the 16-bit Packet Data Length field can describe.
+    pub const MAX_PAYLOAD_LEN: usize = u16::MAX as usize + 1;
+
+    /// The longest payload which can be encoded with [Self::encode_crc],
+    /// the CRC counts towards the Packet Data Length.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub const MAX_PAYLOAD_LEN_CRC: usize = Self::MAX_PAYLOAD_LEN - std::mem::size_of::<u16>();
+
+    /// The longest payload which can be encoded with [Self::encode_crc32],
+    /// the CRC counts towards the Packet Data Length.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub const MAX_PAYLOAD_LEN_CRC32: usize = Self::MAX_PAYLOAD_LEN - std::mem::size_of::<u32>();
+
+    /// Refuse the packet unless every primary header field fits its bits.
+    #[cfg(any(feature = "async-codec", feature = "tokio-codec"))]
+    pub(crate) fn reject_header(self) -> Result<Self, RejectedPacket> {
+        match self.primary_header.validate() {
+            Ok(()) => Ok(self),
+            Err(err) => Err(RejectedPacket {
+                reason: RejectReason::HeaderField(err),
+                packet: Box::new(self),
+            }),
+        }
+    }
+
+    /// Refuse the packet unless its payload length is within `1..=max_len` bytes.
+    #[cfg(any(feature = "async-codec", feature = "tokio-codec"))]
+    pub(crate) fn reject_payload_len(self, max_len: usize) -> Result<Self, RejectedPacket> {
+        let len = self.payload.len();
+        if (1..=max_len).contains(&len) {
+            return Ok(self);
+        }
+        Err(RejectedPacket {
+            reason: RejectReason::PayloadLength { len, max_len },
+            packet: Box::new(self),
+        })
+    }
+
+    /// Check the payload length is within `1..=max_len` bytes.
+    pub(crate) fn check_payload_len(&self, max_len: usize) -> std::io::Result<()> {
+        if (1..=max_len).contains(&self.payload.len()) {
+            return Ok(());
+        }
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "Payload length must be in 1..={max_len} bytes but found {}",
+                self.payload.len()
+            ),
+        ))
+    }
+
+    /// Encodes the packet and header to a bytes array.
+    /// This encoding assumed BigEndian-ness
+    /// Adds the payload len -1 to the appropriate location in the encoded header
+    ///
+    /// Use [Self::encode_checked] or [Self::try_encode] for payloads of unknown length.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the payload is empty or longer than [Self::MAX_PAYLOAD_LEN].
+    ///
+    /// In debug builds this function panics if a header field does not fit in its bit-depth,
+    /// see [PrimaryHeader::validate], as decoding the output would not reproduce `self`.
+    /// Release builds mask such fields instead, see [PrimaryHeader::clamped].
+    /// Use [Self::try_encode] to reject them in every build.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(self.encoded_len());
+        self.encode_into(&mut message);
+        message
+    }
+
+    /// The number of bytes [Self::encode] produces, the primary header
+    /// including the Packet Data Length field followed by the payload.
+    pub fn encoded_len(&self) -> usize {
+        PrimaryHeader::WIRE_LEN + self.payload.len()
+    }
+
+    /// The number of bytes [Self::encode_crc] produces, [Self::encoded_len] plus the CRC-16.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub fn encoded_len_crc(&self) -> usize {
+        self.encoded_len() + std::mem::size_of::<u16>()
+    }
+
+    /// Append the encoded packet to the end of `out` without allocating beyond growing `out`.
+    /// This encoding assumed BigEndian-ness
+    ///
+    /// Returns the number of bytes appended.
+    ///
+    /// # Panics
+    ///
+    /// Panics as [Self::encode].
+    pub fn encode_into(&self, out: &mut Vec<u8>) -> usize {
+        let header = self.header_bytes();
+        let start = out.len();
+        out.reserve(self.encoded_len());
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&self.payload);
+        out.len() - start
+    }
+
+    /// Append the encoded packet to the end of `out`, see [Self::encode_into].
+    ///
+    /// # Panics
+    ///
+    /// Panics as [Self::encode].
+    #[cfg(any(feature = "async-codec", feature = "tokio-codec"))]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(any(feature = "async-codec", feature = "tokio-codec")))
+    )]
+    pub fn encode_into_bytes(&self, out: &mut bytes::BytesMut) -> usize {
+        let header = self.header_bytes();
+        out.reserve(self.encoded_len());
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&self.payload);
+        self.encoded_len()
+    }
+
+    /// The encoded primary header including the Packet Data Length field.
+    fn header_bytes(&self) -> [u8; PrimaryHeader::WIRE_LEN] {
+        // Only checked in debug builds, catches fields which do not survive encoding.
+        #[cfg(debug_assertions)]
+        if let Err(err) = self.primary_header.validate() {
+            panic!("SpacePacket does not roundtrip through encoding: {err}");
+        }
+        // lists the length of the payload minus one as per CCSDS specs
+        let header_2 = match PrimaryHeader::data_length(self.payload.len()) {
+            Ok(header_2) => header_2,
+            Err(err) => panic!("{err}"),
+        };
+        let [b0, b1, b2, b3] = self.primary_header.to_bytes();
+        let [b4, b5] = header_2.to_be_bytes();
+        [b0, b1, b2, b3, b4, b5]
+    }
+    /// Encode the packet as [Self::encode], rejecting header fields wider than their bits
+    /// and payloads the Packet Data Length field cannot describe instead of panicking,
+    /// e.g. for packets built from dynamically sized buffers.
+    ///
+    /// # Errors
+    ///
+    /// Errors with an [EncodeError] if [PrimaryHeader::validate] fails, or if the payload
+    /// is empty or longer than [Self::MAX_PAYLOAD_LEN].
+    pub fn encode_checked(&self) -> std::io::Result<Vec<u8>> {
+        Ok(self.try_encode()?)
+    }
+
+    /// Encode the packet as [Self::encode_checked], keeping the [EncodeError]
+    /// typed for callers matching on the rejected field or length.
+    ///
+    /// # Errors
+    ///
+    /// Errors with the first header field exceeding its bits, see [PrimaryHeader::validate],
+    /// or if the payload is empty or longer than [Self::MAX_PAYLOAD_LEN].
+    pub fn try_encode(&self) -> Result<Vec<u8>, EncodeError> {
+        self.primary_header.validate()?;
+        PrimaryHeader::data_length(self.payload.len())?;
+        Ok(self.encode())
+    }
+
+    /// Decode a packet whose Packet Data Length field may be damaged, for recovering
+    /// data from corrupted captures.
+    ///
+    /// The `buffer` must start with the [PrimaryHeader]. The declared length is ignored and the
+    /// payload is instead read up to the next occurrence of the synchronization `marker`,
+    /// or to the end of the `buffer` when no marker follows.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - `marker` is empty
+    ///  - `buffer` is too short to contain a header and at least 1 byte of payload
+    pub fn decode_until_marker(buffer: &[u8], marker: &[u8]) -> std::io::Result<RecoveredPacket> {
+        if marker.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Synchronization marker must not be empty",
+            ));
+        }
+
+        let (primary_header, length) = PrimaryHeader::decode_with_length(&mut &buffer[..])?;
+        let declared_len = usize::from(length) + 1;
+
+        let remaining = &buffer[PrimaryHeader::WIRE_LEN..];
+        let payload_len = remaining
+            .windows(marker.len())
+            .position(|window| window == marker)
+            .unwrap_or(remaining.len());
+        if payload_len == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "No payload precedes the next synchronization marker",
+            ));
+        }
+
+        Ok(RecoveredPacket {
+            packet: Self {
+                primary_header,
+                payload: remaining[..payload_len].to_vec(),
+            },
+            declared_len,
+            length_recovered: declared_len != payload_len,
+        })
+    }
+
+    /// Decode the header and retrieve the payload
+    /// This decoding assumed BigEndian-ness
+    pub fn decode<R: Read>(buffer: &mut R) -> std::io::Result<Self> {
+        let (primary_header, length) = PrimaryHeader::decode_with_length(buffer)?;
+        // add one to acount for CCSDS standard subtracting 1
+        let message_len = usize::from(length) + 1;
+
+        let payload = {
+            let mut temp = vec![0_u8; message_len];
+            buffer.read_exact(&mut temp)?;
+            temp
+        };
+
+        Ok(Self {
+            primary_header,
+            payload,
+        })
+    }
+
+    /// Decode a packet at the start of `buffer` without copying its payload,
+    /// see [SpacePacketRef::decode].
+    ///
+    /// Returns the packet borrowing its payload from `buffer` and the number of bytes consumed.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [std::io::ErrorKind::UnexpectedEof] if the `buffer` ends within the packet.
+    pub fn decode_borrowed(buffer: &[u8]) -> std::io::Result<(SpacePacketRef<'_>, usize)> {
+        let (packet, rest) = SpacePacketRef::decode(buffer)?;
+        Ok((packet, buffer.len() - rest.len()))
+    }
+
+    /// Decode a packet from the start of `buffer` along with the location of every
+    /// header field and the payload within `buffer`, e.g. for a hex view with field overlays.
+    ///
+    /// Spans are returned in wire order: `version`, `packet_type`, `secondary_header`,
+    /// `apid`, `grouping`, `sequence_count`, `packet_data_length` and `payload`.
+    /// Bytes following the packet are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `buffer` does not hold a complete packet.
+    pub fn decode_annotated(buffer: &[u8]) -> std::io::Result<(Self, Vec<FieldSpan>)> {
+        let packet = Self::decode(&mut &buffer[..])?;
+
+        let payload_start = 8 * PrimaryHeader::WIRE_LEN;
+        let spans = [
+            ("version", 0..3),
+            ("packet_type", 3..4),
+            ("secondary_header", 4..5),
+            ("apid", 5..16),
+            ("grouping", 16..18),
+            ("sequence_count", 18..32),
+            ("packet_data_length", 32..payload_start),
+            (
+                "payload",
+                payload_start..payload_start + 8 * packet.payload.len(),
+            ),
+        ]
+        .into_iter()
+        .map(|(name, bits)| FieldSpan { name, bits })
+        .collect();
+
+        Ok((packet, spans))
+    }
+
+    /// Encode the packet with the Packet Data Length field following `convention`.
+    ///
+    /// [LengthConvention::CcsdsMinusOne] produces the same bytes as [Self::encode],
+    /// [LengthConvention::Actual] is non-standard and only meant for non-compliant peers.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the payload length cannot be described under `convention`.
+    pub fn encode_with_convention(&self, convention: LengthConvention) -> std::io::Result<Vec<u8>> {
+        let length = convention.data_length(self.payload.len())?;
+
+        let mut message = Vec::with_capacity(PrimaryHeader::WIRE_LEN + self.payload.len());
+        message.extend(self.primary_header.to_bytes());
+        message.extend(length.to_be_bytes());
+        message.extend_from_slice(&self.payload);
+        Ok(message)
+    }
+
+    /// Decode a packet whose Packet Data Length field follows `convention`.
+    ///
+    /// [LengthConvention::CcsdsMinusOne] behaves like [Self::decode],
+    /// [LengthConvention::Actual] is non-standard and only meant for non-compliant peers.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the packet cannot be read or the Packet Data Length field describes an empty payload.
+    pub fn decode_with_convention<R: Read>(
+        buffer: &mut R,
+        convention: LengthConvention,
+    ) -> std::io::Result<Self> {
+        let (primary_header, length) = PrimaryHeader::decode_with_length(buffer)?;
+        let message_len = convention.data_field_len(length);
+        if message_len == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Packet Data Length field describes an empty payload",
+            ));
+        }
+
+        let mut payload = vec![0_u8; message_len];
+        buffer.read_exact(&mut payload)?;
+
+        Ok(Self {
+            primary_header,
+            payload,
+        })
+    }
+
+    /// Split the encoded packet into a scatter list borrowing the payload.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the payload is empty or longer than [Self::MAX_PAYLOAD_LEN].
+    pub fn encode_vectored(&self) -> std::io::Result<VectoredPacket<'_>> {
+        self.check_payload_len(Self::MAX_PAYLOAD_LEN)?;
+        Ok(VectoredPacket {
+            header: self.vectored_header(0)?,
+            payload: &self.payload,
+            trailer: None,
+        })
+    }
+
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    /// Split the encoded packet into a scatter list as [Self::encode_vectored],
+    /// with a CRC-16 trailer computed incrementally over the header and payload.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the payload is empty or longer than [Self::MAX_PAYLOAD_LEN_CRC].
+    pub fn encode_vectored_crc(&self, crc: &Crc<u16>) -> std::io::Result<VectoredPacket<'_>> {
+        self.check_payload_len(Self::MAX_PAYLOAD_LEN_CRC)?;
+        let header = self.vectored_header(std::mem::size_of::<u16>())?;
+
+        let mut digest = crc.digest();
+        digest.update(&header);
+        digest.update(&self.payload);
+        Ok(VectoredPacket {
+            header,
+            payload: &self.payload,
+            trailer: Some(digest.finalize().to_be_bytes()),
+        })
+    }
+
+    /// The encoded primary header of a packet followed by a trailer of `trailer_len` bytes.
+    fn vectored_header(
+        &self,
+        trailer_len: usize,
+    ) -> std::io::Result<[u8; PrimaryHeader::WIRE_LEN]> {
+        let mut header = [0_u8; PrimaryHeader::WIRE_LEN];
+        header[..4].copy_from_slice(&self.primary_header.to_bytes());
+        header[PrimaryHeader::LENGTH_FIELD_RANGE].copy_from_slice(
+            &PrimaryHeader::data_length(self.payload.len() + trailer_len)?.to_be_bytes(),
+        );
+        Ok(header)
+    }
+
+    /// Write the encoded packet to `writer` without first copying it into a buffer,
+    /// returning the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the payload is empty or longer than [Self::MAX_PAYLOAD_LEN],
+    /// or if writing to the `writer` fails.
+    pub fn encode_to_writer<W: Write + ?Sized>(&self, writer: &mut W) -> std::io::Result<usize> {
+        let packet = self.encode_vectored()?;
+        packet.write_to(writer)?;
+        Ok(packet.len())
+    }
+
+    /// Encode the CCSDS packet and append the Packet Error Control field computed by `check`.
+    /// The length of the trailer is **included** in the payload length of the CCSDS Packet.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the payload is empty or longer than [Self::MAX_PAYLOAD_LEN] minus the trailer width.
+    pub fn encode_with_trailer<T: TrailerCheck + ?Sized>(
+        &self,
+        check: &T,
+    ) -> std::io::Result<Vec<u8>> {
+        let width = check.width();
+        self.check_payload_len(Self::MAX_PAYLOAD_LEN.saturating_sub(width))?;
+        let mut message = Vec::with_capacity(PrimaryHeader::WIRE_LEN + self.payload.len() + width);
+        message.extend(self.primary_header.to_bytes());
+        // lists the length of the payload minus one as per CCSDS specs
+        // add the trailer width to account for the trailer appended to the end
+        let header_2 = PrimaryHeader::data_length(self.payload.len() + width)?;
+
+        message.extend(header_2.to_be_bytes());
+        message.extend_from_slice(&self.payload);
+
+        let data_len = message.len();
+        message.resize(data_len + width, 0);
+        let (data, trailer) = message.split_at_mut(data_len);
+        check.compute(data, trailer);
+
+        Ok(message)
+    }
+
+    /// Decode a CCSDS packet with an appended Packet Error Control field verified by `check`.
+    /// The length of the trailer is **included** in the payload length of the CCSDS Packet.
+    /// The trailer is stripped from the byte stream and not included in the returned packet.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the packet cannot be read or its payload is too short to hold the trailer and
+    /// at least one byte of data. An invalid trailer is returned as [CheckedPacket::Invalid].
+    pub fn decode_with_trailer<R: Read, T: TrailerCheck + ?Sized>(
+        buffer: &mut R,
+        check: &T,
+    ) -> std::io::Result<CheckedPacket> {
+        let mut full_message = {
             // read the ccsds header
             let header_buffer = {
-                let mut tmp = [0_u8; 6];
+                let mut tmp = [0_u8; PrimaryHeader::WIRE_LEN];
                 buffer.read_exact(&mut tmp)?;
                 tmp
             };
             // get the total length of the packet
             // add one to acount for CCSDS standard subtracting 1
-            let message_len = (&header_buffer.as_slice()[4..6]).read_u16::<BigEndian>()? + 1;
+            let message_len = usize::from(
+                (&header_buffer[PrimaryHeader::LENGTH_FIELD_RANGE]).read_u16::<BigEndian>()?,
+            ) + 1;
+
+            // a single allocation which becomes the payload once the trailer is verified
+            let mut temp = Vec::with_capacity(PrimaryHeader::WIRE_LEN + message_len);
+            temp.extend_from_slice(&header_buffer);
+            temp.resize(PrimaryHeader::WIRE_LEN + message_len, 0);
+            buffer.read_exact(&mut temp[PrimaryHeader::WIRE_LEN..])?;
+            temp
+        };
+
+        let width = check.width();
+        if full_message.len() <= PrimaryHeader::WIRE_LEN + width {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Packet payload of {} bytes cannot hold a {width} byte trailer and data",
+                    full_message.len() - PrimaryHeader::WIRE_LEN
+                ),
+            ));
+        }
+
+        let (data, sent) = full_message.split_at(full_message.len() - width);
+        if !check.verify(data, sent) {
+            let mut computed = vec![0_u8; width];
+            check.compute(data, &mut computed);
+            return Ok(CheckedPacket::Invalid {
+                sent: sent.to_vec(),
+                computed,
+                data: data.to_vec(),
+            });
+        }
+
+        let primary_header = PrimaryHeader::decode(&mut &data[..])?;
+
+        full_message.truncate(full_message.len() - width);
+        full_message.drain(..PrimaryHeader::WIRE_LEN);
+        Ok(CheckedPacket::Valid(Self {
+            primary_header,
+            payload: full_message,
+        }))
+    }
+
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    /// Encode the CCSDS packet and append a CRC-16 value using the provied [Crc].
+    /// This method assumes the length of the CRC should be **included** in the payload length of the CCSDS Packet.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the payload is empty or longer than [Self::MAX_PAYLOAD_LEN_CRC].
+    pub fn encode_crc(&self, crc: &Crc<u16>) -> std::io::Result<Vec<u8>> {
+        self.encode_with_trailer(crc)
+    }
+
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    /// Write the packet as encoded by [Self::encode_crc] to `writer`, computing the CRC
+    /// incrementally instead of buffering the packet. Returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the payload is empty or longer than [Self::MAX_PAYLOAD_LEN_CRC],
+    /// or if writing to the `writer` fails.
+    pub fn encode_crc_to_writer<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        crc: &Crc<u16>,
+    ) -> std::io::Result<usize> {
+        let packet = self.encode_vectored_crc(crc)?;
+        packet.write_to(writer)?;
+        Ok(packet.len())
+    }
+
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    /// Decode a CCSDS packet with an appended a CRC-16 value using the provied [Crc].
+    /// This method assumes the length of the CRC should be **included** in the payload length of the CCSDS Packet.
+    /// The crc is stripped from the byte stream and not included in the returned packet.
+    /// Error if the packet's CRC is not valid.
+    pub fn decode_crc<R: Read>(buffer: &mut R, crc: &Crc<u16>) -> std::io::Result<CompletePacket> {
+        Ok(match Self::decode_with_trailer(buffer, crc)? {
+            CheckedPacket::Valid(packet) => CompletePacket::Valid(packet),
+            CheckedPacket::Invalid {
+                sent,
+                computed,
+                data,
+            } => CompletePacket::InvalidCRC(
+                u16::from_be_bytes([sent[0], sent[1]]),
+                u16::from_be_bytes([computed[0], computed[1]]),
+                data,
+            ),
+        })
+    }
+
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    /// Encode the CCSDS packet and append a big-endian CRC-32 value using the provided [Crc].
+    /// The 4 bytes of the CRC are **included** in the payload length of the CCSDS Packet.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the payload is empty or longer than [Self::MAX_PAYLOAD_LEN_CRC32].
+    pub fn encode_crc32(&self, crc: &Crc<u32>) -> std::io::Result<Vec<u8>> {
+        self.encode_with_trailer(crc)
+    }
+
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    /// Decode a CCSDS packet with an appended big-endian CRC-32 value using the provided [Crc].
+    /// The 4 bytes of the CRC are **included** in the payload length of the CCSDS Packet
+    /// and stripped from the returned packet.
+    ///
+    /// A packet failing its CRC is returned as [CheckedPacket::Invalid] holding
+    /// both 4 byte CRCs, since [CompletePacket::InvalidCRC] only holds CRC-16 values.
+    pub fn decode_crc32<R: Read>(buffer: &mut R, crc: &Crc<u32>) -> std::io::Result<CheckedPacket> {
+        Self::decode_with_trailer(buffer, crc)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "crc")]
+    use crc::{CRC_16_IBM_3740, CRC_32_ISO_HDLC};
+    use rstest::rstest;
+
+    #[rstest]
+    fn header_roundtrip(
+        #[values(
+            GroupingFlag::Interm,
+            GroupingFlag::First,
+            GroupingFlag::Last,
+            GroupingFlag::Unsegm
+        )]
+        grouping: GroupingFlag,
+        #[values(true, false)] secondary_header: bool,
+        #[values(PacketType::Command, PacketType::Telemetry)] packet_type: PacketType,
+    ) {
+        let expected = PrimaryHeader {
+            version: 0_u8,
+            packet_type,
+            apid: 2042_u16,
+            secondary_header,
+            grouping,
+            sequence_count: 16355_u16,
+        };
+
+        let buffer = expected.encode();
+
+        let recovered = PrimaryHeader::decode(&mut buffer.as_slice())
+            .expect("Unable to decode Primary Header.");
+
+        assert_eq!(expected, recovered)
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "SpacePacket does not roundtrip through encoding")]
+    fn spacepacket_encode_roundtrip_check() {
+        // APID is only 11 bits wide
+        SpacePacket::new(
+            0,
+            PacketType::Telemetry,
+            0x800,
+            GroupingFlag::Unsegm,
+            7,
+            false,
+            b"a test input".to_vec(),
+        )
+        .encode();
+    }
+
+    #[test]
+    #[cfg(all(
+        debug_assertions,
+        any(feature = "async-codec", feature = "tokio-codec")
+    ))]
+    #[should_panic(expected = "SpacePacket does not roundtrip through encoding")]
+    fn spacepacket_encode_into_bytes_roundtrip_check() {
+        // sequence count is only 14 bits wide
+        SpacePacket::new(
+            0,
+            PacketType::Telemetry,
+            7,
+            GroupingFlag::Unsegm,
+            0x4000,
+            false,
+            b"a test input".to_vec(),
+        )
+        .encode_into_bytes(&mut bytes::BytesMut::new());
+    }
+
+    #[rstest]
+    #[case(0x7, 0x7FF, 0x3FFF, None)]
+    #[case(0x8, 0, 0, Some(("version", 0x8, 0x7)))]
+    #[case(0, 0x800, 0, Some(("apid", 0x800, 0x7FF)))]
+    #[case(0, 0x1000, 0, Some(("apid", 0x1000, 0x7FF)))]
+    #[case(0, 0, 0x4000, Some(("sequence_count", 0x4000, 0x3FFF)))]
+    #[case(0, 0, u16::MAX, Some(("sequence_count", u16::MAX, 0x3FFF)))]
+    fn spacepacket_try_new(
+        #[case] version: u8,
+        #[case] apid: u16,
+        #[case] sequence_count: u16,
+        #[case] expected: Option<(&'static str, u16, u16)>,
+    ) {
+        let packet = SpacePacket::try_new(
+            version,
+            PacketType::Command,
+            apid,
+            GroupingFlag::Unsegm,
+            sequence_count,
+            false,
+            vec![0x42],
+        );
+        let header = PrimaryHeader {
+            version,
+            packet_type: PacketType::Command,
+            secondary_header: false,
+            apid,
+            grouping: GroupingFlag::Unsegm,
+            sequence_count,
+        };
+        let expected_err =
+            expected.map(|(field, value, max)| FieldOutOfRange { field, value, max });
+        assert_eq!(expected_err.map_or(Ok(()), Err), header.validate());
+        assert_eq!(
+            expected_err.map_or_else(|| Ok(header.encode()), Err),
+            header.try_encode()
+        );
+
+        match expected {
+            None => {
+                let packet = packet.unwrap();
+                let decoded = SpacePacket::decode(&mut packet.encode().as_slice()).unwrap();
+                assert_eq!(packet, decoded);
+            }
+            Some((field, value, max)) => {
+                assert_eq!(Err(FieldOutOfRange { field, value, max }), packet)
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(0x7, 0x7FF, 0x3FFF, 1, true)]
+    #[case(0x8, 0, 0, 1, false)]
+    #[case(0, 0x800, 0, 1, false)]
+    #[case(0, 0, 0x4000, 1, false)]
+    #[case(0, 0, 0, 0, false)]
+    #[case(0, 0, 0, SpacePacket::MAX_PAYLOAD_LEN, true)]
+    #[case(0, 0, 0, SpacePacket::MAX_PAYLOAD_LEN + 1, false)]
+    fn spacepacket_builder(
+        #[case] version: u8,
+        #[case] apid: u16,
+        #[case] sequence_count: u16,
+        #[case] payload_len: usize,
+        #[case] valid: bool,
+    ) {
+        let built = SpacePacket::builder()
+            .version(version)
+            .command()
+            .apid(apid)
+            .grouping(GroupingFlag::Last)
+            .sequence_count(sequence_count)
+            .secondary_header()
+            .payload(vec![0xA5; payload_len])
+            .build();
+
+        match built {
+            Ok(packet) => {
+                assert!(valid);
+                let expected = SpacePacket::new(
+                    version,
+                    PacketType::Command,
+                    apid,
+                    GroupingFlag::Last,
+                    sequence_count,
+                    true,
+                    vec![0xA5; payload_len],
+                );
+                assert_eq!(expected, packet);
+            }
+            Err(err) => {
+                assert!(!valid);
+                assert_eq!(std::io::ErrorKind::InvalidInput, err.kind());
+            }
+        }
+    }
+
+    #[test]
+    fn spacepacket_builder_defaults() {
+        let packet = SpacePacket::builder()
+            .command()
+            .telemetry()
+            .payload([1, 2, 3])
+            .build()
+            .unwrap();
+        assert_eq!(
+            SpacePacket::new(
+                0,
+                PacketType::Telemetry,
+                0,
+                GroupingFlag::Unsegm,
+                0,
+                false,
+                vec![1, 2, 3]
+            ),
+            packet
+        );
+    }
+
+    #[rstest]
+    fn spacepacket_encoded_len(#[values(1, 2, 77, 1000, 65534)] payload_len: usize) {
+        let packet = SpacePacket::idle(payload_len);
+        assert_eq!(packet.encode().len(), packet.encoded_len());
+        #[cfg(feature = "crc")]
+        assert_eq!(
+            packet
+                .encode_crc(&Crc::<u16>::new(&CRC_16_IBM_3740))
+                .unwrap()
+                .len(),
+            packet.encoded_len_crc()
+        );
+    }
+
+    #[test]
+    fn spacepacket_encode_into() {
+        let packets = [
+            SpacePacket::idle(100),
+            SpacePacket::new(
+                0,
+                PacketType::Command,
+                0x42,
+                GroupingFlag::First,
+                7,
+                true,
+                vec![1, 2, 3],
+            ),
+            SpacePacket::idle(1),
+        ];
+
+        let mut out = vec![0xAA];
+        let mut expected = vec![0xAA];
+        for packet in &packets {
+            let written = packet.encode_into(&mut out);
+            expected.extend(packet.encode());
+            assert_eq!(packet.payload.len() + 6, written);
+            assert_eq!(expected, out);
+        }
+
+        #[cfg(any(feature = "async-codec", feature = "tokio-codec"))]
+        {
+            let mut bytes = bytes::BytesMut::from(&[0xAA_u8][..]);
+            for packet in &packets {
+                packet.encode_into_bytes(&mut bytes);
+            }
+            assert_eq!(expected, bytes);
+        }
+    }
+
+    #[rstest]
+    #[case(0, false)]
+    #[case(1, true)]
+    #[case(SpacePacket::MAX_PAYLOAD_LEN, true)]
+    #[case(SpacePacket::MAX_PAYLOAD_LEN + 1, false)]
+    fn spacepacket_encode_checked(#[case] payload_len: usize, #[case] valid: bool) {
+        let packet = SpacePacket::new(
+            0,
+            PacketType::Telemetry,
+            0x42,
+            GroupingFlag::Unsegm,
+            7,
+            false,
+            vec![0xA5; payload_len],
+        );
+
+        assert_eq!(
+            packet.try_encode(),
+            if valid {
+                Ok(packet.encode())
+            } else {
+                Err(EncodeError::Length(LengthOutOfRange {
+                    len: payload_len,
+                    min_len: 1,
+                    max_len: SpacePacket::MAX_PAYLOAD_LEN,
+                }))
+            }
+        );
+        match packet.encode_checked() {
+            Ok(encoded) => {
+                assert!(valid);
+                assert_eq!(packet.encode(), encoded);
+                assert_eq!(
+                    packet,
+                    SpacePacket::decode(&mut encoded.as_slice()).unwrap()
+                );
+            }
+            Err(err) => {
+                assert!(!valid);
+                assert_eq!(std::io::ErrorKind::InvalidInput, err.kind());
+                assert!(err.into_inner().unwrap().is::<EncodeError>());
+            }
+        }
+        #[cfg(feature = "crc")]
+        assert_eq!(
+            valid && payload_len <= SpacePacket::MAX_PAYLOAD_LEN_CRC,
+            packet
+                .encode_crc(&Crc::<u16>::new(&CRC_16_IBM_3740))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn spacepacket_try_encode_field_out_of_range() {
+        // checked before the debug assertion of encode could panic
+        let mut packet = SpacePacket::idle(4);
+        packet.primary_header.apid = 0x800;
+        let expected = FieldOutOfRange {
+            field: "apid",
+            value: 0x800,
+            max: APID_MASK,
+        };
+        assert_eq!(Err(EncodeError::Field(expected)), packet.try_encode());
+
+        let err = packet.encode_checked().unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidInput, err.kind());
+        assert_eq!(expected.to_string(), err.to_string());
+
+        // the header is checked before the payload
+        packet.payload.clear();
+        assert_eq!(Err(EncodeError::Field(expected)), packet.try_encode());
+    }
+
+    #[test]
+    #[should_panic(expected = "but found 0")]
+    fn spacepacket_encode_empty() {
+        SpacePacket::new(
+            0,
+            PacketType::Telemetry,
+            0x42,
+            GroupingFlag::Unsegm,
+            7,
+            false,
+            vec![],
+        )
+        .encode();
+    }
+
+    #[rstest]
+    #[case(IDLE_APID)]
+    #[case(0x7F0)]
+    fn idle_packet(#[case] apid: u16) {
+        let packet = SpacePacket::idle_with_apid(apid, 10);
+
+        assert_eq!(10, packet.payload.len());
+        assert!(packet.is_idle_with_apid(apid));
+        assert_eq!(apid == IDLE_APID, packet.is_idle());
+        assert_eq!(
+            SpacePacket::idle(10),
+            SpacePacket::idle_with_apid(IDLE_APID, 10)
+        );
+
+        let decoded = SpacePacket::decode(&mut packet.encode().as_slice()).unwrap();
+        assert_eq!(packet, decoded);
+        assert!(decoded.is_idle_with_apid(apid));
+        assert_eq!(PacketType::Telemetry, decoded.primary_header.packet_type);
+        assert_eq!(GroupingFlag::Unsegm, decoded.primary_header.grouping);
+    }
+
+    #[rstest]
+    #[case(FillPattern::Constant(0xAA), 0xAA, 0xAA)]
+    #[case(FillPattern::Counting { start: 0xFE }, 0xFE, 0x0C)]
+    #[case(FillPattern::Custom(Box::new(|offset| (offset * 3) as u8)), 0x00, 0x2A)]
+    fn idle_fill_pattern(#[case] pattern: FillPattern, #[case] first: u8, #[case] last: u8) {
+        let mut packet = SpacePacket::idle_with_pattern(0x7F0, 15, &pattern);
+
+        assert!(packet.is_idle_with_apid(0x7F0));
+        assert_eq!(Some(&first), packet.payload.first());
+        assert_eq!(Some(&last), packet.payload.last());
+        assert_eq!(Ok(()), packet.verify_idle_pattern(&pattern));
+
+        packet.payload[9] ^= 0x01;
+        packet.payload[12] ^= 0x01;
+        assert_eq!(
+            Err(IdleCorruption {
+                first_bad_offset: 9
+            }),
+            packet.verify_idle_pattern(&pattern)
+        );
+    }
+
+    /// Accepts at most `limit` bytes of the first non-empty slice per write.
+    struct ShortWriter {
+        written: Vec<u8>,
+        limit: usize,
+    }
+    impl Write for ShortWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(self.limit);
+            self.written.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[rstest]
+    #[case(5, 5, 0)]
+    #[case(5, 9, 4)]
+    #[case(9, 5, -4)]
+    #[case(16380, 3, 7)]
+    #[case(3, 16380, -7)]
+    #[case(0x3FFF, 0, 1)]
+    #[case(0, 0x3FFF, -1)]
+    #[case(0, 0x1FFF, 0x1FFF)]
+    #[case(0, 0x2000, -0x2000)]
+    #[case(0x2000, 0, -0x2000)]
+    #[case(0xC000, 0x0001, 1)]
+    fn sequence_distance(#[case] from: u16, #[case] to: u16, #[case] expected: i32) {
+        assert_eq!(expected, seq_distance(from, to));
+    }
+
+    #[rstest]
+    #[case(LengthConvention::CcsdsMinusOne, [0x00, 0x04])]
+    #[case(LengthConvention::Actual, [0x00, 0x05])]
+    fn length_convention_roundtrip(
+        #[case] convention: LengthConvention,
+        #[case] length_field: [u8; 2],
+    ) {
+        let packet = SpacePacket::new(
+            0,
+            PacketType::Command,
+            0x42,
+            GroupingFlag::Unsegm,
+            17,
+            false,
+            vec![1, 2, 3, 4, 5],
+        );
+
+        let encoded = packet.encode_with_convention(convention).unwrap();
+        assert_eq!(length_field, encoded[PrimaryHeader::LENGTH_FIELD_RANGE]);
+        assert_eq!(
+            packet,
+            SpacePacket::decode_with_convention(&mut encoded.as_slice(), convention).unwrap()
+        );
+        if convention == LengthConvention::default() {
+            assert_eq!(packet.encode(), encoded);
+        }
+    }
 
-            let mut temp = vec![0_u8; message_len as usize];
-            buffer.read_exact(&mut temp)?;
-            [header_buffer.to_vec(), temp].concat()
+    #[rstest]
+    #[case(LengthConvention::CcsdsMinusOne, 0, false)]
+    #[case(LengthConvention::CcsdsMinusOne, SpacePacket::MAX_PAYLOAD_LEN, true)]
+    #[case(LengthConvention::Actual, 0, false)]
+    #[case(LengthConvention::Actual, SpacePacket::MAX_PAYLOAD_LEN - 1, true)]
+    #[case(LengthConvention::Actual, SpacePacket::MAX_PAYLOAD_LEN, false)]
+    fn length_convention_limits(
+        #[case] convention: LengthConvention,
+        #[case] data_len: usize,
+        #[case] fits: bool,
+    ) {
+        assert_eq!(fits, convention.data_length(data_len).is_ok());
+    }
+
+    /// A secondary header holding a 4 byte coarse time.
+    struct CoarseTime(u32);
+    impl SecondaryHeader for CoarseTime {
+        fn encoded_len(&self) -> usize {
+            4
+        }
+
+        fn encode_into(&self, out: &mut Vec<u8>) {
+            out.extend(self.0.to_be_bytes());
+        }
+    }
+
+    #[rstest]
+    fn secondary_header_constructors(#[values(false, true)] flag: bool) {
+        let header = PrimaryHeader {
+            version: 0,
+            packet_type: PacketType::Telemetry,
+            apid: 0x42,
+            secondary_header: flag,
+            grouping: GroupingFlag::Unsegm,
+            sequence_count: 17,
         };
-        let crc_sent = (&full_message[full_message.len() - 2..]).read_u16::<BigEndian>()?;
-        let computed_crc = crc.checksum(&full_message[0..full_message.len() - 2]);
-        match crc_sent == computed_crc {
-            true => {}
-            false => return Ok(CompletePacket::InvalidCRC(crc_sent, computed_crc)),
+
+        let packet =
+            SpacePacket::with_secondary_header(header, &CoarseTime(0x0102_0304), vec![0xAA])
+                .unwrap();
+        assert!(packet.primary_header.secondary_header);
+        assert_eq!(vec![0x01, 0x02, 0x03, 0x04, 0xAA], packet.payload);
+
+        let packet = SpacePacket::without_secondary_header(header, vec![0xAA]).unwrap();
+        assert!(!packet.primary_header.secondary_header);
+        assert_eq!(vec![0xAA], packet.payload);
+
+        let packet = SpacePacket::with_secondary_header(header, &[0x55][..], vec![]).unwrap();
+        assert_eq!(vec![0x55], packet.payload);
+
+        assert!(SpacePacket::with_secondary_header(header, &[][..], vec![0xAA]).is_err());
+        assert!(SpacePacket::without_secondary_header(header, vec![]).is_err());
+    }
+
+    #[rstest]
+    #[case(true, 4, None, true)]
+    #[case(true, 4, Some(4), true)]
+    #[case(true, 3, Some(4), false)]
+    #[case(false, 3, Some(4), true)]
+    fn secondary_header_semantics(
+        #[case] flag: bool,
+        #[case] payload_len: usize,
+        #[case] registered: Option<usize>,
+        #[case] valid: bool,
+    ) {
+        let packet = SpacePacket::new(
+            0,
+            PacketType::Telemetry,
+            0x42,
+            GroupingFlag::Unsegm,
+            0,
+            flag,
+            vec![0; payload_len],
+        );
+        let result = packet.validate_semantics(|apid| registered.filter(|_| apid == 0x42));
+        assert_eq!(valid, result.is_ok());
+    }
+
+    #[test]
+    fn decode_annotated_spans() {
+        let packet = SpacePacket::new(
+            0,
+            PacketType::Command,
+            0x42,
+            GroupingFlag::First,
+            17,
+            true,
+            vec![1, 2, 3],
+        );
+        let mut buffer = packet.encode();
+        buffer.extend([0xAA, 0xBB]);
+
+        let (decoded, spans) = SpacePacket::decode_annotated(&buffer).unwrap();
+        assert_eq!(packet, decoded);
+
+        let names: Vec<_> = spans.iter().map(|span| span.name).collect();
+        assert_eq!(
+            vec![
+                "version",
+                "packet_type",
+                "secondary_header",
+                "apid",
+                "grouping",
+                "sequence_count",
+                "packet_data_length",
+                "payload"
+            ],
+            names
+        );
+        // the spans tile the packet without gaps
+        assert!(spans
+            .windows(2)
+            .all(|pair| pair[0].bits.end == pair[1].bits.start));
+        assert_eq!(0..72, spans[0].bits.start..spans[7].bits.end);
+
+        assert_eq!(0..2, spans[3].bytes());
+        assert_eq!(PrimaryHeader::LENGTH_FIELD_RANGE, spans[6].bytes());
+        assert_eq!(packet.payload, buffer[spans[7].bytes()]);
+        assert_eq!(
+            u64::from(decoded.primary_header.apid),
+            bitfield::read_u(&buffer, spans[3].bits.start, spans[3].bits.len()).unwrap()
+        );
+    }
+
+    #[test]
+    fn decode_annotated_truncated() {
+        let buffer = [0x10, 0x42, 0xC0, 0x11, 0x00, 0x04, 0xFF];
+        let err = SpacePacket::decode_annotated(&buffer).unwrap_err();
+        assert_eq!(std::io::ErrorKind::UnexpectedEof, err.kind());
+    }
+
+    #[test]
+    fn length_convention_actual_empty() {
+        let bytes = [0x10, 0x42, 0xC0, 0x11, 0x00, 0x00, 0xFF];
+        let err = SpacePacket::decode_with_convention(&mut &bytes[..], LengthConvention::Actual)
+            .unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+    }
+
+    #[rstest]
+    #[case(0, 0x7FF, 0x3FFF)]
+    #[case(0x7, 0x800, 0x4000)]
+    #[case(0xFF, 0xFFFF, 0xFFFF)]
+    fn header_clamped(#[case] version: u8, #[case] apid: u16, #[case] sequence_count: u16) {
+        let header = PrimaryHeader {
+            version,
+            packet_type: PacketType::Command,
+            apid,
+            secondary_header: true,
+            grouping: GroupingFlag::First,
+            sequence_count,
         };
+        let clamped = header.clamped();
 
-        let primary_header = PrimaryHeader::decode(&mut full_message.as_slice())?;
+        assert_eq!(header.encode(), clamped.encode());
+        assert_eq!(
+            clamped,
+            PrimaryHeader::decode(&mut header.encode().as_slice()).unwrap()
+        );
+        assert_eq!(clamped, clamped.clamped());
+    }
 
-        Ok(CompletePacket::Valid(Self {
-            primary_header,
-            payload: full_message[6..full_message.len() - 2].to_vec(),
-        }))
+    #[rstest]
+    fn spacepacket_encode_vectored(
+        #[values(1, 77, 65534)] payload_len: usize,
+        #[values(1, 5, 1000)] limit: usize,
+    ) {
+        let packet = SpacePacket::new(
+            0,
+            PacketType::Telemetry,
+            17,
+            GroupingFlag::Unsegm,
+            5,
+            false,
+            (0..payload_len).map(|val| val as u8).collect(),
+        );
+
+        let vectored = packet.encode_vectored().unwrap();
+
+        assert_eq!(packet.encode().len(), vectored.len());
+        let mut writer = ShortWriter {
+            written: vec![],
+            limit,
+        };
+        vectored.write_to(&mut writer).unwrap();
+        assert_eq!(packet.encode(), writer.written);
+
+        #[cfg(feature = "crc")]
+        {
+            let crc = Crc::<u16>::new(&CRC_16_IBM_3740);
+            let vectored = packet.encode_vectored_crc(&crc).unwrap();
+            let mut writer = ShortWriter {
+                written: vec![],
+                limit,
+            };
+            vectored.write_to(&mut writer).unwrap();
+            assert_eq!(packet.encode_crc(&crc).unwrap(), writer.written);
+            assert_eq!(
+                vectored.len(),
+                vectored
+                    .io_slices()
+                    .iter()
+                    .map(|slice| slice.len())
+                    .sum::<usize>()
+            );
+        }
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[rstest]
+    fn spacepacket_encode_to_writer(#[values(1, 77, 65534)] payload_len: usize) {
+        let packet = SpacePacket::new(
+            0,
+            PacketType::Telemetry,
+            17,
+            GroupingFlag::Unsegm,
+            5,
+            true,
+            (0..payload_len).map(|val| val as u8).collect(),
+        );
 
-    #[cfg(feature = "crc")]
-    use crc::CRC_16_IBM_3740;
-    use rstest::rstest;
+        let mut writer = ShortWriter {
+            written: vec![],
+            limit: 5,
+        };
+        let written = packet.encode_to_writer(&mut writer).unwrap();
+        assert_eq!(packet.encode(), writer.written);
+        assert_eq!(payload_len + 6, written);
+
+        let mut header = vec![];
+        assert_eq!(
+            4,
+            packet.primary_header.encode_to_writer(&mut header).unwrap()
+        );
+        assert_eq!(packet.primary_header.encode(), header);
+
+        #[cfg(feature = "crc")]
+        {
+            let crc = Crc::<u16>::new(&CRC_16_IBM_3740);
+            let mut out = vec![];
+            let written = packet.encode_crc_to_writer(&mut out, &crc).unwrap();
+            assert_eq!(packet.encode_crc(&crc).unwrap(), out);
+            assert_eq!(payload_len + 8, written);
+        }
+    }
+
+    #[test]
+    fn spacepacket_encode_to_writer_errors() {
+        let empty = SpacePacket {
+            primary_header: SpacePacket::idle(1).primary_header,
+            payload: vec![],
+        };
+        assert!(empty.encode_to_writer(&mut vec![]).is_err());
+
+        let mut writer = ShortWriter {
+            written: vec![],
+            limit: 0,
+        };
+        let err = SpacePacket::idle(1)
+            .encode_to_writer(&mut writer)
+            .unwrap_err();
+        assert_eq!(std::io::ErrorKind::WriteZero, err.kind());
+    }
 
     #[rstest]
-    fn header_roundtrip(
-        #[values(
-            GroupingFlag::Interm,
+    #[case(1)]
+    #[case(77)]
+    #[case(65536)]
+    fn spacepacket_ref_roundtrip(#[case] payload_len: usize) {
+        let packet = SpacePacket::new(
+            0,
+            PacketType::Command,
+            1555_u16,
             GroupingFlag::First,
+            1423_u16,
+            true,
+            (0..payload_len).map(|val| val as u8).collect(),
+        );
+
+        let mut out = vec![0_u8; payload_len + 16];
+        let written = packet
+            .borrowed()
+            .encode_into(&mut out)
+            .expect("Unable to encode SpacePacketRef.");
+        assert_eq!(payload_len + 6, written);
+
+        let mut scratch = vec![0_u8; 65536];
+        let recovered = SpacePacket::decode_into(&mut &out[..written], &mut scratch)
+            .expect("Unable to decode SpacePacketRef.");
+
+        assert_eq!(packet.borrowed(), recovered);
+    }
+
+    #[rstest]
+    // empty payload
+    #[case(0, 64)]
+    // payload too long for the length field
+    #[case(65537, 65600)]
+    // output too short
+    #[case(10, 15)]
+    fn spacepacket_ref_encode_errors(#[case] payload_len: usize, #[case] out_len: usize) {
+        let payload = vec![0_u8; payload_len];
+        let packet = SpacePacketRef {
+            primary_header: SpacePacket::idle(1).primary_header,
+            payload: &payload,
+        };
+        let err = packet.encode_into(&mut vec![0_u8; out_len]).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidInput, err.kind());
+        if !(1..=SpacePacket::MAX_PAYLOAD_LEN).contains(&payload_len) {
+            assert!(err.into_inner().unwrap().is::<LengthOutOfRange>());
+        }
+    }
+
+    #[test]
+    fn spacepacket_ref_encode_field_out_of_range() {
+        let mut primary_header = SpacePacket::idle(1).primary_header;
+        primary_header.sequence_count = 0x4000;
+        let packet = SpacePacketRef {
+            primary_header,
+            payload: &[0x55; 4],
+        };
+
+        let err = packet.encode_into(&mut [0_u8; 64]).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidInput, err.kind());
+        assert_eq!(
+            FieldOutOfRange {
+                field: "sequence_count",
+                value: 0x4000,
+                max: SEQUENCE_COUNT_MASK,
+            },
+            *err.into_inner()
+                .unwrap()
+                .downcast::<FieldOutOfRange>()
+                .unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(77)]
+    #[case(65536)]
+    fn spacepacket_ref_decode(#[case] payload_len: usize) {
+        let packet = SpacePacket::new(
+            0,
+            PacketType::Telemetry,
+            0x7FF,
             GroupingFlag::Last,
-            GroupingFlag::Unsegm
-        )]
-        grouping: GroupingFlag,
-        #[values(true, false)] secondary_header: bool,
-        #[values(PacketType::Command, PacketType::Telemetry)] packet_type: PacketType,
+            0x3FFF,
+            false,
+            (0..payload_len).map(|val| val as u8).collect(),
+        );
+        let mut encoded = packet.encode();
+        encoded.extend([0xAB, 0xCD]);
+
+        let (decoded, rest) = SpacePacketRef::decode(&encoded).unwrap();
+        assert_eq!(packet.borrowed(), decoded);
+        assert_eq!(&[0xAB, 0xCD], rest);
+        assert_eq!(packet, decoded.into_owned());
+
+        // every truncation errors instead of panicking
+        for len in [0, 3, 5, 6, payload_len + 5] {
+            let err = SpacePacketRef::decode(&encoded[..len]).unwrap_err();
+            assert_eq!(std::io::ErrorKind::UnexpectedEof, err.kind());
+        }
+    }
+
+    #[test]
+    fn spacepacket_decode_borrowed() {
+        let packets = [
+            SpacePacket::idle(100),
+            SpacePacket::idle(1),
+            SpacePacket::idle(50),
+        ];
+        let stream: Vec<u8> = packets.iter().flat_map(SpacePacket::encode).collect();
+
+        let mut position = 0;
+        let mut decoded = vec![];
+        while position < stream.len() {
+            let (packet, consumed) = SpacePacket::decode_borrowed(&stream[position..]).unwrap();
+            decoded.push(SpacePacket::from(packet));
+            position += consumed;
+        }
+        assert_eq!(packets.to_vec(), decoded);
+
+        // the first packet is complete, the second is truncated
+        assert_eq!(106, SpacePacket::decode_borrowed(&stream[..107]).unwrap().1);
+        assert!(SpacePacket::decode_borrowed(&stream[106..112]).is_err());
+    }
+
+    #[test]
+    fn spacepacket_decode_into_vec() {
+        let packets = [
+            SpacePacket::idle(100),
+            SpacePacket::idle(3),
+            SpacePacket::idle(50),
+        ];
+        let stream: Vec<u8> = packets.iter().flat_map(SpacePacket::encode).collect();
+
+        let mut buffer = stream.as_slice();
+        let mut payload = Vec::with_capacity(100);
+        let capacity = payload.capacity();
+        for packet in packets.iter() {
+            let header = SpacePacket::decode_into_vec(&mut buffer, &mut payload).unwrap();
+            assert_eq!(packet.primary_header, header);
+            assert_eq!(packet.payload, payload);
+            assert_eq!(capacity, payload.capacity());
+        }
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn spacepacket_decode_into_short_scratch() {
+        let buffer = SpacePacket::idle(10).encode();
+        let mut scratch = [0_u8; 9];
+
+        let error = SpacePacket::decode_into(&mut buffer.as_slice(), &mut scratch).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidInput, error.kind());
+    }
+
+    #[rstest]
+    // intact length field
+    #[case(40, 40, false)]
+    // damaged length fields
+    #[case(2, 40, true)]
+    #[case(65536, 40, true)]
+    fn spacepacket_decode_until_marker(
+        #[case] declared_len: usize,
+        #[case] payload_len: usize,
+        #[case] length_recovered: bool,
     ) {
-        let expected = PrimaryHeader {
-            version: 0_u8,
+        let marker = [0x1A, 0xCF, 0xFC, 0x1D];
+        let expected = SpacePacket::new(
+            0,
+            PacketType::Telemetry,
+            17,
+            GroupingFlag::Unsegm,
+            5,
+            false,
+            vec![0x42; payload_len],
+        );
+
+        let mut buffer = expected.encode();
+        buffer[PrimaryHeader::LENGTH_FIELD_RANGE]
+            .copy_from_slice(&((declared_len - 1) as u16).to_be_bytes());
+        buffer.extend(marker);
+        buffer.extend(expected.encode());
+
+        let recovered = SpacePacket::decode_until_marker(&buffer, &marker).unwrap();
+        assert_eq!(
+            RecoveredPacket {
+                packet: expected.clone(),
+                declared_len,
+                length_recovered,
+            },
+            recovered
+        );
+        assert_eq!(&marker, &buffer[recovered.consumed()..][..4]);
+
+        // without a following marker the rest of the buffer is the payload
+        let recovered = SpacePacket::decode_until_marker(&buffer[..46], &marker).unwrap();
+        assert_eq!(expected, recovered.packet);
+    }
+
+    #[rstest]
+    #[case(&[0x18, 0x11, 0xC0, 0x00, 0x00, 0x00, 0x1A, 0xCF], &[])]
+    #[case(&[0x18, 0x11, 0xC0, 0x00, 0x00, 0x00, 0x1A, 0xCF], &[0x1A, 0xCF])]
+    #[case(&[0x18, 0x11, 0xC0, 0x00, 0x00, 0x00], &[0x1A, 0xCF])]
+    #[case(&[0x18, 0x11, 0xC0], &[0x1A, 0xCF])]
+    fn spacepacket_decode_until_marker_errors(#[case] buffer: &[u8], #[case] marker: &[u8]) {
+        assert!(SpacePacket::decode_until_marker(buffer, marker).is_err())
+    }
+
+    #[rstest]
+    fn spacepacket_iter_refs(#[values(0, 1, 5, 6, 17)] truncate: usize) {
+        let packets: Vec<SpacePacket> = (1..5_u16)
+            .map(|index| {
+                SpacePacket::new(
+                    0,
+                    PacketType::Telemetry,
+                    17 + index,
+                    GroupingFlag::Unsegm,
+                    index,
+                    false,
+                    vec![index as u8; index as usize * 3],
+                )
+            })
+            .collect();
+        let encoded = packets
+            .iter()
+            .flat_map(|packet| packet.encode())
+            .collect::<Vec<u8>>();
+        let buffer = &encoded[..encoded.len() - truncate];
+
+        let mut iter = SpacePacket::iter_refs(buffer);
+        let complete = match truncate {
+            0 => packets.len(),
+            _ => packets.len() - 1,
+        };
+        for packet in &packets[..complete] {
+            assert_eq!(packet.borrowed(), iter.next().unwrap().unwrap());
+        }
+        if truncate > 0 {
+            assert_eq!(
+                std::io::ErrorKind::UnexpectedEof,
+                iter.next().unwrap().unwrap_err().kind()
+            );
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[rstest]
+    fn header_classify(
+        #[values(PacketType::Telemetry, PacketType::Command)] packet_type: PacketType,
+        #[values(0, 0x42, 0x7FF)] apid: u16,
+        #[values(false, true)] secondary_header: bool,
+    ) {
+        let packet = SpacePacket::new(
+            7,
             packet_type,
-            apid: 2042_u16,
+            apid,
+            GroupingFlag::Unsegm,
+            0x3FFF,
             secondary_header,
-            grouping,
-            sequence_count: 16355_u16,
-        };
+            vec![0xFF; 4],
+        );
+        let encoded = packet.encode();
 
-        let buffer = expected.encode();
+        assert_eq!(
+            (packet_type, apid),
+            PrimaryHeader::classify(&encoded[..2]).unwrap()
+        );
+        assert_eq!(
+            (packet_type, apid),
+            PrimaryHeader::classify(&encoded).unwrap()
+        );
+        assert_eq!(
+            std::io::ErrorKind::UnexpectedEof,
+            PrimaryHeader::classify(&encoded[..1]).unwrap_err().kind()
+        );
+    }
 
-        let recovered = PrimaryHeader::decode(&mut buffer.as_slice())
+    #[test]
+    fn header_decode_with_length() {
+        let packet = SpacePacket::new(
+            0,
+            PacketType::Telemetry,
+            0x42,
+            GroupingFlag::Unsegm,
+            7,
+            false,
+            b"a test input".to_vec(),
+        );
+        let buffer = packet.encode();
+        let mut reader = buffer.as_slice();
+
+        let (header, length) = PrimaryHeader::decode_with_length(&mut reader)
             .expect("Unable to decode Primary Header.");
 
-        assert_eq!(expected, recovered)
+        assert_eq!(packet.primary_header, header);
+        assert_eq!(packet.payload.len() - 1, length as usize);
+        // the reader is left at the start of the payload
+        assert_eq!(packet.payload.as_slice(), reader);
     }
 
     #[rstest]
@@ -379,7 +2876,7 @@ mod test {
             "a test input".as_bytes().to_vec(),
         );
 
-        let buffer = expected.encode_crc(&crc);
+        let buffer = expected.encode_crc(&crc).unwrap();
 
         let recovered = SpacePacket::decode_crc(&mut buffer.as_slice(), &crc)
             .expect("Unable to parse SpacePacket.");
@@ -412,7 +2909,7 @@ mod test {
         );
 
         let (buffer, expected_crc) = {
-            let mut tmp = expected.encode_crc(&crc);
+            let mut tmp = expected.encode_crc(&crc).unwrap();
             let n_bytes = tmp.len();
             let crc = u16::from_be_bytes([tmp[n_bytes - 2], tmp[n_bytes - 1]]);
             tmp[n_bytes - 2..].copy_from_slice(&(crc + 1).to_be_bytes());
@@ -424,8 +2921,158 @@ mod test {
 
         // expected and recovered actually switch here because we alter the CRC on the original message
         assert_eq!(
-            CompletePacket::InvalidCRC(expected_crc + 1, expected_crc),
+            CompletePacket::InvalidCRC(
+                expected_crc + 1,
+                expected_crc,
+                buffer[..buffer.len() - 2].to_vec()
+            ),
+            recovered
+        )
+    }
+
+    #[rstest]
+    #[cfg(feature = "crc")]
+    fn spacepacket_roundtrip_crc32(
+        #[values(
+            GroupingFlag::Interm,
+            GroupingFlag::First,
+            GroupingFlag::Last,
+            GroupingFlag::Unsegm
+        )]
+        grouping: GroupingFlag,
+        #[values(true, false)] secondary_header: bool,
+        #[values(PacketType::Command, PacketType::Telemetry)] packet_type: PacketType,
+    ) {
+        let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+        let expected = SpacePacket::new(
+            0,
+            packet_type,
+            1555_u16,
+            grouping,
+            1423_u16,
+            secondary_header,
+            "a test input".as_bytes().to_vec(),
+        );
+
+        let buffer = expected.encode_crc32(&crc).unwrap();
+        assert_eq!(expected.encode().len() + 4, buffer.len());
+
+        let recovered = SpacePacket::decode_crc32(&mut buffer.as_slice(), &crc)
+            .expect("Unable to parse SpacePacket.");
+
+        assert_eq!(CheckedPacket::Valid(expected), recovered)
+    }
+
+    #[rstest]
+    #[cfg(feature = "crc")]
+    fn spacepacket_roundtrip_invalid_crc32(
+        #[values(
+            GroupingFlag::Interm,
+            GroupingFlag::First,
+            GroupingFlag::Last,
+            GroupingFlag::Unsegm
+        )]
+        grouping: GroupingFlag,
+        #[values(true, false)] secondary_header: bool,
+        #[values(PacketType::Command, PacketType::Telemetry)] packet_type: PacketType,
+    ) {
+        let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+        let expected = SpacePacket::new(
+            0,
+            packet_type,
+            1555_u16,
+            grouping,
+            1423_u16,
+            secondary_header,
+            "a test input".as_bytes().to_vec(),
+        );
+
+        let (buffer, expected_crc) = {
+            let mut tmp = expected.encode_crc32(&crc).unwrap();
+            let n_bytes = tmp.len();
+            let mut crc = [0_u8; 4];
+            crc.copy_from_slice(&tmp[n_bytes - 4..]);
+            let crc = u32::from_be_bytes(crc);
+            tmp[n_bytes - 4..].copy_from_slice(&(crc ^ 0x8000_0000).to_be_bytes());
+            (tmp, crc)
+        };
+
+        let recovered = SpacePacket::decode_crc32(&mut buffer.as_slice(), &crc)
+            .expect("Unable to parse SpacePacket.");
+
+        // the full width of both CRCs is reported
+        assert_eq!(
+            CheckedPacket::Invalid {
+                sent: (expected_crc ^ 0x8000_0000).to_be_bytes().to_vec(),
+                computed: expected_crc.to_be_bytes().to_vec(),
+                data: buffer[..buffer.len() - 4].to_vec()
+            },
             recovered
         )
     }
+
+    #[cfg(feature = "crc")]
+    #[test]
+    fn spacepacket_crc32_payload_len() {
+        let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+        let packet = |len| SpacePacket {
+            primary_header: SpacePacket::idle(1).primary_header,
+            payload: vec![0; len],
+        };
+        assert!(packet(SpacePacket::MAX_PAYLOAD_LEN_CRC32)
+            .encode_crc32(&crc)
+            .is_ok());
+        assert!(packet(SpacePacket::MAX_PAYLOAD_LEN_CRC32 + 1)
+            .encode_crc32(&crc)
+            .is_err());
+    }
+
+    #[rstest]
+    #[case(vec![], "[len=0, first0=]")]
+    #[case(vec![0xDE, 0xAD, 0xBE, 0xEF], "[len=4, first4=DE AD BE EF]")]
+    #[case(
+        (0..16_u8).collect(),
+        "[len=16, first16=00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F]"
+    )]
+    #[case(
+        (0..=255_u8).cycle().take(4096).collect(),
+        "[len=4096, first16=00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F ..]"
+    )]
+    fn payload_summary(#[case] payload: Vec<u8>, #[case] expected: &str) {
+        assert_eq!(expected, format!("{:?}", PayloadSummary(&payload)))
+    }
+
+    #[test]
+    fn payload_summary_alternate() {
+        let payload: Vec<u8> = (0..=255_u8).cycle().take(4096).collect();
+        let expected = format!(
+            "[len=4096, first64={} ..]",
+            (0..64_u8)
+                .map(|byte| format!("{byte:02X}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+
+        assert_eq!(expected, format!("{:#?}", PayloadSummary(&payload)))
+    }
+
+    #[test]
+    fn spacepacket_debug() {
+        let packet = SpacePacket::new(
+            0,
+            PacketType::Telemetry,
+            0x42,
+            GroupingFlag::Unsegm,
+            7,
+            false,
+            [0xDE, 0xAD, 0xBE, 0xEF].repeat(1024),
+        );
+
+        assert_eq!(
+            "SpacePacket { primary_header: PrimaryHeader { version: 0, packet_type: Telemetry, \
+            apid: 66, secondary_header: false, grouping: Unsegm, sequence_count: 7 }, \
+            payload: [len=4096, first16=DE AD BE EF DE AD BE EF DE AD BE EF DE AD BE EF ..] }",
+            format!("{packet:?}")
+        )
+    }
 }
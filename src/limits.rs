@@ -0,0 +1,171 @@
+//! Resource limits for decoding untrusted input.
+//!
+//! Every decoder accepts the worst case input the protocol allows by default.
+//! A [DecodeLimits] configuration, e.g. [DecodeLimits::hardened], caps the memory and work
+//! hostile input can cause. A decoder exceeding a limit discards the offending bytes and
+//! reports a [ResourceLimit].
+//!
+//! ```
+//! # use spacepacket::{consts::ASM, framer::{DiscardReason, FramerEvent, PacketFramer}, limits::{DecodeLimits, Limit, ResourceLimit}};
+//! let mut framer = PacketFramer::new(ASM).with_limits(DecodeLimits::hardened().with_max_item_len(1024));
+//!
+//! // a sync marker followed by a header declaring a 4 KB packet
+//! framer.push(&ASM);
+//! framer.push(&[0x08, 0x42, 0xC0, 0x00, 0x0F, 0xFF, 0x00]);
+//! assert_eq!(
+//!     Some(FramerEvent::Discarded(DiscardReason::ResourceLimit(ResourceLimit {
+//!         which: Limit::ItemLen,
+//!         limit: 1024
+//!     }))),
+//!     framer.next_event()
+//! );
+//! ```
+
+use std::fmt::Display;
+
+/// The resource capped by a [DecodeLimits] configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Limit {
+    /// The length in bytes of a single packet or frame, see [DecodeLimits::with_max_item_len].
+    ItemLen,
+    /// The bytes held while reassembling packets, see [DecodeLimits::with_max_buffered].
+    BufferedBytes,
+    /// The synchronization attempts per pushed chunk, see [DecodeLimits::with_max_resyncs].
+    ResyncAttempts,
+    /// The bytes of a frame available to its secondary header, an inconsistent
+    /// secondary header length or OCF flag exceeds them.
+    FrameLayout,
+}
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ItemLen => write!(f, "item length"),
+            Self::BufferedBytes => write!(f, "buffered bytes"),
+            Self::ResyncAttempts => write!(f, "resynchronization attempts"),
+            Self::FrameLayout => write!(f, "frame layout"),
+        }
+    }
+}
+
+/// Input was rejected because decoding it would exceed a resource limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResourceLimit {
+    /// The exceeded limit.
+    pub which: Limit,
+    /// The configured value of the limit.
+    pub limit: usize,
+}
+impl Display for ResourceLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Exceeded the {} limit of {}", self.which, self.limit)
+    }
+}
+impl std::error::Error for ResourceLimit {}
+impl From<ResourceLimit> for std::io::Error {
+    fn from(err: ResourceLimit) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Caps on the resources a decoder may spend on its input.
+///
+/// The default configuration is unlimited, matching decoders without limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    max_item_len: usize,
+    max_buffered: usize,
+    max_resyncs: usize,
+    check_layout: bool,
+}
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_item_len: usize::MAX,
+            max_buffered: usize::MAX,
+            max_resyncs: usize::MAX,
+            check_layout: false,
+        }
+    }
+}
+impl DecodeLimits {
+    /// Limits suitable for input from untrusted networks.
+    ///
+    ///  - items up to the longest Space Packet of 65542 bytes
+    ///  - 256 KB buffered while reassembling
+    ///  - 16 resynchronizations per pushed chunk
+    ///  - frame layouts are checked
+    pub fn hardened() -> Self {
+        Self {
+            max_item_len: crate::SpacePacket::MAX_PAYLOAD_LEN + crate::PrimaryHeader::WIRE_LEN,
+            max_buffered: 256 * 1024,
+            max_resyncs: 16,
+            check_layout: true,
+        }
+    }
+
+    /// The longest packet or frame accepted, longer items are discarded before their
+    /// bytes are buffered.
+    pub fn with_max_item_len(mut self, len: usize) -> Self {
+        self.max_item_len = len;
+        self
+    }
+
+    /// The most bytes held between calls while reassembling packets,
+    /// the oldest bytes are discarded beyond it.
+    ///
+    /// This must exceed the longest item plus any framing overhead for every item to be decoded.
+    pub fn with_max_buffered(mut self, len: usize) -> Self {
+        self.max_buffered = len;
+        self
+    }
+
+    /// The most times a decoder loses and searches for synchronization within one pushed chunk,
+    /// the rest of the chunk is discarded beyond it.
+    pub fn with_max_resyncs(mut self, count: usize) -> Self {
+        self.max_resyncs = count;
+        self
+    }
+
+    /// Whether the secondary header length and OCF flag of frames must fit the frame.
+    pub fn with_layout_check(mut self, check: bool) -> Self {
+        self.check_layout = check;
+        self
+    }
+
+    /// The longest packet or frame accepted.
+    pub fn max_item_len(&self) -> usize {
+        self.max_item_len
+    }
+
+    /// The most bytes held while reassembling.
+    pub fn max_buffered(&self) -> usize {
+        self.max_buffered
+    }
+
+    /// The most resynchronizations per pushed chunk.
+    pub fn max_resyncs(&self) -> usize {
+        self.max_resyncs
+    }
+
+    /// Whether frame layouts are checked.
+    pub fn checks_layout(&self) -> bool {
+        self.check_layout
+    }
+
+    /// Check an item of `len` bytes against [Self::max_item_len].
+    ///
+    /// # Errors
+    ///
+    /// Errors with [Limit::ItemLen] if the item is too long.
+    pub fn check_item_len(&self, len: usize) -> Result<(), ResourceLimit> {
+        match len > self.max_item_len {
+            true => Err(ResourceLimit {
+                which: Limit::ItemLen,
+                limit: self.max_item_len,
+            }),
+            false => Ok(()),
+        }
+    }
+}
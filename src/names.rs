@@ -0,0 +1,151 @@
+//! Stable names of the header flag enums, e.g. for dropdowns of a ground user interface.
+//!
+//! Every flag enum lists its variants in `VARIANTS` and has a canonical name following
+//! the CCSDS books, used by [Display] and parsed by
+//! [FromStr](std::str::FromStr). The names are part of the API and do not change between
+//! releases, they may be stored in configuration files.
+//!
+//! ```
+//! # use spacepacket::GroupingFlag;
+//! let names: Vec<String> = GroupingFlag::iter().map(|flag| flag.to_string()).collect();
+//! assert_eq!(vec!["Continuation", "First", "Last", "Unsegmented"], names);
+//!
+//! assert_eq!(Ok(GroupingFlag::Unsegm), "Unsegmented".parse());
+//! assert!("Unsegm".parse::<GroupingFlag>().is_err());
+//! ```
+
+use std::fmt::Display;
+
+/// A name did not match any variant of the enum it was parsed as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownVariant {
+    /// The enum the name was parsed as.
+    pub type_name: &'static str,
+    /// The name which was parsed.
+    pub name: String,
+}
+impl Display for UnknownVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown {} name {:?}", self.type_name, self.name)
+    }
+}
+impl std::error::Error for UnknownVariant {}
+impl From<UnknownVariant> for std::io::Error {
+    fn from(err: UnknownVariant) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, err)
+    }
+}
+
+/// Implement the variant list and stable names of a fieldless enum,
+/// see the [module](self) documentation.
+macro_rules! named_variants {
+    ($name:ident { $($variant:ident => $text:literal),+ $(,)? }) => {
+        impl $name {
+            /// Every variant in the order of their encoded values.
+            pub const VARIANTS: &'static [Self] = &[$(Self::$variant),+];
+
+            /// Iterate over [Self::VARIANTS].
+            pub fn iter() -> impl Iterator<Item = Self> {
+                Self::VARIANTS.iter().copied()
+            }
+
+            /// The canonical name of the variant, stable across releases.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $text),+
+                }
+            }
+        }
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+        impl std::str::FromStr for $name {
+            type Err = $crate::names::UnknownVariant;
+
+            /// Parse the canonical name of a variant, see [Self::name].
+            fn from_str(name: &str) -> Result<Self, Self::Err> {
+                Self::iter()
+                    .find(|variant| variant.name() == name)
+                    .ok_or_else(|| $crate::names::UnknownVariant {
+                        type_name: stringify!($name),
+                        name: name.to_owned(),
+                    })
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use crate::{GroupingFlag, PacketType};
+
+    /// Every variant parses from its name and the names are unique.
+    fn roundtrip<T>(expected: &[&str])
+    where
+        T: Copy + Display + FromStr<Err = UnknownVariant> + PartialEq + std::fmt::Debug,
+    {
+        let names: Vec<String> = expected.iter().map(|name| name.to_string()).collect();
+        for name in &names {
+            let variant = T::from_str(name).unwrap();
+            assert_eq!(*name, variant.to_string());
+        }
+        assert_eq!(names.len(), {
+            let mut unique = names.clone();
+            unique.sort();
+            unique.dedup();
+            unique.len()
+        });
+        assert!(T::from_str("").is_err());
+        assert!(T::from_str(&names[0].to_uppercase()).is_err());
+    }
+
+    #[test]
+    fn names_stable() {
+        roundtrip::<PacketType>(&["Telemetry", "Telecommand"]);
+        roundtrip::<GroupingFlag>(&["Continuation", "First", "Last", "Unsegmented"]);
+        assert_eq!(
+            PacketType::VARIANTS,
+            [PacketType::Telemetry, PacketType::Command]
+        );
+        assert_eq!(
+            (0..4).map(GroupingFlag::from_2bits).collect::<Vec<_>>(),
+            GroupingFlag::iter().collect::<Vec<_>>()
+        );
+
+        let err = PacketType::from_str("TC").unwrap_err();
+        assert_eq!("PacketType", err.type_name);
+        assert_eq!("TC", err.name);
+    }
+
+    #[cfg(feature = "tctm")]
+    #[test]
+    fn names_stable_tctm() {
+        use crate::tctm::{
+            tc::{BypassFlag, ControlFlag},
+            tm::{BooleanFieldFlag, SynchronizationFlag},
+        };
+
+        roundtrip::<BooleanFieldFlag>(&["Not present", "Present"]);
+        roundtrip::<SynchronizationFlag>(&["Nominal", "VCA_SDU"]);
+        roundtrip::<BypassFlag>(&["Type-A", "Type-B"]);
+        roundtrip::<ControlFlag>(&["Type-D", "Type-C"]);
+        assert_eq!(
+            BypassFlag::iter()
+                .map(|flag| flag as u8)
+                .collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert_eq!(
+            ControlFlag::iter()
+                .map(|flag| flag as u8)
+                .collect::<Vec<_>>(),
+            [0, 1]
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Unparsed mirrors of the fixed size headers.
+//!
+//! A raw header is the encoded header bytes only, so buffers filled by e.g. DMA capture can be
+//! stored and passed around as headers without parsing. The bit unpacking into the rich header
+//! is deferred until its fields are needed.
+//!
+//! The encoded bytes are also the bridge to other CCSDS crates: a header any implementation
+//! encodes to its 6 wire bytes converts with [RawPrimaryHeader::from], including the Packet
+//! Data Length field as the payload length minus one. With the `interop-ccsds-primary-header`
+//! feature the headers of the `ccsds_primary_header` crate convert directly with `From`.
+//!
+//! With the `zerocopy` feature the raw headers implement the `zerocopy` traits, so a header
+//! can be viewed in place within a received buffer, e.g. with `FromBytes::ref_from_prefix`.
+//!
+//! ```
+//! # use spacepacket::{raw::RawPrimaryHeader, GroupingFlag, PacketType, SpacePacket};
+//! let packet = SpacePacket::new(0, PacketType::Command, 0x42, GroupingFlag::Unsegm, 7, false, vec![1, 2, 3]);
+//! let encoded = packet.encode();
+//!
+//! let raw = RawPrimaryHeader::from_slice(&encoded).unwrap();
+//! assert_eq!(packet.primary_header, raw.header());
+//! assert_eq!(2, raw.data_length());
+//! ```
+
+use crate::{
+    consts::{APID_MASK, SEQUENCE_COUNT_MASK},
+    GroupingFlag, PacketType, PrimaryHeader,
+};
+
+/// The encoded [PrimaryHeader] including the Packet Data Length field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "zerocopy",
+    derive(
+        zerocopy::FromZeroes,
+        zerocopy::FromBytes,
+        zerocopy::AsBytes,
+        zerocopy::Unaligned
+    )
+)]
+#[repr(transparent)]
+pub struct RawPrimaryHeader(pub [u8; PrimaryHeader::WIRE_LEN]);
+const _: () = assert!(std::mem::size_of::<RawPrimaryHeader>() == PrimaryHeader::WIRE_LEN);
+const _: () = assert!(std::mem::align_of::<RawPrimaryHeader>() == 1);
+
+impl RawPrimaryHeader {
+    /// Encode the `header` with the Packet Data Length field `data_length`.
+    pub fn new(header: PrimaryHeader, data_length: u16) -> Self {
+        let [b0, b1, b2, b3] = header.to_bytes();
+        let [b4, b5] = data_length.to_be_bytes();
+        Self([b0, b1, b2, b3, b4, b5])
+    }
+
+    /// Copy the header from the start of `bytes`, `None` if `bytes` is shorter than the header.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        bytes
+            .get(..PrimaryHeader::WIRE_LEN)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(Self)
+    }
+
+    /// The encoded header.
+    pub fn as_bytes(&self) -> &[u8; PrimaryHeader::WIRE_LEN] {
+        &self.0
+    }
+
+    /// Unpack the header fields.
+    pub fn header(&self) -> PrimaryHeader {
+        let [b0, b1, b2, b3, ..] = self.0;
+        let word0 = u16::from_be_bytes([b0, b1]);
+        let word1 = u16::from_be_bytes([b2, b3]);
+
+        PrimaryHeader {
+            version: b0 >> 5,
+            packet_type: PacketType::from_1bit(b0 >> 4),
+            secondary_header: b0 & 0x08 != 0,
+            apid: word0 & APID_MASK,
+            grouping: GroupingFlag::from_2bits(b2 >> 6),
+            sequence_count: word1 & SEQUENCE_COUNT_MASK,
+        }
+    }
+
+    /// The Packet Data Length field, per CCSDS the payload length minus one.
+    pub fn data_length(&self) -> u16 {
+        u16::from_be_bytes([self.0[4], self.0[5]])
+    }
+}
+impl From<[u8; PrimaryHeader::WIRE_LEN]> for RawPrimaryHeader {
+    fn from(bytes: [u8; PrimaryHeader::WIRE_LEN]) -> Self {
+        Self(bytes)
+    }
+}
+impl From<RawPrimaryHeader> for PrimaryHeader {
+    fn from(raw: RawPrimaryHeader) -> Self {
+        raw.header()
+    }
+}
+
+#[cfg(feature = "tctm")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tctm")))]
+pub use tm::RawTmPrimaryHeader;
+
+#[cfg(feature = "tctm")]
+mod tm {
+    use crate::{
+        consts::{FHP_NO_PACKET_START, FHP_ONLY_IDLE_DATA},
+        tctm::tm::{
+            BooleanFieldFlag, FirstHeaderPointer, SynchronizationFlag, TMDataFieldStatus,
+            TMPrimaryHeader,
+        },
+        GroupingFlag,
+    };
+
+    /// The length of an encoded [TMPrimaryHeader].
+    const TM_HEADER_LEN: usize = 6;
+
+    fn flag(bit: u8) -> BooleanFieldFlag {
+        match bit & 0x1 {
+            0 => BooleanFieldFlag::NotPresent,
+            _ => BooleanFieldFlag::Present,
+        }
+    }
+
+    /// The encoded [TMPrimaryHeader].
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+    #[cfg_attr(
+        feature = "zerocopy",
+        derive(
+            zerocopy::FromZeroes,
+            zerocopy::FromBytes,
+            zerocopy::AsBytes,
+            zerocopy::Unaligned
+        )
+    )]
+    #[repr(transparent)]
+    pub struct RawTmPrimaryHeader(pub [u8; TM_HEADER_LEN]);
+    const _: () = assert!(std::mem::size_of::<RawTmPrimaryHeader>() == TM_HEADER_LEN);
+    const _: () = assert!(std::mem::align_of::<RawTmPrimaryHeader>() == 1);
+
+    impl RawTmPrimaryHeader {
+        /// Encode the `header`.
+        pub fn new(header: TMPrimaryHeader) -> Self {
+            Self(header.to_bytes())
+        }
+
+        /// Copy the header from the start of `bytes`, `None` if `bytes` is shorter than the header.
+        pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+            bytes
+                .get(..TM_HEADER_LEN)
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(Self)
+        }
+
+        /// The encoded header.
+        pub fn as_bytes(&self) -> &[u8; TM_HEADER_LEN] {
+            &self.0
+        }
+
+        /// Unpack the header fields.
+        pub fn header(&self) -> TMPrimaryHeader {
+            let [b0, b1, mc_frame_count, vc_frame_count, b4, b5] = self.0;
+            let word0 = u16::from_be_bytes([b0, b1]);
+            let status = u16::from_be_bytes([b4, b5]);
+
+            let first_header_pointer = match status & 0x7FF {
+                FHP_ONLY_IDLE_DATA => FirstHeaderPointer::OnlyIdleData,
+                FHP_NO_PACKET_START => FirstHeaderPointer::NoPacketStart,
+                index => FirstHeaderPointer::ByteIndex(index),
+            };
+            let synchronization_flag = match b4 >> 6 & 0x1 {
+                0 => SynchronizationFlag::Nominal,
+                _ => SynchronizationFlag::VcaSdu,
+            };
+
+            TMPrimaryHeader {
+                tfvn: b0 >> 6,
+                scid: word0 >> 4 & 0x3FF,
+                vcid: b1 >> 1 & 0x7,
+                ocf_flag: flag(b1),
+                mc_frame_count,
+                vc_frame_count,
+                data_field_status: TMDataFieldStatus {
+                    secondary_header_flag: flag(b4 >> 7),
+                    synchronization_flag,
+                    packet_order: b4 >> 5 & 0x1 == 1,
+                    segment_length: GroupingFlag::from_2bits(b4 >> 3),
+                    first_header_pointer,
+                },
+            }
+        }
+    }
+    impl From<[u8; TM_HEADER_LEN]> for RawTmPrimaryHeader {
+        fn from(bytes: [u8; TM_HEADER_LEN]) -> Self {
+            Self(bytes)
+        }
+    }
+    impl From<RawTmPrimaryHeader> for TMPrimaryHeader {
+        fn from(raw: RawTmPrimaryHeader) -> Self {
+            raw.header()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn raw_primary_header_matches_decode() {
+        // every value of each header word, with the other word fixed
+        let words = (0..=u16::MAX)
+            .map(|word| [word, 0xA5C3])
+            .chain((0..=u16::MAX).map(|word| [0x5A3C, word]));
+        for [word0, word1] in words {
+            let [b0, b1] = word0.to_be_bytes();
+            let [b2, b3] = word1.to_be_bytes();
+            let bytes = [b0, b1, b2, b3, 0x12, 0x34];
+
+            let raw = RawPrimaryHeader::from(bytes);
+            let (header, length) = PrimaryHeader::decode_with_length(&mut &bytes[..]).unwrap();
+            assert_eq!(header, raw.header());
+            assert_eq!(length, raw.data_length());
+            assert_eq!(raw, RawPrimaryHeader::new(header, length));
+        }
+    }
+
+    #[test]
+    fn raw_primary_header_short() {
+        assert_eq!(None, RawPrimaryHeader::from_slice(&[0; 5]));
+        assert_eq!(
+            Some(RawPrimaryHeader([1, 2, 3, 4, 5, 6])),
+            RawPrimaryHeader::from_slice(&[1, 2, 3, 4, 5, 6, 7])
+        );
+    }
+
+    #[cfg(feature = "tctm")]
+    #[test]
+    fn raw_tm_primary_header_matches_decode() {
+        use crate::tctm::tm::TMPrimaryHeader;
+
+        let words = (0..=u16::MAX)
+            .map(|word| [word, 0xA5C3])
+            .chain((0..=u16::MAX).map(|word| [0x5A3C, word]));
+        for [word0, status] in words {
+            let [b0, b1] = word0.to_be_bytes();
+            let [b4, b5] = status.to_be_bytes();
+            let bytes = [b0, b1, 0x12, 0x34, b4, b5];
+
+            let raw = RawTmPrimaryHeader::from(bytes);
+            let header = TMPrimaryHeader::decode(&mut &bytes[..]).unwrap();
+            assert_eq!(header, raw.header());
+            assert_eq!(raw, RawTmPrimaryHeader::new(header));
+        }
+    }
+
+    #[cfg(feature = "zerocopy")]
+    #[test]
+    fn raw_headers_zerocopy() {
+        use zerocopy::{AsBytes, FromBytes};
+
+        let encoded = crate::test_util::packet(0x42, 7, 3).encode();
+        let raw = RawPrimaryHeader::ref_from_prefix(&encoded).unwrap();
+        assert_eq!(Some(*raw), RawPrimaryHeader::from_slice(&encoded));
+        assert_eq!(&encoded[..PrimaryHeader::WIRE_LEN], AsBytes::as_bytes(raw));
+        assert_eq!(None, RawPrimaryHeader::ref_from_prefix(&encoded[..5]));
+
+        #[cfg(feature = "tctm")]
+        {
+            let bytes = [0x2F, 0x6B, 0x12, 0x34, 0x18, 0x00, 0xFF];
+            let raw = RawTmPrimaryHeader::ref_from_prefix(&bytes).unwrap();
+            assert_eq!(Some(*raw), RawTmPrimaryHeader::from_slice(&bytes));
+            assert_eq!(&bytes[..6], AsBytes::as_bytes(raw));
+        }
+    }
+}
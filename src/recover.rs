@@ -0,0 +1,205 @@
+//! Explicit, auditable recovery of the payload of packets failing their CRC.
+//!
+//! Science data is sometimes worth more with a few flipped bits than lost entirely.
+//! [CompletePacket::recover_lossy] salvages the packet of a [CompletePacket::InvalidCRC]
+//! only when a [RecoveryPolicy] allows it, and marks it as [MaybeCorrupt::Unverified]
+//! so downstream storage can record that its integrity is unknown.
+//!
+//! ```
+//! # use spacepacket::{crc::{Crc, CRC_16_IBM_3740}, recover::RecoveryPolicy, GroupingFlag, PacketType, SpacePacket};
+//! let crc = Crc::<u16>::new(&CRC_16_IBM_3740);
+//! let packet = SpacePacket::new(0, PacketType::Telemetry, 0x42, GroupingFlag::Unsegm, 7, false, vec![1, 2, 3]);
+//! let mut encoded = packet.encode_crc(&crc).unwrap();
+//! encoded[7] ^= 0x01;
+//!
+//! let policy = RecoveryPolicy::new().with_allowed_apids([0x42]);
+//! let recovered = SpacePacket::decode_crc(&mut encoded.as_slice(), &crc)
+//!     .unwrap()
+//!     .recover_lossy(&policy)
+//!     .unwrap();
+//! assert!(!recovered.is_verified());
+//! assert_eq!(vec![1, 3, 3], recovered.into_inner().payload);
+//! ```
+
+use crate::{consts::PACKET_VERSION_NUMBER, CompletePacket, PrimaryHeader, SpacePacket};
+
+/// The conditions under which [CompletePacket::recover_lossy] salvages a packet
+/// whose CRC is invalid. Packets with a valid CRC are always recovered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryPolicy {
+    require_clean_header: bool,
+    allowed_apids: Option<Vec<u16>>,
+}
+impl RecoveryPolicy {
+    /// A policy recovering every packet with a complete primary header and payload.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only recover packets whose primary header decodes cleanly,
+    /// i.e. carries the CCSDS [PACKET_VERSION_NUMBER].
+    pub fn with_clean_header(mut self) -> Self {
+        self.require_clean_header = true;
+        self
+    }
+
+    /// Only recover packets addressed to one of the `apids`.
+    pub fn with_allowed_apids<I: IntoIterator<Item = u16>>(mut self, apids: I) -> Self {
+        self.allowed_apids = Some(apids.into_iter().collect());
+        self
+    }
+
+    /// Whether the header of a packet failing its CRC permits recovery.
+    fn allows(&self, header: &PrimaryHeader) -> bool {
+        (!self.require_clean_header || header.version == PACKET_VERSION_NUMBER)
+            && self
+                .allowed_apids
+                .as_ref()
+                .map_or(true, |apids| apids.contains(&header.apid))
+    }
+}
+
+/// A value marked with whether its integrity was verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaybeCorrupt<T> {
+    /// The integrity check of the value passed.
+    Verified(T),
+    /// The integrity check of the value failed, it may contain corrupted data.
+    Unverified(T),
+}
+impl<T> MaybeCorrupt<T> {
+    /// Whether the integrity check of the value passed.
+    pub fn is_verified(&self) -> bool {
+        matches!(self, Self::Verified(_))
+    }
+
+    /// Borrow the value regardless of its integrity.
+    pub fn inner(&self) -> &T {
+        match self {
+            Self::Verified(value) | Self::Unverified(value) => value,
+        }
+    }
+
+    /// Take the value regardless of its integrity.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Verified(value) | Self::Unverified(value) => value,
+        }
+    }
+
+    /// Transform the value keeping the integrity marker.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> MaybeCorrupt<U> {
+        match self {
+            Self::Verified(value) => MaybeCorrupt::Verified(f(value)),
+            Self::Unverified(value) => MaybeCorrupt::Unverified(f(value)),
+        }
+    }
+}
+
+impl CompletePacket {
+    /// Recover the packet, salvaging a packet with an invalid CRC if the `policy` allows it.
+    ///
+    /// Valid packets are returned as [MaybeCorrupt::Verified], salvaged packets as
+    /// [MaybeCorrupt::Unverified]. Returns `None` if the `policy` rejects the packet or the
+    /// retained bytes do not hold a primary header and at least one payload byte.
+    pub fn recover_lossy(self, policy: &RecoveryPolicy) -> Option<MaybeCorrupt<SpacePacket>> {
+        match self {
+            Self::Valid(packet) => Some(MaybeCorrupt::Verified(packet)),
+            Self::InvalidCRC(_, _, mut data) => {
+                if data.len() <= PrimaryHeader::WIRE_LEN {
+                    return None;
+                }
+                let primary_header = PrimaryHeader::decode(&mut &data[..]).ok()?;
+                if !policy.allows(&primary_header) {
+                    return None;
+                }
+                data.drain(..PrimaryHeader::WIRE_LEN);
+                Some(MaybeCorrupt::Unverified(SpacePacket {
+                    primary_header,
+                    payload: data,
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_util::packet_with_payload;
+
+    use crc::{Crc, CRC_16_IBM_3740};
+    use rstest::rstest;
+
+    const CRC: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+
+    /// Decode the `packet` with the byte at `index` flipped.
+    fn corrupted(packet: &SpacePacket, index: usize) -> CompletePacket {
+        let mut encoded = packet.encode_crc(&CRC).unwrap();
+        encoded[index] ^= 0xFF;
+        SpacePacket::decode_crc(&mut encoded.as_slice(), &CRC).unwrap()
+    }
+
+    #[test]
+    fn recover_corrupted_payload() {
+        let expected = packet_with_payload(0x42, 11, vec![0x10, 0x20, 0x30, 0x40]);
+        let recovered = corrupted(&expected, 8)
+            .recover_lossy(&RecoveryPolicy::new())
+            .unwrap();
+
+        assert!(!recovered.is_verified());
+        assert_eq!(expected.primary_header, recovered.inner().primary_header);
+        assert_eq!(
+            vec![0x10, 0x20, 0xCF, 0x40],
+            recovered.map(|packet| packet.payload).into_inner()
+        );
+    }
+
+    #[test]
+    fn recover_valid() {
+        let expected = packet_with_payload(0x42, 11, vec![0x10, 0x20, 0x30, 0x40]);
+        let decoded =
+            SpacePacket::decode_crc(&mut expected.encode_crc(&CRC).unwrap().as_slice(), &CRC)
+                .unwrap();
+        let policy = RecoveryPolicy::new()
+            .with_clean_header()
+            .with_allowed_apids([]);
+
+        assert_eq!(
+            Some(MaybeCorrupt::Verified(expected)),
+            decoded.recover_lossy(&policy)
+        );
+    }
+
+    #[rstest]
+    #[case(RecoveryPolicy::new(), 0x42, 8, true)]
+    #[case(RecoveryPolicy::new().with_allowed_apids([0x42, 0x43]), 0x42, 8, true)]
+    #[case(RecoveryPolicy::new().with_allowed_apids([0x43]), 0x42, 8, false)]
+    // the APID is corrupted from 0x42 to 0x0BD
+    #[case(RecoveryPolicy::new().with_allowed_apids([0x42]), 0x42, 1, false)]
+    // the version is corrupted
+    #[case(RecoveryPolicy::new(), 0x42, 0, true)]
+    #[case(RecoveryPolicy::new().with_clean_header(), 0x42, 0, false)]
+    #[case(RecoveryPolicy::new().with_clean_header(), 0x42, 9, true)]
+    fn recover_policy(
+        #[case] policy: RecoveryPolicy,
+        #[case] apid: u16,
+        #[case] index: usize,
+        #[case] recovered: bool,
+    ) {
+        let recovery = corrupted(
+            &packet_with_payload(apid, 11, vec![0x10, 0x20, 0x30, 0x40]),
+            index,
+        )
+        .recover_lossy(&policy);
+        assert_eq!(recovered, recovery.is_some());
+        assert!(recovery.map_or(true, |packet| !packet.is_verified()));
+    }
+
+    #[test]
+    fn recover_without_payload() {
+        let invalid = CompletePacket::InvalidCRC(0, 1, vec![0x08, 0x42, 0xC0, 0x0B, 0x00, 0x01]);
+        assert_eq!(None, invalid.recover_lossy(&RecoveryPolicy::new()));
+    }
+}
@@ -0,0 +1,117 @@
+//! Assignment of the sequence counts of outgoing packets.
+//!
+//! Every APID counts its packets independently, wrapping from 16383 back to 0.
+//!
+//! ```
+//! # use spacepacket::{sequencer::ApidSequencer, SpacePacket};
+//! let mut sequencer = ApidSequencer::new();
+//!
+//! let first = SpacePacket::idle(1).with_next_sequence(&mut sequencer);
+//! let second = SpacePacket::idle(1).with_next_sequence(&mut sequencer);
+//! assert_eq!(0, first.primary_header.sequence_count);
+//! assert_eq!(1, second.primary_header.sequence_count);
+//! ```
+
+use std::collections::HashMap;
+
+use crate::consts::{APID_MASK, SEQUENCE_COUNT_MASK};
+
+/// The 14-bit sequence counter of every APID.
+///
+/// The first count of every APID is 0 unless started elsewhere with [Self::set].
+/// APIDs are masked to 11 bits, counts to 14 bits.
+#[derive(Debug, Clone, Default)]
+pub struct ApidSequencer {
+    /// The count the next packet of every APID receives.
+    next: HashMap<u16, u16>,
+}
+impl ApidSequencer {
+    /// Create a sequencer starting every APID at 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The sequence count for the next packet of the `apid`, advancing its counter.
+    pub fn next(&mut self, apid: u16) -> u16 {
+        let next = self.next.entry(apid & APID_MASK).or_default();
+        let count = *next;
+        *next = count.wrapping_add(1) & SEQUENCE_COUNT_MASK;
+        count
+    }
+
+    /// The sequence count the next packet of the `apid` will receive, without advancing.
+    pub fn peek(&self, apid: u16) -> u16 {
+        self.next
+            .get(&(apid & APID_MASK))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Continue the `apid` at the sequence `count`, e.g. the count saved before a reboot.
+    pub fn set(&mut self, apid: u16, count: u16) {
+        self.next
+            .insert(apid & APID_MASK, count & SEQUENCE_COUNT_MASK);
+    }
+
+    /// Restart every APID at 0.
+    pub fn reset(&mut self) {
+        self.next.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::SpacePacket;
+
+    #[test]
+    fn sequencer_wraps() {
+        let mut sequencer = ApidSequencer::new();
+        sequencer.set(0x42, 16382);
+        assert_eq!(16382, sequencer.next(0x42));
+        assert_eq!(16383, sequencer.next(0x42));
+        assert_eq!(0, sequencer.next(0x42));
+        assert_eq!(1, sequencer.peek(0x42));
+
+        // counts wider than 14 bits are masked
+        sequencer.set(0x42, 0xFFFF);
+        assert_eq!(16383, sequencer.next(0x42));
+        assert_eq!(0, sequencer.next(0x42));
+    }
+
+    #[test]
+    fn sequencer_apids_independent() {
+        let mut sequencer = ApidSequencer::new();
+        for apid in 0..=APID_MASK {
+            assert_eq!(0, sequencer.next(apid));
+        }
+        assert_eq!(1, sequencer.next(7));
+        assert_eq!(2, sequencer.next(7));
+        assert_eq!(1, sequencer.next(8));
+        // APIDs wider than 11 bits are masked
+        assert_eq!(3, sequencer.next(0x800 | 7));
+
+        let packets: Vec<u16> = [7, 8, 7]
+            .into_iter()
+            .map(|apid| {
+                let mut packet = SpacePacket::idle(1);
+                packet.primary_header.apid = apid;
+                packet
+                    .with_next_sequence(&mut sequencer)
+                    .primary_header
+                    .sequence_count
+            })
+            .collect();
+        assert_eq!(vec![4, 2, 5], packets);
+
+        sequencer.reset();
+        assert_eq!(0, sequencer.peek(7));
+    }
+
+    #[test]
+    fn sequencer_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ApidSequencer>();
+    }
+}
@@ -0,0 +1,361 @@
+//! Pluggable destinations for decoded packets.
+//!
+//! A [PacketSink] takes packets one at a time and may push back. A sink returns the packet
+//! it cannot take inside a [SinkError]:
+//!  - [SinkError::Full] is backpressure, the sink may take the same packet later.
+//!    The producer keeps the packet and retries it before any later packet.
+//!  - [SinkError::Closed] is final, the sink takes no more packets and the producer stops.
+//!
+//! A [SinkDriver] implements these rules for producers, it holds the packets a full sink
+//! pushed back and offers them again in order.
+//!
+//! ```
+//! # use spacepacket::{sink::{QueueSink, SinkDriver}, SpacePacket};
+//! let mut driver = SinkDriver::new(Box::new(QueueSink::new(2)));
+//!
+//! let packets = (1..=3).map(SpacePacket::idle);
+//! assert_eq!(2, driver.offer(packets).unwrap());
+//! assert_eq!(1, driver.pending_len());
+//! ```
+
+use std::{collections::VecDeque, fmt::Display, io::Write};
+
+use crate::SpacePacket;
+
+/// A [PacketSink] could not take a packet, see the [module](self) documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SinkError {
+    /// The sink is full, offer the packet again later.
+    Full(Box<SpacePacket>),
+    /// The sink takes no more packets, stop offering packets.
+    Closed(Box<SpacePacket>),
+}
+impl SinkError {
+    /// Whether the packet may be offered again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Full(_))
+    }
+
+    /// Recover the packet which was not taken.
+    pub fn into_packet(self) -> SpacePacket {
+        match self {
+            Self::Full(packet) | Self::Closed(packet) => *packet,
+        }
+    }
+}
+impl Display for SinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Full(_) => write!(f, "Packet sink is full"),
+            Self::Closed(_) => write!(f, "Packet sink is closed"),
+        }
+    }
+}
+impl std::error::Error for SinkError {}
+impl From<SinkError> for std::io::Error {
+    fn from(err: SinkError) -> Self {
+        let kind = match err {
+            SinkError::Full(_) => std::io::ErrorKind::WouldBlock,
+            SinkError::Closed(_) => std::io::ErrorKind::BrokenPipe,
+        };
+        std::io::Error::new(kind, err)
+    }
+}
+
+/// A destination taking packets one at a time.
+///
+/// The trait is object safe, producers can drive any `Box<dyn PacketSink>`.
+/// Closures taking a packet implement it.
+pub trait PacketSink {
+    /// Take the `packet`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the packet inside a [SinkError] if the sink cannot take it,
+    /// see the [module](self) documentation.
+    fn accept(&mut self, packet: SpacePacket) -> Result<(), SinkError>;
+}
+impl<F: FnMut(SpacePacket) -> Result<(), SinkError>> PacketSink for F {
+    fn accept(&mut self, packet: SpacePacket) -> Result<(), SinkError> {
+        self(packet)
+    }
+}
+
+/// An in memory queue of at most `capacity` packets, full until packets are popped.
+#[derive(Debug, Clone)]
+pub struct QueueSink {
+    packets: VecDeque<SpacePacket>,
+    capacity: usize,
+}
+impl QueueSink {
+    /// Create an empty queue holding at most `capacity` packets.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            packets: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Remove the oldest packet.
+    pub fn pop(&mut self) -> Option<SpacePacket> {
+        self.packets.pop_front()
+    }
+
+    /// The number of packets queued.
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// Whether no packet is queued.
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+}
+impl PacketSink for QueueSink {
+    fn accept(&mut self, packet: SpacePacket) -> Result<(), SinkError> {
+        if self.packets.len() >= self.capacity {
+            return Err(SinkError::Full(Box::new(packet)));
+        }
+        self.packets.push_back(packet);
+        Ok(())
+    }
+}
+
+/// Writes every packet encoded with [SpacePacket::encode_to_writer], e.g. to an archive file.
+///
+/// A write which would block is reported as [SinkError::Full]. Any other write error or a
+/// packet which cannot be encoded closes the sink, the error is kept for [Self::error].
+#[derive(Debug)]
+pub struct WriteSink<W> {
+    writer: W,
+    error: Option<std::io::Error>,
+}
+impl<W: Write> WriteSink<W> {
+    /// Write packets to the `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+
+    /// The error which closed the sink.
+    pub fn error(&self) -> Option<&std::io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Unwrap the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+impl<W: Write> PacketSink for WriteSink<W> {
+    fn accept(&mut self, packet: SpacePacket) -> Result<(), SinkError> {
+        if self.error.is_some() {
+            return Err(SinkError::Closed(Box::new(packet)));
+        }
+        match packet.encode_to_writer(&mut self.writer) {
+            Ok(_) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                Err(SinkError::Full(Box::new(packet)))
+            }
+            Err(err) => {
+                self.error = Some(err);
+                Err(SinkError::Closed(Box::new(packet)))
+            }
+        }
+    }
+}
+
+/// Offers packets to a boxed [PacketSink] following its backpressure,
+/// holding the packets pushed back by a full sink until it takes them.
+pub struct SinkDriver {
+    sink: Box<dyn PacketSink + Send>,
+    pending: VecDeque<SpacePacket>,
+    closed: bool,
+}
+impl std::fmt::Debug for SinkDriver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SinkDriver")
+            .field("pending", &self.pending.len())
+            .field("closed", &self.closed)
+            .finish()
+    }
+}
+impl SinkDriver {
+    /// Drive the `sink`.
+    pub fn new(sink: Box<dyn PacketSink + Send>) -> Self {
+        Self {
+            sink,
+            pending: VecDeque::new(),
+            closed: false,
+        }
+    }
+
+    /// Queue the `packets` after any pending packets and offer every queued packet
+    /// in order until the sink is full.
+    ///
+    /// Returns the number of packets the sink took.
+    ///
+    /// # Errors
+    ///
+    /// Errors with the refused packet once the sink is closed. The packets queued after it
+    /// remain available from [Self::take_pending], later calls take no packets.
+    pub fn offer<I: IntoIterator<Item = SpacePacket>>(
+        &mut self,
+        packets: I,
+    ) -> Result<usize, SinkError> {
+        self.pending.extend(packets);
+        self.flush()
+    }
+
+    /// Offer the pending packets again, see [Self::offer].
+    ///
+    /// # Errors
+    ///
+    /// Errors with the refused packet once the sink is closed.
+    pub fn flush(&mut self) -> Result<usize, SinkError> {
+        let mut accepted = 0;
+        while let Some(packet) = self.pending.pop_front() {
+            if self.closed {
+                return Err(SinkError::Closed(Box::new(packet)));
+            }
+            match self.sink.accept(packet) {
+                Ok(()) => accepted += 1,
+                Err(SinkError::Full(packet)) => {
+                    self.pending.push_front(*packet);
+                    break;
+                }
+                Err(err) => {
+                    self.closed = true;
+                    return Err(err);
+                }
+            }
+        }
+        Ok(accepted)
+    }
+
+    /// The number of packets waiting for the sink.
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether the sink was closed.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Remove every packet waiting for the sink.
+    pub fn take_pending(&mut self) -> Vec<SpacePacket> {
+        self.pending.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    /// Takes packets into a shared list, refusing every third offer and closing after `limit`.
+    struct Flaky {
+        taken: Arc<Mutex<Vec<u16>>>,
+        offers: usize,
+        limit: usize,
+    }
+    impl PacketSink for Flaky {
+        fn accept(&mut self, packet: SpacePacket) -> Result<(), SinkError> {
+            self.offers += 1;
+            let mut taken = self.taken.lock().unwrap();
+            if taken.len() >= self.limit {
+                return Err(SinkError::Closed(Box::new(packet)));
+            }
+            if self.offers % 3 == 0 {
+                return Err(SinkError::Full(Box::new(packet)));
+            }
+            taken.push(packet.primary_header.sequence_count);
+            Ok(())
+        }
+    }
+
+    fn packets(counts: std::ops::Range<u16>) -> impl Iterator<Item = SpacePacket> {
+        counts.map(|count| {
+            let mut packet = SpacePacket::idle(1);
+            packet.primary_header.sequence_count = count;
+            packet
+        })
+    }
+
+    #[test]
+    fn driver_backpressure() {
+        let taken = Arc::new(Mutex::new(vec![]));
+        let mut driver = SinkDriver::new(Box::new(Flaky {
+            taken: taken.clone(),
+            offers: 0,
+            limit: 8,
+        }));
+
+        // the third offer is refused, the packet is kept
+        assert_eq!(Ok(2), driver.offer(packets(0..4)));
+        assert_eq!(2, driver.pending_len());
+        // retried before the new packets
+        assert_eq!(Ok(2), driver.offer(packets(4..6)));
+        assert_eq!(Ok(2), driver.flush());
+        assert_eq!(vec![0, 1, 2, 3, 4, 5], *taken.lock().unwrap());
+
+        // the ninth offer is refused, then the sink closes after taking 8 packets
+        assert_eq!(Ok(0), driver.offer(packets(6..12)));
+        let err = driver.flush().unwrap_err();
+        assert!(!err.is_retryable());
+        assert_eq!(8, err.into_packet().primary_header.sequence_count);
+        assert!(driver.is_closed());
+        assert_eq!(
+            vec![9, 10, 11],
+            driver
+                .take_pending()
+                .iter()
+                .map(|packet| packet.primary_header.sequence_count)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!((0..8).collect::<Vec<u16>>(), *taken.lock().unwrap());
+    }
+
+    #[test]
+    fn queue_sink() {
+        let mut queue = QueueSink::new(2);
+        let mut packets = packets(0..3);
+        queue.accept(packets.next().unwrap()).unwrap();
+        queue.accept(packets.next().unwrap()).unwrap();
+        let err = queue.accept(packets.next().unwrap()).unwrap_err();
+        assert!(err.is_retryable());
+
+        assert_eq!(0, queue.pop().unwrap().primary_header.sequence_count);
+        queue.accept(err.into_packet()).unwrap();
+        assert_eq!(2, queue.len());
+    }
+
+    #[test]
+    fn closure_and_write_sinks() {
+        let mut counted = 0;
+        let mut count = |_packet| {
+            counted += 1;
+            Ok(())
+        };
+        let sink: &mut dyn PacketSink = &mut count;
+        packets(0..3).for_each(|packet| sink.accept(packet).unwrap());
+        assert_eq!(3, counted);
+
+        let mut sink = WriteSink::new(vec![]);
+        packets(0..2).for_each(|packet| sink.accept(packet).unwrap());
+        let expected: Vec<u8> = packets(0..2).flat_map(|packet| packet.encode()).collect();
+        assert_eq!(expected, sink.into_inner());
+
+        // a packet which cannot be encoded closes the sink
+        let mut sink = WriteSink::new(vec![]);
+        let mut empty = SpacePacket::idle(1);
+        empty.payload.clear();
+        assert!(matches!(sink.accept(empty), Err(SinkError::Closed(_))));
+        assert!(sink.error().is_some());
+        assert!(sink.accept(SpacePacket::idle(1)).is_err());
+    }
+}
@@ -0,0 +1,362 @@
+//! Advisory guesses at how an unknown blob of bytes is framed.
+//!
+//! Integrators are often handed a capture without knowing whether it holds bare packets,
+//! packets behind a synchronization marker, fixed length frames such as CADUs or CLTUs.
+//! [sniff] looks for the structures of each framing and ranks the framings found.
+//!
+//! The guesses are heuristics, not a decode. Random data matches a structure by chance,
+//! a framing can nest another and several framings can fit the same bytes, e.g. packets of
+//! a constant length behind the [ASM] also look like fixed length frames. Confirm a guess
+//! by decoding with the parameters it reports.
+//!
+//! ```
+//! # use spacepacket::{consts::ASM, sniff::Framing};
+//! // four frames of 10 bytes, each preceded by the ASM
+//! let blob: Vec<u8> = (0..4_u8)
+//!     .flat_map(|frame| ASM.into_iter().chain([frame; 10]))
+//!     .collect();
+//!
+//! let guesses = spacepacket::sniff(&blob);
+//! assert_eq!(
+//!     Framing::AsmFrames { offset: 0, frame_len: 10, count: 4 },
+//!     guesses[0].framing
+//! );
+//! ```
+
+use crate::consts::{ASM, CLTU_START_SEQUENCE, CLTU_TAIL_SEQUENCE, PACKET_VERSION_NUMBER};
+
+/// The length of a BCH codeblock of a CLTU, 7 information bytes and 1 parity byte.
+const CODEBLOCK_LEN: usize = 8;
+
+/// The shortest blob whose byte distribution is judged.
+const MIN_ENTROPY_LEN: usize = 256;
+
+/// A framing [sniff] found structures of, with the parameters needed to decode it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Framing {
+    /// Space Packets back to back, their lengths chain to exactly the end of the blob.
+    Packets {
+        /// The number of packets chained.
+        count: usize,
+    },
+    /// Space Packets each preceded by the [ASM], their lengths ending at the next marker,
+    /// see [SpacePacketCodec](crate::codec::SpacePacketCodec).
+    AsmPackets {
+        /// The offset of the first marker.
+        offset: usize,
+        /// The number of packets ending at the next marker or the end of the blob.
+        count: usize,
+    },
+    /// Fixed length frames each preceded by the [ASM], e.g. CADUs of TM Transfer Frames.
+    AsmFrames {
+        /// The offset of the first marker.
+        offset: usize,
+        /// The length of every frame following a marker, excluding the marker itself.
+        frame_len: usize,
+        /// The number of markers spaced `frame_len` bytes apart.
+        count: usize,
+    },
+    /// CLTUs, a [CLTU_START_SEQUENCE] followed by codeblocks and the [CLTU_TAIL_SEQUENCE].
+    Cltus {
+        /// The offset of the first CLTU.
+        offset: usize,
+        /// The number of complete CLTUs.
+        count: usize,
+    },
+    /// Bytes as evenly distributed as randomized, encrypted or compressed data.
+    Randomized {
+        /// The Shannon entropy of the bytes in bits per byte.
+        entropy: f64,
+    },
+}
+
+/// A [Framing] and the confidence in it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FramingGuess {
+    /// The guessed framing.
+    pub framing: Framing,
+    /// How well the blob fits the framing, from `0.0` exclusive to `1.0`.
+    /// Only comparable between the guesses of one blob.
+    pub confidence: f64,
+}
+
+/// Guess how the `bytes` are framed, most confident guess first.
+///
+/// Returns no guesses when no structure was found. The guesses are advisory only,
+/// see the [module](self) documentation.
+pub fn sniff(bytes: &[u8]) -> Vec<FramingGuess> {
+    let mut guesses: Vec<FramingGuess> = [
+        chained_packets(bytes),
+        asm_packets(bytes),
+        asm_frames(bytes),
+        cltus(bytes),
+        randomized(bytes),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|guess| guess.confidence > 0.0)
+    .collect();
+    guesses.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    guesses
+}
+
+/// The confidence gained from `count` independent matches of a structure.
+fn repeated(count: usize) -> f64 {
+    1.0 - 0.5_f64.powi(count.min(64) as i32)
+}
+
+/// The length of the packet whose header starts `bytes`, if its version is plausible.
+fn packet_len(bytes: &[u8]) -> Option<usize> {
+    match bytes {
+        [b0, _, _, _, b4, b5, ..] if b0 >> 5 == PACKET_VERSION_NUMBER => {
+            Some(6 + usize::from(u16::from_be_bytes([*b4, *b5])) + 1)
+        }
+        _ => None,
+    }
+}
+
+fn chained_packets(bytes: &[u8]) -> Option<FramingGuess> {
+    let mut position = 0;
+    let mut count = 0;
+    while position < bytes.len() {
+        position += packet_len(&bytes[position..])?;
+        count += 1;
+    }
+    (position == bytes.len() && count > 0).then(|| FramingGuess {
+        framing: Framing::Packets { count },
+        confidence: repeated(count),
+    })
+}
+
+fn asm_positions(bytes: &[u8]) -> Vec<usize> {
+    bytes
+        .windows(ASM.len())
+        .enumerate()
+        .filter(|(_, window)| *window == ASM)
+        .map(|(position, _)| position)
+        .collect()
+}
+
+fn asm_packets(bytes: &[u8]) -> Option<FramingGuess> {
+    let positions = asm_positions(bytes);
+    let ends = positions.iter().skip(1).copied().chain([bytes.len()]);
+    let count = positions
+        .iter()
+        .zip(ends)
+        .filter(|(start, end)| {
+            let start = *start + ASM.len();
+            packet_len(&bytes[start..]).map(|len| start + len) == Some(*end)
+        })
+        .count();
+    (count > 0).then(|| FramingGuess {
+        framing: Framing::AsmPackets {
+            offset: positions[0],
+            count,
+        },
+        confidence: count as f64 / positions.len() as f64 * repeated(count),
+    })
+}
+
+fn asm_frames(bytes: &[u8]) -> Option<FramingGuess> {
+    let positions = asm_positions(bytes);
+    let spacings: Vec<usize> = positions
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .filter(|spacing| *spacing > ASM.len())
+        .collect();
+
+    // the most common spacing, the shortest of equally common ones
+    let mut sorted = spacings.clone();
+    sorted.sort_unstable();
+    let (spacing, repeats) = runs(&sorted)
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))?;
+
+    let offset = positions
+        .windows(2)
+        .find(|pair| pair[1] - pair[0] == spacing)
+        .map(|pair| pair[0])?;
+    Some(FramingGuess {
+        framing: Framing::AsmFrames {
+            offset,
+            frame_len: spacing - ASM.len(),
+            count: repeats + 1,
+        },
+        confidence: repeats as f64 / spacings.len() as f64 * repeated(repeats),
+    })
+}
+
+/// The runs of equal values in a sorted slice as `(value, run length)`.
+fn runs(sorted: &[usize]) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = vec![];
+    for value in sorted {
+        match runs.last_mut() {
+            Some((last, len)) if last == value => *len += 1,
+            _ => runs.push((*value, 1)),
+        }
+    }
+    runs
+}
+
+fn cltus(bytes: &[u8]) -> Option<FramingGuess> {
+    let mut offset = None;
+    let mut count = 0;
+    let mut covered = 0;
+    let mut position = 0;
+    while let Some(start) = bytes[position..]
+        .windows(CLTU_START_SEQUENCE.len())
+        .position(|window| window == CLTU_START_SEQUENCE)
+        .map(|start| position + start)
+    {
+        position = start + CLTU_START_SEQUENCE.len();
+        let tail = bytes[position..]
+            .chunks_exact(CODEBLOCK_LEN)
+            .position(|block| block == CLTU_TAIL_SEQUENCE);
+        if let Some(blocks) = tail.filter(|blocks| *blocks > 0) {
+            position += (blocks + 1) * CODEBLOCK_LEN;
+            offset.get_or_insert(start);
+            count += 1;
+            covered += position - start;
+        }
+    }
+    Some(FramingGuess {
+        framing: Framing::Cltus {
+            offset: offset?,
+            count,
+        },
+        confidence: covered as f64 / bytes.len() as f64 * repeated(count),
+    })
+}
+
+fn randomized(bytes: &[u8]) -> Option<FramingGuess> {
+    if bytes.len() < MIN_ENTROPY_LEN {
+        return None;
+    }
+    let mut histogram = [0_usize; 256];
+    bytes
+        .iter()
+        .for_each(|byte| histogram[usize::from(*byte)] += 1);
+    let len = bytes.len() as f64;
+    let entropy: f64 = histogram
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / len;
+            -p * p.log2()
+        })
+        .sum();
+
+    // uniformly random bytes fall short of 8 bits by the bias of the sample
+    let expected = 8.0 - 255.0 / (2.0 * len * std::f64::consts::LN_2);
+    Some(FramingGuess {
+        framing: Framing::Randomized { entropy },
+        confidence: ((entropy - expected + 0.5) / 0.5).clamp(0.0, 1.0) * 0.9,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_util::packet_with_payload;
+
+    /// Deterministic bytes which look random.
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_F491_u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sniff_packets() {
+        let blob: Vec<u8> = [10, 3, 200, 1]
+            .into_iter()
+            .flat_map(|len| packet_with_payload(0x42, len as u16, vec![0x55; len]).encode())
+            .collect();
+        let guesses = sniff(&blob);
+        assert_eq!(Framing::Packets { count: 4 }, guesses[0].framing);
+
+        // a truncated blob does not chain
+        assert!(sniff(&blob[..blob.len() - 1])
+            .iter()
+            .all(|guess| !matches!(guess.framing, Framing::Packets { .. })));
+    }
+
+    #[test]
+    fn sniff_asm_packets() {
+        let mut blob = vec![0x00; 3];
+        for len in [10, 3, 200, 1, 17] {
+            blob.extend(ASM);
+            blob.extend(packet_with_payload(0x42, len as u16, vec![0x55; len]).encode());
+        }
+        let guesses = sniff(&blob);
+        assert_eq!(
+            Framing::AsmPackets {
+                offset: 3,
+                count: 5
+            },
+            guesses[0].framing
+        );
+    }
+
+    #[test]
+    fn sniff_asm_frames() {
+        let data = noise(6 * 1115);
+        let mut blob = data[..7].to_vec();
+        for frame in data.chunks(1115) {
+            blob.extend(ASM);
+            blob.extend(frame);
+        }
+
+        let guesses = sniff(&blob);
+        assert_eq!(
+            Framing::AsmFrames {
+                offset: 7,
+                frame_len: 1115,
+                count: 6
+            },
+            guesses[0].framing
+        );
+        // the frames carry randomized data
+        assert!(guesses
+            .iter()
+            .any(|guess| matches!(guess.framing, Framing::Randomized { .. })));
+    }
+
+    #[test]
+    fn sniff_cltus() {
+        let mut blob = vec![0x55; 4];
+        for blocks in [1, 3] {
+            blob.extend(CLTU_START_SEQUENCE);
+            blob.extend(noise(blocks * CODEBLOCK_LEN));
+            blob.extend(CLTU_TAIL_SEQUENCE);
+            blob.extend([0x55; 2]);
+        }
+
+        let guesses = sniff(&blob);
+        assert_eq!(
+            Framing::Cltus {
+                offset: 4,
+                count: 2
+            },
+            guesses[0].framing
+        );
+    }
+
+    #[test]
+    fn sniff_randomized() {
+        let guesses = sniff(&noise(4096));
+        assert!(matches!(guesses[0].framing, Framing::Randomized { entropy } if entropy > 7.9));
+        assert!(guesses[0].confidence > 0.8);
+
+        // constant data has no structure
+        assert_eq!(Vec::<FramingGuess>::new(), sniff(&[0x55; 4096]));
+        assert_eq!(Vec::<FramingGuess>::new(), sniff(&[]));
+    }
+}
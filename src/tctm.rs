@@ -1,7 +1,15 @@
-//! TeleCommand (TC; CCSDS 231.0-B-4 )
-//! and Telemetry (TM; CCSDS 132.0-B-3 ) Transfer Frame
+//! TeleCommand (TC; CCSDS 231.0-B-4 ),
+//! Telemetry (TM; CCSDS 132.0-B-3 ) and Advanced Orbiting Systems (AOS; CCSDS 732.0-B-4 ) Transfer Frame
 //! definitions, en/de-coding.
+pub mod aos;
+pub mod channel;
+pub mod clcw;
 pub mod cltu;
-pub(crate) mod randomizer;
+pub mod extractor;
+pub mod farm;
+pub mod randomizer;
+pub mod sim;
 pub mod tc;
+pub mod test_patterns;
 pub mod tm;
+pub mod uplink;
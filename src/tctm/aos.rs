@@ -0,0 +1,328 @@
+//! Implementation of the Advanced Orbiting Systems (AOS) Transfer Frame as defined in CCSDS 732.0-B-4
+//!
+//! Only frames carrying a Multiplexing Protocol Data Unit (M_PDU) are supported, their packet
+//! zone is located with a First Header Pointer as in a [TMTransferFrame](crate::tctm::tm::TMTransferFrame)
+//! and extracted by the same [PacketExtractor](crate::tctm::extractor::PacketExtractor).
+//!
+//! The Frame Header Error Control, the Insert Zone and the Frame Error Control Field are
+//! managed parameters of the physical channel which are not supported, frames must be
+//! de-randomized with [derandomize](crate::tctm::randomizer::derandomize) before decoding.
+//!
+//! ```
+//! # use spacepacket::tctm::{aos::{AOSPrimaryHeader, AOSTransferFrame}, extractor::PacketExtractor, tm::FirstHeaderPointer};
+//! # use spacepacket::{GroupingFlag, PacketType, SpacePacket};
+//! let packet = SpacePacket::new(0, PacketType::Telemetry, 0x42, GroupingFlag::Unsegm, 7, false, vec![1, 2, 3]);
+//! let frame = AOSTransferFrame {
+//!     primary_header: AOSPrimaryHeader::new(0x9C, 1),
+//!     first_header_pointer: FirstHeaderPointer::ByteIndex(0),
+//!     packet_zone: packet.encode(),
+//!     ocf: None,
+//! };
+//!
+//! let encoded = frame.encode();
+//! let decoded = AOSTransferFrame::decode(encoded.as_slice(), encoded.len(), false).unwrap();
+//! assert_eq!(vec![packet], PacketExtractor::new().push(&decoded));
+//! ```
+
+use std::io::{Error, ErrorKind, Read};
+
+use crate::tctm::{extractor::PacketZone, tm::FirstHeaderPointer};
+
+/// The Transfer Frame Version Number of AOS frames.
+const AOS_TFVN: u8 = 0b01;
+
+/// The largest 24-bit Virtual Channel Frame Count.
+const FRAME_COUNT_MASK: u32 = 0xFF_FFFF;
+
+/// Primary Header of an [AOSTransferFrame].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AOSPrimaryHeader {
+    /// Transfer Frame Version number, `01` for AOS frames.
+    /// Encoded in 2 bits
+    pub tfvn: u8,
+
+    /// 8-bit unique identifier for the spacecraft
+    pub scid: u8,
+
+    /// The identifier of the virtual channel to which this
+    /// frame belongs. 6-bits maximum.
+    pub vcid: u8,
+
+    /// Sequence count (modulo 2^24) of frames in the virtual channel.
+    pub vc_frame_count: u32,
+
+    /// Whether the frame is replayed rather than sent in real time.
+    pub replay_flag: bool,
+
+    /// The 4-bit Virtual Channel Frame Count Cycle extending [Self::vc_frame_count],
+    /// `None` if the cycle is not in use.
+    pub vc_frame_count_cycle: Option<u8>,
+}
+impl AOSPrimaryHeader {
+    /// Length of the encoded primary header without Frame Header Error Control.
+    pub const LEN: usize = 6;
+
+    /// A real time header of the spacecraft `scid` and virtual channel `vcid`
+    /// with a frame count of 0 and no frame count cycle.
+    pub fn new(scid: u8, vcid: u8) -> Self {
+        Self {
+            tfvn: AOS_TFVN,
+            scid,
+            vcid,
+            vc_frame_count: 0,
+            replay_flag: false,
+            vc_frame_count_cycle: None,
+        }
+    }
+
+    /// Encode self into a byte steam
+    pub fn encode(self) -> Vec<u8> {
+        self.to_bytes().to_vec()
+    }
+
+    fn to_bytes(self) -> [u8; Self::LEN] {
+        let first_word = u16::from(self.tfvn & 0x3) << 14
+            | u16::from(self.scid) << 6
+            | u16::from(self.vcid & 0x3F);
+        let signaling = u8::from(self.replay_flag) << 7
+            | u8::from(self.vc_frame_count_cycle.is_some()) << 6
+            | self.vc_frame_count_cycle.unwrap_or_default() & 0xF;
+
+        let [b0, b1] = first_word.to_be_bytes();
+        let [_, b2, b3, b4] = (self.vc_frame_count & FRAME_COUNT_MASK).to_be_bytes();
+        [b0, b1, b2, b3, b4, signaling]
+    }
+
+    /// Decode from a byte steam
+    pub fn decode<R: Read>(buffer: &mut R) -> Result<Self, Error> {
+        let mut bytes = [0_u8; Self::LEN];
+        buffer.read_exact(&mut bytes)?;
+        let [b0, b1, b2, b3, b4, signaling] = bytes;
+        let first_word = u16::from_be_bytes([b0, b1]);
+
+        Ok(Self {
+            tfvn: (first_word >> 14) as u8 & 0x3,
+            scid: (first_word >> 6) as u8,
+            vcid: first_word as u8 & 0x3F,
+            vc_frame_count: u32::from_be_bytes([0, b2, b3, b4]),
+            replay_flag: signaling & 0x80 != 0,
+            vc_frame_count_cycle: (signaling & 0x40 != 0).then_some(signaling & 0xF),
+        })
+    }
+}
+
+/// An AOS Transfer Frame carrying Space Packets in an M_PDU, defined in CCSDS 732.0-B-4.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AOSTransferFrame {
+    /// AOS primary header meta-data
+    pub primary_header: AOSPrimaryHeader,
+
+    /// The location of the first packet header in the [Self::packet_zone],
+    /// encoded in the M_PDU header.
+    pub first_header_pointer: FirstHeaderPointer,
+
+    /// The M_PDU packet zone following the M_PDU header.
+    ///
+    /// The length of the packet zone is fixed on a per physical channel basis.
+    pub packet_zone: Vec<u8>,
+
+    /// The Operational Control Field, whose presence is fixed for the physical channel.
+    pub ocf: Option<[u8; 4]>,
+}
+impl AOSTransferFrame {
+    /// Length of the M_PDU header holding the First Header Pointer.
+    const MPDU_HEADER_LEN: usize = 2;
+
+    /// Encode into a byte stream
+    pub fn encode(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(
+            AOSPrimaryHeader::LEN + Self::MPDU_HEADER_LEN + self.packet_zone.len() + 4,
+        );
+        message.extend_from_slice(&self.primary_header.to_bytes());
+        // the 5 spare bits of the M_PDU header are zero
+        message.extend_from_slice(&self.first_header_pointer.into_u16().to_be_bytes());
+        message.extend_from_slice(&self.packet_zone);
+        if let Some(ocf) = self.ocf {
+            message.extend_from_slice(&ocf);
+        }
+        message
+    }
+
+    /// Decode a Transfer Frame of `length` bytes from a byte stream.
+    ///
+    /// Frame lengths and the presence of the Operational Control Field are fixed on a
+    /// per-mission physical channel basis, as such it is impossible to decode a frame
+    /// without apriori knowledge of them.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the frame cannot be read, the `length` is too short for the headers and
+    /// any OCF, or the First Header Pointer is invalid.
+    pub fn decode<R: Read>(mut buffer: R, length: usize, has_ocf: bool) -> Result<Self, Error> {
+        let ocf_len = if has_ocf { 4 } else { 0 };
+        let zone_len = length
+            .checked_sub(AOSPrimaryHeader::LEN + Self::MPDU_HEADER_LEN + ocf_len)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("AOS frame of length {length} is too short for its headers"),
+                )
+            })?;
+
+        let primary_header = AOSPrimaryHeader::decode(&mut buffer)?;
+        let mut mpdu_header = [0_u8; Self::MPDU_HEADER_LEN];
+        buffer.read_exact(&mut mpdu_header)?;
+        let first_header_pointer =
+            FirstHeaderPointer::from_u16(u16::from_be_bytes(mpdu_header) & 0x7FF)?;
+
+        let mut packet_zone = vec![0_u8; zone_len];
+        buffer.read_exact(&mut packet_zone)?;
+        let ocf = match has_ocf {
+            true => {
+                let mut ocf = [0_u8; 4];
+                buffer.read_exact(&mut ocf)?;
+                Some(ocf)
+            }
+            false => None,
+        };
+
+        Ok(Self {
+            primary_header,
+            first_header_pointer,
+            packet_zone,
+            ocf,
+        })
+    }
+}
+impl PacketZone for AOSTransferFrame {
+    fn packet_zone(&self) -> &[u8] {
+        &self.packet_zone
+    }
+
+    fn first_header_pointer(&self) -> FirstHeaderPointer {
+        self.first_header_pointer
+    }
+
+    /// The 24-bit frame count, extended by the frame count cycle when in use.
+    fn frame_count(&self) -> u32 {
+        let count = self.primary_header.vc_frame_count & FRAME_COUNT_MASK;
+        match self.primary_header.vc_frame_count_cycle {
+            Some(cycle) => u32::from(cycle & 0xF) << 24 | count,
+            None => count,
+        }
+    }
+
+    fn frame_count_modulus(&self) -> u32 {
+        match self.primary_header.vc_frame_count_cycle {
+            Some(_) => 1 << 28,
+            None => 1 << 24,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(AOSPrimaryHeader::new(0, 0), [0x40, 0x00, 0x00, 0x00, 0x00, 0x00])]
+    #[case(
+        AOSPrimaryHeader {
+            tfvn: AOS_TFVN,
+            scid: 0xFF,
+            vcid: 0x3F,
+            vc_frame_count: FRAME_COUNT_MASK,
+            replay_flag: true,
+            vc_frame_count_cycle: Some(0xF),
+        },
+        [0x7F, 0xFF, 0xFF, 0xFF, 0xFF, 0xCF]
+    )]
+    #[case(
+        AOSPrimaryHeader {
+            vc_frame_count: 0x12_3456,
+            vc_frame_count_cycle: Some(0x5),
+            ..AOSPrimaryHeader::new(0x9C, 0x21)
+        },
+        [0x67, 0x21, 0x12, 0x34, 0x56, 0x45]
+    )]
+    fn aos_primary_header(#[case] header: AOSPrimaryHeader, #[case] expected: [u8; 6]) {
+        assert_eq!(expected.to_vec(), header.encode());
+        assert_eq!(
+            header,
+            AOSPrimaryHeader::decode(&mut &expected[..]).unwrap()
+        );
+    }
+
+    #[rstest]
+    fn aos_frame_roundtrip(
+        #[values(None, Some([1, 2, 3, 4]))] ocf: Option<[u8; 4]>,
+        #[values(
+            FirstHeaderPointer::ByteIndex(0),
+            FirstHeaderPointer::ByteIndex(9),
+            FirstHeaderPointer::OnlyIdleData,
+            FirstHeaderPointer::NoPacketStart
+        )]
+        first_header_pointer: FirstHeaderPointer,
+    ) {
+        let frame = AOSTransferFrame {
+            primary_header: AOSPrimaryHeader::new(0x9C, 1),
+            first_header_pointer,
+            packet_zone: (0..40).collect(),
+            ocf,
+        };
+        let encoded = frame.encode();
+        assert_eq!(8 + 40 + ocf.map_or(0, |_| 4), encoded.len());
+        assert_eq!(
+            frame,
+            AOSTransferFrame::decode(encoded.as_slice(), encoded.len(), ocf.is_some()).unwrap()
+        );
+    }
+
+    #[test]
+    fn aos_frame_too_short() {
+        let encoded = AOSTransferFrame {
+            primary_header: AOSPrimaryHeader::new(0x9C, 1),
+            first_header_pointer: FirstHeaderPointer::NoPacketStart,
+            packet_zone: vec![],
+            ocf: None,
+        }
+        .encode();
+
+        assert!(AOSTransferFrame::decode(encoded.as_slice(), 8, false).is_ok());
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            AOSTransferFrame::decode(encoded.as_slice(), 8, true)
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            AOSTransferFrame::decode(encoded.as_slice(), 9, false)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn aos_frame_count_cycle() {
+        let mut frame = AOSTransferFrame {
+            primary_header: AOSPrimaryHeader::new(0x9C, 1),
+            first_header_pointer: FirstHeaderPointer::NoPacketStart,
+            packet_zone: vec![],
+            ocf: None,
+        };
+        frame.primary_header.vc_frame_count = FRAME_COUNT_MASK;
+        assert_eq!(
+            (FRAME_COUNT_MASK, 1 << 24),
+            (frame.frame_count(), frame.frame_count_modulus())
+        );
+
+        frame.primary_header.vc_frame_count_cycle = Some(3);
+        assert_eq!(
+            (3 << 24 | FRAME_COUNT_MASK, 1 << 28),
+            (frame.frame_count(), frame.frame_count_modulus())
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Identification and routing of Transfer Frames by their
+//! Spacecraft ID (SCID) and Virtual Channel ID (VCID).
+//!
+//! A [ChannelRouter] maps every [ChannelId] a ground system receives to a
+//! caller chosen value, e.g. a handler or a queue, and looks up the value for
+//! any frame or header implementing [ChannelHeader].
+
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind},
+};
+
+use crate::tctm::{
+    tc::{TCPrimaryHeader, TCTransferFrame, TCTransferFrameView},
+    tm::{TMPrimaryHeader, TMTransferFrame, TMTransferFrameView},
+};
+
+/// A Spacecraft ID and Virtual Channel ID pair identifying a single virtual channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChannelId {
+    /// Spacecraft ID. 10-bits maximum.
+    pub scid: u16,
+
+    /// Virtual Channel ID. 6-bits maximum.
+    pub vcid: u8,
+}
+impl ChannelId {
+    /// Create a new channel identifier.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the pair fails [Self::validate].
+    pub fn new(scid: u16, vcid: u8) -> Result<Self, Error> {
+        let channel = Self { scid, vcid };
+        channel.validate()?;
+        Ok(channel)
+    }
+
+    /// Check the identifiers fit in the widest header fields of TM and TC Transfer Frames.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - the spacecraft ID is > 1023
+    ///  - the virtual channel ID is > 63
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.scid > 1023 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Spacecraft ID must be <=1023 but found {}", self.scid),
+            ));
+        }
+
+        if self.vcid > 63 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Virtual Channel ID must be <=63 but found {}", self.vcid),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Access to the [ChannelId] of a Transfer Frame or its header.
+pub trait ChannelHeader {
+    /// The virtual channel the frame was sent on.
+    fn channel_id(&self) -> ChannelId;
+}
+impl ChannelHeader for TMPrimaryHeader {
+    fn channel_id(&self) -> ChannelId {
+        ChannelId {
+            scid: self.scid,
+            vcid: self.vcid,
+        }
+    }
+}
+impl ChannelHeader for TMTransferFrame {
+    fn channel_id(&self) -> ChannelId {
+        self.primary_header.channel_id()
+    }
+}
+impl<'a> ChannelHeader for TMTransferFrameView<'a> {
+    fn channel_id(&self) -> ChannelId {
+        self.primary_header.channel_id()
+    }
+}
+impl ChannelHeader for TCPrimaryHeader {
+    fn channel_id(&self) -> ChannelId {
+        ChannelId {
+            scid: self.scid,
+            vcid: self.vcid,
+        }
+    }
+}
+impl ChannelHeader for TCTransferFrame {
+    fn channel_id(&self) -> ChannelId {
+        self.header().channel_id()
+    }
+}
+impl<'a> ChannelHeader for TCTransferFrameView<'a> {
+    fn channel_id(&self) -> ChannelId {
+        self.header.channel_id()
+    }
+}
+
+/// Maps the [ChannelId] of incoming frames to a value of type `T`.
+///
+/// ```
+/// # use spacepacket::tctm::{channel::{ChannelId, ChannelRouter}, tm::TMPrimaryHeader};
+/// let mut router = ChannelRouter::new();
+/// router.insert(ChannelId::new(758, 3).unwrap(), "housekeeping");
+///
+/// let header = TMPrimaryHeader::builder().scid(758).vcid(3).build().unwrap();
+/// assert_eq!(Some(&"housekeeping"), router.route(&header));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelRouter<T> {
+    routes: HashMap<ChannelId, T>,
+}
+impl<T> Default for ChannelRouter<T> {
+    fn default() -> Self {
+        Self {
+            routes: HashMap::new(),
+        }
+    }
+}
+impl<T> ChannelRouter<T> {
+    /// Create a router without any routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Route frames of the `channel` to `value`, returning the value previously routed to, if any.
+    pub fn insert(&mut self, channel: ChannelId, value: T) -> Option<T> {
+        self.routes.insert(channel, value)
+    }
+
+    /// Remove the route of the `channel`, returning its value if it was routed.
+    pub fn remove(&mut self, channel: &ChannelId) -> Option<T> {
+        self.routes.remove(channel)
+    }
+
+    /// The value routed to from the `channel`.
+    pub fn get(&self, channel: &ChannelId) -> Option<&T> {
+        self.routes.get(channel)
+    }
+
+    /// The value routed to from the channel of the `frame`.
+    pub fn route<F: ChannelHeader + ?Sized>(&self, frame: &F) -> Option<&T> {
+        self.routes.get(&frame.channel_id())
+    }
+
+    /// A mutable reference to the value routed to from the channel of the `frame`.
+    pub fn route_mut<F: ChannelHeader + ?Sized>(&mut self, frame: &F) -> Option<&mut T> {
+        self.routes.get_mut(&frame.channel_id())
+    }
+
+    /// Iterate over all routes in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&ChannelId, &T)> {
+        self.routes.iter()
+    }
+
+    /// The number of routed channels.
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// Whether no channel is routed.
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::tctm::tc::{BypassFlag, ControlFlag};
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0, 0)]
+    #[case(1023, 63)]
+    fn channel_id_valid(#[case] scid: u16, #[case] vcid: u8) {
+        assert_eq!(
+            ChannelId { scid, vcid },
+            ChannelId::new(scid, vcid).unwrap()
+        )
+    }
+
+    #[rstest]
+    #[case(1024, 0)]
+    #[case(0, 64)]
+    #[should_panic]
+    fn channel_id_invalid(#[case] scid: u16, #[case] vcid: u8) {
+        ChannelId::new(scid, vcid).unwrap();
+    }
+
+    #[test]
+    fn router_route() {
+        let tm_frame = TMTransferFrame {
+            primary_header: TMPrimaryHeader::builder()
+                .scid(758)
+                .vcid(3)
+                .build()
+                .unwrap(),
+            secondary_header: None,
+            data_field: vec![0_u8; 10],
+        };
+        let tc_frame = TCTransferFrame::new(
+            TCPrimaryHeader {
+                tfvn: 0,
+                bypass_flag: BypassFlag::TypeB,
+                control_flag: ControlFlag::TypeD,
+                scid: 758,
+                vcid: 3,
+                sequence_number: 0,
+            },
+            vec![0_u8; 10],
+        )
+        .unwrap();
+        let other = TMTransferFrame {
+            primary_header: TMPrimaryHeader::builder()
+                .scid(758)
+                .vcid(4)
+                .build()
+                .unwrap(),
+            secondary_header: None,
+            data_field: vec![0_u8; 10],
+        };
+
+        let mut router = ChannelRouter::new();
+        assert!(router.is_empty());
+        assert_eq!(None, router.insert(ChannelId::new(758, 3).unwrap(), 1));
+        assert_eq!(Some(1), router.insert(ChannelId::new(758, 3).unwrap(), 2));
+        assert_eq!(1, router.len());
+
+        assert_eq!(Some(&2), router.route(&tm_frame));
+        assert_eq!(Some(&2), router.route(&tm_frame.view()));
+        assert_eq!(Some(&2), router.route(&tc_frame));
+        assert_eq!(Some(&2), router.route(&tc_frame.view()));
+        assert_eq!(None, router.route(&other));
+
+        *router.route_mut(&tc_frame.header()).unwrap() += 1;
+        assert_eq!(Some(&3), router.get(&ChannelId::new(758, 3).unwrap()));
+
+        assert_eq!(Some(3), router.remove(&tm_frame.channel_id()));
+        assert_eq!(None, router.route(&tm_frame));
+    }
+}
@@ -0,0 +1,226 @@
+//! Communications Link Control Word (CLCW) as defined in CCSDS 232.0-B-4.
+//!
+//! The CLCW is the Type-1 report carried in the Operational Control Field
+//! of TM Transfer Frames to report the FARM status of a TC virtual channel.
+
+use std::{
+    fmt::Debug,
+    io::{Error, ErrorKind, Read},
+};
+
+use byteorder::{BigEndian, ReadBytesExt};
+
+use crate::tctm::farm::FarmBCounter;
+#[cfg(doc)]
+use crate::tctm::tm::TMFramePacker;
+
+/// A Communications Link Control Word reporting the status of the FARM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clcw {
+    /// CLCW version number. Currently fixed to '00'
+    /// Encoded in 2 bits
+    pub version: u8,
+
+    /// Mission specific status field. Encoded in 3 bits
+    pub status: u8,
+
+    /// The Command Operation Procedure in effect. COP-1 is '01'
+    /// Encoded in 2 bits
+    pub cop_in_effect: u8,
+
+    /// The TC virtual channel this report applies to. 6-bits maximum.
+    pub vcid: u8,
+
+    /// Set when the physical layer reports no RF is available.
+    pub no_rf_available: bool,
+
+    /// Set when the physical layer reports no bit lock.
+    pub no_bit_lock: bool,
+
+    /// Set when the FARM is in the Lockout state.
+    pub lockout: bool,
+
+    /// Set when the FARM cannot accept more frames.
+    pub wait: bool,
+
+    /// Set when one or more Type-A frames must be retransmitted.
+    pub retransmit: bool,
+
+    /// Two least significant bits of the FARM-B frame counter.
+    pub farm_b_counter: u8,
+
+    /// The next expected frame sequence number, N(R).
+    pub report_value: u8,
+}
+impl Clcw {
+    /// Report the current value of the `counter` in [Self::farm_b_counter].
+    pub fn with_farm_b_counter(mut self, counter: &FarmBCounter) -> Self {
+        self.farm_b_counter = counter.value();
+        self
+    }
+
+    /// Validate values which require bit masks will fit in the
+    /// desginate bit-depth
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - [Self::version] > 3
+    ///  - [Self::status] > 7
+    ///  - [Self::cop_in_effect] > 3
+    ///  - [Self::vcid] > 63
+    ///  - [Self::farm_b_counter] > 3
+    pub fn validate(&self) -> Result<(), Error> {
+        let limits = [
+            ("CLCW version number", self.version, 3),
+            ("Status field", self.status, 7),
+            ("COP in effect", self.cop_in_effect, 3),
+            ("Virtual Channel ID", self.vcid, 63),
+            ("FARM-B counter", self.farm_b_counter, 3),
+        ];
+        for (name, value, max) in limits {
+            if value > max {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{name} must be <={max} but found {value}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode into the 4 byte Operational Control Field.
+    /// Assumes Big Endian byte order
+    pub fn encode(self) -> Vec<u8> {
+        // the leading Control Word Type bit is 0 for a CLCW
+        let word = (self.version as u32 & 0x3) << 29
+            | (self.status as u32 & 0x7) << 26
+            | (self.cop_in_effect as u32 & 0x3) << 24
+            | (self.vcid as u32 & 0x3f) << 18
+            // two spare bits here reserved
+            | (self.no_rf_available as u32) << 15
+            | (self.no_bit_lock as u32) << 14
+            | (self.lockout as u32) << 13
+            | (self.wait as u32) << 12
+            | (self.retransmit as u32) << 11
+            | (self.farm_b_counter as u32 & 0x3) << 9
+            // one spare bit here reserved
+            | self.report_value as u32;
+
+        word.to_be_bytes().to_vec()
+    }
+
+    /// Decode from the 4 byte Operational Control Field.
+    /// Assumes Big Endian byte order
+    ///
+    /// # Errors
+    ///
+    /// Errors if the Control Word Type is not a CLCW.
+    pub fn decode<R: Read>(buffer: &mut R) -> Result<Self, Error> {
+        let word = buffer.read_u32::<BigEndian>()?;
+
+        if word >> 31 != 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Operational Control Field does not contain a CLCW.",
+            ));
+        }
+
+        Ok(Self {
+            version: ((word >> 29) & 0x3) as u8,
+            status: ((word >> 26) & 0x7) as u8,
+            cop_in_effect: ((word >> 24) & 0x3) as u8,
+            vcid: ((word >> 18) & 0x3f) as u8,
+            no_rf_available: (word >> 15) & 0x1 == 1,
+            no_bit_lock: (word >> 14) & 0x1 == 1,
+            lockout: (word >> 13) & 0x1 == 1,
+            wait: (word >> 12) & 0x1 == 1,
+            retransmit: (word >> 11) & 0x1 == 1,
+            farm_b_counter: ((word >> 9) & 0x3) as u8,
+            report_value: (word & 0xff) as u8,
+        })
+    }
+}
+
+/// Provides the latest [Clcw] to embed in the Operational Control Field of downlink frames.
+///
+/// The source is consulted as each frame is finalized, see [TMFramePacker::with_clcw_source],
+/// so it should report live FARM state rather than a snapshot.
+///
+/// ```
+/// # use std::sync::{Arc, Mutex};
+/// # use spacepacket::tctm::{clcw::{Clcw, ClcwSource}, farm::FarmBCounter};
+/// let counter = Arc::new(Mutex::new(FarmBCounter::new()));
+/// let template = Clcw::decode(&mut [0x01, 0x00, 0x00, 0x00].as_slice()).unwrap();
+///
+/// let farm = counter.clone();
+/// let source = move |_vcid: u8| template.with_farm_b_counter(&farm.lock().unwrap());
+///
+/// counter.lock().unwrap().increment();
+/// assert_eq!(1, source.current_clcw(0).farm_b_counter);
+/// ```
+pub trait ClcwSource {
+    /// The CLCW to report in the next frame of the TM virtual channel `vcid`.
+    fn current_clcw(&self, vcid: u8) -> Clcw;
+}
+impl ClcwSource for Clcw {
+    /// Report the same CLCW on every virtual channel.
+    fn current_clcw(&self, _vcid: u8) -> Clcw {
+        *self
+    }
+}
+impl<F: Fn(u8) -> Clcw> ClcwSource for F {
+    fn current_clcw(&self, vcid: u8) -> Clcw {
+        self(vcid)
+    }
+}
+impl Debug for dyn ClcwSource + Send + Sync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ClcwSource")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    fn clcw_roundtrip(
+        #[values(0, 63)] vcid: u8,
+        #[values(true, false)] flags: bool,
+        #[values(0, 3)] farm_b_counter: u8,
+        #[values(0, 255)] report_value: u8,
+    ) {
+        let expected = Clcw {
+            version: 0,
+            status: 5,
+            cop_in_effect: 1,
+            vcid,
+            no_rf_available: flags,
+            no_bit_lock: !flags,
+            lockout: flags,
+            wait: !flags,
+            retransmit: flags,
+            farm_b_counter,
+            report_value,
+        };
+        expected.validate().unwrap();
+
+        let bytes = expected.encode();
+
+        let recovered = Clcw::decode(&mut bytes.as_slice()).expect("Unable to decode CLCW.");
+
+        assert_eq!(expected, recovered)
+    }
+
+    #[rstest]
+    #[case([0x01, 0x00, 0x00, 0x00])]
+    #[should_panic]
+    // control word type 1 is not a CLCW
+    #[case([0x81, 0x00, 0x00, 0x00])]
+    fn clcw_control_word_type(#[case] bytes: [u8; 4]) {
+        Clcw::decode(&mut bytes.as_slice()).unwrap();
+    }
+}
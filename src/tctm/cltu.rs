@@ -1,11 +1,12 @@
-//! Generate Communications Link Transmission Unit (CLTU) packets
+//! Generate and decode Communications Link Transmission Unit (CLTU) packets
 //! as defined in CCSDS 231.0-B-4
 
-use crate::tctm::randomizer::{apply_randomization, Randomization};
+use crate::tctm::randomizer::Randomization;
 
 mod bch;
+pub use bch::{correct_bch_block, BchError};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Possible  CCSDS 231.0-B-4  CLTU encoding types
 pub enum EncodingScheme {
     /// A modified (63, 56) Bose-Chaudhuri-Hocquenghem code.
@@ -16,20 +17,279 @@ pub enum EncodingScheme {
     BCHRandomized,
 }
 
+/// A CLTU could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CltuError {
+    /// The CLTU does not begin with the start sequence.
+    MissingStartSequence,
+    /// A codeblock holds more bit errors than can be corrected.
+    Uncorrectable {
+        /// The index of the codeblock, counting from 0 after the start sequence.
+        codeblock: usize,
+        /// The error of the codeblock.
+        error: BchError,
+    },
+    /// The input ended before the tail sequence, e.g. the carrier dropped mid-CLTU.
+    TruncatedCltu {
+        /// The number of complete codeblocks decoded.
+        codeblocks_recovered: usize,
+        /// The number of data bytes decoded, the length of `data`.
+        bytes_recovered: usize,
+        /// The data bytes of the complete codeblocks, de-randomized and including any fill.
+        data: Vec<u8>,
+    },
+}
+impl std::fmt::Display for CltuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingStartSequence => write!(f, "CLTU does not begin with the start sequence"),
+            Self::Uncorrectable { codeblock, error } => {
+                write!(f, "BCH parity error in codeblock {codeblock}: {error}")
+            }
+            Self::TruncatedCltu {
+                codeblocks_recovered,
+                bytes_recovered,
+                ..
+            } => write!(
+                f,
+                "CLTU ends without the tail sequence after {codeblocks_recovered} codeblocks \
+                 of {bytes_recovered} bytes"
+            ),
+        }
+    }
+}
+impl std::error::Error for CltuError {}
+impl From<CltuError> for std::io::Error {
+    fn from(err: CltuError) -> Self {
+        let kind = match err {
+            CltuError::TruncatedCltu { .. } => std::io::ErrorKind::UnexpectedEof,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, err)
+    }
+}
+
 /// Generates a Communications Link Transmission Unit (CLTU) from an input
 /// byte stream using the chosen encoding scheme.
 pub fn generate_ctlu<P: AsRef<[u8]>>(bytes: P, encoding: EncodingScheme) -> Vec<u8> {
-    let bytes = bytes.as_ref();
+    let mut output = vec![];
+    encode_into(bytes.as_ref(), encoding, &mut output);
+    output
+}
+
+/// Generates a Communications Link Transmission Unit (CLTU) from an input
+/// byte stream using the chosen encoding scheme and appends it to `out`.
+///
+/// Reusing the same `out` buffer for many CLTUs avoids allocating once
+/// its capacity has grown to fit the largest CLTU.
+pub fn encode_into(input: &[u8], encoding: EncodingScheme, out: &mut Vec<u8>) {
+    // start, codeblocks and tail sequence
+    out.reserve(2 + (input.len() + 6) / 7 * 8 + 8);
+    encode_chunks_into([input], encoding, out)
+}
+
+/// Generates a Communications Link Transmission Unit (CLTU) from a sequence of byte chunks,
+/// e.g. a scatter list, as if they were one contiguous buffer.
+pub fn generate_ctlu_chunks<I>(chunks: I, encoding: EncodingScheme) -> Vec<u8>
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    let mut output = vec![];
+    encode_chunks_into(chunks, encoding, &mut output);
+    output
+}
+
+/// Generates a Communications Link Transmission Unit (CLTU) from a sequence of byte chunks
+/// and appends it to `out`, see [generate_ctlu_chunks] and [encode_into].
+pub fn encode_chunks_into<I>(chunks: I, encoding: EncodingScheme, out: &mut Vec<u8>)
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
     match encoding {
-        EncodingScheme::BCH => bch::encode_bch_ctlu(bytes),
+        EncodingScheme::BCH => bch::encode_bch_ctlu_into(chunks, None, out),
         EncodingScheme::BCHRandomized => {
-            bch::encode_bch_ctlu(apply_randomization(bytes, Randomization::TC).as_slice())
+            bch::encode_bch_ctlu_into(chunks, Some(Randomization::TC), out)
         }
     }
 }
 
+/// Generates a Communications Link Transmission Unit (CLTU) from an input byte stream
+/// keeping the start sequence, codeblocks and tail sequence apart, see [Cltu].
+pub fn generate_cltu_structured<P: AsRef<[u8]>>(bytes: P, encoding: EncodingScheme) -> Cltu {
+    let encoded = generate_ctlu(bytes, encoding);
+    let (start, rest) = encoded.split_at(2);
+    let (codeblocks, tail) = rest.split_at(rest.len() - 8);
+
+    Cltu {
+        start_sequence: start.try_into().unwrap(),
+        codeblocks: codeblocks
+            .chunks_exact(8)
+            .map(|codeblock| codeblock.try_into().unwrap())
+            .collect(),
+        tail_sequence: tail.try_into().unwrap(),
+    }
+}
+
+/// A CLTU split into its transmission units, e.g. to pace a modem codeblock by codeblock.
+///
+/// Iterating over a `&Cltu` yields the start sequence, every codeblock and the tail sequence in order.
+///
+/// ```
+/// # use spacepacket::tctm::cltu::{generate_ctlu, generate_cltu_structured, EncodingScheme};
+/// let cltu = generate_cltu_structured([0x42; 10], EncodingScheme::BCH);
+/// assert_eq!(2, cltu.codeblocks.len());
+/// assert_eq!(vec![2, 8, 8, 8], cltu.into_iter().map(<[u8]>::len).collect::<Vec<_>>());
+/// assert_eq!(generate_ctlu([0x42; 10], EncodingScheme::BCH), cltu.as_contiguous());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cltu {
+    /// The start sequence preceding the codeblocks.
+    pub start_sequence: [u8; 2],
+    /// The BCH codeblocks, 7 data bytes followed by a parity byte.
+    pub codeblocks: Vec<[u8; 8]>,
+    /// The tail sequence following the codeblocks.
+    pub tail_sequence: [u8; 8],
+}
+impl Cltu {
+    /// The length of the encoded CLTU in bytes.
+    pub fn len(&self) -> usize {
+        self.start_sequence.len() + 8 * self.codeblocks.len() + self.tail_sequence.len()
+    }
+
+    /// Always false, a CLTU holds at least its start and tail sequence.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The CLTU as one contiguous buffer, as returned by [generate_ctlu].
+    pub fn as_contiguous(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.len());
+        out.extend(self.into_iter().flatten());
+        out
+    }
+}
+
+impl<'a> IntoIterator for &'a Cltu {
+    type Item = &'a [u8];
+    type IntoIter = CltuUnits<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        CltuUnits {
+            cltu: self,
+            index: 0,
+        }
+    }
+}
+
+/// Iterator over the transmission units of a [Cltu].
+#[derive(Debug, Clone)]
+pub struct CltuUnits<'a> {
+    cltu: &'a Cltu,
+    index: usize,
+}
+impl<'a> Iterator for CltuUnits<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let codeblocks = &self.cltu.codeblocks;
+        let unit: &'a [u8] = match self.index {
+            0 => &self.cltu.start_sequence,
+            index if index <= codeblocks.len() => &codeblocks[index - 1],
+            index if index == codeblocks.len() + 1 => &self.cltu.tail_sequence,
+            _ => return None,
+        };
+        self.index += 1;
+        Some(unit)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.cltu.codeblocks.len() + 2).saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+impl ExactSizeIterator for CltuUnits<'_> {}
+
+/// Recover the TC Transfer Frame carried by a CLTU encoded with the `encoding`,
+/// the inverse of [generate_ctlu].
+///
+/// The start sequence is stripped and every codeblock up to the tail sequence is checked
+/// against its BCH parity, then de-randomized for [EncodingScheme::BCHRandomized].
+/// The fill of the last codeblock is removed using the Frame Length field of the frame,
+/// since a frame may itself end with bytes equal to the fill.
+///
+/// # Errors
+///
+/// This function errors under the following circumstances
+///  - `bytes` does not begin with the start sequence
+///  - a codeblock does not match its BCH parity
+///  - `bytes` ends without the tail sequence, with a [CltuError::TruncatedCltu] holding
+///    the data recovered, see [decode_cltu_lenient]
+///  - the Frame Length field does not end the frame within the last codeblock
+///
+/// The [CltuError] is available from the [std::io::Error::get_ref] of the first three errors.
+pub fn decode_cltu<P: AsRef<[u8]>>(bytes: P, encoding: EncodingScheme) -> std::io::Result<Vec<u8>> {
+    remove_fill(decode(bytes.as_ref(), encoding)?)
+}
+
+/// Truncate the decoded codeblock data to the Frame Length field of the frame it carries.
+fn remove_fill(mut frame: Vec<u8>) -> std::io::Result<Vec<u8>> {
+    let frame_len = match frame.get(2..4) {
+        Some(&[b2, b3]) => usize::from(u16::from_be_bytes([b2, b3]) & 0x3FF) + 1,
+        _ => 0,
+    };
+    if frame_len > frame.len() || frame.len() - frame_len >= 7 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "TC frame length {frame_len} does not end within the last codeblock of {} data bytes",
+                frame.len()
+            ),
+        ));
+    }
+    frame.truncate(frame_len);
+    Ok(frame)
+}
+
+/// Recover as much of a TC Transfer Frame as possible from a CLTU which may be truncated,
+/// for forensic use of recordings where the carrier dropped mid-CLTU.
+///
+/// A complete CLTU is decoded as [decode_cltu]. When the input ends before the tail sequence
+/// the data bytes of every complete codeblock are returned instead, without removing any fill
+/// since the frame may not be complete.
+///
+/// # Errors
+///
+/// This function errors under the following circumstances
+///  - `bytes` does not begin with the start sequence
+///  - a codeblock holds more bit errors than can be corrected
+///  - a complete CLTU fails [decode_cltu]
+pub fn decode_cltu_lenient<P: AsRef<[u8]>>(
+    bytes: P,
+    encoding: EncodingScheme,
+) -> std::io::Result<Vec<u8>> {
+    match decode_codeblocks(bytes.as_ref(), encoding) {
+        Err(CltuError::TruncatedCltu { data, .. }) => Ok(data),
+        Err(err) => Err(err.into()),
+        Ok(data) => remove_fill(data),
+    }
+}
+
+fn decode_codeblocks(cltu: &[u8], encoding: EncodingScheme) -> Result<Vec<u8>, CltuError> {
+    match encoding {
+        EncodingScheme::BCH => bch::decode_bch_ctlu(cltu, None),
+        EncodingScheme::BCHRandomized => bch::decode_bch_ctlu(cltu, Some(Randomization::TC)),
+    }
+}
+
+/// Recover the (possibly padded) TC frame bytes of a CLTU encoded with the `encoding`.
+pub(crate) fn decode(cltu: &[u8], encoding: EncodingScheme) -> std::io::Result<Vec<u8>> {
+    Ok(decode_codeblocks(cltu, encoding)?)
+}
+
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use super::*;
 
     use rstest::rstest;
@@ -37,7 +297,7 @@ mod test {
     // test values derived from https://github.com/yamcs/yamcs/blob/78b9553caf3c9f7ef7a6e6897d236a69aeed8190/yamcs-core/src/test/java/org/yamcs/tctm/ccsds/error/BchCltuGeneratorTest.java
     // and by extension from SpacePyLibrary
     // https://github.com/Stefan-Korner/SpacePyLibrary/blob/master/UnitTest/testData.py
-    const TC_FRAME_01: &[u8] = &[
+    pub(crate) const TC_FRAME_01: &[u8] = &[
         0x22, 0xF6, 0x00, 0xFF, 0x00, 0x42, 0x1A, 0x8C, 0xC0, 0x0E, 0x01, 0x0D, 0x19, 0x06, 0x02,
         0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00,
         0x00, 0x0F, 0x00, 0x01, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x0F, 0x00, 0x02, 0xFF, 0x00, 0x00,
@@ -58,7 +318,7 @@ mod test {
         0x1A,
     ];
 
-    const CLTU_01: &[u8] = &[
+    pub(crate) const CLTU_01: &[u8] = &[
         0xEB, 0x90, 0x22, 0xF6, 0x00, 0xFF, 0x00, 0x42, 0x1A, 0x12, 0x8C, 0xC0, 0x0E, 0x01, 0x0D,
         0x19, 0x06, 0x5A, 0x02, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x8A, 0x00, 0x01, 0x00, 0x00,
         0x00, 0xFF, 0x00, 0xCC, 0x00, 0x00, 0x00, 0x0F, 0x00, 0x01, 0xFF, 0x28, 0x00, 0x00, 0x00,
@@ -82,13 +342,13 @@ mod test {
         0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0x79,
     ];
 
-    const TC_FRAME_02: &[u8] = &[
+    pub(crate) const TC_FRAME_02: &[u8] = &[
         0x22, 0xF6, 0x00, 0x23, 0x00, 0x82, 0x00, 0x0F, 0x00, 0x1D, 0xFF, 0x00, 0x00, 0x00, 0x00,
         0x0F, 0x00, 0x1E, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x0F, 0x00, 0x1F, 0xFF, 0x00, 0x00, 0x00,
         0x00, 0x0F, 0xAC, 0x8F, 0x00, 0x68,
     ];
 
-    const CLTU_02: &[u8] = &[
+    pub(crate) const CLTU_02: &[u8] = &[
         0xEB, 0x90, 0x22, 0xF6, 0x00, 0x23, 0x00, 0x82, 0x00, 0x24, 0x0F, 0x00, 0x1D, 0xFF, 0x00,
         0x00, 0x00, 0x34, 0x00, 0x0F, 0x00, 0x1E, 0xFF, 0x00, 0x00, 0x10, 0x00, 0x00, 0x0F, 0x00,
         0x1F, 0xFF, 0x00, 0xD8, 0x00, 0x00, 0x00, 0x0F, 0xAC, 0x8F, 0x00, 0x90, 0x68, 0x55, 0x55,
@@ -101,4 +361,227 @@ mod test {
     fn cltu_gen(#[case] tc_frame: &[u8], #[case] cltu: &[u8]) {
         assert_eq!(cltu, generate_ctlu(tc_frame, EncodingScheme::BCH))
     }
+
+    #[rstest]
+    fn cltu_encode_into(
+        #[values(TC_FRAME_01, TC_FRAME_02)] tc_frame: &[u8],
+        #[values(EncodingScheme::BCH, EncodingScheme::BCHRandomized)] encoding: EncodingScheme,
+    ) {
+        let expected = generate_ctlu(tc_frame, encoding);
+
+        // existing contents are kept and the CLTU appended
+        let mut out = vec![0xAB, 0xCD];
+        encode_into(tc_frame, encoding, &mut out);
+        assert_eq!(&[0xAB, 0xCD], &out[..2]);
+        assert_eq!(expected, out[2..]);
+
+        // a cleared buffer is reused without growing
+        out.clear();
+        let capacity = out.capacity();
+        encode_into(tc_frame, encoding, &mut out);
+        assert_eq!(expected, out);
+        assert_eq!(capacity, out.capacity());
+    }
+
+    #[rstest]
+    fn cltu_structured(
+        #[values(TC_FRAME_01, TC_FRAME_02)] tc_frame: &[u8],
+        #[values(EncodingScheme::BCH, EncodingScheme::BCHRandomized)] encoding: EncodingScheme,
+    ) {
+        let expected = generate_ctlu(tc_frame, encoding);
+        let cltu = generate_cltu_structured(tc_frame, encoding);
+
+        assert_eq!(expected.len(), cltu.len());
+        assert_eq!(expected, cltu.as_contiguous());
+        assert_eq!((tc_frame.len() + 6) / 7, cltu.codeblocks.len());
+        assert_eq!(cltu.codeblocks.len() + 2, cltu.into_iter().len());
+        assert_eq!(&expected[..2], cltu.into_iter().next().unwrap());
+        assert_eq!(
+            &expected[expected.len() - 8..],
+            cltu.into_iter().last().unwrap()
+        );
+
+        for codeblock in &cltu.codeblocks {
+            assert_eq!(
+                bch::compute_bch_parity(codeblock[..7].try_into().unwrap()),
+                codeblock[7]
+            );
+        }
+    }
+
+    /// Split the `bytes` into chunks with pseudo-random lengths of 0 to 15 bytes.
+    pub(crate) fn random_chunks(bytes: &[u8], seed: u32) -> Vec<&[u8]> {
+        let mut state = seed;
+        let mut rest = bytes;
+        let mut chunks = vec![];
+        while !rest.is_empty() {
+            // linear congruential generator from Numerical Recipes
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            let (chunk, remainder) = rest.split_at(((state >> 24) as usize % 16).min(rest.len()));
+            chunks.push(chunk);
+            rest = remainder;
+        }
+        chunks
+    }
+
+    #[rstest]
+    fn cltu_chunks(
+        #[values(TC_FRAME_01, TC_FRAME_02)] tc_frame: &[u8],
+        #[values(EncodingScheme::BCH, EncodingScheme::BCHRandomized)] encoding: EncodingScheme,
+        #[values(0, 1, 7, 42, 1234)] seed: u32,
+    ) {
+        let chunks = random_chunks(tc_frame, seed);
+        assert_eq!(tc_frame, chunks.concat());
+
+        assert_eq!(
+            generate_ctlu(tc_frame, encoding),
+            generate_ctlu_chunks(&chunks, encoding)
+        );
+    }
+
+    #[rstest]
+    #[case(TC_FRAME_01)]
+    #[case(TC_FRAME_02)]
+    fn cltu_randomized(#[case] tc_frame: &[u8]) {
+        use crate::tctm::randomizer::apply_randomization;
+
+        assert_eq!(
+            generate_ctlu(
+                apply_randomization(tc_frame, Randomization::TC),
+                EncodingScheme::BCH
+            ),
+            generate_ctlu(tc_frame, EncodingScheme::BCHRandomized)
+        )
+    }
+
+    #[rstest]
+    fn cltu_decode_frame(
+        #[values(TC_FRAME_01, TC_FRAME_02)] tc_frame: &[u8],
+        #[values(EncodingScheme::BCH, EncodingScheme::BCHRandomized)] encoding: EncodingScheme,
+    ) {
+        let cltu = generate_ctlu(tc_frame, encoding);
+        assert_eq!(tc_frame, decode_cltu(&cltu, encoding).unwrap());
+
+        let mut damaged = cltu.clone();
+        damaged[10] ^= 0x81;
+        let err = decode_cltu(&damaged, encoding).unwrap_err();
+        assert!(err.to_string().contains("parity"));
+        assert!(decode_cltu(&cltu[2..], encoding).is_err());
+        assert!(decode_cltu(&cltu[..cltu.len() - 8], encoding).is_err());
+    }
+
+    #[test]
+    fn cltu_decode_corrects_single_bits() {
+        // flip one bit in every codeblock of CLTU_01, each at a different position
+        let mut damaged = CLTU_01.to_vec();
+        let codeblocks = (CLTU_01.len() - 10) / 8;
+        for block in 0..codeblocks {
+            let bit = block * 5 % 64;
+            damaged[2 + block * 8 + bit / 8] ^= 0x80 >> (bit % 8);
+        }
+        assert_eq!(
+            TC_FRAME_01,
+            decode_cltu(&damaged, EncodingScheme::BCH).unwrap()
+        );
+    }
+
+    #[rstest]
+    // mid codeblock
+    #[case(2 + 3 * 8 + 5, 3)]
+    // between codeblocks
+    #[case(2 + 5 * 8, 5)]
+    // only the start sequence
+    #[case(2, 0)]
+    // inside the tail sequence
+    #[case(CLTU_01.len() - 3, (CLTU_01.len() - 10) / 8)]
+    fn cltu_decode_truncated(#[case] cut: usize, #[case] codeblocks: usize) {
+        let truncated = &CLTU_01[..cut];
+        let err = decode_cltu(truncated, EncodingScheme::BCH).unwrap_err();
+        assert_eq!(std::io::ErrorKind::UnexpectedEof, err.kind());
+        let expected = TC_FRAME_01[..(codeblocks * 7).min(TC_FRAME_01.len())].to_vec();
+        match err.get_ref().unwrap().downcast_ref::<CltuError>().unwrap() {
+            CltuError::TruncatedCltu {
+                codeblocks_recovered,
+                bytes_recovered,
+                data,
+            } => {
+                assert_eq!(codeblocks, *codeblocks_recovered);
+                assert_eq!(codeblocks * 7, *bytes_recovered);
+                assert_eq!(expected, data[..expected.len()]);
+            }
+            err => panic!("unexpected error {err}"),
+        }
+
+        let partial = decode_cltu_lenient(truncated, EncodingScheme::BCH).unwrap();
+        assert_eq!(codeblocks * 7, partial.len());
+        assert_eq!(expected, partial[..expected.len()]);
+    }
+
+    #[rstest]
+    fn cltu_decode_lenient(
+        #[values(EncodingScheme::BCH, EncodingScheme::BCHRandomized)] encoding: EncodingScheme,
+    ) {
+        let cltu = generate_ctlu(TC_FRAME_02, encoding);
+        assert_eq!(TC_FRAME_02, decode_cltu_lenient(&cltu, encoding).unwrap());
+
+        // the recovered bytes are de-randomized
+        let partial = decode_cltu_lenient(&cltu[..2 + 2 * 8 + 1], encoding).unwrap();
+        assert_eq!(TC_FRAME_02[..14], partial);
+
+        assert!(decode_cltu_lenient(&cltu[1..], encoding).is_err());
+
+        // a complete CLTU still needs a frame length ending in its last codeblock
+        let mut frame = TC_FRAME_02.to_vec();
+        frame[3] = frame[3].wrapping_add(8);
+        let cltu = generate_ctlu(&frame, encoding);
+        let err = decode_cltu_lenient(&cltu, encoding).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+    }
+
+    #[test]
+    fn cltu_decode_frame_vector() {
+        assert_eq!(
+            TC_FRAME_01,
+            decode_cltu(CLTU_01, EncodingScheme::BCH).unwrap()
+        );
+        assert_eq!(
+            TC_FRAME_02,
+            decode_cltu(CLTU_02, EncodingScheme::BCH).unwrap()
+        );
+    }
+
+    #[test]
+    fn cltu_decode_frame_fill_like_data() {
+        // a frame of 15 bytes ending with bytes equal to the fill
+        let mut frame = TC_FRAME_02[..15].to_vec();
+        frame[3] = 14;
+        frame[12..].fill(0x55);
+        let cltu = generate_ctlu(&frame, EncodingScheme::BCH);
+        assert_eq!(frame, decode_cltu(&cltu, EncodingScheme::BCH).unwrap());
+
+        // a frame length beyond the data
+        frame[3] = 30;
+        let cltu = generate_ctlu(&frame, EncodingScheme::BCH);
+        assert!(decode_cltu(&cltu, EncodingScheme::BCH).is_err());
+    }
+
+    #[rstest]
+    fn cltu_decode(
+        #[values(TC_FRAME_01, TC_FRAME_02)] tc_frame: &[u8],
+        #[values(EncodingScheme::BCH, EncodingScheme::BCHRandomized)] encoding: EncodingScheme,
+    ) {
+        let cltu = generate_ctlu(tc_frame, encoding);
+        let decoded = decode(&cltu, encoding).unwrap();
+        assert_eq!(tc_frame, &decoded[..tc_frame.len()]);
+        assert_eq!(0, decoded.len() % 7);
+
+        // a single bit error is corrected, two are not
+        let mut damaged = cltu.clone();
+        damaged[3] ^= 0x01;
+        assert_eq!(decoded, decode(&damaged, encoding).unwrap());
+        damaged[4] ^= 0x01;
+        assert!(decode(&damaged, encoding).is_err());
+        assert!(decode(&cltu[..cltu.len() - 8], encoding).is_err());
+        assert!(decode(&cltu[2..], encoding).is_err());
+    }
 }
@@ -1,9 +1,15 @@
 use lazy_static::lazy_static;
+
+use std::io::{Error, ErrorKind};
+
+use super::CltuError;
+use crate::consts::{CLTU_START_SEQUENCE, CLTU_TAIL_SEQUENCE};
+use crate::tctm::randomizer::{
+    apply_randomization_in_place, randomization_generator, Randomization,
+};
 /// CCSDS BCH polynomial x^7 + x^6 + x^2 + 1
 /// is then left shifted 1 bit
 const CCSDS_POLYNOMIAL: u8 = 0x8A_u8;
-const START_SEQUNCE: &[u8] = &[0xEB, 0x90];
-const TAIL_SEQUENCE: &[u8] = &[0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0x79];
 
 lazy_static! {
     static ref LOOKUP_TALBE: [u8; 256] = (0_u8..=255)
@@ -36,29 +42,159 @@ pub fn compute_bch_parity(bytes: &[u8; 7]) -> u8 {
     remainder
 }
 
-pub(crate) fn encode_bch_ctlu(bytes: &[u8]) -> Vec<u8> {
-    let mut output = START_SEQUNCE.to_vec();
+/// A BCH codeblock holds more bit errors than can be corrected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BchError {
+    /// The syndrome does not match any single bit error.
+    Uncorrectable {
+        /// The 7 bit syndrome of the codeblock, in the upper bits as the parity.
+        syndrome: u8,
+    },
+}
+impl std::fmt::Display for BchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Uncorrectable { syndrome } => {
+                write!(
+                    f,
+                    "Uncorrectable BCH codeblock with syndrome {syndrome:#04X}"
+                )
+            }
+        }
+    }
+}
+impl std::error::Error for BchError {}
+impl From<BchError> for Error {
+    fn from(err: BchError) -> Self {
+        Error::new(ErrorKind::InvalidData, err)
+    }
+}
 
-    let mut iter = bytes.chunks_exact(7);
+/// Check a BCH codeblock of 7 data bytes and the parity byte, correcting a single bit error
+/// as in the error correcting mode of CCSDS 231.0-B-4.
+///
+/// The filler bit of the parity byte is ignored. Like any single error correcting decoder,
+/// some patterns of three or more bit errors are miscorrected instead of being detected.
+///
+/// # Errors
+///
+/// Errors if the codeblock holds more than one bit error.
+pub fn correct_bch_block(block: &[u8; 8]) -> Result<[u8; 7], BchError> {
+    let mut data: [u8; 7] = block[..7].try_into().unwrap();
+    let syndrome = (compute_bch_parity(&data) ^ block[7]) & 0xFE;
+    if syndrome == 0 {
+        return Ok(data);
+    }
+    // an error in the parity bits is its own syndrome
+    if syndrome.count_ones() == 1 {
+        return Ok(data);
+    }
 
-    (&mut iter).for_each(|chunk| {
-        output.extend_from_slice(chunk);
-        // unwraping is safe here because we have forced chunks of length 7
-        output.push(compute_bch_parity(chunk.try_into().unwrap()));
+    // the code is linear, the complement of the remainder cancels out against all zero data
+    let zero_parity = compute_bch_parity(&[0; 7]);
+    let position = (0..56).find(|bit| {
+        let mut error = [0_u8; 7];
+        error[bit / 8] = 0x80 >> (bit % 8);
+        compute_bch_parity(&error) ^ zero_parity == syndrome
     });
+    match position {
+        Some(bit) => {
+            data[bit / 8] ^= 0x80 >> (bit % 8);
+            Ok(data)
+        }
+        None => Err(BchError::Uncorrectable { syndrome }),
+    }
+}
+
+/// Append the BCH encoded CLTU of the concatenated `chunks` to the `output`,
+/// optionally randomizing the input bytes before encoding.
+pub(crate) fn encode_bch_ctlu_into<I>(
+    chunks: I,
+    randomization: Option<Randomization>,
+    output: &mut Vec<u8>,
+) where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    output.extend_from_slice(&CLTU_START_SEQUENCE);
 
-    // handle any remainder by resizing to 7-bytes chunk
-    if !iter.remainder().is_empty() {
-        let mut remainder = iter.remainder().to_vec();
+    // the randomization sequence and codeblock continue across chunk boundaries
+    let mut sequence =
+        randomization.map(|randomizer| randomization_generator(randomizer).iter().cycle());
+    let mut codeblock = [0_u8; 7];
+    let mut filled = 0;
+
+    for chunk in chunks {
+        for byte in chunk.as_ref() {
+            codeblock[filled] = match sequence.as_mut() {
+                // unwraping is safe here because the sequence cycles forever
+                Some(sequence) => byte ^ sequence.next().unwrap(),
+                None => *byte,
+            };
+            filled += 1;
+
+            if filled == codeblock.len() {
+                output.extend_from_slice(&codeblock);
+                output.push(compute_bch_parity(&codeblock));
+                filled = 0;
+            }
+        }
+    }
+
+    // handle any remainder by filling the 7-bytes codeblock
+    if filled > 0 {
         // pad with bits of alternating 0 and 1s starting with 0
-        remainder.resize(7, 0x55_u8);
-        output.extend_from_slice(&remainder);
-        // unwraping is safe here because we have forced a  length of 7
-        output.push(compute_bch_parity(remainder.as_slice().try_into().unwrap()));
+        // only the input is randomized, not the fill bits
+        codeblock[filled..].fill(0x55_u8);
+        output.extend_from_slice(&codeblock);
+        output.push(compute_bch_parity(&codeblock));
     }
-    output.extend_from_slice(TAIL_SEQUENCE);
+    output.extend_from_slice(&CLTU_TAIL_SEQUENCE);
+}
+
+/// Recover the data bytes of a BCH encoded CLTU, the inverse of [encode_bch_ctlu_into].
+///
+/// Codeblocks with a single bit error are corrected, see [correct_bch_block], and the fill
+/// bytes of the last codeblock are kept since only the frame itself knows its length.
+/// The codeblocks recovered before the input ends without the tail sequence are returned
+/// inside [CltuError::TruncatedCltu].
+pub(crate) fn decode_bch_ctlu(
+    cltu: &[u8],
+    randomization: Option<Randomization>,
+) -> Result<Vec<u8>, CltuError> {
+    let codeblocks = cltu
+        .strip_prefix(&CLTU_START_SEQUENCE)
+        .ok_or(CltuError::MissingStartSequence)?;
+
+    let mut output = Vec::with_capacity(codeblocks.len() / 8 * 7);
+    let derandomize = |output: &mut Vec<u8>| {
+        if let Some(randomizer) = randomization {
+            apply_randomization_in_place(output, randomizer);
+        }
+    };
+    for codeblock in codeblocks.chunks(8) {
+        if codeblock == CLTU_TAIL_SEQUENCE {
+            derandomize(&mut output);
+            return Ok(output);
+        }
 
-    output
+        let codeblock: &[u8; 8] = match codeblock.try_into() {
+            Ok(codeblock) => codeblock,
+            Err(_) => break,
+        };
+        let data = correct_bch_block(codeblock).map_err(|error| CltuError::Uncorrectable {
+            codeblock: output.len() / 7,
+            error,
+        })?;
+        output.extend_from_slice(&data);
+    }
+
+    derandomize(&mut output);
+    Err(CltuError::TruncatedCltu {
+        codeblocks_recovered: output.len() / 7,
+        bytes_recovered: output.len(),
+        data: output,
+    })
 }
 
 #[cfg(test)]
@@ -79,4 +215,48 @@ mod test {
     fn bch_encoding(#[case] input: [u8; 7], #[case] parity: u8) {
         assert_eq!(parity, compute_bch_parity(&input))
     }
+
+    #[test]
+    fn bch_single_bit_syndromes_unique() {
+        let zero_parity = compute_bch_parity(&[0; 7]);
+        let mut syndromes: Vec<u8> = (0..56)
+            .map(|bit| {
+                let mut error = [0_u8; 7];
+                error[bit / 8] = 0x80 >> (bit % 8);
+                compute_bch_parity(&error) ^ zero_parity
+            })
+            .chain((1..8).map(|bit| 1 << bit))
+            .collect();
+        syndromes.sort_unstable();
+        syndromes.dedup();
+        assert_eq!(63, syndromes.len());
+        assert!(!syndromes.contains(&0));
+    }
+
+    #[rstest]
+    fn bch_correct_single_bit(
+        #[values(
+            [0x22, 0xF6, 0x00, 0xFF, 0x00, 0x42, 0x1A, 0x12],
+            [0x10, 0xE4, 0xC1, 0x55, 0x55, 0x55, 0x55, 0x3E]
+        )]
+        block: [u8; 8],
+    ) {
+        let data: [u8; 7] = block[..7].try_into().unwrap();
+        assert_eq!(Ok(data), correct_bch_block(&block));
+        for bit in 0..64 {
+            let mut damaged = block;
+            damaged[bit / 8] ^= 0x80 >> (bit % 8);
+            assert_eq!(Ok(data), correct_bch_block(&damaged), "bit {bit}");
+        }
+    }
+
+    #[test]
+    fn bch_uncorrectable() {
+        let mut block = [0x22, 0xF6, 0x00, 0xFF, 0x00, 0x42, 0x1A, 0x12];
+        block[0] ^= 0x81;
+        assert!(matches!(
+            correct_bch_block(&block),
+            Err(BchError::Uncorrectable { .. })
+        ));
+    }
 }
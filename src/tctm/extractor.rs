@@ -0,0 +1,443 @@
+//! Extraction of [SpacePacket]s from the packet zone of a sequence of Transfer Frames.
+//!
+//! Packets may span multiple frames. The [PacketExtractor] reassembles packets
+//! across frame boundaries using the First Header Pointer of every frame and
+//! resynchronizes on the next packet header whenever a frame is lost.
+//!
+//! The extraction state machine is generic over the [PacketZone] trait so the same
+//! logic serves every frame type which uses a First Header Pointer mechanism, the
+//! [TMTransferFrame](crate::tctm::tm::TMTransferFrame) and the M_PDU of the
+//! [AOSTransferFrame](crate::tctm::aos::AOSTransferFrame).
+
+use crate::{
+    anomaly::{AnomalyKind, AnomalyLog, Layer},
+    limits::{DecodeLimits, Limit, ResourceLimit},
+    sink::{SinkDriver, SinkError},
+    tctm::tm::FirstHeaderPointer,
+    PrimaryHeader, SpacePacket, IDLE_APID,
+};
+
+/// Access to the packet zone of a Transfer Frame.
+pub trait PacketZone {
+    /// The bytes of the frame containing packets, excluding all frame headers and trailers.
+    fn packet_zone(&self) -> &[u8];
+
+    /// Location of the first packet header which starts inside the packet zone.
+    fn first_header_pointer(&self) -> FirstHeaderPointer;
+
+    /// The virtual channel frame count of this frame, used to detect frame loss.
+    fn frame_count(&self) -> u32;
+
+    /// The value at which [Self::frame_count] wraps to zero.
+    fn frame_count_modulus(&self) -> u32;
+}
+
+/// Reassembles [SpacePacket]s from a sequence of frames on a single virtual channel.
+///
+/// Idle Packets are discarded.
+#[derive(Debug, Clone)]
+pub struct PacketExtractor {
+    /// Bytes of a packet which has started but not yet completed.
+    partial: Vec<u8>,
+    /// Whether [Self::partial] is known to start at a packet boundary.
+    synchronized: bool,
+    /// The frame count expected for the next frame.
+    expected_count: Option<u32>,
+    /// The total number of frames detected as lost.
+    lost_frames: u64,
+    /// Packets with this APID are discarded as Idle Packets.
+    idle_apid: u16,
+    limits: DecodeLimits,
+    /// The total number of times reassembly was abandoned for exceeding a limit.
+    limit_violations: u64,
+    /// The total number of packet zone bytes received before the current frame.
+    received: u64,
+    anomaly_log: Option<AnomalyLog>,
+}
+impl Default for PacketExtractor {
+    fn default() -> Self {
+        Self {
+            partial: vec![],
+            synchronized: false,
+            expected_count: None,
+            lost_frames: 0,
+            idle_apid: IDLE_APID,
+            limits: DecodeLimits::default(),
+            limit_violations: 0,
+            received: 0,
+            anomaly_log: None,
+        }
+    }
+}
+impl PacketExtractor {
+    /// Create a new extractor which synchronizes on the first packet header it receives.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Discard packets with a mission specific idle APID instead of [IDLE_APID].
+    pub fn with_idle_apid(mut self, idle_apid: u16) -> Self {
+        self.idle_apid = idle_apid;
+        self
+    }
+
+    /// Cap the memory spent reassembling hostile input, see [DecodeLimits].
+    ///
+    /// A packet declaring a length beyond [DecodeLimits::max_item_len], or reassembly holding more
+    /// than [DecodeLimits::max_buffered] bytes, is discarded and extraction resumes at the next
+    /// packet header, as if a frame was lost. Each is counted in [Self::limit_violations].
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Record lost frames and limit violations in the `log`, as [Layer::Extractor] events
+    /// at the offset of the frame in the concatenated packet zones of all frames received.
+    pub fn with_anomaly_log(mut self, log: AnomalyLog) -> Self {
+        self.anomaly_log = Some(log);
+        self
+    }
+
+    fn record(&self, kind: AnomalyKind) {
+        if let Some(log) = &self.anomaly_log {
+            log.record(self.received, Layer::Extractor, kind);
+        }
+    }
+
+    /// The total number of times reassembly was abandoned for exceeding a [DecodeLimits] limit.
+    pub fn limit_violations(&self) -> u64 {
+        self.limit_violations
+    }
+
+    /// The total number of frames detected as missing from the sequence of frames received.
+    pub fn lost_frames(&self) -> u64 {
+        self.lost_frames
+    }
+
+    /// Process the next frame and offer all packets completed by it to the `driver`'s sink.
+    ///
+    /// Returns the number of packets the sink took, see [SinkDriver::offer].
+    ///
+    /// # Errors
+    ///
+    /// Errors with the refused packet once the sink is closed.
+    pub fn push_to<Z: PacketZone + ?Sized>(
+        &mut self,
+        frame: &Z,
+        driver: &mut SinkDriver,
+    ) -> Result<usize, SinkError> {
+        driver.offer(self.push(frame))
+    }
+
+    /// Process the next frame and return all packets completed by it.
+    ///
+    /// When frame loss is detected any partially reassembled packet is discarded
+    /// and extraction resumes at the next packet header.
+    pub fn push<Z: PacketZone + ?Sized>(&mut self, frame: &Z) -> Vec<SpacePacket> {
+        let count = frame.frame_count();
+        let modulus = frame.frame_count_modulus().max(1);
+        if let Some(expected) = self.expected_count {
+            if count != expected {
+                // in u64, the sum overflows a u32 for moduli above 2^31
+                let lost = (u64::from(count) + u64::from(modulus) - u64::from(expected))
+                    % u64::from(modulus);
+                self.lost_frames += lost;
+                self.record(AnomalyKind::FramesLost { count: lost });
+                self.resynchronize();
+            }
+        }
+        self.expected_count = Some(((u64::from(count) + 1) % u64::from(modulus)) as u32);
+
+        let zone = frame.packet_zone();
+        let mut packets = vec![];
+
+        match frame.first_header_pointer() {
+            FirstHeaderPointer::OnlyIdleData => self.resynchronize(),
+            FirstHeaderPointer::NoPacketStart => {
+                if self.synchronized {
+                    self.partial.extend_from_slice(zone);
+                    self.drain_packets(&mut packets);
+                }
+            }
+            FirstHeaderPointer::ByteIndex(index) if (index as usize) < zone.len() => {
+                let (continuation, start) = zone.split_at(index as usize);
+                if self.synchronized {
+                    self.partial.extend_from_slice(continuation);
+                    self.drain_packets(&mut packets);
+                }
+                // anything not consumed by the continuation is inconsistent with
+                // the pointer, the pointer is trusted to resynchronize.
+                self.partial.clear();
+                self.partial.extend_from_slice(start);
+                self.synchronized = true;
+                self.drain_packets(&mut packets);
+            }
+            FirstHeaderPointer::ByteIndex(_) => self.resynchronize(),
+        }
+        self.received += zone.len() as u64;
+
+        packets
+    }
+
+    fn resynchronize(&mut self) {
+        self.partial.clear();
+        self.synchronized = false;
+    }
+
+    fn drain_packets(&mut self, packets: &mut Vec<SpacePacket>) {
+        let mut consumed = 0;
+        loop {
+            let remaining = &self.partial[consumed..];
+            if remaining.len() < PrimaryHeader::WIRE_LEN {
+                break;
+            }
+            let packet_len = u16::from_be_bytes(
+                remaining[PrimaryHeader::LENGTH_FIELD_RANGE]
+                    .try_into()
+                    .unwrap(),
+            ) as usize
+                + 1
+                + PrimaryHeader::WIRE_LEN;
+            if let Err(limit) = self.limits.check_item_len(packet_len) {
+                self.limit_violations += 1;
+                self.record(AnomalyKind::ResourceLimit(limit));
+                self.resynchronize();
+                return;
+            }
+            if remaining.len() < packet_len {
+                break;
+            }
+
+            if let Ok(packet) = SpacePacket::decode(&mut &remaining[..packet_len]) {
+                if !packet.is_idle_with_apid(self.idle_apid) {
+                    packets.push(packet);
+                }
+            }
+            consumed += packet_len;
+        }
+        self.partial.drain(..consumed);
+        if self.partial.len() > self.limits.max_buffered() {
+            self.limit_violations += 1;
+            self.record(AnomalyKind::ResourceLimit(ResourceLimit {
+                which: Limit::BufferedBytes,
+                limit: self.limits.max_buffered(),
+            }));
+            self.resynchronize();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{
+        tctm::{
+            aos::{AOSPrimaryHeader, AOSTransferFrame},
+            tm::{TMFramePacker, TMPrimaryHeader, TMTransferFrame},
+        },
+        test_util::packet,
+    };
+
+    use rstest::rstest;
+
+    const DATA_FIELD_LEN: usize = 64;
+
+    /// A minimal frame with a frame count as wide as the trait allows.
+    struct WideCountZone {
+        data: Vec<u8>,
+        pointer: FirstHeaderPointer,
+        count: u32,
+        modulus: u32,
+    }
+    impl PacketZone for WideCountZone {
+        fn packet_zone(&self) -> &[u8] {
+            &self.data
+        }
+
+        fn first_header_pointer(&self) -> FirstHeaderPointer {
+            self.pointer
+        }
+
+        fn frame_count(&self) -> u32 {
+            self.count
+        }
+
+        fn frame_count_modulus(&self) -> u32 {
+            self.modulus
+        }
+    }
+
+    fn tm_frames(packets: &[SpacePacket]) -> Vec<TMTransferFrame> {
+        let mut packer = TMFramePacker::new(
+            TMPrimaryHeader::builder()
+                .scid(758)
+                .vcid(1)
+                .counts(0, 250)
+                .build()
+                .unwrap(),
+            DATA_FIELD_LEN,
+        )
+        .unwrap();
+        packer.extend(packets);
+        packer.flush()
+    }
+
+    /// AOS frames carrying the packet zones of [tm_frames], decoded from their encoding.
+    fn aos_frames(packets: &[SpacePacket]) -> Vec<AOSTransferFrame> {
+        tm_frames(packets)
+            .into_iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                let mut primary_header = AOSPrimaryHeader::new(0x9C, 1);
+                // start near the top of the 24-bit count to exercise wrapping
+                primary_header.vc_frame_count = ((1 << 24) - 3 + index as u32) % (1 << 24);
+                let encoded = AOSTransferFrame {
+                    primary_header,
+                    first_header_pointer: frame.first_header_pointer(),
+                    packet_zone: frame.data_field,
+                    ocf: Some([0; 4]),
+                }
+                .encode();
+                AOSTransferFrame::decode(encoded.as_slice(), encoded.len(), true).unwrap()
+            })
+            .collect()
+    }
+
+    fn wide_frames(packets: &[SpacePacket]) -> Vec<WideCountZone> {
+        aos_frames(packets)
+            .into_iter()
+            .enumerate()
+            .map(|(index, frame)| WideCountZone {
+                pointer: frame.first_header_pointer(),
+                data: frame.packet_zone,
+                count: ((u64::from(u32::MAX) - 2 + index as u64) % u64::from(u32::MAX)) as u32,
+                modulus: u32::MAX,
+            })
+            .collect()
+    }
+
+    fn extract<Z: PacketZone>(frames: &[Z], skip: Option<usize>) -> (Vec<SpacePacket>, u64) {
+        let mut extractor = PacketExtractor::new();
+        let packets = frames
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != skip)
+            .flat_map(|(_, frame)| extractor.push(frame))
+            .collect();
+        (packets, extractor.lost_frames())
+    }
+
+    fn scenarios() -> Vec<Vec<SpacePacket>> {
+        vec![
+            // many small packets
+            (0..20).map(|count| packet(0x42, count, 7)).collect(),
+            // a packet spanning three frames
+            vec![
+                packet(0x42, 0, 4),
+                packet(0x42, 1, 2 * DATA_FIELD_LEN + 4),
+                packet(0x42, 2, 14),
+            ],
+            // packets ending exactly on frame boundaries
+            vec![
+                packet(0x42, 0, DATA_FIELD_LEN - 6),
+                packet(0x42, 1, DATA_FIELD_LEN - 6),
+                packet(0x42, 2, 1),
+            ],
+            // a packet header split across frames
+            vec![packet(0x42, 0, DATA_FIELD_LEN - 9), packet(0x42, 1, 24)],
+        ]
+    }
+
+    #[rstest]
+    fn extraction_parity(#[values(0, 1, 2, 3)] scenario: usize) {
+        let packets = scenarios().swap_remove(scenario);
+
+        let (tm_packets, tm_lost) = extract(&tm_frames(&packets), None);
+        let (aos_packets, aos_lost) = extract(&aos_frames(&packets), None);
+
+        assert_eq!(packets, tm_packets);
+        assert_eq!(packets, aos_packets);
+        assert_eq!((0, 0), (tm_lost, aos_lost));
+    }
+
+    #[test]
+    fn frame_loss_resynchronizes() {
+        let packets = vec![
+            packet(0x42, 0, 4),
+            packet(0x42, 1, 2 * DATA_FIELD_LEN + 4),
+            packet(0x42, 2, 14),
+        ];
+
+        // losing the middle of the spanning packet drops only that packet
+        let (tm_packets, tm_lost) = extract(&tm_frames(&packets), Some(1));
+        let (aos_packets, aos_lost) = extract(&aos_frames(&packets), Some(1));
+
+        let expected = vec![packets[0].clone(), packets[2].clone()];
+        assert_eq!(expected, tm_packets);
+        assert_eq!(expected, aos_packets);
+        assert_eq!((1, 1), (tm_lost, aos_lost));
+    }
+
+    #[test]
+    fn frame_loss_large_modulus() {
+        let packets = scenarios().swap_remove(0);
+        let (_, lost) = extract(&wide_frames(&packets), Some(3));
+        assert_eq!(1, lost);
+    }
+
+    #[test]
+    fn starts_mid_packet() {
+        let packets = vec![packet(0x42, 0, 2 * DATA_FIELD_LEN + 4), packet(0x42, 1, 14)];
+
+        // the first frame is never received, the continuation is ignored.
+        let (tm_packets, _) = extract(&tm_frames(&packets)[1..], None);
+        let (aos_packets, _) = extract(&aos_frames(&packets)[1..], None);
+
+        assert_eq!(packets[1..], tm_packets);
+        assert_eq!(packets[1..], aos_packets);
+    }
+
+    #[rstest]
+    #[case(DecodeLimits::default(), 3, 0)]
+    #[case(DecodeLimits::hardened().with_max_item_len(100), 2, 1)]
+    #[case(DecodeLimits::hardened().with_max_buffered(100), 2, 1)]
+    fn extraction_limits(
+        #[case] limits: DecodeLimits,
+        #[case] extracted: usize,
+        #[case] violations: u64,
+    ) {
+        let packets = vec![
+            packet(0x42, 0, 4),
+            packet(0x42, 1, 2 * DATA_FIELD_LEN + 4),
+            packet(0x42, 2, 14),
+        ];
+
+        let mut extractor = PacketExtractor::new().with_limits(limits);
+        let recovered: Vec<_> = tm_frames(&packets)
+            .iter()
+            .flat_map(|frame| extractor.push(frame))
+            .collect();
+
+        assert_eq!(extracted, recovered.len());
+        assert_eq!(violations, extractor.limit_violations());
+        assert_eq!(0, extractor.lost_frames());
+    }
+
+    #[test]
+    fn extraction_to_sink() {
+        use crate::sink::QueueSink;
+
+        let packets: Vec<_> = (0..20).map(|count| packet(0x42, count, 7)).collect();
+        let mut driver = SinkDriver::new(Box::new(QueueSink::new(8)));
+        let mut extractor = PacketExtractor::new();
+
+        let taken: usize = tm_frames(&packets)
+            .iter()
+            .map(|frame| extractor.push_to(frame, &mut driver).unwrap())
+            .sum();
+        assert_eq!(8, taken);
+        assert_eq!(12, driver.pending_len());
+        assert_eq!(packets[8..], driver.take_pending());
+    }
+}
@@ -0,0 +1,130 @@
+//! Frame Acceptance and Reporting Mechanism (FARM) state of a receiving
+//! entity as defined in CCSDS 232.1-B-2.
+//!
+//! Only the FARM-B counter is currently provided, it is kept independent so a full
+//! FARM-1 can own one and report it through [Clcw::with_farm_b_counter].
+
+#[cfg(doc)]
+use crate::tctm::clcw::Clcw;
+use crate::tctm::tc::TCTransferFrame;
+
+/// Counts accepted Type-BD frames modulo 4, as reported in the CLCW.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FarmBCounter {
+    count: u8,
+}
+impl FarmBCounter {
+    /// Create a new counter starting at 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The 2-bit value of the counter.
+    pub fn value(&self) -> u8 {
+        self.count
+    }
+
+    /// Count one accepted Type-BD frame, wrapping from 3 to 0.
+    pub fn increment(&mut self) {
+        self.count = (self.count + 1) & 0x3;
+    }
+
+    /// Count the accepted `frame` if it is a Type-BD frame,
+    /// returns whether the counter was incremented.
+    pub fn accept(&mut self, frame: &TCTransferFrame) -> bool {
+        let counted = frame.is_bd();
+        if counted {
+            self.increment();
+        }
+        counted
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::tctm::{
+        clcw::Clcw,
+        tc::{BypassFlag, ControlFlag, TCPrimaryHeader},
+    };
+
+    use rstest::rstest;
+
+    fn frame(bypass_flag: BypassFlag, control_flag: ControlFlag) -> TCTransferFrame {
+        TCTransferFrame::new(
+            TCPrimaryHeader {
+                tfvn: 0,
+                bypass_flag,
+                control_flag,
+                scid: 758,
+                vcid: 3,
+                sequence_number: 0,
+            },
+            vec![0_u8; 10],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn farm_b_counter_wraps() {
+        let mut counter = FarmBCounter::new();
+        let values: Vec<u8> = (0..9)
+            .map(|_| {
+                assert!(counter.accept(&frame(BypassFlag::TypeB, ControlFlag::TypeD)));
+                counter.value()
+            })
+            .collect();
+        assert_eq!(vec![1, 2, 3, 0, 1, 2, 3, 0, 1], values);
+    }
+
+    #[rstest]
+    #[case(BypassFlag::TypeA, ControlFlag::TypeD)]
+    #[case(BypassFlag::TypeB, ControlFlag::TypeC)]
+    #[case(BypassFlag::TypeA, ControlFlag::TypeC)]
+    fn farm_b_counter_ignores(#[case] bypass_flag: BypassFlag, #[case] control_flag: ControlFlag) {
+        let mut counter = FarmBCounter::new();
+        counter.increment();
+
+        assert!(!counter.accept(&frame(bypass_flag, control_flag)));
+        assert_eq!(1, counter.value());
+    }
+
+    #[test]
+    fn farm_b_counter_clcw() {
+        let mut counter = FarmBCounter::new();
+        (0..6).for_each(|_| counter.increment());
+
+        let clcw = Clcw {
+            version: 0,
+            status: 0,
+            cop_in_effect: 1,
+            vcid: 3,
+            no_rf_available: false,
+            no_bit_lock: false,
+            lockout: false,
+            wait: false,
+            retransmit: false,
+            farm_b_counter: 0,
+            report_value: 0,
+        }
+        .with_farm_b_counter(&counter);
+
+        assert_eq!(2, clcw.farm_b_counter);
+        assert!(clcw.validate().is_ok());
+    }
+
+    #[rstest]
+    #[case(BypassFlag::TypeA, ControlFlag::TypeD, [true, false, false])]
+    #[case(BypassFlag::TypeB, ControlFlag::TypeD, [false, true, false])]
+    #[case(BypassFlag::TypeB, ControlFlag::TypeC, [false, false, true])]
+    #[case(BypassFlag::TypeA, ControlFlag::TypeC, [false, false, false])]
+    fn frame_type_predicates(
+        #[case] bypass_flag: BypassFlag,
+        #[case] control_flag: ControlFlag,
+        #[case] expected: [bool; 3],
+    ) {
+        let frame = frame(bypass_flag, control_flag);
+        assert_eq!(expected, [frame.is_ad(), frame.is_bd(), frame.is_bc()]);
+    }
+}
@@ -1,3 +1,6 @@
+//! Pseudo-randomization sequences defined in CCSDS 131.0-B-5 and CCSDS 231.0-B-4
+//! and utilities to apply and detect them.
+
 use lazy_static::lazy_static;
 lazy_static! {
     // CCSDS 131.0-B-5 TC randomizer with generator polynomial
@@ -74,33 +77,205 @@ lazy_static! {
     };
 }
 
-#[cfg_attr(test, derive(Clone, Copy))]
-pub(crate) enum Randomization {
+/// The pseudo-randomization sequences available to TC and TM links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Randomization {
+    /// CCSDS 231.0-B-4 TC randomizer with polynomial h(x) = x^8 + x^6 + x^4 + x^3 + x^2 + x + 1
     TC,
+    /// 255 Bit repeating randomization with polynomial h(x) = x^8 + x^7 + x^5 + x^3 + 1
     Tm255,
+    /// 131071 Bit repeating randomization with polynomial h(x) = x^17 + x^14 + 1
     Tm131071,
 }
 
+pub(crate) fn randomization_generator(randomizer: Randomization) -> &'static [u8] {
+    match randomizer {
+        Randomization::TC => &TC_RANDOMIZER,
+        Randomization::Tm255 => &TM_RANDOMIZER_255,
+        Randomization::Tm131071 => &TM_RANDOMIZER_131071,
+    }
+}
+
+/// One period of the pseudo-randomization sequence of the `scheme`, which repeats indefinitely.
+pub fn sequence(scheme: Randomization) -> &'static [u8] {
+    randomization_generator(scheme)
+}
+
+/// The number of bytes after which the sequence of the `scheme` repeats.
+///
+/// Each LFSR has a maximal bit period of 255 or 131071 bits, both coprime to 8,
+/// so the byte sequence repeats after the same number of bytes.
+pub fn period(scheme: Randomization) -> usize {
+    sequence(scheme).len()
+}
+
+/// Generate `len` bytes of the pseudo-noise sequence of the `scheme`, starting at the
+/// beginning of its period and wrapping around as often as needed.
+pub fn pn_sequence(scheme: Randomization, len: usize) -> Vec<u8> {
+    sequence(scheme).iter().copied().cycle().take(len).collect()
+}
+
 pub(crate) fn apply_randomization<P: AsRef<[u8]>>(bytes: P, randomizer: Randomization) -> Vec<u8> {
-    let randomization_generator = match randomizer {
-        Randomization::TC => &(*TC_RANDOMIZER),
-        Randomization::Tm255 => &(*TM_RANDOMIZER_255),
-        Randomization::Tm131071 => &(*TM_RANDOMIZER_131071),
-    };
-    bytes
-        .as_ref()
-        .iter()
-        .zip(randomization_generator.iter().cycle())
-        .map(|(val, rand)| val ^ rand)
-        .collect()
+    apply_randomization_chunks([bytes], randomizer)
+}
+
+/// Randomize the `bytes` with the sequence `which`, starting at the beginning of its period,
+/// e.g. a TM frame before attaching the ASM or a TC frame before BCH encoding.
+pub fn randomize<P: AsRef<[u8]>>(bytes: P, which: Randomization) -> Vec<u8> {
+    apply_randomization(bytes, which)
+}
+
+/// Recover the `bytes` randomized with the sequence `which`, the inverse of [randomize].
+///
+/// The sequence is XORed onto the data, so this is the same operation as [randomize].
+pub fn derandomize<P: AsRef<[u8]>>(bytes: P, which: Randomization) -> Vec<u8> {
+    apply_randomization(bytes, which)
+}
+
+/// Apply randomization to a sequence of byte chunks as if they were one contiguous buffer,
+/// e.g. a scatter list, without first concatenating the chunks.
+pub fn apply_randomization_chunks<I>(chunks: I, randomizer: Randomization) -> Vec<u8>
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    let sequence = randomization_generator(randomizer);
+    let mut output = vec![];
+    // the sequence continues across chunk boundaries
+    let mut position = 0;
+    for chunk in chunks {
+        let start = output.len();
+        output.extend_from_slice(chunk.as_ref());
+        position = xor_sequence(&mut output[start..], sequence, position);
+    }
+    output
+}
+
+/// Apply randomization without allocating a new buffer.
+pub(crate) fn apply_randomization_in_place(bytes: &mut [u8], randomizer: Randomization) {
+    xor_sequence(bytes, randomization_generator(randomizer), 0);
+}
+
+/// XOR the repeating `sequence`, starting at `position`, onto `bytes`.
+///
+/// Returns the position in the `sequence` following the last byte.
+fn xor_sequence(mut bytes: &mut [u8], sequence: &[u8], mut position: usize) -> usize {
+    while !bytes.is_empty() {
+        // XOR contiguous runs up to the end of the sequence before wrapping
+        let run = bytes.len().min(sequence.len() - position);
+        let (head, tail) = bytes.split_at_mut(run);
+        xor_words(head, &sequence[position..position + run]);
+        bytes = tail;
+        position = (position + run) % sequence.len();
+    }
+    position
+}
+
+/// XOR `key` onto `bytes` eight bytes at a time, both must have the same length.
+fn xor_words(bytes: &mut [u8], key: &[u8]) {
+    let mut words = bytes.chunks_exact_mut(8);
+    let mut key_words = key.chunks_exact(8);
+    for (word, key_word) in (&mut words).zip(&mut key_words) {
+        let xored = u64::from_ne_bytes((&*word).try_into().unwrap())
+            ^ u64::from_ne_bytes(key_word.try_into().unwrap());
+        word.copy_from_slice(&xored.to_ne_bytes());
+    }
+
+    words
+        .into_remainder()
+        .iter_mut()
+        .zip(key_words.remainder())
+        .for_each(|(val, rand)| *val ^= rand);
+}
+
+/// Detect whether a captured byte stream was randomized with the given scheme.
+///
+/// The start of the `sample` is de-randomized and compared against the `known_prefix`,
+/// e.g. the first two bytes of a TC frame header (`0x22 0xF6`).
+/// Returns `false` if the `known_prefix` is empty or longer than the `sample`.
+pub fn detect(sample: &[u8], known_prefix: &[u8], scheme: Randomization) -> bool {
+    if known_prefix.is_empty() || sample.len() < known_prefix.len() {
+        return false;
+    }
+    apply_randomization(&sample[..known_prefix.len()], scheme) == known_prefix
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    use crate::tctm::cltu::test::{random_chunks, TC_FRAME_01};
+
     use rstest::rstest;
 
+    #[rstest]
+    fn randomization_chunks(
+        #[values(Randomization::TC, Randomization::Tm255, Randomization::Tm131071)]
+        randomizer: Randomization,
+        #[values(0, 1, 7, 42, 1234)] seed: u32,
+    ) {
+        assert_eq!(
+            apply_randomization(TC_FRAME_01, randomizer),
+            apply_randomization_chunks(random_chunks(TC_FRAME_01, seed), randomizer)
+        );
+    }
+
+    #[rstest]
+    fn randomization_matches_bytewise(
+        #[values(Randomization::TC, Randomization::Tm255, Randomization::Tm131071)]
+        randomizer: Randomization,
+        #[values(0, 1, 7, 8, 9, 254, 255, 256, 1000, 131071, 131080)] len: usize,
+    ) {
+        let bytes: Vec<u8> = (0..len).map(|val| (val * 31) as u8).collect();
+        let expected: Vec<u8> = bytes
+            .iter()
+            .zip(randomization_generator(randomizer).iter().cycle())
+            .map(|(val, rand)| val ^ rand)
+            .collect();
+
+        assert_eq!(expected, apply_randomization(&bytes, randomizer));
+
+        let mut in_place = bytes;
+        apply_randomization_in_place(&mut in_place, randomizer);
+        assert_eq!(expected, in_place);
+    }
+
+    /// Whether the bits of `bytes` repeat every `period` bits.
+    fn repeats_every(bytes: &[u8], period: usize) -> bool {
+        let bit = |index: usize| (bytes[index / 8] >> (7 - index % 8)) & 1;
+        (0..bytes.len() * 8 - period).all(|index| bit(index) == bit(index + period))
+    }
+
+    #[rstest]
+    #[case(Randomization::TC, 255)]
+    #[case(Randomization::Tm255, 255)]
+    #[case(Randomization::Tm131071, 131071)]
+    fn randomizer_period(#[case] scheme: Randomization, #[case] expected: usize) {
+        assert_eq!(expected, period(scheme));
+
+        let repeated = apply_randomization(vec![0_u8; 2 * expected + 1], scheme);
+        assert_eq!(repeated[0], repeated[period(scheme)]);
+        assert_eq!(repeated[..expected], repeated[expected..2 * expected]);
+
+        // the bit sequence has the full LFSR period and no shorter one
+        let sequence = sequence(scheme);
+        assert!(repeats_every(sequence, expected));
+        for divisor in (1..expected).filter(|divisor| expected % divisor == 0) {
+            assert!(!repeats_every(sequence, divisor), "period {divisor}");
+        }
+    }
+
+    #[rstest]
+    fn pn_sequence_wraps(
+        #[values(Randomization::TC, Randomization::Tm255, Randomization::Tm131071)]
+        scheme: Randomization,
+        #[values(0, 1, 255, 300)] len: usize,
+    ) {
+        let pn = pn_sequence(scheme, len);
+        assert_eq!(len, pn.len());
+        assert_eq!(apply_randomization(vec![0_u8; len], scheme), pn);
+    }
+
     #[test]
     fn tc_randomizer() {
         let expected_seq = [
@@ -157,8 +332,33 @@ mod test {
 
         assert_ne!(input_bytes, random_bytes);
 
-        let recovered_bytes = apply_randomization(random_bytes, randomization);
+        let recovered_bytes = apply_randomization(&random_bytes, randomization);
+
+        assert_eq!(input_bytes, recovered_bytes);
+        assert_eq!(random_bytes, randomize(&input_bytes, randomization));
+        assert_eq!(input_bytes, derandomize(&random_bytes, randomization));
+
+        let mut in_place = input_bytes.clone();
+        apply_randomization_in_place(&mut in_place, randomization);
+        assert_eq!(apply_randomization(&input_bytes, randomization), in_place);
+    }
+
+    #[rstest]
+    fn detect_randomization(
+        #[values(Randomization::TC, Randomization::Tm255, Randomization::Tm131071)]
+        randomization: Randomization,
+    ) {
+        let frame = [0x22_u8, 0xF6, 0x00, 0x23, 0x00, 0x82, 0x00, 0x0F];
+        let random_bytes = apply_randomization(frame, randomization);
+
+        assert!(detect(&random_bytes, &frame[..2], randomization));
+        assert!(!detect(&frame, &frame[..2], randomization));
+    }
 
-        assert_eq!(input_bytes, recovered_bytes)
+    #[rstest]
+    #[case(&[0x22, 0xF6], &[])]
+    #[case(&[0x22], &[0x22, 0xF6])]
+    fn detect_invalid_lengths(#[case] sample: &[u8], #[case] known_prefix: &[u8]) {
+        assert!(!detect(sample, known_prefix, Randomization::TC))
     }
 }
@@ -0,0 +1,651 @@
+//! A deterministic in-process spacecraft for closed loop tests of ground software.
+//!
+//! CLTUs produced by an [UplinkPipeline] are sent over an in-memory [GroundLink] to a
+//! [LoopbackSpacecraft], which decodes the TC Transfer Frames, counts them with its FARM,
+//! routes the commands to per APID responders and packs the responses into TM Transfer Frames.
+//! Every call to [LoopbackSpacecraft::step] downlinks a fixed number of frames, which the ground
+//! decodes with [downlink_decode]. Fault hooks can drop or damage the bytes of either link.
+//!
+//! ```
+//! # use spacepacket::{tctm::{channel::ChannelId, cltu::EncodingScheme, extractor::PacketExtractor, sim::LoopbackSpacecraft, tm::{TMPrimaryHeader, TmChannelConfig}, uplink::UplinkPipeline}, GroupingFlag, PacketType, SpacePacket};
+//! let channel = ChannelId::new(758, 0).unwrap();
+//! let tm_header = TMPrimaryHeader::builder().scid(758).build().unwrap();
+//! let (spacecraft, ground) = LoopbackSpacecraft::new(channel, tm_header, 64).unwrap();
+//! // echo every command on APID 17 as telemetry on APID 18
+//! let mut spacecraft = spacecraft.with_responder(17, |command| {
+//!     vec![SpacePacket::new(0, PacketType::Telemetry, 18, GroupingFlag::Unsegm, 0, false, command.payload)]
+//! });
+//!
+//! let pipeline = UplinkPipeline::new(channel, EncodingScheme::BCH);
+//! let command = SpacePacket::new(0, PacketType::Command, 17, GroupingFlag::Unsegm, 0, false, vec![0x42; 4]);
+//! ground.send_commands(&pipeline, &[command]).unwrap();
+//! spacecraft.step().unwrap();
+//!
+//! let mut extractor = PacketExtractor::new();
+//! let responses: Vec<SpacePacket> = ground
+//!     .receive_frames(TmChannelConfig::new(64))
+//!     .into_iter()
+//!     .flat_map(|frame| extractor.push(&frame.unwrap()))
+//!     .collect();
+//! assert_eq!(vec![0x42; 4], responses[0].payload);
+//! ```
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Debug,
+    io::{Error, ErrorKind},
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+#[cfg(feature = "crc")]
+use crc::{Crc, CRC_16_IBM_3740};
+
+use crate::{
+    tctm::{
+        channel::ChannelId,
+        clcw::Clcw,
+        cltu::{self, EncodingScheme},
+        farm::FarmBCounter,
+        tc::{owned_packets, TCTransferFrame},
+        tm::{
+            downlink_decode, BooleanFieldFlag, TMFramePacker, TMPrimaryHeader, TMRandomization,
+            TMTransferFrame, TmChannelConfig, ASM,
+        },
+        uplink::UplinkPipeline,
+    },
+    GroupingFlag, PrimaryHeader, SpacePacket,
+};
+
+/// The CRC used for the TC Frame Error Control Field.
+#[cfg(feature = "crc")]
+const FECF_CRC: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+
+/// Produces the telemetry responses to a command received on a single APID.
+pub type Responder = Box<dyn Fn(SpacePacket) -> Vec<SpacePacket> + Send>;
+
+/// Inspects the bytes sent over a link, returning `None` to drop them
+/// or the bytes to deliver, possibly damaged.
+pub type FaultHook = Box<dyn FnMut(Vec<u8>) -> Option<Vec<u8>> + Send>;
+
+/// The ground end of the in-memory links to a [LoopbackSpacecraft].
+#[derive(Debug)]
+pub struct GroundLink {
+    uplink: Sender<Vec<u8>>,
+    downlink: Receiver<Vec<u8>>,
+}
+impl GroundLink {
+    /// Send a single CLTU to the spacecraft.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [ErrorKind::BrokenPipe] if the spacecraft has been dropped.
+    pub fn send(&self, cltu: Vec<u8>) -> Result<(), Error> {
+        self.uplink
+            .send(cltu)
+            .map_err(|_| Error::new(ErrorKind::BrokenPipe, "Spacecraft has been dropped"))
+    }
+
+    /// Encode the `packets` with the `pipeline` and send every resulting CLTU.
+    ///
+    /// # Errors
+    ///
+    /// Errors if [UplinkPipeline::encode_commands] fails or the spacecraft has been dropped.
+    pub fn send_commands(
+        &self,
+        pipeline: &UplinkPipeline,
+        packets: &[SpacePacket],
+    ) -> Result<(), Error> {
+        pipeline
+            .encode_commands(packets)?
+            .into_iter()
+            .try_for_each(|cltu| self.send(cltu))
+    }
+
+    /// All physical channel bytes downlinked since the last call, one entry per frame.
+    pub fn receive(&self) -> Vec<Vec<u8>> {
+        self.downlink.try_iter().collect()
+    }
+
+    /// Decode every frame downlinked since the last call with [downlink_decode].
+    pub fn receive_frames(&self, config: TmChannelConfig) -> Vec<Result<TMTransferFrame, Error>> {
+        self.receive()
+            .iter()
+            .map(|physical| downlink_decode(physical, config))
+            .collect()
+    }
+}
+
+/// A simulated spacecraft answering commands received over a [GroundLink].
+pub struct LoopbackSpacecraft {
+    tc_channel: ChannelId,
+    encoding: EncodingScheme,
+    segment_header: bool,
+    #[cfg(feature = "crc")]
+    fecf: bool,
+    responders: BTreeMap<u16, Responder>,
+    farm_b: FarmBCounter,
+    /// The sequence count of the next response on every APID.
+    sequence_counts: BTreeMap<u16, u16>,
+    tm_header: TMPrimaryHeader,
+    packer: TMFramePacker,
+    frames_per_step: usize,
+    randomization: TMRandomization,
+    /// Frames packed but not yet downlinked.
+    pending_frames: VecDeque<TMTransferFrame>,
+    uplink_fault: Option<FaultHook>,
+    downlink_fault: Option<FaultHook>,
+    uplink: Receiver<Vec<u8>>,
+    downlink: Sender<Vec<u8>>,
+    rejected_frames: u64,
+    unrouted_packets: u64,
+}
+impl Debug for LoopbackSpacecraft {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoopbackSpacecraft")
+            .field("tc_channel", &self.tc_channel)
+            .field("encoding", &self.encoding)
+            .field("responders", &self.responders.keys())
+            .field("farm_b", &self.farm_b)
+            .field("frames_per_step", &self.frames_per_step)
+            .field("pending_frames", &self.pending_frames.len())
+            .field("rejected_frames", &self.rejected_frames)
+            .field("unrouted_packets", &self.unrouted_packets)
+            .finish()
+    }
+}
+impl LoopbackSpacecraft {
+    /// Create a spacecraft accepting BCH encoded CLTUs on the `tc_channel` without a
+    /// Segment Header or FECF, and downlinking one unrandomized `tm_frame_len` byte frame per step.
+    ///
+    /// When the `tm_header` has an Operational Control Field it carries a CLCW
+    /// reporting the FARM-B counter of the `tc_channel`.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - the `tm_header` fails [TMPrimaryHeader::validate]
+    ///  - `tm_frame_len` leaves no room for an Idle Packet after the headers and OCF
+    pub fn new(
+        tc_channel: ChannelId,
+        tm_header: TMPrimaryHeader,
+        tm_frame_len: usize,
+    ) -> Result<(Self, GroundLink), Error> {
+        let ocf_len = match tm_header.ocf_flag {
+            BooleanFieldFlag::Present => 4,
+            BooleanFieldFlag::NotPresent => 0,
+        };
+        let data_field_len = tm_frame_len.saturating_sub(6 + ocf_len);
+        if data_field_len < SpacePacket::MIN_WIRE_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "TM frame length {tm_frame_len} leaves no room for an Idle Packet \
+                    after {} bytes of headers",
+                    6 + ocf_len
+                ),
+            ));
+        }
+
+        let (uplink_tx, uplink_rx) = channel();
+        let (downlink_tx, downlink_rx) = channel();
+        let spacecraft = Self {
+            tc_channel,
+            encoding: EncodingScheme::BCH,
+            segment_header: false,
+            #[cfg(feature = "crc")]
+            fecf: false,
+            responders: BTreeMap::new(),
+            farm_b: FarmBCounter::new(),
+            sequence_counts: BTreeMap::new(),
+            tm_header,
+            packer: TMFramePacker::new(tm_header, data_field_len)?,
+            frames_per_step: 1,
+            randomization: TMRandomization::None,
+            pending_frames: VecDeque::new(),
+            uplink_fault: None,
+            downlink_fault: None,
+            uplink: uplink_rx,
+            downlink: downlink_tx,
+            rejected_frames: 0,
+            unrouted_packets: 0,
+        };
+        let ground = GroundLink {
+            uplink: uplink_tx,
+            downlink: downlink_rx,
+        };
+        Ok((spacecraft, ground))
+    }
+
+    /// Answer commands on the `apid` with the `responder`, replacing any previous responder.
+    ///
+    /// The sequence counts of the responses are overwritten with a counter per APID.
+    pub fn with_responder<F>(mut self, apid: u16, responder: F) -> Self
+    where
+        F: Fn(SpacePacket) -> Vec<SpacePacket> + Send + 'static,
+    {
+        self.responders.insert(apid, Box::new(responder));
+        self
+    }
+
+    /// Decode CLTUs with the given `encoding`.
+    pub fn with_encoding(mut self, encoding: EncodingScheme) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Expect a [TCSegmentHeader](crate::tctm::tc::TCSegmentHeader) at the start of every TC frame,
+    /// as produced by [UplinkPipeline::with_segment_header].
+    pub fn with_segment_header(mut self) -> Self {
+        self.segment_header = true;
+        self
+    }
+
+    /// Validate and strip the FECF of every TC frame, as produced by [UplinkPipeline::with_fecf].
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub fn with_fecf(mut self) -> Self {
+        self.fecf = true;
+        self
+    }
+
+    /// Randomize every downlinked frame.
+    pub fn with_randomization(mut self, randomization: TMRandomization) -> Self {
+        self.randomization = randomization;
+        self
+    }
+
+    /// Downlink exactly `frames_per_step` frames on every [Self::step], filling with Idle Packets.
+    pub fn with_frames_per_step(mut self, frames_per_step: usize) -> Self {
+        self.frames_per_step = frames_per_step;
+        self
+    }
+
+    /// Pass every received CLTU through the `hook` before it is decoded.
+    pub fn with_uplink_fault<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(Vec<u8>) -> Option<Vec<u8>> + Send + 'static,
+    {
+        self.uplink_fault = Some(Box::new(hook));
+        self
+    }
+
+    /// Pass the physical channel bytes of every frame through the `hook` before it is downlinked.
+    pub fn with_downlink_fault<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(Vec<u8>) -> Option<Vec<u8>> + Send + 'static,
+    {
+        self.downlink_fault = Some(Box::new(hook));
+        self
+    }
+
+    /// The FARM-B counter of accepted Type-BD frames.
+    pub fn farm_b(&self) -> &FarmBCounter {
+        &self.farm_b
+    }
+
+    /// The number of CLTUs which could not be decoded or were addressed to another channel.
+    pub fn rejected_frames(&self) -> u64 {
+        self.rejected_frames
+    }
+
+    /// The number of commands received on an APID without a responder.
+    pub fn unrouted_packets(&self) -> u64 {
+        self.unrouted_packets
+    }
+
+    /// Process every CLTU received since the last step, then downlink the configured
+    /// number of frames.
+    ///
+    /// Returns the number of frames downlinked, which excludes frames dropped by the fault hook.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [ErrorKind::BrokenPipe] if the [GroundLink] has been dropped, or as
+    /// [TMFramePacker::push] if a responder returns a packet that cannot be encoded.
+    pub fn step(&mut self) -> Result<usize, Error> {
+        let cltus: Vec<Vec<u8>> = self.uplink.try_iter().collect();
+        for cltu in cltus {
+            let cltu = match self.uplink_fault.as_mut() {
+                Some(hook) => hook(cltu),
+                None => Some(cltu),
+            };
+            if let Some(cltu) = cltu {
+                match self.receive_cltu(&cltu) {
+                    Ok(commands) => {
+                        for command in commands {
+                            self.respond(command)?;
+                        }
+                    }
+                    Err(_) => self.rejected_frames += 1,
+                }
+            }
+        }
+
+        self.pending_frames
+            .extend(std::iter::from_fn(|| self.packer.pop_frame()));
+        if self.pending_frames.len() < self.frames_per_step && self.packer.pending_len() > 0 {
+            self.pending_frames.extend(self.packer.flush());
+        }
+        while self.pending_frames.len() < self.frames_per_step {
+            let fill = self.packer.data_field_len() - PrimaryHeader::WIRE_LEN;
+            self.packer.push(&SpacePacket::idle(fill))?;
+            self.pending_frames.extend(self.packer.pop_frame());
+        }
+
+        let ocf = match self.tm_header.ocf_flag {
+            BooleanFieldFlag::Present => self.clcw().encode(),
+            BooleanFieldFlag::NotPresent => vec![],
+        };
+        let mut sent = 0;
+        for mut frame in self.pending_frames.drain(..self.frames_per_step) {
+            frame.data_field.extend_from_slice(&ocf);
+            let mut physical = ASM.to_vec();
+            physical.extend(frame.into_bytes(self.randomization));
+
+            let physical = match self.downlink_fault.as_mut() {
+                Some(hook) => hook(physical),
+                None => Some(physical),
+            };
+            if let Some(physical) = physical {
+                self.downlink.send(physical).map_err(|_| {
+                    Error::new(ErrorKind::BrokenPipe, "Ground link has been dropped")
+                })?;
+                sent += 1;
+            }
+        }
+        Ok(sent)
+    }
+
+    /// The CLCW reporting the FARM state of the TC channel.
+    fn clcw(&self) -> Clcw {
+        Clcw {
+            version: 0,
+            status: 0,
+            cop_in_effect: 1,
+            vcid: self.tc_channel.vcid,
+            no_rf_available: false,
+            no_bit_lock: false,
+            lockout: false,
+            wait: false,
+            retransmit: false,
+            farm_b_counter: 0,
+            report_value: 0,
+        }
+        .with_farm_b_counter(&self.farm_b)
+    }
+
+    /// Decode a CLTU into the commands of its frame, counting the frame in the FARM.
+    fn receive_cltu(&mut self, cltu: &[u8]) -> Result<Vec<SpacePacket>, Error> {
+        let frame = TCTransferFrame::decode(&mut cltu::decode(cltu, self.encoding)?.as_slice())?;
+        let header = frame.header();
+        if header.scid != self.tc_channel.scid || header.vcid != self.tc_channel.vcid {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "TC frame addressed to another channel",
+            ));
+        }
+
+        #[allow(unused_mut)]
+        let mut data = frame.payload();
+        #[cfg(feature = "crc")]
+        if self.fecf {
+            let (message, fecf) = data
+                .len()
+                .checked_sub(2)
+                .map(|end| data.split_at(end))
+                .ok_or_else(|| Error::from(ErrorKind::UnexpectedEof))?;
+            let mut encoded = frame.encode();
+            encoded.truncate(encoded.len() - 2);
+            if FECF_CRC.checksum(&encoded).to_be_bytes() != fecf {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "CRC failure on TC Transfer Frame",
+                ));
+            }
+            data = message;
+        }
+
+        if self.segment_header {
+            let (segment_header, rest) = data
+                .split_first()
+                .ok_or_else(|| Error::from(ErrorKind::UnexpectedEof))?;
+            // segmented packets are not reassembled
+            if segment_header >> 6 != GroupingFlag::Unsegm as u8 {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "Segmented TC packets are not reassembled",
+                ));
+            }
+            data = rest;
+        }
+
+        let commands = owned_packets(data).collect::<Result<Vec<_>, _>>()?;
+        self.farm_b.accept(&frame);
+        Ok(commands)
+    }
+
+    /// Pack the responses to a `command` for the downlink.
+    fn respond(&mut self, command: SpacePacket) -> Result<(), Error> {
+        let responses = match self.responders.get(&command.primary_header.apid) {
+            Some(responder) => responder(command),
+            None => {
+                self.unrouted_packets += 1;
+                return Ok(());
+            }
+        };
+
+        for mut response in responses {
+            let count = self
+                .sequence_counts
+                .entry(response.primary_header.apid)
+                .or_default();
+            response.primary_header.sequence_count = *count;
+            *count = (*count + 1) & 0x3FFF;
+            self.packer.push(&response)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{tctm::extractor::PacketExtractor, PacketType};
+
+    use rstest::rstest;
+
+    const TM_FRAME_LEN: usize = 64;
+
+    fn command(apid: u16, payload: Vec<u8>) -> SpacePacket {
+        SpacePacket::new(
+            0,
+            PacketType::Command,
+            apid,
+            GroupingFlag::Unsegm,
+            0,
+            false,
+            payload,
+        )
+    }
+
+    /// A spacecraft echoing commands on APID 17 as two responses on APID 18.
+    fn spacecraft(encoding: EncodingScheme) -> (LoopbackSpacecraft, GroundLink) {
+        let tm_header = TMPrimaryHeader::builder()
+            .scid(758)
+            .vcid(1)
+            .with_ocf()
+            .build()
+            .unwrap();
+        let (spacecraft, ground) =
+            LoopbackSpacecraft::new(ChannelId::new(758, 0).unwrap(), tm_header, TM_FRAME_LEN)
+                .unwrap();
+        let spacecraft = spacecraft
+            .with_encoding(encoding)
+            .with_segment_header()
+            .with_fecf()
+            .with_randomization(TMRandomization::Tm255)
+            .with_frames_per_step(3)
+            .with_responder(17, |command| {
+                let mut response = command;
+                response.primary_header.packet_type = PacketType::Telemetry;
+                response.primary_header.apid = 18;
+                vec![response.clone(), response]
+            });
+        (spacecraft, ground)
+    }
+
+    fn pipeline(encoding: EncodingScheme) -> UplinkPipeline {
+        UplinkPipeline::new(ChannelId::new(758, 0).unwrap(), encoding)
+            .with_segment_header(0)
+            .unwrap()
+            .with_fecf()
+            .unwrap()
+            .with_max_frame_len(64)
+            .unwrap()
+    }
+
+    fn config() -> TmChannelConfig<'static> {
+        TmChannelConfig::new(TM_FRAME_LEN).with_randomization(TMRandomization::Tm255)
+    }
+
+    /// Decode the downlinked frames, returning the responses and the last CLCW.
+    fn receive(ground: &GroundLink, extractor: &mut PacketExtractor) -> (Vec<SpacePacket>, Clcw) {
+        let frames: Vec<TMTransferFrame> = ground
+            .receive_frames(config())
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        let clcw = frames.last().unwrap().clcw(false).unwrap().unwrap();
+        let packets = frames
+            .iter()
+            .flat_map(|frame| extractor.push(frame))
+            .collect();
+        (packets, clcw)
+    }
+
+    #[rstest]
+    fn sim_round_trip(
+        #[values(EncodingScheme::BCH, EncodingScheme::BCHRandomized)] encoding: EncodingScheme,
+    ) {
+        let (mut spacecraft, ground) = spacecraft(encoding);
+        let mut extractor = PacketExtractor::new();
+
+        let commands = [command(17, vec![0x01; 30]), command(17, vec![0x02; 40])];
+        ground
+            .send_commands(&pipeline(encoding), &commands)
+            .unwrap();
+        assert_eq!(3, spacecraft.step().unwrap());
+
+        let (mut responses, clcw) = receive(&ground, &mut extractor);
+        // the remaining responses are downlinked on the next step
+        assert_eq!(3, spacecraft.step().unwrap());
+        responses.extend(receive(&ground, &mut extractor).0);
+
+        assert_eq!(4, responses.len());
+        for (count, response) in responses.iter().enumerate() {
+            assert_eq!(18, response.primary_header.apid);
+            assert_eq!(count as u16, response.primary_header.sequence_count);
+        }
+        assert_eq!(vec![0x01; 30], responses[0].payload);
+        assert_eq!(vec![0x02; 40], responses[3].payload);
+
+        // the commands are too long to share a single Type-BD frame
+        assert_eq!(2, clcw.farm_b_counter);
+        assert_eq!(0, spacecraft.rejected_frames());
+    }
+
+    #[test]
+    fn sim_idle_schedule() {
+        let (mut spacecraft, ground) = spacecraft(EncodingScheme::BCH);
+        let mut extractor = PacketExtractor::new();
+
+        for _ in 0..3 {
+            assert_eq!(3, spacecraft.step().unwrap());
+            let (responses, clcw) = receive(&ground, &mut extractor);
+            assert!(responses.is_empty());
+            assert_eq!(0, clcw.farm_b_counter);
+        }
+    }
+
+    #[test]
+    fn sim_deterministic() {
+        let run = || {
+            let (mut spacecraft, ground) = spacecraft(EncodingScheme::BCHRandomized);
+            ground
+                .send_commands(
+                    &pipeline(EncodingScheme::BCHRandomized),
+                    &[command(17, vec![0x03; 100])],
+                )
+                .unwrap();
+            spacecraft.step().unwrap();
+            spacecraft.step().unwrap();
+            ground.receive()
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn sim_faults() {
+        let (spacecraft, ground) = spacecraft(EncodingScheme::BCH);
+        let mut uplinked = 0;
+        let mut downlinked = 0;
+        let mut spacecraft = spacecraft
+            // drop the first CLTU and damage the second beyond correction
+            .with_uplink_fault(move |mut cltu| {
+                uplinked += 1;
+                match uplinked {
+                    1 => None,
+                    2 => {
+                        cltu[4] ^= 0x11;
+                        Some(cltu)
+                    }
+                    _ => Some(cltu),
+                }
+            })
+            // drop every third frame
+            .with_downlink_fault(move |physical| {
+                downlinked += 1;
+                (downlinked % 3 != 0).then_some(physical)
+            });
+
+        let pipeline = pipeline(EncodingScheme::BCH);
+        for payload in [0x01, 0x02, 0x03] {
+            ground
+                .send_commands(&pipeline, &[command(17, vec![payload; 4])])
+                .unwrap();
+        }
+        ground
+            .send_commands(&pipeline, &[command(99, vec![0x04; 4])])
+            .unwrap();
+
+        assert_eq!(2, spacecraft.step().unwrap());
+        assert_eq!(1, spacecraft.rejected_frames());
+        assert_eq!(1, spacecraft.unrouted_packets());
+        // the unrouted command was still accepted by the FARM
+        assert_eq!(2, spacecraft.farm_b().value());
+
+        let mut extractor = PacketExtractor::new();
+        let (responses, clcw) = receive(&ground, &mut extractor);
+        assert_eq!(2, clcw.farm_b_counter);
+        assert_eq!(2, responses.len());
+        assert_eq!(vec![0x03; 4], responses[0].payload);
+    }
+
+    #[test]
+    fn sim_errors() {
+        let tm_header = TMPrimaryHeader::builder()
+            .scid(758)
+            .with_ocf()
+            .build()
+            .unwrap();
+        let channel = ChannelId::new(758, 0).unwrap();
+        assert!(LoopbackSpacecraft::new(channel, tm_header, 16).is_err());
+        assert!(LoopbackSpacecraft::new(channel, tm_header, 17).is_ok());
+
+        let (mut spacecraft, ground) = LoopbackSpacecraft::new(channel, tm_header, 17).unwrap();
+        drop(ground);
+        let err = spacecraft.step().unwrap_err();
+        assert_eq!(ErrorKind::BrokenPipe, err.kind());
+    }
+}
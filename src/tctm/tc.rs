@@ -2,10 +2,15 @@
 //! as defined in CCSDS 232.0-B-4
 //!
 
-use std::io::{Error, ErrorKind, Read};
+use std::{
+    fmt::{Debug, Display},
+    io::{Error, ErrorKind, Read},
+};
 
 use byteorder::{BigEndian, ReadBytesExt};
 
+use crate::{GroupingFlag, PayloadSummary, PrimaryHeader, SpacePacket};
+
 /// The Bypass Flag is used to control the types of
 /// Frame Acceptanc Check performed by the receiving entity.
 #[repr(u8)]
@@ -29,6 +34,10 @@ impl BypassFlag {
         }
     }
 }
+named_variants!(BypassFlag {
+    TypeA => "Type-A",
+    TypeB => "Type-B",
+});
 
 /// Control Command Flag indicates if the packet contains
 /// data (Type-D) or control information to set up the
@@ -54,13 +63,48 @@ impl ControlFlag {
         }
     }
 }
+named_variants!(ControlFlag {
+    TypeD => "Type-D",
+    TypeC => "Type-C",
+});
+
+/// The classification of a TC Transfer Frame by its [BypassFlag] and [ControlFlag].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TcFrameType {
+    /// Sequence controlled data frame, accepted in order by the FARM.
+    TypeAD,
+    /// Expedited data frame, bypassing the acceptance checks.
+    TypeBD,
+    /// Control command frame configuring the FARM.
+    TypeBC,
+    /// A sequence controlled control command,
+    /// not a valid combination under CCSDS 232.0-B-4.
+    TypeAC,
+}
+impl TcFrameType {
+    /// The combination of flags selecting this frame type.
+    pub fn flags(self) -> (BypassFlag, ControlFlag) {
+        match self {
+            Self::TypeAD => (BypassFlag::TypeA, ControlFlag::TypeD),
+            Self::TypeBD => (BypassFlag::TypeB, ControlFlag::TypeD),
+            Self::TypeBC => (BypassFlag::TypeB, ControlFlag::TypeC),
+            Self::TypeAC => (BypassFlag::TypeA, ControlFlag::TypeC),
+        }
+    }
+
+    /// Whether this frame type is defined by CCSDS 232.0-B-4.
+    pub fn is_valid(self) -> bool {
+        self != Self::TypeAC
+    }
+}
 
 /// Primary Header for a TC Transfer Frame
 /// This Header is only meant to be used with a [TCTransferFrame]
 /// as the length of the payload is calculated at encoding time.
 // When calclulating Length of this header, only 10 bits are allowed.
 // Additionally it is considered length -1 consistent with SpacePackets
-// this leaves a maximum size of 1024 bytes per packet
+// this leaves a maximum size of 1024 bytes per frame,
+// the 5 byte header and a payload of at most 1019 bytes
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TCPrimaryHeader {
     /// Transfer Frame Version number.
@@ -89,6 +133,31 @@ pub struct TCPrimaryHeader {
     pub sequence_number: u8,
 }
 impl TCPrimaryHeader {
+    /// The frame type selected by the bypass and control flags.
+    pub fn frame_type(&self) -> TcFrameType {
+        match (self.bypass_flag, self.control_flag) {
+            (BypassFlag::TypeA, ControlFlag::TypeD) => TcFrameType::TypeAD,
+            (BypassFlag::TypeB, ControlFlag::TypeD) => TcFrameType::TypeBD,
+            (BypassFlag::TypeB, ControlFlag::TypeC) => TcFrameType::TypeBC,
+            (BypassFlag::TypeA, ControlFlag::TypeC) => TcFrameType::TypeAC,
+        }
+    }
+
+    /// Whether this is a sequence controlled data frame, Type-AD.
+    pub fn is_ad(&self) -> bool {
+        self.frame_type() == TcFrameType::TypeAD
+    }
+
+    /// Whether this is an expedited data frame, Type-BD.
+    pub fn is_bd(&self) -> bool {
+        self.frame_type() == TcFrameType::TypeBD
+    }
+
+    /// Whether this is a control command frame, Type-BC.
+    pub fn is_bc(&self) -> bool {
+        self.frame_type() == TcFrameType::TypeBC
+    }
+
     /// Validate header values which require bit masks will fit in the
     /// desginate bit-depth
     ///
@@ -126,6 +195,248 @@ impl TCPrimaryHeader {
     }
 }
 
+/// The optional one byte Segment Header at the start of a TC frame payload
+/// used by the Multiplexer Access Point (MAP) service to segment and
+/// multiplex packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TCSegmentHeader {
+    /// Whether the frame contains a whole packet, or multiple whole packets,
+    /// or the first, a continuing or the last segment of a packet.
+    /// Uses the same encoding as the Space Packet grouping flag.
+    pub sequence_flags: GroupingFlag,
+
+    /// The MAP identifier. 6-bits maximum.
+    pub map_id: u8,
+}
+impl TCSegmentHeader {
+    /// Validate values which require bit masks will fit in the
+    /// desginate bit-depth
+    ///
+    /// # Errors
+    ///
+    /// Errors if [Self::map_id] > 63
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.map_id > 63 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("MAP ID must be <=63 but found {}", self.map_id),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Encode into the single header byte.
+    pub fn encode(self) -> u8 {
+        (self.sequence_flags as u8) << 6 | (self.map_id & 0x3f)
+    }
+
+    /// Decode from the single header byte.
+    pub fn decode(byte: u8) -> Self {
+        Self {
+            sequence_flags: GroupingFlag::from_2bits(byte >> 6),
+            map_id: byte & 0x3f,
+        }
+    }
+}
+
+/// A [TCTransferFrame] which borrows its payload.
+///
+/// Used to en/de-code frames without allocating, see [TCTransferFrame::decode_into].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TCTransferFrameView<'a> {
+    /// Primary Header information with exception of the payload
+    /// length.
+    pub header: TCPrimaryHeader,
+
+    /// Borrowed payload with a maximum length of 1019 bytes
+    pub payload: &'a [u8],
+}
+impl<'a> Debug for TCTransferFrameView<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TCTransferFrameView")
+            .field("header", &self.header)
+            .field("payload", &PayloadSummary(self.payload))
+            .finish()
+    }
+}
+impl<'a> TCTransferFrameView<'a> {
+    /// Encode the primary header, including the frame length.
+    fn header_bytes(&self) -> [u8; 5] {
+        let TCPrimaryHeader {
+            tfvn,
+            bypass_flag,
+            control_flag,
+            scid,
+            vcid,
+            sequence_number,
+        } = self.header;
+
+        let first_word = {
+            (tfvn as u16 & 0x3_u16) << 14
+            | (bypass_flag as u16 & 0x1_u16) << 13
+            | (control_flag as u16 & 0x1_u16) << 12
+            // two spare bits here reserved
+            | (scid & 0x3ff_u16)
+        };
+
+        // Add 5 to account for the header length as well
+        let encoded_len = self.payload.len() + 5 - 1;
+        // callers check the payload fits in MAX_PAYLOAD_LEN before encoding
+        debug_assert!(encoded_len < TCTransferFrame::MAX_LEN);
+        let encoded_len = encoded_len as u16;
+        let second_word = { ((vcid as u16 & 0x3f_u16) << 10) | (encoded_len & 0x3ff_u16) };
+
+        let [b0, b1] = first_word.to_be_bytes();
+        let [b2, b3] = second_word.to_be_bytes();
+        [b0, b1, b2, b3, sequence_number]
+    }
+
+    /// Encode the Transfer frame into the start of the `out` buffer without allocating.
+    /// Assumes Big Endian byte order
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - payload length is > 1019 bytes
+    ///  - `out` is too short to hold the encoded frame
+    pub fn encode_into(&self, out: &mut [u8]) -> Result<usize, Error> {
+        if self.payload.len() > TCTransferFrame::MAX_PAYLOAD_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Payload length must be <=1019 bytes but supplied payload has length {}",
+                    self.payload.len()
+                ),
+            ));
+        }
+
+        let encoded_len = 5 + self.payload.len();
+        let out_len = out.len();
+        let out = out.get_mut(..encoded_len).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Output buffer of length {out_len} cannot hold frame of length {encoded_len}"
+                ),
+            )
+        })?;
+
+        out[..5].copy_from_slice(&self.header_bytes());
+        out[5..].copy_from_slice(self.payload);
+
+        Ok(encoded_len)
+    }
+}
+
+/// A Type-A frame arrived with a sequence number other than the one expected,
+/// returned by [TCTransferFrame::decode_expecting] wrapped in an [ErrorKind::InvalidData] error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceMismatch {
+    /// The sequence number the receiver expected.
+    pub expected: u8,
+    /// The sequence number of the received frame.
+    pub received: u8,
+}
+impl Display for SequenceMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Expected TC frame sequence number {} but received {}",
+            self.expected, self.received
+        )
+    }
+}
+impl std::error::Error for SequenceMismatch {}
+
+/// The mission limits on the TC Transfer Frames sent to a spacecraft, often far below
+/// the [TCTransferFrame::MAX_LEN] the protocol allows, e.g. as negotiated with the ground network.
+///
+/// The default limits are the protocol maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcFrameLimits {
+    /// The longest frame, including the primary header and any segment header and FECF.
+    pub max_frame_len: usize,
+}
+impl Default for TcFrameLimits {
+    fn default() -> Self {
+        Self {
+            max_frame_len: TCTransferFrame::MAX_LEN,
+        }
+    }
+}
+impl TcFrameLimits {
+    /// Limit frames to `max_frame_len` bytes.
+    pub fn new(max_frame_len: usize) -> Self {
+        Self { max_frame_len }
+    }
+
+    /// Check the limit can be described by the Frame Length field and holds a payload.
+    ///
+    /// # Errors
+    ///
+    /// Errors if [Self::max_frame_len] is <= 5 or > [TCTransferFrame::MAX_LEN] bytes.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.max_frame_len <= 5 || self.max_frame_len > TCTransferFrame::MAX_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Maximum TC frame length must be in 6..={} but found {}",
+                    TCTransferFrame::MAX_LEN,
+                    self.max_frame_len
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The longest payload of a frame, [Self::max_frame_len] less the 5 byte primary header.
+    pub fn max_payload_len(&self) -> usize {
+        self.max_frame_len.saturating_sub(5)
+    }
+
+    /// Check a frame of `frame_len` bytes against [Self::max_frame_len].
+    ///
+    /// # Errors
+    ///
+    /// Errors with a [FrameTooLong] if the frame is too long.
+    pub fn check_frame_len(&self, frame_len: usize) -> Result<(), FrameTooLong> {
+        match frame_len > self.max_frame_len {
+            true => Err(FrameTooLong {
+                limit: self.max_frame_len,
+                len: frame_len,
+            }),
+            false => Ok(()),
+        }
+    }
+}
+
+/// A TC Transfer Frame would exceed the [TcFrameLimits] of the mission,
+/// returned wrapped in an [ErrorKind::InvalidInput] error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTooLong {
+    /// The configured [TcFrameLimits::max_frame_len].
+    pub limit: usize,
+    /// The length of the rejected frame, including all headers.
+    pub len: usize,
+}
+impl Display for FrameTooLong {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "TC frame of length {} exceeds the maximum frame length of {}",
+            self.len, self.limit
+        )
+    }
+}
+impl std::error::Error for FrameTooLong {}
+impl From<FrameTooLong> for Error {
+    fn from(err: FrameTooLong) -> Self {
+        Error::new(ErrorKind::InvalidInput, err)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 /// A TeleCommand (TC) Transfer Frame per CCSDS 232.0-B-4
 pub struct TCTransferFrame {
@@ -137,31 +448,131 @@ pub struct TCTransferFrame {
     payload: Vec<u8>,
 }
 impl TCTransferFrame {
+    /// The longest frame the 10-bit Frame Length field can describe, header included.
+    pub const MAX_LEN: usize = 1024;
+
+    /// The longest payload of a frame, [Self::MAX_LEN] less the 5 byte primary header.
+    pub const MAX_PAYLOAD_LEN: usize = Self::MAX_LEN - 5;
+
     /// Initialize a new TC Transfer Frame.
     ///
     /// # Errors
     ///
     /// This function errors under the following circumstances
-    ///  - payload length is > 1019 bytes
+    ///  - payload length is > 1019 bytes, with a [FrameTooLong]
     ///  - [TCPrimaryHeader::tfvn] > 3
     ///  - [TCPrimaryHeader::scid] > 1023
     ///  - [TCPrimaryHeader::vcid] > 63
     pub fn new(header: TCPrimaryHeader, payload: Vec<u8>) -> Result<Self, Error> {
+        Self::new_with_limits(header, payload, &TcFrameLimits::default())
+    }
+
+    /// Initialize a new TC Transfer Frame no longer than the mission `limits`.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - the frame is longer than [TcFrameLimits::max_frame_len], with a [FrameTooLong]
+    ///  - the header fails [TCTransferFrame::new]
+    pub fn new_with_limits(
+        header: TCPrimaryHeader,
+        payload: Vec<u8>,
+        limits: &TcFrameLimits,
+    ) -> Result<Self, Error> {
         header.validate()?;
 
-        if payload.len() > 1019 {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                format!(
-                    "Payload length must be <=1019 bytes but supplied payload has length {}",
-                    payload.len()
-                ),
-            ));
-        }
+        let frame_len = payload.len() + 5;
+        limits.check_frame_len(frame_len)?;
+        TcFrameLimits::default().check_frame_len(frame_len)?;
 
         Ok(Self { header, payload })
     }
 
+    /// Initialize a new TC Transfer Frame carrying the encoded `packet` as its payload.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - the packet payload is empty
+    ///  - the encoded packet is > 1019 bytes, a packet payload > 1013 bytes
+    ///  - the header fails [TCTransferFrame::new]
+    pub fn from_space_packet(header: TCPrimaryHeader, packet: &SpacePacket) -> Result<Self, Error> {
+        Self::from_space_packet_with_limits(header, packet, &TcFrameLimits::default())
+    }
+
+    /// Initialize a new TC Transfer Frame carrying the encoded `packet`,
+    /// no longer than the mission `limits`.
+    ///
+    /// # Errors
+    ///
+    /// As [Self::from_space_packet], with a [FrameTooLong] if the frame is longer
+    /// than [TcFrameLimits::max_frame_len].
+    pub fn from_space_packet_with_limits(
+        header: TCPrimaryHeader,
+        packet: &SpacePacket,
+        limits: &TcFrameLimits,
+    ) -> Result<Self, Error> {
+        packet.check_payload_len(Self::MAX_PAYLOAD_LEN - PrimaryHeader::WIRE_LEN)?;
+        Self::new_with_limits(header, packet.encode(), limits)
+    }
+
+    /// Decode the Space Packet at the start of the payload,
+    /// the inverse of [Self::from_space_packet].
+    ///
+    /// # Errors
+    ///
+    /// Errors with [ErrorKind::UnexpectedEof] if the payload ends within the packet.
+    pub fn space_packet(&self) -> Result<SpacePacket, Error> {
+        SpacePacket::decode(&mut self.payload.as_slice())
+    }
+
+    /// Iterate over the Space Packets concatenated in the payload.
+    ///
+    /// Iteration stops after the first error, e.g. trailing bytes too short to hold a packet.
+    pub fn space_packets(&self) -> impl Iterator<Item = Result<SpacePacket, Error>> + '_ {
+        owned_packets(&self.payload)
+    }
+
+    /// Split the [TCSegmentHeader] of the MAP service from the start of the payload.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [ErrorKind::UnexpectedEof] if the payload is empty.
+    pub fn segment_header(&self) -> Result<(TCSegmentHeader, &[u8]), Error> {
+        match self.payload.split_first() {
+            Some((byte, data)) => Ok((TCSegmentHeader::decode(*byte), data)),
+            None => Err(ErrorKind::UnexpectedEof.into()),
+        }
+    }
+
+    /// Decode the Space Packet following the [TCSegmentHeader] when the MAP service is in use.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [ErrorKind::UnexpectedEof] if the payload ends within the segment header or packet.
+    pub fn segmented_space_packet(&self) -> Result<(TCSegmentHeader, SpacePacket), Error> {
+        let (segment_header, mut data) = self.segment_header()?;
+        Ok((segment_header, SpacePacket::decode(&mut data)?))
+    }
+
+    /// Iterate over the Space Packets following the [TCSegmentHeader] when the MAP service is in use.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [ErrorKind::UnexpectedEof] if the payload is empty.
+    pub fn segmented_space_packets(
+        &self,
+    ) -> Result<
+        (
+            TCSegmentHeader,
+            impl Iterator<Item = Result<SpacePacket, Error>> + '_,
+        ),
+        Error,
+    > {
+        let (segment_header, data) = self.segment_header()?;
+        Ok((segment_header, owned_packets(data)))
+    }
+
     /// Retrieve the meta-data header information for this packet.
     /// Header information does not include length of the payload.
     pub fn header(&self) -> TCPrimaryHeader {
@@ -173,40 +584,65 @@ impl TCTransferFrame {
         self.payload.as_slice()
     }
 
-    /// Encode the Transfer frame into a byte stream.
-    /// Assumes Big Endian byte order
-    pub fn encode(mut self) -> Vec<u8> {
-        let TCPrimaryHeader {
-            tfvn,
-            bypass_flag,
-            control_flag,
-            scid,
-            vcid,
-            sequence_number,
-        } = self.header;
+    /// The frame type selected by the bypass and control flags.
+    pub fn frame_type(&self) -> TcFrameType {
+        self.header.frame_type()
+    }
 
-        let first_word = {
-            (tfvn as u16 & 0x3_u16) << 14
-            | (bypass_flag as u16 & 0x1_u16) << 13
-            | (control_flag as u16 & 0x1_u16) << 12
-            // two spare bits here reserved
-            | (scid & 0x3ff_u16)
-        };
+    /// Whether this is a sequence controlled data frame, Type-AD.
+    pub fn is_ad(&self) -> bool {
+        self.header.is_ad()
+    }
 
-        // Add 5 to account for the header length as well
-        let encoded_len = (self.payload.len() - 1 + 5) as u16;
-        let second_word = { ((vcid as u16 & 0x3f_u16) << 10) | (encoded_len & 0x3ff_u16) };
+    /// Whether this is an expedited data frame, Type-BD.
+    pub fn is_bd(&self) -> bool {
+        self.header.is_bd()
+    }
 
-        let mut message = first_word.to_be_bytes().to_vec();
+    /// Whether this is a control command frame, Type-BC.
+    pub fn is_bc(&self) -> bool {
+        self.header.is_bc()
+    }
 
-        message.extend_from_slice(&second_word.to_be_bytes());
-        message.push(sequence_number);
+    /// Borrow this frame as a [TCTransferFrameView].
+    pub fn view(&self) -> TCTransferFrameView<'_> {
+        TCTransferFrameView {
+            header: self.header,
+            payload: &self.payload,
+        }
+    }
 
-        message.append(&mut self.payload);
+    /// Encode the Transfer frame into a byte stream.
+    /// Assumes Big Endian byte order
+    pub fn encode(&self) -> Vec<u8> {
+        let header = self.view().header_bytes();
+        let mut message = Vec::with_capacity(header.len() + self.payload.len());
+        message.extend_from_slice(&header);
+        message.extend_from_slice(&self.payload);
+        message
+    }
 
+    /// Encode the Transfer frame into a byte stream, reusing the allocation of the payload.
+    /// Assumes Big Endian byte order
+    pub fn into_bytes(self) -> Vec<u8> {
+        let header = self.view().header_bytes();
+        let mut message = self.payload;
+        message.splice(0..0, header);
         message
     }
 
+    /// Encode the Transfer frame into the start of the `out` buffer without allocating.
+    /// Assumes Big Endian byte order
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `out` is too short to hold the encoded frame.
+    pub fn encode_into(&self, out: &mut [u8]) -> Result<usize, Error> {
+        self.view().encode_into(out)
+    }
+
     /// Decode a transfer frame from a byte stream.
     /// Assumes Big Endian byte order
     pub fn decode<R: Read>(buffer: &mut R) -> Result<Self, Error> {
@@ -214,7 +650,14 @@ impl TCTransferFrame {
         let second_word = buffer.read_u16::<BigEndian>()?;
 
         // subtract 5 to accound for the length of the Primary Header
-        let payload_len = (second_word & 0x3ff_u16) + 1 - 5;
+        let payload_len = usize::from((second_word & 0x3ff_u16) + 1)
+            .checked_sub(5)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    "TC Transfer Frame length is shorter than the primary header",
+                )
+            })?;
 
         let header = TCPrimaryHeader {
             tfvn: ((first_word >> 14) & 0x3_u16) as u8,
@@ -225,12 +668,93 @@ impl TCTransferFrame {
             sequence_number: buffer.read_u8()?,
         };
 
-        let mut payload = vec![0_u8; payload_len as usize];
+        let mut payload = vec![0_u8; payload_len];
 
         buffer.read_exact(&mut payload)?;
 
         Self::new(header, payload)
     }
+
+    /// Decode a transfer frame and check a Type-A frame carries the `expected` sequence number,
+    /// a simple in-order check for a single virtual channel without the full FARM-1.
+    /// Type-B frames bypass the check.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the frame cannot be decoded, or with [ErrorKind::InvalidData] wrapping a
+    /// [SequenceMismatch] if the sequence number of a Type-A frame is not `expected`.
+    pub fn decode_expecting<R: Read>(buffer: &mut R, expected: u8) -> Result<Self, Error> {
+        let frame = Self::decode(buffer)?;
+        let received = frame.header.sequence_number;
+        if frame.header.bypass_flag == BypassFlag::TypeA && received != expected {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                SequenceMismatch { expected, received },
+            ));
+        }
+        Ok(frame)
+    }
+
+    /// Decode a transfer frame without allocating by reading the payload into the caller
+    /// provided `scratch` buffer. The returned [TCTransferFrameView] borrows its payload from `scratch`.
+    /// Assumes Big Endian byte order
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - the encoded frame length is shorter than the primary header
+    ///  - `scratch` is shorter than the payload
+    ///  - the header fails [TCPrimaryHeader::validate]
+    pub fn decode_into<'a, R: Read>(
+        buffer: &mut R,
+        scratch: &'a mut [u8],
+    ) -> Result<TCTransferFrameView<'a>, Error> {
+        let first_word = buffer.read_u16::<BigEndian>()?;
+        let second_word = buffer.read_u16::<BigEndian>()?;
+
+        // subtract 5 to accound for the length of the Primary Header
+        let payload_len = usize::from((second_word & 0x3ff_u16) + 1)
+            .checked_sub(5)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    "TC Transfer Frame length is shorter than the primary header",
+                )
+            })?;
+
+        let header = TCPrimaryHeader {
+            tfvn: ((first_word >> 14) & 0x3_u16) as u8,
+            bypass_flag: BypassFlag::from_u8(((first_word >> 13) & 0x1_u16) as u8)?,
+            control_flag: ControlFlag::from_u8(((first_word >> 12) & 0x1_u16) as u8)?,
+            scid: first_word & 0x3ff_u16,
+            vcid: ((second_word >> 10) & 0x3f_u16) as u8,
+            sequence_number: buffer.read_u8()?,
+        };
+        header.validate()?;
+
+        let scratch_len = scratch.len();
+        let payload = scratch.get_mut(..payload_len).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Scratch buffer of length {scratch_len} cannot hold payload of length {payload_len}"
+                ),
+            )
+        })?;
+        buffer.read_exact(payload)?;
+
+        Ok(TCTransferFrameView { header, payload })
+    }
+}
+
+/// Decode the Space Packets concatenated in `data` into owned packets.
+pub(crate) fn owned_packets(data: &[u8]) -> impl Iterator<Item = Result<SpacePacket, Error>> + '_ {
+    SpacePacket::iter_refs(data).map(|packet| {
+        packet.map(|packet| SpacePacket {
+            primary_header: packet.primary_header,
+            payload: packet.payload.to_vec(),
+        })
+    })
 }
 
 #[cfg(test)]
@@ -240,6 +764,27 @@ mod test {
 
     use rstest::rstest;
 
+    #[rstest]
+    #[case(TcFrameType::TypeAD)]
+    #[case(TcFrameType::TypeBD)]
+    #[case(TcFrameType::TypeBC)]
+    #[case(TcFrameType::TypeAC)]
+    fn frame_type(#[case] expected: TcFrameType) {
+        let (bypass_flag, control_flag) = expected.flags();
+        let header = TCPrimaryHeader {
+            tfvn: 0,
+            bypass_flag,
+            control_flag,
+            scid: 5,
+            vcid: 2,
+            sequence_number: 0,
+        };
+        let frame = TCTransferFrame::new(header, vec![0x42]).unwrap();
+
+        assert_eq!(expected, frame.frame_type());
+        assert_eq!(expected != TcFrameType::TypeAC, expected.is_valid());
+    }
+
     #[rstest]
     #[case(0, 5, 2)]
     #[should_panic]
@@ -264,6 +809,50 @@ mod test {
         assert!(header.validate().is_ok())
     }
 
+    #[rstest]
+    #[case(BypassFlag::TypeA, 23, true)]
+    #[case(BypassFlag::TypeA, 24, false)]
+    #[case(BypassFlag::TypeB, 24, true)]
+    fn frame_decode_expecting(
+        #[case] bypass_flag: BypassFlag,
+        #[case] expected: u8,
+        #[case] valid: bool,
+    ) {
+        let frame = TCTransferFrame::new(
+            TCPrimaryHeader {
+                tfvn: 0,
+                bypass_flag,
+                control_flag: ControlFlag::TypeD,
+                scid: 758,
+                vcid: 3,
+                sequence_number: 23,
+            },
+            vec![0x42; 10],
+        )
+        .unwrap();
+        let encoded = frame.encode();
+
+        match TCTransferFrame::decode_expecting(&mut encoded.as_slice(), expected) {
+            Ok(decoded) => {
+                assert!(valid);
+                assert_eq!(frame, decoded);
+            }
+            Err(error) => {
+                assert!(!valid);
+                assert_eq!(ErrorKind::InvalidData, error.kind());
+                assert_eq!(
+                    Some(&SequenceMismatch {
+                        expected,
+                        received: 23
+                    }),
+                    error
+                        .get_ref()
+                        .and_then(|inner| inner.downcast_ref::<SequenceMismatch>())
+                );
+            }
+        }
+    }
+
     #[rstest]
     #[case(b"some bytes foo bar baz".to_vec())]
     #[should_panic]
@@ -288,7 +877,7 @@ mod test {
         )
         .unwrap();
 
-        let buffer = expected.clone().encode();
+        let buffer = expected.encode();
 
         let recovered = TCTransferFrame::decode(&mut buffer.as_slice())
             .expect("Should be able to roundtrip TCTransferFrame");
@@ -296,6 +885,342 @@ mod test {
         assert_eq!(expected, recovered)
     }
 
+    #[rstest]
+    #[case(TcFrameLimits::default(), 1019, None)]
+    #[case(TcFrameLimits::default(), 1020, Some(FrameTooLong { limit: 1024, len: 1025 }))]
+    #[case(TcFrameLimits::new(256), 251, None)]
+    #[case(TcFrameLimits::new(256), 252, Some(FrameTooLong { limit: 256, len: 257 }))]
+    // limits beyond the protocol maximum are still capped by it
+    #[case(TcFrameLimits::new(2048), 1020, Some(FrameTooLong { limit: 1024, len: 1025 }))]
+    fn frame_limits(
+        #[case] limits: TcFrameLimits,
+        #[case] payload_len: usize,
+        #[case] expected: Option<FrameTooLong>,
+    ) {
+        let header = TCPrimaryHeader {
+            tfvn: 0,
+            bypass_flag: BypassFlag::TypeB,
+            control_flag: ControlFlag::TypeD,
+            scid: 758,
+            vcid: 3,
+            sequence_number: 23,
+        };
+
+        let result = TCTransferFrame::new_with_limits(header, vec![0x42; payload_len], &limits);
+        assert_eq!(
+            expected.as_ref(),
+            result
+                .as_ref()
+                .err()
+                .and_then(|err| err.get_ref())
+                .and_then(|inner| inner.downcast_ref())
+        );
+        assert_eq!(expected.is_none(), result.is_ok());
+
+        let packet = SpacePacket::new(
+            0,
+            crate::PacketType::Command,
+            17,
+            GroupingFlag::Unsegm,
+            0,
+            false,
+            vec![0x42; payload_len - PrimaryHeader::WIRE_LEN],
+        );
+        assert_eq!(
+            expected.is_none(),
+            TCTransferFrame::from_space_packet_with_limits(header, &packet, &limits).is_ok()
+        );
+    }
+
+    #[rstest]
+    #[case(1018, true)]
+    #[case(1019, true)]
+    #[case(1020, false)]
+    fn frame_max_len(#[case] payload_len: usize, #[case] valid: bool) {
+        let header = TCPrimaryHeader {
+            tfvn: 0,
+            bypass_flag: BypassFlag::TypeB,
+            control_flag: ControlFlag::TypeD,
+            scid: 758,
+            vcid: 3,
+            sequence_number: 23,
+        };
+        let payload: Vec<u8> = (0..payload_len).map(|val| val as u8).collect();
+
+        let frame = TCTransferFrame::new(header, payload.clone());
+        let view = TCTransferFrameView {
+            header,
+            payload: &payload,
+        };
+        let mut out = [0_u8; TCTransferFrame::MAX_LEN + 8];
+        assert_eq!(valid, frame.is_ok());
+        assert_eq!(valid, view.encode_into(&mut out).is_ok());
+
+        if let Ok(frame) = frame {
+            let encoded = frame.encode();
+            assert!(encoded.len() <= TCTransferFrame::MAX_LEN);
+            assert_eq!(
+                frame,
+                TCTransferFrame::decode(&mut encoded.as_slice()).unwrap()
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(1013)]
+    fn frame_from_space_packet(#[case] payload_len: usize) {
+        let header = TCPrimaryHeader {
+            tfvn: 0,
+            bypass_flag: BypassFlag::TypeB,
+            control_flag: ControlFlag::TypeD,
+            scid: 758,
+            vcid: 3,
+            sequence_number: 23,
+        };
+        let packet = SpacePacket::new(
+            0,
+            crate::PacketType::Command,
+            17,
+            GroupingFlag::Unsegm,
+            5,
+            false,
+            (0..payload_len).map(|val| val as u8).collect(),
+        );
+
+        let frame = TCTransferFrame::from_space_packet(header, &packet).unwrap();
+        assert_eq!(header, frame.header());
+        assert_eq!(packet.encode(), frame.payload());
+        assert_eq!(packet, SpacePacket::decode(&mut frame.payload()).unwrap());
+    }
+
+    #[rstest]
+    fn frame_space_packets(#[values(false, true)] segmented: bool) {
+        let header = TCPrimaryHeader {
+            tfvn: 0,
+            bypass_flag: BypassFlag::TypeB,
+            control_flag: ControlFlag::TypeD,
+            scid: 758,
+            vcid: 3,
+            sequence_number: 23,
+        };
+        let packets: Vec<SpacePacket> = (1..4_u16)
+            .map(|index| {
+                SpacePacket::new(
+                    0,
+                    crate::PacketType::Command,
+                    17 + index,
+                    GroupingFlag::Unsegm,
+                    index,
+                    false,
+                    vec![index as u8; index as usize * 5],
+                )
+            })
+            .collect();
+        let segment_header = TCSegmentHeader {
+            sequence_flags: GroupingFlag::Unsegm,
+            map_id: 5,
+        };
+
+        let mut payload = vec![];
+        if segmented {
+            payload.push(segment_header.encode());
+        }
+        payload.extend(packets.iter().flat_map(|packet| packet.encode()));
+        let frame = TCTransferFrame::new(header, payload).unwrap();
+
+        let recovered = if segmented {
+            let (recovered_header, recovered) = frame.segmented_space_packets().unwrap();
+            assert_eq!(segment_header, recovered_header);
+            assert_eq!(
+                (segment_header, packets[0].clone()),
+                frame.segmented_space_packet().unwrap()
+            );
+            recovered.collect::<Result<Vec<_>, _>>().unwrap()
+        } else {
+            assert_eq!(packets[0], frame.space_packet().unwrap());
+            frame
+                .space_packets()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+        assert_eq!(packets, recovered);
+    }
+
+    #[test]
+    fn frame_space_packets_truncated() {
+        let packet = SpacePacket::new(
+            0,
+            crate::PacketType::Command,
+            17,
+            GroupingFlag::Unsegm,
+            5,
+            false,
+            vec![0x42; 10],
+        );
+        let mut payload = packet.encode();
+        payload.extend(&packet.encode()[..8]);
+        let frame = TCTransferFrame::new(
+            TCPrimaryHeader {
+                tfvn: 0,
+                bypass_flag: BypassFlag::TypeB,
+                control_flag: ControlFlag::TypeD,
+                scid: 758,
+                vcid: 3,
+                sequence_number: 23,
+            },
+            payload,
+        )
+        .unwrap();
+
+        let mut packets = frame.space_packets();
+        assert_eq!(packet, packets.next().unwrap().unwrap());
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            packets.next().unwrap().unwrap_err().kind()
+        );
+        assert!(packets.next().is_none());
+
+        let empty = TCTransferFrame::new(frame.header(), vec![]).unwrap();
+        assert!(empty.segmented_space_packet().is_err());
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(1014)]
+    #[should_panic]
+    fn frame_from_space_packet_invalid(#[case] payload_len: usize) {
+        let packet = SpacePacket::new(
+            0,
+            crate::PacketType::Command,
+            17,
+            GroupingFlag::Unsegm,
+            5,
+            false,
+            vec![0_u8; payload_len],
+        );
+        TCTransferFrame::from_space_packet(
+            TCPrimaryHeader {
+                tfvn: 0,
+                bypass_flag: BypassFlag::TypeB,
+                control_flag: ControlFlag::TypeD,
+                scid: 758,
+                vcid: 3,
+                sequence_number: 23,
+            },
+            &packet,
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(1019)]
+    fn frame_view_roundtrip(#[case] payload_len: usize) {
+        let expected = TCTransferFrame::new(
+            TCPrimaryHeader {
+                tfvn: 0,
+                bypass_flag: BypassFlag::TypeB,
+                control_flag: ControlFlag::TypeD,
+                scid: 758,
+                vcid: 3,
+                sequence_number: 23,
+            },
+            (0..payload_len).map(|val| val as u8).collect(),
+        )
+        .unwrap();
+
+        let mut out = [0_u8; 1024];
+        let written = expected.encode_into(&mut out).unwrap();
+        assert_eq!(expected.encode(), out[..written]);
+        assert_eq!(expected.clone().into_bytes(), out[..written]);
+
+        let mut scratch = [0_u8; 1019];
+        let view = TCTransferFrame::decode_into(&mut &out[..written], &mut scratch).unwrap();
+        assert_eq!(expected.view(), view);
+    }
+
+    #[test]
+    fn frame_view_errors() {
+        let payload = [0_u8; 1020];
+        let view = TCTransferFrameView {
+            header: TCPrimaryHeader {
+                tfvn: 0,
+                bypass_flag: BypassFlag::TypeB,
+                control_flag: ControlFlag::TypeD,
+                scid: 758,
+                vcid: 3,
+                sequence_number: 23,
+            },
+            payload: &payload,
+        };
+        assert!(view.encode_into(&mut [0_u8; 2048]).is_err());
+
+        let view = TCTransferFrameView {
+            payload: &payload[..100],
+            ..view
+        };
+        assert!(view.encode_into(&mut [0_u8; 104]).is_err());
+
+        let encoded = view.encode_into(&mut [0_u8; 105]).unwrap();
+        assert_eq!(105, encoded);
+
+        let mut out = [0_u8; 105];
+        view.encode_into(&mut out).unwrap();
+        assert!(TCTransferFrame::decode_into(&mut &out[..], &mut [0_u8; 99]).is_err());
+    }
+
+    #[rstest]
+    fn segment_header_roundtrip(
+        #[values(
+            GroupingFlag::Interm,
+            GroupingFlag::First,
+            GroupingFlag::Last,
+            GroupingFlag::Unsegm
+        )]
+        sequence_flags: GroupingFlag,
+        #[values(0, 2, 63)] map_id: u8,
+    ) {
+        let expected = TCSegmentHeader {
+            sequence_flags,
+            map_id,
+        };
+        assert!(expected.validate().is_ok());
+        assert_eq!(expected, TCSegmentHeader::decode(expected.encode()))
+    }
+
+    #[test]
+    fn segment_header_spacepy() {
+        // first byte of the payload in TC_FRAME_02 of SpacePyLibrary
+        assert_eq!(
+            TCSegmentHeader {
+                sequence_flags: GroupingFlag::Last,
+                map_id: 2
+            },
+            TCSegmentHeader::decode(0x82)
+        );
+        assert!(TCSegmentHeader {
+            sequence_flags: GroupingFlag::Last,
+            map_id: 64
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[rstest]
+    fn tc_decode_short_length(#[values(0, 1, 3)] encoded_len: u16) {
+        // a frame length field below the primary header length
+        let bytes = [
+            0x22,
+            0xF6,
+            (encoded_len >> 8) as u8,
+            encoded_len as u8,
+            0x00,
+        ];
+        let err = TCTransferFrame::decode(&mut &bytes[..]).unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+    }
+
     #[test]
     fn tc_compare_spacepy() {
         // test data from https://github.com/Stefan-Korner/SpacePyLibrary/blob/master/UnitTest/testData.py
@@ -0,0 +1,213 @@
+//! Known bit patterns for link commissioning and bit error rate (BER) testing.
+//!
+//! Every pattern payload starts with a big-endian 32-bit index, e.g. a frame count,
+//! followed by the [PATTERN_SCHEME] pseudo-noise (PN) sequence.
+//! [verify_test_pattern] correlates a received payload against the expected PN data,
+//! tolerating byte slips of up to [MAX_SLIP] bytes in either direction.
+
+use std::io::{Error, ErrorKind};
+
+use crate::{
+    tctm::{
+        randomizer::{pn_sequence, sequence, Randomization},
+        tm::{TMPrimaryHeader, TMTransferFrame},
+    },
+    GroupingFlag, PacketType, SpacePacket,
+};
+
+/// The PN sequence following the index of every pattern payload.
+pub const PATTERN_SCHEME: Randomization = Randomization::Tm131071;
+
+/// Length of the big-endian index at the start of every pattern payload.
+pub const INDEX_LEN: usize = 4;
+
+/// The largest number of bytes dropped or inserted which [verify_test_pattern] detects.
+pub const MAX_SLIP: usize = 8;
+
+/// The result of correlating a received payload against the expected pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatternReport {
+    /// The index embedded at the start of the payload.
+    pub index: u32,
+    /// The number of PN data bits differing from the expected pattern at the best alignment.
+    pub bit_errors: usize,
+    /// The alignment of the PN data with the fewest bit errors.
+    /// Positive values count bytes dropped from the pattern, negative values count bytes inserted.
+    pub slipped_bytes: isize,
+}
+
+/// Build a pattern payload of the `index` followed by `pn_len` bytes of PN data.
+pub fn pattern_payload(index: u32, pn_len: usize) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(INDEX_LEN + pn_len);
+    payload.extend_from_slice(&index.to_be_bytes());
+    payload.extend(pn_sequence(PATTERN_SCHEME, pn_len));
+    payload
+}
+
+/// Build an unsegmented telemetry packet carrying a pattern payload with `pn_len` bytes of PN data.
+///
+/// The sequence count is the `index` modulo the 14-bit counter.
+pub fn test_packet(apid: u16, index: u32, pn_len: usize) -> SpacePacket {
+    SpacePacket::new(
+        0,
+        PacketType::Telemetry,
+        apid,
+        GroupingFlag::Unsegm,
+        (index & 0x3FFF) as u16,
+        false,
+        pattern_payload(index, pn_len),
+    )
+}
+
+/// Build a TM Transfer Frame whose entire data field of `data_field_len` bytes is a pattern payload.
+///
+/// # Errors
+///
+/// Errors if `data_field_len` is shorter than the [INDEX_LEN].
+pub fn test_frame(
+    primary_header: TMPrimaryHeader,
+    index: u32,
+    data_field_len: usize,
+) -> Result<TMTransferFrame, Error> {
+    let pn_len = data_field_len.checked_sub(INDEX_LEN).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Test pattern data field must be at least {INDEX_LEN} bytes but found {data_field_len}"
+            ),
+        )
+    })?;
+    Ok(TMTransferFrame {
+        primary_header,
+        secondary_header: None,
+        data_field: pattern_payload(index, pn_len),
+    })
+}
+
+/// Correlate a received pattern `payload` against the expected PN data.
+///
+/// Every alignment up to [MAX_SLIP] bytes either side is compared over the whole payload,
+/// the one with the fewest bit errors is reported, preferring the smallest slip on ties.
+///
+/// # Errors
+///
+/// Errors if the `payload` is too short to contain the index.
+pub fn verify_test_pattern(payload: &[u8]) -> Result<PatternReport, Error> {
+    if payload.len() < INDEX_LEN {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            format!(
+                "Test pattern payload must be at least {INDEX_LEN} bytes but found {}",
+                payload.len()
+            ),
+        ));
+    }
+    let (index, received) = payload.split_at(INDEX_LEN);
+    let index = u32::from_be_bytes(index.try_into().unwrap());
+
+    let sequence = sequence(PATTERN_SCHEME);
+    // inserted bytes are compared against the end of the previous period of the sequence
+    let bit_errors = |slip: isize| {
+        let start = (slip.rem_euclid(sequence.len() as isize)) as usize;
+        received
+            .iter()
+            .zip(sequence.iter().cycle().skip(start))
+            .map(|(val, expected)| (val ^ expected).count_ones() as usize)
+            .sum::<usize>()
+    };
+
+    let (slipped_bytes, bit_errors) = std::iter::once(0)
+        .chain((1..=MAX_SLIP as isize).flat_map(|slip| [-slip, slip]))
+        .map(|slip| (slip, bit_errors(slip)))
+        .fold((0, usize::MAX), |best, candidate| {
+            match candidate.1 < best.1 {
+                true => candidate,
+                false => best,
+            }
+        });
+
+    Ok(PatternReport {
+        index,
+        bit_errors,
+        slipped_bytes,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    fn pattern_packet_roundtrip(#[values(0, 1, 0x4000, u32::MAX)] index: u32) {
+        let packet = test_packet(17, index, 200);
+        assert_eq!(
+            (index & 0x3FFF) as u16,
+            packet.primary_header.sequence_count
+        );
+
+        let decoded = SpacePacket::decode(&mut packet.encode().as_slice()).unwrap();
+        assert_eq!(
+            PatternReport {
+                index,
+                bit_errors: 0,
+                slipped_bytes: 0
+            },
+            verify_test_pattern(&decoded.payload).unwrap()
+        );
+    }
+
+    #[test]
+    fn pattern_frame() {
+        let header = TMPrimaryHeader::builder().scid(758).build().unwrap();
+        let frame = test_frame(header, 42, 100).unwrap();
+        assert_eq!(100, frame.data_field.len());
+
+        let report = verify_test_pattern(&frame.data_field).unwrap();
+        assert_eq!(42, report.index);
+        assert_eq!(0, report.bit_errors);
+
+        assert!(test_frame(header, 42, 3).is_err());
+    }
+
+    #[rstest]
+    #[case(&[INDEX_LEN], 1)]
+    #[case(&[10, 20, 30], 3)]
+    #[case(&[10, 11, 12, 13, 14, 15, 16, 17], 8)]
+    fn pattern_bit_errors(#[case] offsets: &[usize], #[case] expected: usize) {
+        let mut payload = pattern_payload(7, 500);
+        offsets.iter().for_each(|offset| payload[*offset] ^= 0x01);
+
+        let report = verify_test_pattern(&payload).unwrap();
+        assert_eq!(expected, report.bit_errors);
+        assert_eq!(0, report.slipped_bytes);
+    }
+
+    #[rstest]
+    fn pattern_dropped_bytes(#[values(1, 3, 8)] dropped: usize) {
+        let mut payload = pattern_payload(7, 500);
+        payload.drain(INDEX_LEN..INDEX_LEN + dropped);
+
+        let report = verify_test_pattern(&payload).unwrap();
+        assert_eq!(dropped as isize, report.slipped_bytes);
+        assert_eq!(0, report.bit_errors);
+    }
+
+    #[rstest]
+    fn pattern_inserted_bytes(#[values(1, 3, 8)] inserted: usize) {
+        let mut payload = pattern_payload(7, 500);
+        payload.splice(INDEX_LEN..INDEX_LEN, vec![0xAA; inserted]);
+
+        let report = verify_test_pattern(&payload).unwrap();
+        assert_eq!(-(inserted as isize), report.slipped_bytes);
+        // only the inserted bytes can differ
+        assert!(report.bit_errors <= 8 * inserted);
+    }
+
+    #[test]
+    fn pattern_too_short() {
+        let err = verify_test_pattern(&[0, 0, 1]).unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedEof, err.kind());
+    }
+}
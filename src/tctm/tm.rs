@@ -1,14 +1,38 @@
 //! Implementation of the Telemetry Frame (TM) as defined in CCSDS 132.0-B-3
 
-use std::io::{Error, ErrorKind, Read};
+use std::{
+    fmt::Debug,
+    io::{Error, ErrorKind, Read},
+};
 
 use byteorder::{BigEndian, ReadBytesExt};
 #[cfg(feature = "crc")]
 use crc::Crc;
 
-use crate::GroupingFlag;
-
-use crate::tctm::randomizer::{apply_randomization, Randomization};
+use crate::{
+    consts::{FHP_NO_PACKET_START, FHP_ONLY_IDLE_DATA},
+    limits::{DecodeLimits, Limit, ResourceLimit},
+    raw::RawTmPrimaryHeader,
+    GroupingFlag, PayloadSummary,
+};
+
+use crate::tctm::{
+    clcw::Clcw,
+    extractor::PacketZone,
+    randomizer::{apply_randomization_in_place, Randomization},
+};
+
+mod bitstream;
+mod downlink;
+mod mux;
+mod packer;
+mod packets;
+pub use crate::consts::ASM;
+pub use bitstream::{BitAccumulator, BitCountPosition, BitstreamSdu};
+pub use downlink::{downlink_decode, CodeblockDecoder, TmChannelConfig};
+pub use mux::{MultiplexerEvent, VirtualChannelMultiplexer};
+pub use packer::{CollectFrames, TMFramePacker};
+pub use packets::{TmPacket, TmPacketIter};
 
 /// Randomization Schemes for TM Transfer Frames as defined CCSDS in 131.0-B-5
 #[derive(Debug, Clone, Copy)]
@@ -21,7 +45,15 @@ pub enum TMRandomization {
     Tm131071,
 }
 
-// apply randomization to bytestream
+impl TMRandomization {
+    fn randomization(self) -> Option<Randomization> {
+        match self {
+            TMRandomization::None => None,
+            TMRandomization::Tm255 => Some(Randomization::Tm255),
+            TMRandomization::Tm131071 => Some(Randomization::Tm131071),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Flag to indicate if the associated field is present in a TM Tranfser Frame.
@@ -41,6 +73,10 @@ impl BooleanFieldFlag {
         }
     }
 }
+named_variants!(BooleanFieldFlag {
+    NotPresent => "Not present",
+    Present => "Present",
+});
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Flag to identify type type of data in the TM Transfer Frame Data Field.
@@ -64,6 +100,10 @@ impl SynchronizationFlag {
         }
     }
 }
+named_variants!(SynchronizationFlag {
+    Nominal => "Nominal",
+    VcaSdu => "VCA_SDU",
+});
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -96,31 +136,37 @@ impl SegmentLength {
     }
 }
 
+// Byte indices must stay below the reserved pointers, see CCSDS 132.0-B-3 section 4.1.2.7.3.
+const _: () = assert!(FirstHeaderPointer::OnlyIdleData.into_u16() == FHP_ONLY_IDLE_DATA);
+const _: () = assert!(FirstHeaderPointer::NoPacketStart.into_u16() == FHP_NO_PACKET_START);
+const _: () =
+    assert!(FirstHeaderPointer::ByteIndex(FHP_ONLY_IDLE_DATA - 1).into_u16() < FHP_ONLY_IDLE_DATA);
+
 #[repr(u16)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FirstHeaderPointer {
     /// Index of  the first header must be less than 2046
     ByteIndex(u16),
     /// This packet contains only IDLE data
-    OnlyIdleData = 0b111_1111_1110,
+    OnlyIdleData = FHP_ONLY_IDLE_DATA,
     /// This frame is entirely a continuation of a previous packet
     /// No new packet header starts inside of this frame.
-    NoPacketStart = 0b111_1111_1111,
+    NoPacketStart = FHP_NO_PACKET_START,
 }
 impl FirstHeaderPointer {
-    pub fn into_u16(self) -> u16 {
+    pub const fn into_u16(self) -> u16 {
         match self {
             FirstHeaderPointer::ByteIndex(value) => value & 0x7ff,
-            FirstHeaderPointer::OnlyIdleData => 0b111_1111_1110,
-            FirstHeaderPointer::NoPacketStart => 0b111_1111_1111,
+            FirstHeaderPointer::OnlyIdleData => FHP_ONLY_IDLE_DATA,
+            FirstHeaderPointer::NoPacketStart => FHP_NO_PACKET_START,
         }
     }
 
     pub fn from_u16(value: u16) -> Result<Self, Error> {
         match value {
-            val if val < 2046 => Ok(Self::ByteIndex(val)),
-            0b111_1111_1110 => Ok(Self::OnlyIdleData),
-            0b111_1111_1111 => Ok(Self::NoPacketStart),
+            val if val < FHP_ONLY_IDLE_DATA => Ok(Self::ByteIndex(val)),
+            FHP_ONLY_IDLE_DATA => Ok(Self::OnlyIdleData),
+            FHP_NO_PACKET_START => Ok(Self::NoPacketStart),
             val => Err(Error::new(
                 ErrorKind::InvalidData,
                 format!(
@@ -190,6 +236,10 @@ impl TMDataFieldStatus {
 
     /// Encode into a byte stream
     pub fn encode(self) -> Vec<u8> {
+        self.to_bytes().to_vec()
+    }
+
+    fn to_bytes(self) -> [u8; 2] {
         let Self {
             secondary_header_flag,
             synchronization_flag,
@@ -204,7 +254,7 @@ impl TMDataFieldStatus {
             | (segment_length as u16) << 11
             | first_header_pointer.into_u16();
 
-        word.to_be_bytes().to_vec()
+        word.to_be_bytes()
     }
 
     /// Decode the Status field from a byte stream
@@ -251,6 +301,11 @@ pub struct TMPrimaryHeader {
     pub data_field_status: TMDataFieldStatus,
 }
 impl TMPrimaryHeader {
+    /// Start building a header with a [TMPrimaryHeaderBuilder].
+    pub fn builder() -> TMPrimaryHeaderBuilder {
+        TMPrimaryHeaderBuilder::default()
+    }
+
     /// Validate header values which require bit masks will fit in the
     /// desginate bit-depth
     ///
@@ -292,6 +347,10 @@ impl TMPrimaryHeader {
 
     /// Encode self into a byte steam
     pub fn encode(self) -> Vec<u8> {
+        self.to_bytes().to_vec()
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; 6] {
         let Self {
             tfvn,
             scid,
@@ -309,28 +368,159 @@ impl TMPrimaryHeader {
                 | ocf_flag as u16
         };
 
-        let mut message = first_word.to_be_bytes().to_vec();
-
-        message.push(mc_frame_count);
-        message.push(vc_frame_count);
-        message.extend_from_slice(&data_field_status.encode());
-
-        message
+        let [b0, b1] = first_word.to_be_bytes();
+        let [b4, b5] = data_field_status.to_bytes();
+        [b0, b1, mc_frame_count, vc_frame_count, b4, b5]
     }
 
     /// Decode from a byte steam
+    ///
+    /// The fields are unpacked by [RawTmPrimaryHeader::header].
     pub fn decode<R: Read>(buffer: &mut R) -> Result<Self, Error> {
-        let first_word = buffer.read_u16::<BigEndian>()?;
+        let mut bytes = [0_u8; 6];
+        buffer.read_exact(&mut bytes)?;
+        Ok(RawTmPrimaryHeader(bytes).header())
+    }
+}
 
-        Ok(Self {
-            tfvn: (first_word >> 14) as u8 & 0x3_u8,
-            scid: (first_word >> 4) & 0x3ff_u16,
-            vcid: (first_word >> 1) as u8 & 0x7_u8,
-            ocf_flag: BooleanFieldFlag::from_u8((first_word & 0x1_u16) as u8)?,
-            mc_frame_count: buffer.read_u8()?,
-            vc_frame_count: buffer.read_u8()?,
-            data_field_status: TMDataFieldStatus::decode(buffer)?,
-        })
+/// Builder for a [TMPrimaryHeader] which fills in the fixed values of a nominal
+/// packet carrying virtual channel.
+///
+/// Only the spacecraft ID is required. All other fields default to:
+///  - `tfvn`: `0`
+///  - `vcid`: `0`
+///  - `mc_frame_count` and `vc_frame_count`: `0`
+///  - `ocf_flag`: [BooleanFieldFlag::NotPresent]
+///  - `secondary_header_flag`: [BooleanFieldFlag::NotPresent]
+///  - `synchronization_flag`: [SynchronizationFlag::Nominal]
+///  - `packet_order`: `false`, reserved when synchronization is nominal
+///  - `segment_length`: [GroupingFlag::Unsegm], fixed when synchronization is nominal
+///  - `first_header_pointer`: [FirstHeaderPointer::ByteIndex] `0`
+///
+/// ```
+/// use spacepacket::tctm::tm::TMPrimaryHeader;
+///
+/// let builder = TMPrimaryHeader::builder().scid(758).vcid(3).with_ocf();
+/// assert!(builder.defaulted().contains(&"vc_frame_count"));
+///
+/// let header = builder.build().unwrap();
+/// assert_eq!(758, header.scid);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TMPrimaryHeaderBuilder {
+    scid: Option<u16>,
+    vcid: Option<u8>,
+    counts: Option<(u8, u8)>,
+    ocf_flag: Option<BooleanFieldFlag>,
+    secondary_header_flag: Option<BooleanFieldFlag>,
+    first_header_pointer: Option<FirstHeaderPointer>,
+}
+impl TMPrimaryHeaderBuilder {
+    /// Fields which are always set to their nominal value by this builder.
+    const FIXED_FIELDS: [&'static str; 4] = [
+        "tfvn",
+        "synchronization_flag",
+        "packet_order",
+        "segment_length",
+    ];
+
+    /// Set the 10-bit spacecraft ID.
+    pub fn scid(mut self, scid: u16) -> Self {
+        self.scid = Some(scid);
+        self
+    }
+
+    /// Set the 3-bit virtual channel ID.
+    pub fn vcid(mut self, vcid: u8) -> Self {
+        self.vcid = Some(vcid);
+        self
+    }
+
+    /// Set the master and virtual channel frame counts.
+    pub fn counts(mut self, mc_frame_count: u8, vc_frame_count: u8) -> Self {
+        self.counts = Some((mc_frame_count, vc_frame_count));
+        self
+    }
+
+    /// Mark the Operational Control Field as present in the frame trailer.
+    pub fn with_ocf(mut self) -> Self {
+        self.ocf_flag = Some(BooleanFieldFlag::Present);
+        self
+    }
+
+    /// Mark a [TMSecondaryHeader] as present at the start of the data field.
+    pub fn secondary_header(mut self) -> Self {
+        self.secondary_header_flag = Some(BooleanFieldFlag::Present);
+        self
+    }
+
+    /// Set the location of the first packet header in the data field.
+    pub fn first_header_pointer(mut self, first_header_pointer: FirstHeaderPointer) -> Self {
+        self.first_header_pointer = Some(first_header_pointer);
+        self
+    }
+
+    /// The names of all [TMPrimaryHeader] and [TMDataFieldStatus] fields which
+    /// will be set to a default value by [Self::build].
+    pub fn defaulted(&self) -> Vec<&'static str> {
+        let optional = [
+            ("vcid", self.vcid.is_none()),
+            ("mc_frame_count", self.counts.is_none()),
+            ("vc_frame_count", self.counts.is_none()),
+            ("ocf_flag", self.ocf_flag.is_none()),
+            (
+                "secondary_header_flag",
+                self.secondary_header_flag.is_none(),
+            ),
+            ("first_header_pointer", self.first_header_pointer.is_none()),
+        ];
+        Self::FIXED_FIELDS
+            .into_iter()
+            .chain(
+                optional
+                    .into_iter()
+                    .filter_map(|(name, defaulted)| defaulted.then_some(name)),
+            )
+            .collect()
+    }
+
+    /// Construct the header.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - the spacecraft ID was not set
+    ///  - the resulting header fails [TMPrimaryHeader::validate]
+    pub fn build(self) -> Result<TMPrimaryHeader, Error> {
+        let scid = self.scid.ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "Spacecraft ID is required to build a TM Primary Header",
+            )
+        })?;
+        let (mc_frame_count, vc_frame_count) = self.counts.unwrap_or_default();
+
+        let header = TMPrimaryHeader {
+            tfvn: 0,
+            scid,
+            vcid: self.vcid.unwrap_or_default(),
+            ocf_flag: self.ocf_flag.unwrap_or(BooleanFieldFlag::NotPresent),
+            mc_frame_count,
+            vc_frame_count,
+            data_field_status: TMDataFieldStatus {
+                secondary_header_flag: self
+                    .secondary_header_flag
+                    .unwrap_or(BooleanFieldFlag::NotPresent),
+                synchronization_flag: SynchronizationFlag::Nominal,
+                packet_order: false,
+                segment_length: GroupingFlag::Unsegm,
+                first_header_pointer: self
+                    .first_header_pointer
+                    .unwrap_or(FirstHeaderPointer::ByteIndex(0)),
+            },
+        };
+        header.validate()?;
+        Ok(header)
     }
 }
 
@@ -381,19 +571,29 @@ impl TMSecondaryHeader {
     }
 
     /// Encode to a byte steam
-    pub fn encode(self) -> Vec<u8> {
-        let Self {
-            tfvn,
-            mut data_field,
-        } = self;
+    pub fn encode(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(1 + self.data_field.len());
+        message.push(self.first_byte());
+        message.extend_from_slice(&self.data_field);
+        message
+    }
+
+    /// Encode to a byte steam, reusing the allocation of the data field.
+    pub fn into_bytes(self) -> Vec<u8> {
+        let first_byte = self.first_byte();
+        let mut message = self.data_field;
+        message.insert(0, first_byte);
+        message
+    }
 
+    fn first_byte(&self) -> u8 {
         // the secondary header is 1 byte
         // and the encoded length is total length -1
         // so we need to take len() +1 -1 or just len
-        let packet_len = (data_field.len()) as u8;
-        let mut message = vec![{ (tfvn & 0x3_u8) << 6 | packet_len }];
-        message.append(&mut data_field);
-        message
+        // Self::validate limits the data field to 63 bytes, longer fields are
+        // masked rather than overflowing into the version number
+        let packet_len = (self.data_field.len() & 0x3f) as u8;
+        (self.tfvn & 0x3_u8) << 6 | packet_len
     }
 
     /// Decode from a byte steam
@@ -417,39 +617,377 @@ impl TMSecondaryHeader {
 /// A Telemetry (TM) Transfer Frame used in telemetry downlink defined in  CCSDS 132.0-B-3
 /// Operational Control Field and Frame Error Control Field are not automatically
 /// decoded and are left in the data_field of this structure.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct TMTransferFrame {
     /// TM primary header meta-data
     pub primary_header: TMPrimaryHeader,
 
-    /// The Data Field of telemetry values. May have a [TMSecondaryHeader]
-    /// at the beginning. This is indicated by the presence of [TMDataFieldStatus::secondary_header_flag]
+    /// The secondary header at the start of the data field.
     ///
-    /// The length of this field is fixed on a per physical channel basis.
+    /// [TMDataFieldStatus::secondary_header_flag] indicates its presence, the flag is decoded
+    /// into this field and checked against it by [TMTransferFrame::validate] when encoding.
+    pub secondary_header: Option<TMSecondaryHeader>,
+
+    /// The Data Field of telemetry values following any [Self::secondary_header].
+    ///
+    /// The length of the data field is fixed on a per physical channel basis.
     /// As such it is impossible to decode one without apriori knowledge of the
     /// length.
     pub data_field: Vec<u8>,
 }
+impl PacketZone for TMTransferFrame {
+    /// The data field without the Operational Control Field, if present.
+    ///
+    /// Any Frame Error Control Field must already be removed from the data field.
+    fn packet_zone(&self) -> &[u8] {
+        let end = match self.primary_header.ocf_flag {
+            BooleanFieldFlag::Present => self.data_field.len().saturating_sub(4),
+            BooleanFieldFlag::NotPresent => self.data_field.len(),
+        };
+        &self.data_field[..end]
+    }
+
+    fn first_header_pointer(&self) -> FirstHeaderPointer {
+        self.primary_header.data_field_status.first_header_pointer
+    }
+
+    fn frame_count(&self) -> u32 {
+        u32::from(self.primary_header.vc_frame_count)
+    }
+
+    fn frame_count_modulus(&self) -> u32 {
+        256
+    }
+}
+
+impl Debug for TMTransferFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TMTransferFrame")
+            .field("primary_header", &self.primary_header)
+            .field("secondary_header", &self.secondary_header)
+            .field("data_field", &PayloadSummary(&self.data_field))
+            .finish()
+    }
+}
+/// A [TMTransferFrame] which borrows its data field.
+///
+/// Used to en/de-code frames without allocating, see [TMTransferFrame::decode_into].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TMTransferFrameView<'a> {
+    /// TM primary header meta-data
+    pub primary_header: TMPrimaryHeader,
+
+    /// The borrowed Data Field, see [TMTransferFrame::data_field].
+    ///
+    /// Unlike [TMTransferFrame::data_field] any [TMSecondaryHeader] remains encoded at its start.
+    pub data_field: &'a [u8],
+}
+impl<'a> Debug for TMTransferFrameView<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TMTransferFrameView")
+            .field("primary_header", &self.primary_header)
+            .field("data_field", &PayloadSummary(self.data_field))
+            .finish()
+    }
+}
+impl<'a> TMTransferFrameView<'a> {
+    /// Encode this frame into the start of the `out` buffer without allocating.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `out` is too short to hold the encoded frame.
+    pub fn encode_into(
+        &self,
+        randomization: TMRandomization,
+        out: &mut [u8],
+    ) -> Result<usize, Error> {
+        let encoded_len = 6 + self.data_field.len();
+        let out_len = out.len();
+        let out = out.get_mut(..encoded_len).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Output buffer of length {out_len} cannot hold frame of length {encoded_len}"
+                ),
+            )
+        })?;
+
+        out[..6].copy_from_slice(&self.primary_header.to_bytes());
+        out[6..].copy_from_slice(self.data_field);
+        if let Some(randomization) = randomization.randomization() {
+            apply_randomization_in_place(out, randomization);
+        }
+
+        Ok(encoded_len)
+    }
+}
+
 impl TMTransferFrame {
-    fn _encode_helper(self) -> Vec<u8> {
+    /// Borrow this frame as a [TMTransferFrameView].
+    ///
+    /// The view borrows only the [Self::data_field], any [Self::secondary_header] is not part of it.
+    pub fn view(&self) -> TMTransferFrameView<'_> {
+        TMTransferFrameView {
+            primary_header: self.primary_header,
+            data_field: &self.data_field,
+        }
+    }
+
+    /// Encode this frame into the start of the `out` buffer without allocating.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - the frame fails [Self::validate]
+    ///  - `out` is too short to hold the encoded frame
+    pub fn encode_into(
+        &self,
+        randomization: TMRandomization,
+        out: &mut [u8],
+    ) -> Result<usize, Error> {
+        self.validate()?;
+        match &self.secondary_header {
+            None => self.view().encode_into(randomization, out),
+            Some(secondary_header) => {
+                let secondary_header = secondary_header.encode();
+                let start = 6 + secondary_header.len();
+                let encoded_len = start + self.data_field.len();
+                let out_len = out.len();
+                let out = out.get_mut(..encoded_len).ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "Output buffer of length {out_len} cannot hold frame of length {encoded_len}"
+                        ),
+                    )
+                })?;
+
+                out[..6].copy_from_slice(&self.primary_header.to_bytes());
+                out[6..start].copy_from_slice(&secondary_header);
+                out[start..].copy_from_slice(&self.data_field);
+                if let Some(randomization) = randomization.randomization() {
+                    apply_randomization_in_place(out, randomization);
+                }
+
+                Ok(encoded_len)
+            }
+        }
+    }
+
+    /// Validate the [Self::secondary_header] against [TMDataFieldStatus::secondary_header_flag].
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - the flag is [BooleanFieldFlag::Present] without a secondary header or vice versa
+    ///  - the secondary header fails [TMSecondaryHeader::validate]
+    pub fn validate(&self) -> Result<(), Error> {
+        let flag = self.primary_header.data_field_status.secondary_header_flag;
+        match (flag, &self.secondary_header) {
+            (BooleanFieldFlag::Present, Some(secondary_header)) => secondary_header.validate(),
+            (BooleanFieldFlag::NotPresent, None) => Ok(()),
+            (flag, secondary_header) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "TM secondary header flag is {flag} but the frame has {} secondary header",
+                    match secondary_header {
+                        Some(_) => "a",
+                        None => "no",
+                    }
+                ),
+            )),
+        }
+    }
+
+    /// Decode a Transfer Frame without allocating by reading the entire frame into the caller
+    /// provided `scratch` buffer. The returned [TMTransferFrameView] borrows its data field from `scratch`.
+    ///
+    /// The `length` parameter is the length of the entire Frame, as in [Self::decode].
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - `length` is shorter than the primary header
+    ///  - `scratch` is shorter than `length`
+    ///  - the primary header cannot be decoded
+    pub fn decode_into<'a, R: Read>(
+        buffer: &mut R,
+        length: usize,
+        randomization: TMRandomization,
+        scratch: &'a mut [u8],
+    ) -> Result<TMTransferFrameView<'a>, Error> {
+        if length < 6 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("TM Transfer Frame length must be at least 6 but found {length}"),
+            ));
+        }
+        let scratch_len = scratch.len();
+        let frame = scratch.get_mut(..length).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Scratch buffer of length {scratch_len} cannot hold frame of length {length}"
+                ),
+            )
+        })?;
+
+        buffer.read_exact(frame)?;
+        if let Some(randomization) = randomization.randomization() {
+            apply_randomization_in_place(frame, randomization);
+        }
+
+        let frame: &'a [u8] = frame;
+        Ok(TMTransferFrameView {
+            primary_header: TMPrimaryHeader::decode(&mut &frame[..6])?,
+            data_field: &frame[6..],
+        })
+    }
+
+    /// Parse the Operational Control Field at the end of the data field as a [Clcw].
+    ///
+    /// `has_fecf` indicates whether the 2 byte Frame Error Control Field still follows the OCF
+    /// at the end of the data field.
+    ///
+    /// Returns `None` if [TMPrimaryHeader::ocf_flag] is [BooleanFieldFlag::NotPresent].
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - the data field is too short to contain the OCF
+    ///  - the OCF does not contain a CLCW
+    pub fn clcw(&self, has_fecf: bool) -> Result<Option<Clcw>, Error> {
+        if self.primary_header.ocf_flag == BooleanFieldFlag::NotPresent {
+            return Ok(None);
+        }
+
+        let end = match has_fecf {
+            true => self.data_field.len().checked_sub(2),
+            false => Some(self.data_field.len()),
+        };
+        let ocf = end
+            .and_then(|end| self.data_field.get(end.checked_sub(4)?..end))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!(
+                        "TM data field of length {} is too short to contain an OCF",
+                        self.data_field.len()
+                    ),
+                )
+            })?;
+
+        Clcw::decode(&mut &ocf[..]).map(Some)
+    }
+
+    /// Parse the [PacketZone] of a VCA frame as a [BitstreamSdu] with the bit count at `position`.
+    ///
+    /// Any Frame Error Control Field must already be removed from the data field.
+    ///
+    /// Returns `None` if [TMDataFieldStatus::synchronization_flag] is not [SynchronizationFlag::VcaSdu].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the zone does not contain a valid [BitstreamSdu].
+    pub fn bitstream_sdu(&self, position: BitCountPosition) -> Result<Option<BitstreamSdu>, Error> {
+        if self.primary_header.data_field_status.synchronization_flag != SynchronizationFlag::VcaSdu
+        {
+            return Ok(None);
+        }
+        BitstreamSdu::decode(self.packet_zone(), position).map(Some)
+    }
+
+    /// Iterate over the packets in the packet zone of this frame alone.
+    ///
+    /// The first packet starts at the First Header Pointer. The bytes before it are yielded
+    /// as a [TmPacket::Continuation] of an earlier packet and a last packet continuing in the
+    /// next frame as [TmPacket::Spanning], so they may be stitched to the neighbouring frames.
+    /// Idle Packets are yielded like any other packet.
+    ///
+    /// Frames with [FirstHeaderPointer::OnlyIdleData], [FirstHeaderPointer::NoPacketStart]
+    /// or [SynchronizationFlag::VcaSdu] yield nothing, a [PacketExtractor](crate::tctm::extractor::PacketExtractor) reassembles packets
+    /// across any number of frames.
+    ///
+    /// Any Frame Error Control Field must already be removed from the data field.
+    ///
+    /// # Errors
+    ///
+    /// Yields one error and ends if the First Header Pointer is outside the packet zone
+    /// or a packet fails to decode.
+    pub fn packets(&self) -> TmPacketIter<'_> {
+        TmPacketIter::new(self)
+    }
+
+    fn _encode_helper(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(6 + 64 + self.data_field.len());
+        message.extend_from_slice(&self.primary_header.to_bytes());
+        if let Some(secondary_header) = &self.secondary_header {
+            message.extend(secondary_header.encode());
+        }
+        message.extend_from_slice(&self.data_field);
+        message
+    }
+
+    fn _randomize(mut message: Vec<u8>, randomization: TMRandomization) -> Vec<u8> {
+        if let Some(randomization) = randomization.randomization() {
+            apply_randomization_in_place(&mut message, randomization);
+        }
+        message
+    }
+
+    /// Encode this packet into a byte stream
+    ///
+    /// The frame is not validated, see [Self::try_encode].
+    pub fn encode(&self, randomization: TMRandomization) -> Vec<u8> {
+        Self::_randomize(self._encode_helper(), randomization)
+    }
+
+    /// Encode this packet into a byte stream as [Self::encode] after checking [Self::validate].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the frame fails [Self::validate].
+    pub fn try_encode(&self, randomization: TMRandomization) -> Result<Vec<u8>, Error> {
+        self.validate()?;
+        Ok(self.encode(randomization))
+    }
+
+    /// Encode this packet into a byte stream, reusing the allocation of the data field.
+    ///
+    /// The frame is not validated, see [Self::try_encode].
+    pub fn into_bytes(self, randomization: TMRandomization) -> Vec<u8> {
         let Self {
             primary_header,
-            mut data_field,
+            secondary_header,
+            data_field: mut message,
         } = self;
-        let mut message = primary_header.encode();
-        message.append(&mut data_field);
-        message
+        let secondary_header = secondary_header.map(TMSecondaryHeader::into_bytes);
+        message.splice(
+            0..0,
+            primary_header
+                .to_bytes()
+                .into_iter()
+                .chain(secondary_header.into_iter().flatten()),
+        );
+        Self::_randomize(message, randomization)
     }
-    /// Encode this packet into a byte stream
-    pub fn encode(self, randomization: TMRandomization) -> Vec<u8> {
-        match randomization {
-            TMRandomization::None => self._encode_helper(),
-            TMRandomization::Tm255 => {
-                apply_randomization(self._encode_helper().as_slice(), Randomization::Tm255)
-            }
-            TMRandomization::Tm131071 => {
-                apply_randomization(self._encode_helper().as_slice(), Randomization::Tm131071)
+
+    /// Split the secondary header from the start of the `data_field` if the `primary_header` flags it.
+    fn _split_secondary_header(
+        primary_header: &TMPrimaryHeader,
+        mut data_field: Vec<u8>,
+    ) -> Result<(Option<TMSecondaryHeader>, Vec<u8>), Error> {
+        match primary_header.data_field_status.secondary_header_flag {
+            BooleanFieldFlag::Present => {
+                let secondary_header = TMSecondaryHeader::decode(&mut data_field.as_slice())?;
+                data_field.drain(..1 + secondary_header.data_field.len());
+                Ok((Some(secondary_header), data_field))
             }
+            BooleanFieldFlag::NotPresent => Ok((None, data_field)),
         }
     }
 
@@ -458,22 +996,9 @@ impl TMTransferFrame {
         length: usize,
         randomization: TMRandomization,
     ) -> Result<Vec<u8>, Error> {
-        let byte_array = {
-            let mut tmp = vec![0_u8; length];
-            buffer.read_exact(&mut tmp)?;
-            tmp
-        };
-
-        let buffer_vec = match randomization {
-            TMRandomization::None => byte_array,
-            TMRandomization::Tm255 => {
-                apply_randomization(byte_array.as_slice(), Randomization::Tm255)
-            }
-            TMRandomization::Tm131071 => {
-                apply_randomization(byte_array.as_slice(), Randomization::Tm131071)
-            }
-        };
-        Ok(buffer_vec)
+        let mut byte_array = vec![0_u8; length];
+        buffer.read_exact(&mut byte_array)?;
+        Ok(Self::_randomize(byte_array, randomization))
     }
 
     /// Decode a Transfer Frame from a byte stream.
@@ -485,37 +1010,90 @@ impl TMTransferFrame {
     ///  - Primary Header [6-bytes]
     ///  - Secondary Header (<= 64 bytes, if present)
     ///  - Trailer (2, 4, or 6 bytes, if present)
+    ///
+    /// The secondary header is decoded into [Self::secondary_header] if flagged as present.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the frame cannot be read or the flagged secondary header does not fit the frame.
     pub fn decode<R: Read>(
         buffer: R,
         length: usize,
         randomization: TMRandomization,
     ) -> Result<Self, Error> {
-        let buffer = Self::_decode_helper(buffer, length, randomization)?;
-        let mut buffer = buffer.as_slice();
+        let mut data_field = Self::_decode_helper(buffer, length, randomization)?;
+        let primary_header = RawTmPrimaryHeader::from_slice(&data_field)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!("Frame of length {length} is too short for a primary header"),
+                )
+            })?
+            .header();
+        // reuse the allocation of the whole frame for the data field
+        data_field.drain(..6);
+        let (secondary_header, data_field) =
+            Self::_split_secondary_header(&primary_header, data_field)?;
 
         Ok(Self {
-            primary_header: TMPrimaryHeader::decode(&mut buffer)?,
-            data_field: {
-                let mut tmp = vec![0_u8; length - 6];
-                buffer.read_exact(&mut tmp)?;
-                tmp
-            },
+            primary_header,
+            secondary_header,
+            data_field,
         })
     }
 
+    /// Decode a Transfer Frame from untrusted input as [Self::decode] within the `limits`.
+    ///
+    /// The `length` is checked against [DecodeLimits::max_item_len] before any allocation,
+    /// and the frame layout with [Self::check_layout] if [DecodeLimits::checks_layout].
+    ///
+    /// # Errors
+    ///
+    /// Errors as [Self::decode] or with a [ResourceLimit] if a limit is exceeded.
+    pub fn decode_with_limits<R: Read>(
+        buffer: R,
+        length: usize,
+        randomization: TMRandomization,
+        limits: &DecodeLimits,
+    ) -> Result<Self, Error> {
+        limits.check_item_len(length)?;
+        let frame = Self::decode(buffer, length, randomization)?;
+        if limits.checks_layout() {
+            frame.check_layout()?;
+        }
+        Ok(frame)
+    }
+
+    /// Check the OCF flag is consistent with the data field, i.e. the OCF fits in the
+    /// data field following the [Self::secondary_header].
+    ///
+    /// # Errors
+    ///
+    /// Errors with [Limit::FrameLayout] and the length of the data field otherwise.
+    pub fn check_layout(&self) -> Result<(), ResourceLimit> {
+        let ocf_len = match self.primary_header.ocf_flag {
+            BooleanFieldFlag::Present => 4,
+            BooleanFieldFlag::NotPresent => 0,
+        };
+
+        match self.data_field.len() >= ocf_len {
+            true => Ok(()),
+            false => Err(ResourceLimit {
+                which: Limit::FrameLayout,
+                limit: self.data_field.len(),
+            }),
+        }
+    }
+
     #[cfg(feature = "crc")]
     #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
     /// Encode the TM Tansfer Frame and append a CRC-16 value using the provied [Crc].
-    pub fn encode_crc(self, crc: &Crc<u16>, randomization: TMRandomization) -> Vec<u8> {
+    ///
+    /// The frame is not validated, see [Self::validate].
+    pub fn encode_crc(&self, crc: &Crc<u16>, randomization: TMRandomization) -> Vec<u8> {
         let mut message = self._encode_helper();
         message.extend(crc.checksum(message.as_slice()).to_be_bytes());
-        match randomization {
-            TMRandomization::None => message,
-            TMRandomization::Tm255 => apply_randomization(message.as_slice(), Randomization::Tm255),
-            TMRandomization::Tm131071 => {
-                apply_randomization(message.as_slice(), Randomization::Tm131071)
-            }
-        }
+        Self::_randomize(message, randomization)
     }
 
     #[cfg(feature = "crc")]
@@ -548,13 +1126,16 @@ impl TMTransferFrame {
                 ),
             ));
         }
+        let primary_header = TMPrimaryHeader::decode(&mut buffer)?;
+        let mut data_field = vec![0_u8; length - 6];
+        buffer.read_exact(&mut data_field)?;
+        let (secondary_header, data_field) =
+            Self::_split_secondary_header(&primary_header, data_field)?;
+
         Ok(Self {
-            primary_header: TMPrimaryHeader::decode(&mut buffer)?,
-            data_field: {
-                let mut tmp = vec![0_u8; length - 6];
-                buffer.read_exact(&mut tmp)?;
-                tmp
-            },
+            primary_header,
+            secondary_header,
+            data_field,
         })
     }
 }
@@ -643,6 +1224,62 @@ mod test {
         assert_eq!(expected, recovered)
     }
 
+    #[test]
+    fn tm_header_builder() {
+        let builder = TMPrimaryHeader::builder()
+            .scid(758)
+            .vcid(3)
+            .counts(1, 2)
+            .with_ocf()
+            .first_header_pointer(FirstHeaderPointer::NoPacketStart);
+
+        assert_eq!(
+            vec![
+                "tfvn",
+                "synchronization_flag",
+                "packet_order",
+                "segment_length",
+                "secondary_header_flag"
+            ],
+            builder.defaulted()
+        );
+
+        let expected = TMPrimaryHeader {
+            tfvn: 0,
+            scid: 758,
+            vcid: 3,
+            ocf_flag: BooleanFieldFlag::Present,
+            mc_frame_count: 1,
+            vc_frame_count: 2,
+            data_field_status: TMDataFieldStatus {
+                secondary_header_flag: BooleanFieldFlag::NotPresent,
+                synchronization_flag: SynchronizationFlag::Nominal,
+                packet_order: false,
+                segment_length: GroupingFlag::Unsegm,
+                first_header_pointer: FirstHeaderPointer::NoPacketStart,
+            },
+        };
+        assert_eq!(expected, builder.build().unwrap());
+    }
+
+    #[rstest]
+    #[case(TMPrimaryHeader::builder().scid(1023).vcid(7))]
+    #[should_panic]
+    // scid is required
+    #[case(TMPrimaryHeader::builder().vcid(7))]
+    #[should_panic]
+    // scid out of bounds
+    #[case(TMPrimaryHeader::builder().scid(1024))]
+    #[should_panic]
+    // vcid out of bounds
+    #[case(TMPrimaryHeader::builder().scid(5).vcid(8))]
+    #[should_panic]
+    // first header index out of bounds
+    #[case(TMPrimaryHeader::builder().scid(5).first_header_pointer(FirstHeaderPointer::ByteIndex(2046)))]
+    fn tm_header_builder_validation(#[case] builder: TMPrimaryHeaderBuilder) {
+        builder.build().unwrap();
+    }
+
     #[test]
     fn tm_compare_spacepy() {
         // test data from https://github.com/Stefan-Korner/SpacePyLibrary/blob/master/UnitTest/testData.py
@@ -731,21 +1368,12 @@ mod test {
         ];
 
         let expected = TMTransferFrame {
-            primary_header: TMPrimaryHeader {
-                tfvn: 0,
-                scid: 758,
-                vcid: 0,
-                ocf_flag: BooleanFieldFlag::Present,
-                mc_frame_count: 0,
-                vc_frame_count: 0,
-                data_field_status: TMDataFieldStatus {
-                    secondary_header_flag: BooleanFieldFlag::NotPresent,
-                    synchronization_flag: SynchronizationFlag::Nominal,
-                    packet_order: false,
-                    segment_length: GroupingFlag::Unsegm,
-                    first_header_pointer: FirstHeaderPointer::ByteIndex(0),
-                },
-            },
+            primary_header: TMPrimaryHeader::builder()
+                .scid(758)
+                .with_ocf()
+                .build()
+                .unwrap(),
+            secondary_header: None,
             data_field: vec![
                 0x0C, 0xD2, 0xC0, 0x00, 0x00, 0x1A, 0x10, 0x03, 0x19, 0x16, 0x92, 0x5E, 0x92, 0x00,
                 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
@@ -833,6 +1461,281 @@ mod test {
         let parsed_tm = TMTransferFrame::decode(&mut input_bytes, 1115, TMRandomization::None)
             .expect("Unable to parse TM Frame.");
 
-        assert_eq!(expected, parsed_tm)
+        assert_eq!(expected, parsed_tm);
+
+        let expected_clcw = Clcw {
+            version: 0,
+            status: 0,
+            cop_in_effect: 1,
+            vcid: 0,
+            no_rf_available: false,
+            no_bit_lock: false,
+            lockout: false,
+            wait: false,
+            retransmit: false,
+            farm_b_counter: 0,
+            report_value: 0,
+        };
+        assert_eq!(
+            Some(expected_clcw),
+            parsed_tm.clcw(true).expect("Unable to parse CLCW.")
+        );
+    }
+
+    #[rstest]
+    #[case(BooleanFieldFlag::NotPresent, false, vec![0x01, 0, 0, 0x07], None)]
+    #[case(BooleanFieldFlag::Present, false, vec![0xAA, 0x01, 0, 0, 0x07], Some(0x07))]
+    #[case(BooleanFieldFlag::Present, true, vec![0x01, 0, 0, 0x07, 0xBB, 0xD6], Some(0x07))]
+    #[should_panic]
+    // too short for OCF and FECF
+    #[case(BooleanFieldFlag::Present, true, vec![0x01, 0, 0, 0x07], None)]
+    fn tm_frame_clcw(
+        #[case] ocf_flag: BooleanFieldFlag,
+        #[case] has_fecf: bool,
+        #[case] data_field: Vec<u8>,
+        #[case] report_value: Option<u8>,
+    ) {
+        let mut primary_header = TMPrimaryHeader::builder().scid(758).build().unwrap();
+        primary_header.ocf_flag = ocf_flag;
+        let frame = TMTransferFrame {
+            primary_header,
+            secondary_header: None,
+            data_field,
+        };
+
+        assert_eq!(
+            report_value,
+            frame.clcw(has_fecf).unwrap().map(|clcw| clcw.report_value)
+        )
+    }
+
+    #[rstest]
+    #[case(SynchronizationFlag::Nominal, BooleanFieldFlag::NotPresent, vec![0xAB, 0x03], None)]
+    #[case(SynchronizationFlag::VcaSdu, BooleanFieldFlag::NotPresent, vec![0xAB, 0x03], Some(3))]
+    // the OCF is not part of the SDU
+    #[case(SynchronizationFlag::VcaSdu, BooleanFieldFlag::Present, vec![0xAB, 0x05, 0, 0, 0, 0], Some(5))]
+    #[should_panic]
+    #[case(SynchronizationFlag::VcaSdu, BooleanFieldFlag::NotPresent, vec![0xAB, 0x00], None)]
+    fn tm_frame_bitstream_sdu(
+        #[case] synchronization_flag: SynchronizationFlag,
+        #[case] ocf_flag: BooleanFieldFlag,
+        #[case] data_field: Vec<u8>,
+        #[case] valid_bits: Option<u8>,
+    ) {
+        let mut primary_header = TMPrimaryHeader::builder().scid(758).build().unwrap();
+        primary_header.ocf_flag = ocf_flag;
+        primary_header.data_field_status.synchronization_flag = synchronization_flag;
+        let frame = TMTransferFrame {
+            primary_header,
+            secondary_header: None,
+            data_field,
+        };
+
+        let sdu = frame.bitstream_sdu(BitCountPosition::LastByte).unwrap();
+        assert_eq!(
+            valid_bits,
+            sdu.as_ref().map(|sdu| sdu.valid_bits_in_last_byte)
+        );
+        if let Some(sdu) = sdu {
+            assert_eq!(vec![0xAB], sdu.data);
+        }
+    }
+
+    #[rstest]
+    #[case(BooleanFieldFlag::NotPresent, BooleanFieldFlag::Present, vec![0x3F; 4], true, true)]
+    // a 64 byte secondary header in a 10 byte data field does not decode
+    #[case(BooleanFieldFlag::Present, BooleanFieldFlag::NotPresent, vec![0x3F; 10], false, false)]
+    // the OCF leaves 6 bytes for the secondary header
+    #[case(BooleanFieldFlag::Present, BooleanFieldFlag::Present, vec![0x05; 10], true, true)]
+    #[case(BooleanFieldFlag::Present, BooleanFieldFlag::Present, vec![0x06; 10], false, true)]
+    #[case(BooleanFieldFlag::NotPresent, BooleanFieldFlag::Present, vec![0x00; 3], false, true)]
+    fn tm_frame_limits(
+        #[case] secondary_header_flag: BooleanFieldFlag,
+        #[case] ocf_flag: BooleanFieldFlag,
+        #[case] data_field: Vec<u8>,
+        #[case] consistent: bool,
+        #[case] decodes: bool,
+    ) {
+        let mut primary_header = TMPrimaryHeader::builder().scid(758).build().unwrap();
+        primary_header.ocf_flag = ocf_flag;
+        primary_header.data_field_status.secondary_header_flag = secondary_header_flag;
+        let encoded = TMTransferFrame {
+            primary_header,
+            secondary_header: None,
+            data_field,
+        }
+        .encode(TMRandomization::None);
+
+        let limits = DecodeLimits::hardened();
+        let decoded = TMTransferFrame::decode_with_limits(
+            encoded.as_slice(),
+            encoded.len(),
+            TMRandomization::None,
+            &limits,
+        );
+        assert_eq!(consistent, decoded.is_ok());
+        // the layout is not checked by default
+        assert_eq!(
+            decodes,
+            TMTransferFrame::decode_with_limits(
+                encoded.as_slice(),
+                encoded.len(),
+                TMRandomization::None,
+                &DecodeLimits::default()
+            )
+            .is_ok()
+        );
+
+        let error = TMTransferFrame::decode_with_limits(
+            encoded.as_slice(),
+            encoded.len(),
+            TMRandomization::None,
+            &limits.with_max_item_len(encoded.len() - 1),
+        )
+        .unwrap_err();
+        assert_eq!(
+            Some(&ResourceLimit {
+                which: Limit::ItemLen,
+                limit: encoded.len() - 1
+            }),
+            error
+                .get_ref()
+                .and_then(|err| err.downcast_ref::<ResourceLimit>())
+        );
+    }
+
+    #[rstest]
+    #[case(None)]
+    #[case(Some(TMSecondaryHeader { tfvn: 0, data_field: vec![0x5A; 7] }))]
+    fn tm_frame_secondary_header_roundtrip(
+        #[case] secondary_header: Option<TMSecondaryHeader>,
+        #[values(TMRandomization::None, TMRandomization::Tm255)] randomization: TMRandomization,
+    ) {
+        let mut primary_header = TMPrimaryHeader::builder().scid(758).build().unwrap();
+        if secondary_header.is_some() {
+            primary_header.data_field_status.secondary_header_flag = BooleanFieldFlag::Present;
+        }
+        let frame = TMTransferFrame {
+            primary_header,
+            secondary_header,
+            data_field: vec![0xC3; 20],
+        };
+
+        let encoded = frame.try_encode(randomization).unwrap();
+        assert_eq!(frame.clone().into_bytes(randomization), encoded);
+        let mut out = [0_u8; 64];
+        let written = frame.encode_into(randomization, &mut out).unwrap();
+        assert_eq!(encoded, out[..written]);
+
+        let decoded =
+            TMTransferFrame::decode(encoded.as_slice(), encoded.len(), randomization).unwrap();
+        assert_eq!(frame, decoded);
+        assert_eq!(vec![0xC3; 20], decoded.packet_zone());
+    }
+
+    #[test]
+    fn tm_frame_secondary_header_flag_mismatch() {
+        let mut frame = TMTransferFrame {
+            primary_header: TMPrimaryHeader::builder().scid(758).build().unwrap(),
+            secondary_header: Some(TMSecondaryHeader {
+                tfvn: 0,
+                data_field: vec![0x5A; 7],
+            }),
+            data_field: vec![0xC3; 20],
+        };
+        assert!(frame.validate().is_err());
+        assert!(frame.try_encode(TMRandomization::None).is_err());
+        assert!(frame
+            .encode_into(TMRandomization::None, &mut [0; 64])
+            .is_err());
+
+        frame.secondary_header = None;
+        frame.primary_header.data_field_status.secondary_header_flag = BooleanFieldFlag::Present;
+        let error = frame.try_encode(TMRandomization::None).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+    }
+
+    #[test]
+    fn tm_secondary_header_into_bytes() {
+        let header = TMSecondaryHeader {
+            tfvn: 1,
+            data_field: vec![0x42; 12],
+        };
+        assert_eq!(header.encode(), header.clone().into_bytes());
+        assert_eq!(vec![0x4C], header.encode()[..1]);
+    }
+
+    #[rstest]
+    fn tm_frame_view_roundtrip(
+        #[values(
+            TMRandomization::None,
+            TMRandomization::Tm255,
+            TMRandomization::Tm131071
+        )]
+        randomization: TMRandomization,
+    ) {
+        let frame = TMTransferFrame {
+            primary_header: TMPrimaryHeader::builder()
+                .scid(758)
+                .vcid(3)
+                .counts(1, 2)
+                .build()
+                .unwrap(),
+            secondary_header: None,
+            data_field: (0..1109_u32).map(|val| val as u8).collect(),
+        };
+
+        let mut out = [0_u8; 2048];
+        let written = frame.encode_into(randomization, &mut out).unwrap();
+        assert_eq!(frame.encode(randomization), out[..written]);
+        assert_eq!(frame.clone().into_bytes(randomization), out[..written]);
+
+        let mut scratch = [0_u8; 2048];
+        let view =
+            TMTransferFrame::decode_into(&mut &out[..], written, randomization, &mut scratch)
+                .unwrap();
+        assert_eq!(frame.view(), view);
+    }
+
+    #[rstest]
+    // frame shorter than a header
+    #[case(5, 2048)]
+    // scratch too short
+    #[case(1115, 1114)]
+    fn tm_decode_into_errors(#[case] length: usize, #[case] scratch_len: usize) {
+        let input = [0_u8; 2048];
+        let mut scratch = vec![0_u8; scratch_len];
+        let result = TMTransferFrame::decode_into(
+            &mut &input[..],
+            length,
+            TMRandomization::None,
+            &mut scratch,
+        );
+        assert_eq!(ErrorKind::InvalidInput, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn tm_frame_debug() {
+        let frame = TMTransferFrame {
+            primary_header: TMPrimaryHeader::builder()
+                .scid(758)
+                .counts(1, 2)
+                .build()
+                .unwrap(),
+            secondary_header: None,
+            data_field: vec![0xAB; 1109],
+        };
+
+        let debug = format!("{frame:?}");
+        assert!(debug.starts_with("TMTransferFrame { primary_header: TMPrimaryHeader { "));
+        assert!(debug.ends_with(
+            "data_field: [len=1109, first16=AB AB AB AB AB AB AB AB AB AB AB AB AB AB AB AB ..] }"
+        ));
+
+        let pretty = format!("{frame:#?}");
+        assert!(pretty.contains(&format!(
+            "    data_field: [len=1109, first64={} ..],\n",
+            ["AB"; 64].join(" ")
+        )));
     }
 }
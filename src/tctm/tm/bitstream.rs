@@ -0,0 +1,267 @@
+//! Bitstream data carried by the Virtual Channel Access (VCA) service.
+//!
+//! A bitstream need not end on a byte boundary, so every [BitstreamSdu] carries the number
+//! of valid bits in its last data byte in an extra count byte. Where the count byte sits
+//! is a mission convention, see [BitCountPosition]. Bits are numbered from the most
+//! significant bit, the valid bits of the last byte are its most significant bits.
+//!
+//! The [BitAccumulator] concatenates the bitstreams of consecutive frames.
+//!
+//! ```
+//! # use spacepacket::tctm::tm::{BitAccumulator, BitCountPosition, BitstreamSdu};
+//! let sdu = BitstreamSdu::new(vec![0xAB, 0xC0], 4).unwrap();
+//! let encoded = sdu.encode(BitCountPosition::LastByte);
+//! assert_eq!(vec![0xAB, 0xC0, 4], encoded);
+//!
+//! let mut bits = BitAccumulator::new();
+//! bits.push(&BitstreamSdu::decode(&encoded, BitCountPosition::LastByte).unwrap());
+//! bits.push(&sdu);
+//! assert_eq!(24, bits.bit_len());
+//! assert_eq!(&[0xAB, 0xCA, 0xBC], bits.as_bytes());
+//! ```
+
+use std::io::{Error, ErrorKind};
+
+/// Where the count of valid bits sits within an encoded [BitstreamSdu].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitCountPosition {
+    /// The count byte precedes the data.
+    FirstByte,
+    /// The count byte follows the data.
+    #[default]
+    LastByte,
+}
+
+/// A VCA Service Data Unit carrying a bitstream whose last byte may be partially valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitstreamSdu {
+    /// The bitstream, the unused bits of the last byte are ignored.
+    pub data: Vec<u8>,
+    /// The number of valid bits in the last byte of [Self::data], from 1 to 8.
+    pub valid_bits_in_last_byte: u8,
+}
+impl BitstreamSdu {
+    /// Create a new SDU checking it with [Self::validate].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the SDU is invalid.
+    pub fn new(data: Vec<u8>, valid_bits_in_last_byte: u8) -> Result<Self, Error> {
+        let sdu = Self {
+            data,
+            valid_bits_in_last_byte,
+        };
+        sdu.validate()?;
+        Ok(sdu)
+    }
+
+    /// Validate the bit count.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - [Self::data] is empty
+    ///  - [Self::valid_bits_in_last_byte] is not in `1..=8`
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.data.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Bitstream SDU must contain at least 1 data byte",
+            ));
+        }
+        if !(1..=8).contains(&self.valid_bits_in_last_byte) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Valid bits in the last byte must be in 1..=8 but found {}",
+                    self.valid_bits_in_last_byte
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The number of valid bits in the bitstream.
+    pub fn bit_len(&self) -> usize {
+        match self.data.len() {
+            0 => 0,
+            len => 8 * (len - 1) + usize::from(self.valid_bits_in_last_byte),
+        }
+    }
+
+    /// Encode the data with the count byte at `position`.
+    pub fn encode(&self, position: BitCountPosition) -> Vec<u8> {
+        let mut message = Vec::with_capacity(self.data.len() + 1);
+        if position == BitCountPosition::FirstByte {
+            message.push(self.valid_bits_in_last_byte);
+        }
+        message.extend_from_slice(&self.data);
+        if position == BitCountPosition::LastByte {
+            message.push(self.valid_bits_in_last_byte);
+        }
+        message
+    }
+
+    /// Decode an SDU whose count byte sits at `position`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `sdu` holds no data byte or the count is not in `1..=8`.
+    pub fn decode(sdu: &[u8], position: BitCountPosition) -> Result<Self, Error> {
+        let split = match position {
+            BitCountPosition::FirstByte => sdu.split_first(),
+            BitCountPosition::LastByte => sdu.split_last(),
+        };
+        let (count, data) = split.ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnexpectedEof,
+                "Bitstream SDU must contain the bit count",
+            )
+        })?;
+
+        let sdu = Self {
+            data: data.to_vec(),
+            valid_bits_in_last_byte: *count,
+        };
+        sdu.validate()
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+        Ok(sdu)
+    }
+}
+
+/// Concatenates the bitstreams of consecutive [BitstreamSdu]s into one contiguous bit vector.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BitAccumulator {
+    bytes: Vec<u8>,
+    bit_len: usize,
+}
+impl BitAccumulator {
+    /// Create an empty accumulator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append the valid bits of the `sdu`.
+    pub fn push(&mut self, sdu: &BitstreamSdu) {
+        let bit_len = sdu.bit_len();
+        let shift = self.bit_len % 8;
+        match shift {
+            0 => self.bytes.extend_from_slice(&sdu.data),
+            _ => {
+                // merge every byte across the boundary of the partially filled last byte
+                for byte in &sdu.data {
+                    *self.bytes.last_mut().unwrap() |= byte >> shift;
+                    self.bytes.push(byte << (8 - shift));
+                }
+            }
+        }
+        self.bit_len += bit_len;
+        // drop the bytes and bits past the valid bits
+        self.bytes.truncate((self.bit_len + 7) / 8);
+        if let (Some(last), 1..=7) = (self.bytes.last_mut(), self.bit_len % 8) {
+            *last &= 0xFF << (8 - self.bit_len % 8);
+        }
+    }
+
+    /// The number of accumulated bits.
+    pub fn bit_len(&self) -> usize {
+        self.bit_len
+    }
+
+    /// The value of the bit at `index`, numbered from the most significant bit of the first byte.
+    pub fn bit(&self, index: usize) -> Option<bool> {
+        (index < self.bit_len).then(|| self.bytes[index / 8] & (0x80 >> (index % 8)) != 0)
+    }
+
+    /// The accumulated bits, any unused bits of the last byte are zero.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Take the accumulated bits, returning the bytes and the number of valid bits.
+    pub fn take(&mut self) -> (Vec<u8>, usize) {
+        let bit_len = std::mem::take(&mut self.bit_len);
+        (std::mem::take(&mut self.bytes), bit_len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    fn bitstream_roundtrip(
+        #[values(BitCountPosition::FirstByte, BitCountPosition::LastByte)]
+        position: BitCountPosition,
+        #[values(1, 5, 8)] valid_bits: u8,
+    ) {
+        let sdu = BitstreamSdu::new(vec![0x12, 0x34, 0x56], valid_bits).unwrap();
+        let encoded = sdu.encode(position);
+        assert_eq!(4, encoded.len());
+        assert_eq!(16 + usize::from(valid_bits), sdu.bit_len());
+        assert_eq!(sdu, BitstreamSdu::decode(&encoded, position).unwrap());
+    }
+
+    #[rstest]
+    #[case(&[], ErrorKind::UnexpectedEof)]
+    #[case(&[0x05], ErrorKind::InvalidData)]
+    #[case(&[0xAA, 0x00], ErrorKind::InvalidData)]
+    #[case(&[0xAA, 0x09], ErrorKind::InvalidData)]
+    fn bitstream_decode_errors(#[case] sdu: &[u8], #[case] kind: ErrorKind) {
+        let err = BitstreamSdu::decode(sdu, BitCountPosition::LastByte).unwrap_err();
+        assert_eq!(kind, err.kind());
+    }
+
+    #[rstest]
+    #[case(vec![], 8)]
+    #[case(vec![0xAA], 0)]
+    #[case(vec![0xAA], 9)]
+    fn bitstream_invalid(#[case] data: Vec<u8>, #[case] valid_bits: u8) {
+        assert!(BitstreamSdu::new(data, valid_bits).is_err());
+    }
+
+    #[test]
+    fn bitstream_accumulate() {
+        let mut bits = BitAccumulator::new();
+        // 3 bits, 101
+        bits.push(&BitstreamSdu::new(vec![0xBF], 3).unwrap());
+        // 12 bits, 1100 0011 1111
+        bits.push(&BitstreamSdu::new(vec![0xC3, 0xFF], 4).unwrap());
+        // 8 bits, 0000 0001
+        bits.push(&BitstreamSdu::new(vec![0x01], 8).unwrap());
+
+        assert_eq!(23, bits.bit_len());
+        // 1011 1000 0111 1110 0000 001
+        assert_eq!(&[0xB8, 0x7E, 0x02], bits.as_bytes());
+        assert_eq!(Some(true), bits.bit(0));
+        assert_eq!(Some(false), bits.bit(1));
+        assert_eq!(Some(true), bits.bit(22));
+        assert_eq!(None, bits.bit(23));
+
+        assert_eq!((vec![0xB8, 0x7E, 0x02], 23), bits.take());
+        assert_eq!(0, bits.bit_len());
+    }
+
+    #[rstest]
+    fn bitstream_accumulate_bitwise(#[values(1, 3, 7, 8)] valid_bits: u8) {
+        let sdus: Vec<_> = (0..5_u8)
+            .map(|index| BitstreamSdu::new(vec![index * 37, 0xFF - index], valid_bits).unwrap())
+            .collect();
+
+        let mut bits = BitAccumulator::new();
+        let mut expected = vec![];
+        for sdu in &sdus {
+            bits.push(sdu);
+            expected.extend(
+                (0..sdu.bit_len()).map(|index| sdu.data[index / 8] & (0x80 >> (index % 8)) != 0),
+            );
+        }
+
+        assert_eq!(expected.len(), bits.bit_len());
+        for (index, bit) in expected.into_iter().enumerate() {
+            assert_eq!(Some(bit), bits.bit(index));
+        }
+    }
+}
@@ -0,0 +1,369 @@
+//! Decoding of [TMTransferFrame]s received from the physical channel.
+//!
+//! [downlink_decode] reverses every stage of the synchronization and channel coding
+//! defined in CCSDS 131.0-B-5: the Attached Sync Marker (ASM) is located and stripped,
+//! the codeblock is de-randomized and error corrected, then the Frame Error Control Field (FECF)
+//! is validated before the frame is parsed.
+
+use std::{
+    fmt::Debug,
+    io::{Error, ErrorKind},
+};
+
+#[cfg(feature = "crc")]
+use crc::{Crc, CRC_16_IBM_3740};
+
+use super::{TMRandomization, TMTransferFrame};
+use crate::{consts::ASM, tctm::randomizer::apply_randomization_in_place};
+
+/// The CRC used for the TM Frame Error Control Field.
+#[cfg(feature = "crc")]
+const FECF_CRC: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+
+/// An error correcting code, e.g. Reed-Solomon, whose check symbols follow the frame in a codeblock.
+pub trait CodeblockDecoder {
+    /// The number of check symbol bytes appended to every frame.
+    fn check_len(&self) -> usize;
+
+    /// Correct the de-randomized `codeblock` of frame and check symbols in place.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the codeblock contains more errors than the code can correct.
+    fn correct(&self, codeblock: &mut [u8]) -> Result<(), Error>;
+}
+
+/// The synchronization and channel coding stages applied to a TM physical channel.
+///
+/// ```
+/// # use spacepacket::tctm::tm::{downlink_decode, TMPrimaryHeader, TMRandomization, TMTransferFrame, TmChannelConfig, ASM};
+/// let frame = TMTransferFrame {
+///     primary_header: TMPrimaryHeader::builder().scid(758).build().unwrap(),
+///     secondary_header: None,
+///     data_field: vec![0x42; 10],
+/// };
+/// let physical = [&ASM[..], &frame.encode(TMRandomization::Tm255)].concat();
+///
+/// let config = TmChannelConfig::new(16).with_randomization(TMRandomization::Tm255);
+/// assert_eq!(frame, downlink_decode(&physical, config).unwrap());
+/// ```
+#[derive(Clone, Copy)]
+pub struct TmChannelConfig<'a> {
+    frame_len: usize,
+    asm: Option<&'a [u8]>,
+    randomization: TMRandomization,
+    codeblock_decoder: Option<&'a dyn CodeblockDecoder>,
+    #[cfg(feature = "crc")]
+    fecf: bool,
+}
+impl<'a> Debug for TmChannelConfig<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("TmChannelConfig");
+        debug
+            .field("frame_len", &self.frame_len)
+            .field("asm", &self.asm)
+            .field("randomization", &self.randomization)
+            .field(
+                "check_len",
+                &self.codeblock_decoder.map(|decoder| decoder.check_len()),
+            );
+        #[cfg(feature = "crc")]
+        debug.field("fecf", &self.fecf);
+        debug.finish()
+    }
+}
+impl<'a> TmChannelConfig<'a> {
+    /// Configure a channel of `frame_len` byte frames, including all headers and the FECF,
+    /// preceded by the CCSDS [ASM] without randomization or error correction.
+    pub fn new(frame_len: usize) -> Self {
+        Self {
+            frame_len,
+            asm: Some(&ASM),
+            randomization: TMRandomization::None,
+            codeblock_decoder: None,
+            #[cfg(feature = "crc")]
+            fecf: false,
+        }
+    }
+
+    /// Search for a mission specific synchronization marker instead of the CCSDS [ASM],
+    /// an empty `asm` behaves like [Self::without_asm].
+    pub fn with_asm(mut self, asm: &'a [u8]) -> Self {
+        self.asm = Some(asm);
+        self
+    }
+
+    /// Decode codeblocks which start at the beginning of the physical bytes without any marker.
+    pub fn without_asm(mut self) -> Self {
+        self.asm = None;
+        self
+    }
+
+    /// De-randomize every codeblock.
+    pub fn with_randomization(mut self, randomization: TMRandomization) -> Self {
+        self.randomization = randomization;
+        self
+    }
+
+    /// Correct every de-randomized codeblock with the `decoder` before parsing the frame.
+    pub fn with_codeblock_decoder(mut self, decoder: &'a dyn CodeblockDecoder) -> Self {
+        self.codeblock_decoder = Some(decoder);
+        self
+    }
+
+    /// Validate and strip the 2 byte Frame Error Control Field at the end of every frame.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub fn with_fecf(mut self) -> Self {
+        self.fecf = true;
+        self
+    }
+
+    /// The length of every frame, including all headers and the FECF.
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+
+    /// The length of every codeblock following the ASM, the frame and any check symbols.
+    pub fn codeblock_len(&self) -> usize {
+        self.frame_len
+            + self
+                .codeblock_decoder
+                .map_or(0, |decoder| decoder.check_len())
+    }
+
+    fn fecf_len(&self) -> usize {
+        #[cfg(feature = "crc")]
+        if self.fecf {
+            return 2;
+        }
+        0
+    }
+}
+
+/// Decode the first [TMTransferFrame] in the `physical_bytes` received on a channel
+/// with the given `config`.
+///
+/// The stages toggled by the `config` are applied in order: locate and strip the ASM,
+/// de-randomize, correct the codeblock, validate and strip the FECF, then parse the frame.
+///
+/// # Errors
+///
+/// This function errors under the following circumstances
+///  - the configured frame length is shorter than the primary header and FECF
+///  - the ASM is not found
+///  - the `physical_bytes` end before the codeblock
+///  - the codeblock cannot be corrected
+///  - the FECF does not match the frame
+pub fn downlink_decode(
+    physical_bytes: &[u8],
+    config: TmChannelConfig,
+) -> Result<TMTransferFrame, Error> {
+    let frame_len = config.frame_len;
+    if frame_len < 6 + config.fecf_len() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "TM Transfer Frame length must be at least {} but found {frame_len}",
+                6 + config.fecf_len()
+            ),
+        ));
+    }
+
+    let start = match config.asm {
+        Some(asm) if !asm.is_empty() => {
+            let position = physical_bytes
+                .windows(asm.len())
+                .position(|window| window == asm)
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        "Attached Sync Marker not found in physical channel bytes",
+                    )
+                })?;
+            position + asm.len()
+        }
+        _ => 0,
+    };
+
+    let codeblock_len = config.codeblock_len();
+    let mut codeblock = physical_bytes
+        .get(start..start + codeblock_len)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "Codeblock of length {codeblock_len} truncated after {} bytes",
+                    physical_bytes.len() - start
+                ),
+            )
+        })?
+        .to_vec();
+
+    if let Some(randomization) = config.randomization.randomization() {
+        apply_randomization_in_place(&mut codeblock, randomization);
+    }
+
+    if let Some(decoder) = config.codeblock_decoder {
+        decoder.correct(&mut codeblock)?;
+    }
+    codeblock.truncate(frame_len);
+
+    #[cfg(feature = "crc")]
+    if config.fecf {
+        let (message, fecf) = codeblock.split_at(frame_len - 2);
+        let attached_crc = u16::from_be_bytes([fecf[0], fecf[1]]);
+        let computed_crc = FECF_CRC.checksum(message);
+        if computed_crc != attached_crc {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "CRC failure on TM Transfer Frame. \
+                    Expected {attached_crc:#04X} Computed {computed_crc:#04X}"
+                ),
+            ));
+        }
+        codeblock.truncate(frame_len - 2);
+    }
+
+    let frame_len = codeblock.len();
+    TMTransferFrame::decode(codeblock.as_slice(), frame_len, TMRandomization::None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::tctm::tm::TMPrimaryHeader;
+
+    use rstest::rstest;
+
+    /// A single check byte holding the XOR of the frame,
+    /// which corrects the first frame byte assuming only it was damaged.
+    struct XorCheck;
+    impl CodeblockDecoder for XorCheck {
+        fn check_len(&self) -> usize {
+            1
+        }
+
+        fn correct(&self, codeblock: &mut [u8]) -> Result<(), Error> {
+            let syndrome = codeblock.iter().fold(0, |acc, val| acc ^ val);
+            codeblock[0] ^= syndrome;
+            Ok(())
+        }
+    }
+
+    struct Uncorrectable;
+    impl CodeblockDecoder for Uncorrectable {
+        fn check_len(&self) -> usize {
+            2
+        }
+
+        fn correct(&self, _codeblock: &mut [u8]) -> Result<(), Error> {
+            Err(Error::new(ErrorKind::InvalidData, "uncorrectable"))
+        }
+    }
+
+    fn frame() -> TMTransferFrame {
+        TMTransferFrame {
+            primary_header: TMPrimaryHeader::builder()
+                .scid(758)
+                .vcid(2)
+                .counts(3, 4)
+                .build()
+                .unwrap(),
+            secondary_header: None,
+            data_field: (0..40).collect(),
+        }
+    }
+
+    #[rstest]
+    fn downlink_randomized(
+        #[values(
+            TMRandomization::None,
+            TMRandomization::Tm255,
+            TMRandomization::Tm131071
+        )]
+        randomization: TMRandomization,
+    ) {
+        let frame = frame();
+        let physical = [
+            &[0x00, 0x1A, 0xCF][..],
+            &ASM,
+            &frame.encode(randomization),
+            &[0xFF; 3],
+        ]
+        .concat();
+
+        let config = TmChannelConfig::new(46).with_randomization(randomization);
+        assert_eq!(frame, downlink_decode(&physical, config).unwrap());
+    }
+
+    #[test]
+    fn downlink_custom_asm() {
+        let frame = frame();
+        let physical = [&[0xEB, 0x90][..], &frame.encode(TMRandomization::None)].concat();
+
+        let config = TmChannelConfig::new(46).with_asm(&[0xEB, 0x90]);
+        assert_eq!(frame, downlink_decode(&physical, config).unwrap());
+
+        let config = TmChannelConfig::new(46).without_asm();
+        assert_eq!(frame, downlink_decode(&physical[2..], config).unwrap());
+    }
+
+    #[test]
+    fn downlink_corrected() {
+        let frame = frame();
+        let mut codeblock = frame.encode(TMRandomization::None);
+        codeblock.push(codeblock.iter().fold(0, |acc, val| acc ^ val));
+        // damage the first byte, then randomize the whole codeblock
+        codeblock[0] ^= 0x05;
+        let mut physical = codeblock;
+        apply_randomization_in_place(&mut physical, crate::tctm::randomizer::Randomization::Tm255);
+        physical.splice(0..0, ASM);
+
+        let config = TmChannelConfig::new(46)
+            .with_randomization(TMRandomization::Tm255)
+            .with_codeblock_decoder(&XorCheck);
+        assert_eq!(47, config.codeblock_len());
+        assert_eq!(frame, downlink_decode(&physical, config).unwrap());
+    }
+
+    #[test]
+    fn downlink_fecf() {
+        let frame = frame();
+        let physical = [
+            &ASM[..],
+            &frame.encode_crc(&FECF_CRC, TMRandomization::Tm255),
+        ]
+        .concat();
+
+        let config = TmChannelConfig::new(48)
+            .with_randomization(TMRandomization::Tm255)
+            .with_fecf();
+        assert_eq!(frame, downlink_decode(&physical, config).unwrap());
+
+        let mut damaged = physical;
+        damaged[10] ^= 0x01;
+        let err = downlink_decode(&damaged, config).unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+    }
+
+    #[test]
+    fn downlink_errors() {
+        let encoded = [&ASM[..], &frame().encode(TMRandomization::None)].concat();
+
+        let err = downlink_decode(&encoded[1..], TmChannelConfig::new(46)).unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+
+        let err = downlink_decode(&encoded, TmChannelConfig::new(47)).unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedEof, err.kind());
+
+        let err = downlink_decode(&encoded, TmChannelConfig::new(5)).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+
+        let config = TmChannelConfig::new(44).with_codeblock_decoder(&Uncorrectable);
+        let err = downlink_decode(&encoded, config).unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+    }
+}
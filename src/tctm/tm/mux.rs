@@ -0,0 +1,412 @@
+//! Multiplexing of Virtual Channels into one Master Channel of [TMTransferFrame]s.
+
+use std::{
+    collections::VecDeque,
+    io::{Error, ErrorKind},
+};
+
+use crate::SpacePacket;
+
+use super::{FirstHeaderPointer, TMFramePacker, TMPrimaryHeader, TMTransferFrame};
+
+/// The byte filling the data field of OID frames.
+const IDLE_FILL: u8 = 0x55;
+
+/// A notable change of the Virtual Channels of a [VirtualChannelMultiplexer].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiplexerEvent {
+    /// More idle frames than the starvation limit were emitted in a row
+    /// while the Virtual Channel had nothing queued, its producer may be stuck.
+    Starvation {
+        /// The starved Virtual Channel.
+        vcid: u8,
+        /// The idle frames emitted in a row while the Virtual Channel was empty.
+        idle_frames: usize,
+    },
+    /// A starved Virtual Channel queued data again.
+    StarvationCleared {
+        /// The Virtual Channel which recovered.
+        vcid: u8,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct VirtualChannel {
+    packer: TMFramePacker,
+    /// The idle frames allowed in a row while empty, overriding the default limit.
+    max_idle_frames: Option<usize>,
+    /// The channel only carries idle data and is never starved.
+    idle_only: bool,
+    /// The idle frames emitted in a row while the channel was empty.
+    idle_frames: usize,
+    starving: bool,
+}
+
+/// Emits one [TMTransferFrame] at a time from several Virtual Channels sharing a Master Channel.
+///
+/// Every Virtual Channel packs its packets with its own [TMFramePacker]. Channels with a
+/// complete frame are served in turn, an Only Idle Data (OID) frame is emitted when none has
+/// one. The Master Channel Frame Count of every emitted frame is assigned by the multiplexer.
+///
+/// A starvation monitor reports Virtual Channels whose producer may be stuck: once more than
+/// the starvation limit of idle frames are emitted in a row while a channel has nothing queued
+/// a [MultiplexerEvent::Starvation] is reported, followed by
+/// [MultiplexerEvent::StarvationCleared] once it queues data again.
+///
+/// ```
+/// # use spacepacket::{tctm::tm::{MultiplexerEvent, TMFramePacker, TMPrimaryHeader, VirtualChannelMultiplexer}, SpacePacket};
+/// let header = |vcid| TMPrimaryHeader::builder().scid(758).vcid(vcid).build().unwrap();
+/// let mut mux = VirtualChannelMultiplexer::new(header(7), 64)
+///     .unwrap()
+///     .with_channel(TMFramePacker::new(header(1), 64).unwrap())
+///     .unwrap()
+///     .with_starvation_limit(2);
+///
+/// (0..3).for_each(|_| drop(mux.next_frame()));
+/// assert_eq!(
+///     Some(MultiplexerEvent::Starvation { vcid: 1, idle_frames: 3 }),
+///     mux.pop_event()
+/// );
+///
+/// mux.push(1, &SpacePacket::idle(58)).unwrap();
+/// assert_eq!(1, mux.next_frame().primary_header.vcid);
+/// assert_eq!(Some(MultiplexerEvent::StarvationCleared { vcid: 1 }), mux.pop_event());
+/// ```
+#[derive(Debug, Clone)]
+pub struct VirtualChannelMultiplexer {
+    channels: Vec<VirtualChannel>,
+    /// Template of the header of OID frames.
+    idle_header: TMPrimaryHeader,
+    data_field_len: usize,
+    /// Index of the channel served first by the next frame.
+    next: usize,
+    mc_frame_count: u8,
+    /// The idle frames allowed in a row while a channel is empty.
+    max_idle_frames: Option<usize>,
+    events: VecDeque<MultiplexerEvent>,
+}
+impl VirtualChannelMultiplexer {
+    /// Create a multiplexer without Virtual Channels, emitting frames with a data field of
+    /// `data_field_len` bytes. OID frames use the `idle_header`, usually on VCID 7.
+    ///
+    /// The Master Channel Frame Count starts at the count of the `idle_header`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the `idle_header` fails [TMPrimaryHeader::validate].
+    pub fn new(idle_header: TMPrimaryHeader, data_field_len: usize) -> Result<Self, Error> {
+        idle_header.validate()?;
+        Ok(Self {
+            channels: vec![],
+            idle_header,
+            data_field_len,
+            next: 0,
+            mc_frame_count: idle_header.mc_frame_count,
+            max_idle_frames: None,
+            events: VecDeque::new(),
+        })
+    }
+
+    /// Add a Virtual Channel packing its packets with the `packer`.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - the `packer` produces a different data field length than the multiplexer
+    ///  - a channel with the VCID of the `packer` was already added
+    pub fn with_channel(mut self, packer: TMFramePacker) -> Result<Self, Error> {
+        if packer.data_field_len() != self.data_field_len {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Virtual Channel data field length {} differs from the Master Channel {}",
+                    packer.data_field_len(),
+                    self.data_field_len
+                ),
+            ));
+        }
+        if self.channel_index(packer.vcid()).is_some() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Virtual Channel {} was already added", packer.vcid()),
+            ));
+        }
+        self.channels.push(VirtualChannel {
+            packer,
+            max_idle_frames: None,
+            idle_only: false,
+            idle_frames: 0,
+            starving: false,
+        });
+        Ok(self)
+    }
+
+    /// Report a Virtual Channel as starved after more than `max_idle_frames` idle frames
+    /// in a row while it had nothing queued, for every channel without its own limit.
+    pub fn with_starvation_limit(mut self, max_idle_frames: usize) -> Self {
+        self.max_idle_frames = Some(max_idle_frames);
+        self
+    }
+
+    /// Set the starvation limit of the Virtual Channel `vcid`, see [Self::with_starvation_limit].
+    /// Has no effect for VCIDs which were not added.
+    pub fn with_channel_starvation_limit(mut self, vcid: u8, max_idle_frames: usize) -> Self {
+        if let Some(index) = self.channel_index(vcid) {
+            self.channels[index].max_idle_frames = Some(max_idle_frames);
+        }
+        self
+    }
+
+    /// Never report the Virtual Channel `vcid` as starved, e.g. a channel only carrying idle data.
+    /// Has no effect for VCIDs which were not added.
+    pub fn with_idle_only(mut self, vcid: u8) -> Self {
+        if let Some(index) = self.channel_index(vcid) {
+            self.channels[index].idle_only = true;
+        }
+        self
+    }
+
+    fn channel_index(&self, vcid: u8) -> Option<usize> {
+        self.channels
+            .iter()
+            .position(|channel| channel.packer.vcid() == vcid)
+    }
+
+    /// Queue the `packet` on the Virtual Channel `vcid`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if no channel with the `vcid` was added, or as [TMFramePacker::push] if the
+    /// `packet` cannot be encoded.
+    pub fn push(&mut self, vcid: u8, packet: &SpacePacket) -> Result<(), Error> {
+        let index = self.channel_index(vcid).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("No Virtual Channel {vcid} in the multiplexer"),
+            )
+        })?;
+        let channel = &mut self.channels[index];
+        channel.packer.push(packet)?;
+        channel.idle_frames = 0;
+        if std::mem::take(&mut channel.starving) {
+            self.events
+                .push_back(MultiplexerEvent::StarvationCleared { vcid });
+        }
+        Ok(())
+    }
+
+    /// Emit the next frame of the Master Channel, the next complete frame of a Virtual Channel
+    /// in turn or an OID frame.
+    pub fn next_frame(&mut self) -> TMTransferFrame {
+        let count = self.channels.len();
+        let served = (0..count)
+            .map(|offset| (self.next + offset) % count)
+            .find_map(|index| Some((index, self.channels[index].packer.pop_frame()?)));
+
+        let mut frame = match served {
+            Some((index, frame)) => {
+                self.next = (index + 1) % count;
+                frame
+            }
+            None => {
+                self.idle_frame_emitted();
+                self.idle_frame()
+            }
+        };
+        frame.primary_header.mc_frame_count = self.mc_frame_count;
+        self.mc_frame_count = self.mc_frame_count.wrapping_add(1);
+        frame
+    }
+
+    fn idle_frame(&mut self) -> TMTransferFrame {
+        let mut primary_header = self.idle_header;
+        primary_header.data_field_status.first_header_pointer = FirstHeaderPointer::OnlyIdleData;
+        self.idle_header.vc_frame_count = self.idle_header.vc_frame_count.wrapping_add(1);
+        TMTransferFrame {
+            primary_header,
+            secondary_header: None,
+            data_field: vec![IDLE_FILL; self.data_field_len],
+        }
+    }
+
+    /// Count the idle frame against every empty channel.
+    fn idle_frame_emitted(&mut self) {
+        for channel in &mut self.channels {
+            if channel.packer.pending_len() > 0 {
+                channel.idle_frames = 0;
+                continue;
+            }
+            channel.idle_frames += 1;
+
+            let limit = channel.max_idle_frames.or(self.max_idle_frames);
+            let exceeded = matches!(limit, Some(limit) if channel.idle_frames > limit);
+            if exceeded && !channel.idle_only && !channel.starving {
+                channel.starving = true;
+                self.events.push_back(MultiplexerEvent::Starvation {
+                    vcid: channel.packer.vcid(),
+                    idle_frames: channel.idle_frames,
+                });
+            }
+        }
+    }
+
+    /// Remove the oldest event not yet retrieved.
+    pub fn pop_event(&mut self) -> Option<MultiplexerEvent> {
+        self.events.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rstest::rstest;
+
+    const DATA_FIELD_LEN: usize = 64;
+
+    fn header(vcid: u8) -> TMPrimaryHeader {
+        TMPrimaryHeader::builder()
+            .scid(758)
+            .vcid(vcid)
+            .build()
+            .unwrap()
+    }
+
+    fn mux() -> VirtualChannelMultiplexer {
+        [1, 2].into_iter().fold(
+            VirtualChannelMultiplexer::new(header(7), DATA_FIELD_LEN).unwrap(),
+            |mux, vcid| {
+                mux.with_channel(TMFramePacker::new(header(vcid), DATA_FIELD_LEN).unwrap())
+                    .unwrap()
+            },
+        )
+    }
+
+    fn events(mux: &mut VirtualChannelMultiplexer) -> Vec<MultiplexerEvent> {
+        std::iter::from_fn(|| mux.pop_event()).collect()
+    }
+
+    #[test]
+    fn mux_round_robin() {
+        let mut mux = mux();
+        for _ in 0..2 {
+            mux.push(1, &SpacePacket::idle(DATA_FIELD_LEN - 6)).unwrap();
+            mux.push(2, &SpacePacket::idle(DATA_FIELD_LEN - 6)).unwrap();
+        }
+
+        let frames: Vec<TMTransferFrame> = (0..5).map(|_| mux.next_frame()).collect();
+        assert_eq!(
+            vec![(1, 0), (2, 1), (1, 2), (2, 3), (7, 4)],
+            frames
+                .iter()
+                .map(|frame| (
+                    frame.primary_header.vcid,
+                    frame.primary_header.mc_frame_count
+                ))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            FirstHeaderPointer::OnlyIdleData,
+            frames[4]
+                .primary_header
+                .data_field_status
+                .first_header_pointer
+        );
+        assert!(mux.push(3, &SpacePacket::idle(1)).is_err());
+    }
+
+    #[test]
+    fn mux_rejects_channels() {
+        let other_len = TMFramePacker::new(header(3), DATA_FIELD_LEN + 1).unwrap();
+        assert!(mux().with_channel(other_len).is_err());
+        let duplicate = TMFramePacker::new(header(1), DATA_FIELD_LEN).unwrap();
+        assert!(mux().with_channel(duplicate).is_err());
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(1)]
+    #[case(5)]
+    fn mux_starvation_boundary(#[case] limit: usize) {
+        let mut mux = mux().with_starvation_limit(limit);
+        for _ in 0..limit {
+            mux.next_frame();
+        }
+        assert_eq!(Vec::<MultiplexerEvent>::new(), events(&mut mux));
+
+        mux.next_frame();
+        assert_eq!(
+            vec![
+                MultiplexerEvent::Starvation {
+                    vcid: 1,
+                    idle_frames: limit + 1
+                },
+                MultiplexerEvent::Starvation {
+                    vcid: 2,
+                    idle_frames: limit + 1
+                },
+            ],
+            events(&mut mux)
+        );
+        // reported once while starving
+        mux.next_frame();
+        assert_eq!(Vec::<MultiplexerEvent>::new(), events(&mut mux));
+    }
+
+    #[test]
+    fn mux_starvation_reset() {
+        let mut mux = mux()
+            .with_starvation_limit(2)
+            .with_channel_starvation_limit(2, 4);
+        (0..3).for_each(|_| drop(mux.next_frame()));
+        assert_eq!(
+            vec![MultiplexerEvent::Starvation {
+                vcid: 1,
+                idle_frames: 3
+            }],
+            events(&mut mux)
+        );
+
+        // data clears the starvation and restarts the count
+        mux.push(1, &SpacePacket::idle(10)).unwrap();
+        assert_eq!(
+            vec![MultiplexerEvent::StarvationCleared { vcid: 1 }],
+            events(&mut mux)
+        );
+        // the partial frame of VC 1 is pending, only VC 2 is starved
+        (0..2).for_each(|_| drop(mux.next_frame()));
+        assert_eq!(
+            vec![MultiplexerEvent::Starvation {
+                vcid: 2,
+                idle_frames: 5
+            }],
+            events(&mut mux)
+        );
+
+        // complete the pending frame, the count restarts after it
+        mux.push(1, &SpacePacket::idle(DATA_FIELD_LEN - 16 - 6))
+            .unwrap();
+        assert_eq!(1, mux.next_frame().primary_header.vcid);
+        (0..3).for_each(|_| drop(mux.next_frame()));
+        assert_eq!(
+            vec![MultiplexerEvent::Starvation {
+                vcid: 1,
+                idle_frames: 3
+            }],
+            events(&mut mux)
+        );
+    }
+
+    #[test]
+    fn mux_idle_only_never_starves() {
+        let mut mux = mux().with_starvation_limit(1).with_idle_only(2);
+        (0..10).for_each(|_| drop(mux.next_frame()));
+        assert_eq!(
+            vec![MultiplexerEvent::Starvation {
+                vcid: 1,
+                idle_frames: 2
+            }],
+            events(&mut mux)
+        );
+    }
+}
@@ -0,0 +1,550 @@
+//! Packing of [SpacePacket]s into fixed length [TMTransferFrame]s.
+
+use std::{
+    collections::VecDeque,
+    io::{Error, ErrorKind},
+    sync::Arc,
+};
+
+#[cfg(doc)]
+use crate::tctm::clcw::Clcw;
+use crate::{tctm::clcw::ClcwSource, PrimaryHeader, SpacePacket};
+
+use super::{BooleanFieldFlag, FirstHeaderPointer, TMPrimaryHeader, TMTransferFrame};
+
+/// The largest possible TM Transfer Frame is 2048 bytes, 6 of which are the Primary Header.
+const MAX_DATA_FIELD_LEN: usize = 2048 - 6;
+
+/// The Operational Control Field is 4 bytes.
+const OCF_LEN: usize = 4;
+
+/// Packs a stream of [SpacePacket]s into fixed length [TMTransferFrame]s.
+///
+/// Packets are placed back to back in the frame data field. A packet which
+/// does not fit in the remainder of a frame continues at the start of the next frame(s)
+/// without any additional headers. The [FirstHeaderPointer] of every frame points to the
+/// first packet header which starts inside that frame, or is [FirstHeaderPointer::NoPacketStart]
+/// if the frame is entirely a continuation of a previous packet.
+///
+/// The master and virtual channel frame counts of the header template
+/// are incremented (modulo 256) for every frame produced.
+///
+/// With a [ClcwSource] the last 4 bytes of every data field are the Operational Control Field,
+/// see [TMFramePacker::with_clcw_source].
+#[derive(Debug, Clone)]
+pub struct TMFramePacker {
+    /// Template used for the primary header of every frame.
+    header: TMPrimaryHeader,
+    /// Length of the data field of every frame.
+    data_field_len: usize,
+    /// Encoded packet bytes not yet placed in a frame.
+    buffer: VecDeque<u8>,
+    /// Stream offsets at which packet headers start.
+    packet_starts: VecDeque<u64>,
+    /// Total number of stream bytes already placed in frames.
+    consumed: u64,
+    /// Total number of stream bytes ever pushed into this packer.
+    produced: u64,
+    /// Provides the CLCW of the Operational Control Field of every frame.
+    clcw_source: Option<Arc<dyn ClcwSource + Send + Sync>>,
+}
+impl TMFramePacker {
+    /// Create a new packer producing frames with a data field of exactly `data_field_len` bytes.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - `header` fails [TMPrimaryHeader::validate]
+    ///  - `data_field_len` is 0 or > 2042 bytes
+    pub fn new(header: TMPrimaryHeader, data_field_len: usize) -> Result<Self, Error> {
+        header.validate()?;
+
+        if data_field_len == 0 || data_field_len > MAX_DATA_FIELD_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "TM Data Field length must be in 1..={MAX_DATA_FIELD_LEN} but found {data_field_len}"
+                ),
+            ));
+        }
+
+        Ok(Self {
+            header,
+            data_field_len,
+            buffer: VecDeque::new(),
+            packet_starts: VecDeque::new(),
+            consumed: 0,
+            produced: 0,
+            clcw_source: None,
+        })
+    }
+
+    /// Embed the [Clcw] reported by the `source` in the Operational Control Field of every frame.
+    ///
+    /// The `source` is consulted when each frame is finalized by [Self::pop_frame] or
+    /// [Self::flush], not when its packets are pushed, so every frame carries the latest FARM state.
+    /// The last 4 bytes of the data field are reserved for the OCF.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the data field is too short to hold the OCF and at least one packet byte.
+    pub fn with_clcw_source<S: ClcwSource + Send + Sync + 'static>(
+        mut self,
+        source: S,
+    ) -> Result<Self, Error> {
+        if self.data_field_len <= OCF_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "TM Data Field length must be > {OCF_LEN} to contain an OCF but found {}",
+                    self.data_field_len
+                ),
+            ));
+        }
+        self.header.ocf_flag = BooleanFieldFlag::Present;
+        self.clcw_source = Some(Arc::new(source));
+        Ok(self)
+    }
+
+    /// The length of the data field of every frame produced.
+    pub fn data_field_len(&self) -> usize {
+        self.data_field_len
+    }
+
+    /// The Virtual Channel of every frame produced.
+    pub fn vcid(&self) -> u8 {
+        self.header.vcid
+    }
+
+    /// The length of the data field available to packets.
+    fn zone_len(&self) -> usize {
+        match self.clcw_source {
+            Some(_) => self.data_field_len - OCF_LEN,
+            None => self.data_field_len,
+        }
+    }
+
+    /// Number of bytes waiting to be placed in a frame.
+    pub fn pending_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Queue a packet to be placed in the next available frame(s).
+    ///
+    /// # Errors
+    ///
+    /// Errors as [SpacePacket::try_encode] if the packet cannot be encoded, nothing is queued.
+    pub fn push(&mut self, packet: &SpacePacket) -> Result<(), Error> {
+        self.push_encoded(packet.try_encode()?);
+        Ok(())
+    }
+
+    fn push_encoded(&mut self, bytes: Vec<u8>) {
+        self.packet_starts.push_back(self.produced);
+        self.produced += bytes.len() as u64;
+        self.buffer.extend(bytes);
+    }
+
+    /// Retrieve the next completely filled frame, if enough data has been queued.
+    pub fn pop_frame(&mut self) -> Option<TMTransferFrame> {
+        match self.buffer.len() >= self.zone_len() {
+            true => Some(self.next_frame()),
+            false => None,
+        }
+    }
+
+    /// Pad any partially filled frame with an Idle Packet and return all remaining frames.
+    ///
+    /// When fewer than 7 bytes remain in the last frame the Idle Packet spans
+    /// into as many additional frames as needed to hold it.
+    pub fn flush(&mut self) -> Vec<TMTransferFrame> {
+        let zone_len = self.zone_len();
+        let remainder = self.buffer.len() % zone_len;
+        if remainder != 0 {
+            let mut fill = zone_len - remainder;
+            while fill < SpacePacket::MIN_WIRE_LEN {
+                fill += zone_len;
+            }
+            self.push_encoded(SpacePacket::idle(fill - PrimaryHeader::WIRE_LEN).encode());
+        }
+
+        std::iter::from_fn(|| self.pop_frame()).collect()
+    }
+
+    fn next_frame(&mut self) -> TMTransferFrame {
+        let zone_len = self.zone_len();
+        let frame_end = self.consumed + zone_len as u64;
+
+        let first_header_pointer = match self.packet_starts.front() {
+            // the offset is below data_field_len, at most MAX_DATA_FIELD_LEN
+            Some(start) if *start < frame_end => {
+                FirstHeaderPointer::ByteIndex((start - self.consumed) as u16)
+            }
+            _ => FirstHeaderPointer::NoPacketStart,
+        };
+        while matches!(self.packet_starts.front(), Some(start) if *start < frame_end) {
+            self.packet_starts.pop_front();
+        }
+
+        let mut data_field: Vec<u8> = self.buffer.drain(..zone_len).collect();
+        if let Some(source) = &self.clcw_source {
+            data_field.extend(source.current_clcw(self.header.vcid).encode());
+        }
+        self.consumed = frame_end;
+
+        let mut primary_header = self.header;
+        primary_header.data_field_status.first_header_pointer = first_header_pointer;
+
+        self.header.mc_frame_count = self.header.mc_frame_count.wrapping_add(1);
+        self.header.vc_frame_count = self.header.vc_frame_count.wrapping_add(1);
+
+        TMTransferFrame {
+            primary_header,
+            secondary_header: None,
+            data_field,
+        }
+    }
+}
+impl Extend<SpacePacket> for TMFramePacker {
+    /// Queue every packet, completed frames are retrieved with [TMFramePacker::pop_frame].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a packet cannot be encoded, use [TMFramePacker::push] to handle the error.
+    fn extend<T: IntoIterator<Item = SpacePacket>>(&mut self, iter: T) {
+        for packet in iter {
+            if let Err(err) = self.push(&packet) {
+                panic!("{err}");
+            }
+        }
+    }
+}
+impl<'a> Extend<&'a SpacePacket> for TMFramePacker {
+    /// Queue every packet, completed frames are retrieved with [TMFramePacker::pop_frame].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a packet cannot be encoded, use [TMFramePacker::push] to handle the error.
+    fn extend<T: IntoIterator<Item = &'a SpacePacket>>(&mut self, iter: T) {
+        for packet in iter {
+            if let Err(err) = self.push(packet) {
+                panic!("{err}");
+            }
+        }
+    }
+}
+
+/// Pack an iterator of [SpacePacket]s into [TMTransferFrame]s.
+///
+/// ```
+/// # use spacepacket::{tctm::tm::{CollectFrames, TMFramePacker, TMPrimaryHeader}, SpacePacket};
+/// let packer = TMFramePacker::new(TMPrimaryHeader::builder().scid(758).build().unwrap(), 64).unwrap();
+///
+/// let frames = (0..10).map(|_| SpacePacket::idle(20)).collect_frames(packer)?;
+/// assert_eq!(5, frames.len());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub trait CollectFrames: Iterator<Item = SpacePacket> + Sized {
+    /// Push every packet into the `packer` and [flush](TMFramePacker::flush) it at the end,
+    /// returning all frames including those already completed in the `packer`.
+    ///
+    /// # Errors
+    ///
+    /// Stops at the first packet [TMFramePacker::push] rejects and returns its error.
+    fn collect_frames(self, mut packer: TMFramePacker) -> Result<Vec<TMTransferFrame>, Error> {
+        let mut frames = vec![];
+        for packet in self {
+            packer.push(&packet)?;
+            frames.extend(std::iter::from_fn(|| packer.pop_frame()));
+        }
+        frames.extend(packer.flush());
+        Ok(frames)
+    }
+}
+impl<I: Iterator<Item = SpacePacket>> CollectFrames for I {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{tctm::clcw::Clcw, test_util::packet, IDLE_APID};
+
+    use rstest::rstest;
+
+    const DATA_FIELD_LEN: usize = 64;
+
+    fn packer() -> TMFramePacker {
+        TMFramePacker::new(
+            TMPrimaryHeader::builder()
+                .scid(758)
+                .vcid(1)
+                .counts(0, 255)
+                .build()
+                .unwrap(),
+            DATA_FIELD_LEN,
+        )
+        .expect("Unable to create packer.")
+    }
+
+    fn pointers(frames: &[TMTransferFrame]) -> Vec<FirstHeaderPointer> {
+        frames
+            .iter()
+            .map(|frame| frame.primary_header.data_field_status.first_header_pointer)
+            .collect()
+    }
+
+    fn pack(packets: &[SpacePacket]) -> Vec<TMTransferFrame> {
+        let mut packer = packer();
+        let mut frames = vec![];
+        for packet in packets {
+            packer.push(packet).unwrap();
+            frames.extend(std::iter::from_fn(|| packer.pop_frame()));
+        }
+        frames.extend(packer.flush());
+        frames
+    }
+
+    #[rstest]
+    #[case(2042)]
+    #[should_panic]
+    #[case(0)]
+    #[should_panic]
+    #[case(2043)]
+    fn data_field_len_validation(#[case] len: usize) {
+        let header = packer().header;
+        TMFramePacker::new(header, len).unwrap();
+    }
+
+    #[test]
+    fn packet_spans_three_frames() {
+        let packets = [packet(0x42, 0, 2 * DATA_FIELD_LEN + 4), packet(0x42, 1, 14)];
+        let frames = pack(&packets);
+
+        assert_eq!(
+            vec![
+                FirstHeaderPointer::ByteIndex(0),
+                FirstHeaderPointer::NoPacketStart,
+                FirstHeaderPointer::ByteIndex(10),
+            ],
+            pointers(&frames)
+        );
+
+        let stream: Vec<u8> = frames.iter().flat_map(|f| f.data_field.clone()).collect();
+        let expected: Vec<u8> = packets.iter().flat_map(|p| p.encode()).collect();
+        assert_eq!(expected, stream[..expected.len()]);
+    }
+
+    #[test]
+    fn packet_fills_three_frames() {
+        let frames = pack(&[packet(0x42, 0, 3 * DATA_FIELD_LEN - 6), packet(0x42, 1, 14)]);
+
+        assert_eq!(
+            vec![
+                FirstHeaderPointer::ByteIndex(0),
+                FirstHeaderPointer::NoPacketStart,
+                FirstHeaderPointer::NoPacketStart,
+                FirstHeaderPointer::ByteIndex(0),
+            ],
+            pointers(&frames)
+        );
+    }
+
+    #[test]
+    fn packet_ends_at_frame_boundary() {
+        let frames = pack(&[packet(0x42, 0, DATA_FIELD_LEN - 6), packet(0x42, 1, 14)]);
+
+        assert_eq!(
+            vec![
+                FirstHeaderPointer::ByteIndex(0),
+                FirstHeaderPointer::ByteIndex(0),
+            ],
+            pointers(&frames)
+        );
+    }
+
+    #[test]
+    fn packet_ends_at_last_byte_of_data_zone() {
+        // the second packet starts mid-frame and its final byte
+        // is the last byte of the second frame's data zone
+        let frames = pack(&[
+            packet(0x42, 0, 4),
+            packet(0x42, 1, 2 * DATA_FIELD_LEN - 16),
+            packet(0x42, 2, 14),
+        ]);
+
+        assert_eq!(
+            vec![
+                FirstHeaderPointer::ByteIndex(0),
+                FirstHeaderPointer::NoPacketStart,
+                FirstHeaderPointer::ByteIndex(0),
+            ],
+            pointers(&frames)
+        );
+        assert_eq!(
+            &frames[1].data_field[DATA_FIELD_LEN - 1],
+            packet(0x42, 1, 2 * DATA_FIELD_LEN - 16)
+                .payload
+                .last()
+                .unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(DATA_FIELD_LEN, 20, 1)]
+    // fewer than 7 bytes remain so the idle packet spans into another frame
+    #[case(DATA_FIELD_LEN, DATA_FIELD_LEN - 3, 2)]
+    // frames shorter than an idle packet, it spans several frames
+    #[case(3, 7, 5)]
+    #[case(2, 7, 7)]
+    fn flush_pads_with_idle_packet(
+        #[case] data_field_len: usize,
+        #[case] len: usize,
+        #[case] n_frames: usize,
+    ) {
+        let mut packer = TMFramePacker::new(packer().header, data_field_len).unwrap();
+        packer.push(&packet(0x42, 0, len - 6)).unwrap();
+        let frames = packer.flush();
+
+        assert_eq!(n_frames, frames.len());
+
+        let stream: Vec<u8> = frames.iter().flat_map(|f| f.data_field.clone()).collect();
+        let idle = SpacePacket::decode(&mut &stream[len..]).expect("Unable to decode idle packet.");
+        assert_eq!(IDLE_APID, idle.primary_header.apid);
+        assert_eq!(stream.len(), len + idle.encode().len());
+    }
+
+    #[test]
+    fn extend_and_collect_frames() {
+        let packets: Vec<SpacePacket> = (0..10)
+            .map(|count| packet(0x42, count as u16, 7 + count))
+            .collect();
+
+        let mut extended = packer();
+        extended.extend(&packets[..5]);
+        extended.extend(packets[5..].iter().cloned());
+        let mut frames: Vec<TMTransferFrame> =
+            std::iter::from_fn(|| extended.pop_frame()).collect();
+        frames.extend(extended.flush());
+
+        assert_eq!(pack(&packets), frames);
+        assert_eq!(
+            frames,
+            packets.into_iter().collect_frames(packer()).unwrap()
+        );
+    }
+
+    #[test]
+    fn clcw_evaluated_at_frame_emission() {
+        use crate::tctm::farm::FarmBCounter;
+        use std::sync::{Arc, Mutex};
+
+        let farm = Arc::new(Mutex::new(FarmBCounter::new()));
+        let live = farm.clone();
+        let clcw = Clcw {
+            version: 0,
+            status: 0,
+            cop_in_effect: 1,
+            vcid: 3,
+            no_rf_available: false,
+            no_bit_lock: false,
+            lockout: false,
+            wait: false,
+            retransmit: false,
+            farm_b_counter: 0,
+            report_value: 17,
+        };
+        let mut packer = packer()
+            .with_clcw_source(move |_vcid: u8| clcw.with_farm_b_counter(&live.lock().unwrap()))
+            .unwrap();
+
+        // the packets of both frames are queued before the FARM accepts a frame
+        let packets = [packet(0x42, 0, DATA_FIELD_LEN + 14)];
+        packer.extend(&packets);
+        farm.lock().unwrap().increment();
+
+        let first = packer.pop_frame().unwrap();
+        farm.lock().unwrap().increment();
+        let frames = packer.flush();
+        assert_eq!(1, frames.len());
+
+        assert_eq!(DATA_FIELD_LEN, first.data_field.len());
+        assert_eq!(BooleanFieldFlag::Present, first.primary_header.ocf_flag);
+        assert_eq!(
+            vec![1, 2],
+            [&first, &frames[0]]
+                .iter()
+                .map(|frame| frame.clcw(false).unwrap().unwrap().farm_b_counter)
+                .collect::<Vec<_>>()
+        );
+
+        // the packets are placed around the OCF
+        let stream: Vec<u8> = [first, frames[0].clone()]
+            .iter()
+            .flat_map(|frame| frame.data_field[..DATA_FIELD_LEN - 4].to_vec())
+            .collect();
+        assert_eq!(
+            packets[0],
+            SpacePacket::decode(&mut stream.as_slice()).unwrap()
+        );
+    }
+
+    #[test]
+    fn clcw_source_data_field_len() {
+        let clcw = Clcw::decode(&mut [0x01, 0x00, 0x00, 0x00].as_slice()).unwrap();
+        let header = packer().header;
+        assert!(TMFramePacker::new(header, 4)
+            .unwrap()
+            .with_clcw_source(clcw)
+            .is_err());
+        assert!(TMFramePacker::new(header, 5)
+            .unwrap()
+            .with_clcw_source(clcw)
+            .is_ok());
+    }
+
+    #[test]
+    fn frame_counts_wrap() {
+        let frames = pack(&[packet(0x42, 0, 2 * DATA_FIELD_LEN - 6)]);
+
+        assert_eq!(
+            vec![(0, 255), (1, 0)],
+            frames
+                .iter()
+                .map(|f| (
+                    f.primary_header.mc_frame_count,
+                    f.primary_header.vc_frame_count
+                ))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn push_rejects_unencodable_packet() {
+        let mut packer = packer();
+        let mut out_of_range = SpacePacket::idle(4);
+        out_of_range.primary_header.apid = 0x800;
+        let mut empty = SpacePacket::idle(4);
+        empty.payload.clear();
+
+        for packet in [out_of_range, empty] {
+            let err = packer.push(&packet).unwrap_err();
+            assert_eq!(ErrorKind::InvalidInput, err.kind());
+        }
+        assert_eq!(0, packer.pending_len());
+        assert!(packer.pop_frame().is_none());
+
+        let err = [packet(0x42, 0, 4), SpacePacket::idle(0)]
+            .into_iter()
+            .collect_frames(packer)
+            .unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+    }
+
+    #[test]
+    #[should_panic(expected = "Field apid")]
+    fn extend_panics_on_unencodable_packet() {
+        let mut packet = SpacePacket::idle(4);
+        packet.primary_header.apid = 0x800;
+        packer().extend([packet]);
+    }
+}
@@ -0,0 +1,212 @@
+//! Iteration over the [SpacePacket]s in the data field of a single [TMTransferFrame].
+
+use std::io::{Error, ErrorKind};
+
+use crate::{tctm::extractor::PacketZone, PrimaryHeader, SpacePacket};
+
+use super::{FirstHeaderPointer, SynchronizationFlag, TMTransferFrame};
+
+/// A part of the packet zone of one frame, see [TMTransferFrame::packets].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TmPacket<'a> {
+    /// The bytes before the First Header Pointer, completing a packet started in an earlier frame.
+    Continuation(&'a [u8]),
+    /// A packet contained entirely in this frame.
+    Complete(SpacePacket),
+    /// The start of a packet continuing in the next frame.
+    Spanning(&'a [u8]),
+}
+impl<'a> TmPacket<'a> {
+    /// The packet, `None` for the parts of a packet spanning frames.
+    pub fn into_complete(self) -> Option<SpacePacket> {
+        match self {
+            Self::Complete(packet) => Some(packet),
+            Self::Continuation(_) | Self::Spanning(_) => None,
+        }
+    }
+}
+
+/// Iterator over the [TmPacket]s of one frame, created by [TMTransferFrame::packets].
+///
+/// [PacketExtractor](crate::tctm::extractor::PacketExtractor) reassembles packets across frames.
+#[derive(Debug)]
+pub struct TmPacketIter<'a> {
+    /// The remainder of the packet zone, `None` once the iterator is exhausted.
+    remaining: Option<&'a [u8]>,
+    /// The continuation bytes before the first packet header, not yet yielded.
+    continuation: &'a [u8],
+    /// An invalid First Header Pointer, yielded instead of any packet.
+    error: Option<Error>,
+}
+impl<'a> TmPacketIter<'a> {
+    pub(super) fn new(frame: &'a TMTransferFrame) -> Self {
+        let mut iter = Self {
+            remaining: None,
+            continuation: &[],
+            error: None,
+        };
+        let status = frame.primary_header.data_field_status;
+        let index = match (status.synchronization_flag, status.first_header_pointer) {
+            (SynchronizationFlag::Nominal, FirstHeaderPointer::ByteIndex(index)) => index as usize,
+            _ => return iter,
+        };
+
+        let zone = frame.packet_zone();
+        match index < zone.len() {
+            true => {
+                let (continuation, remaining) = zone.split_at(index);
+                iter.continuation = continuation;
+                iter.remaining = Some(remaining);
+            }
+            false => {
+                iter.error = Some(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "First Header Pointer {index} is outside the packet zone of length {}",
+                        zone.len()
+                    ),
+                ))
+            }
+        }
+        iter
+    }
+}
+impl<'a> Iterator for TmPacketIter<'a> {
+    type Item = Result<TmPacket<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.error.take() {
+            return Some(Err(err));
+        }
+        if !self.continuation.is_empty() {
+            return Some(Ok(TmPacket::Continuation(std::mem::take(
+                &mut self.continuation,
+            ))));
+        }
+
+        let remaining = self.remaining.filter(|remaining| !remaining.is_empty())?;
+        let packet_len = remaining
+            .get(PrimaryHeader::LENGTH_FIELD_RANGE)
+            .map(|length| {
+                usize::from(u16::from_be_bytes([length[0], length[1]]))
+                    + 1
+                    + PrimaryHeader::WIRE_LEN
+            });
+        match packet_len {
+            Some(packet_len) if packet_len <= remaining.len() => {
+                let (packet, rest) = remaining.split_at(packet_len);
+                let packet = SpacePacket::decode(&mut &packet[..]);
+                // a packet which cannot be decoded leaves the following boundaries unknown
+                self.remaining = packet.is_ok().then_some(rest);
+                Some(packet.map(TmPacket::Complete))
+            }
+            _ => {
+                self.remaining = None;
+                Some(Ok(TmPacket::Spanning(remaining)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{
+        tctm::tm::{BooleanFieldFlag, TMFramePacker, TMPrimaryHeader},
+        test_util::packet,
+    };
+
+    const DATA_FIELD_LEN: usize = 40;
+
+    fn frame(first_header_pointer: FirstHeaderPointer, data_field: Vec<u8>) -> TMTransferFrame {
+        TMTransferFrame {
+            primary_header: TMPrimaryHeader::builder()
+                .scid(758)
+                .first_header_pointer(first_header_pointer)
+                .build()
+                .unwrap(),
+            secondary_header: None,
+            data_field,
+        }
+    }
+
+    #[test]
+    fn tm_frame_packets() {
+        let header = TMPrimaryHeader::builder().scid(758).build().unwrap();
+        let mut packer = TMFramePacker::new(header, DATA_FIELD_LEN).unwrap();
+        let packets = [
+            packet(1, 0, 10),
+            packet(2, 0, 4),
+            packet(3, 0, 30),
+            packet(4, 0, 2),
+            packet(5, 0, 20),
+        ];
+        packer.extend(&packets);
+        let first = packer.pop_frame().unwrap();
+        let second = packer.pop_frame().unwrap();
+
+        let items: Vec<TmPacket> = first.packets().map(Result::unwrap).collect();
+        let spanning = packets[2].encode();
+        assert_eq!(
+            vec![
+                TmPacket::Complete(packets[0].clone()),
+                TmPacket::Complete(packets[1].clone()),
+                TmPacket::Spanning(&spanning[..DATA_FIELD_LEN - 16 - 10]),
+            ],
+            items
+        );
+
+        let items: Vec<TmPacket> = second.packets().map(Result::unwrap).collect();
+        assert_eq!(
+            TmPacket::Continuation(&spanning[DATA_FIELD_LEN - 16 - 10..]),
+            items[0]
+        );
+        assert_eq!(Some(packets[3].clone()), items[1].clone().into_complete());
+    }
+
+    #[test]
+    fn tm_frame_packets_none() {
+        let data_field = packet(1, 0, 34).encode();
+        for pointer in [
+            FirstHeaderPointer::OnlyIdleData,
+            FirstHeaderPointer::NoPacketStart,
+        ] {
+            assert_eq!(0, frame(pointer, data_field.clone()).packets().count());
+        }
+
+        let mut vca = frame(FirstHeaderPointer::ByteIndex(0), data_field.clone());
+        vca.primary_header.data_field_status.synchronization_flag = SynchronizationFlag::VcaSdu;
+        assert_eq!(0, vca.packets().count());
+
+        let packets: Vec<_> = frame(FirstHeaderPointer::ByteIndex(0), data_field)
+            .packets()
+            .map(|item| item.unwrap().into_complete())
+            .collect();
+        assert_eq!(vec![Some(packet(1, 0, 34))], packets);
+    }
+
+    #[test]
+    fn tm_frame_packets_errors() {
+        // the pointer is beyond the packet zone once the OCF is removed
+        let mut with_ocf = frame(FirstHeaderPointer::ByteIndex(37), vec![0; DATA_FIELD_LEN]);
+        with_ocf.primary_header.ocf_flag = BooleanFieldFlag::Present;
+        let mut items = with_ocf.packets();
+        assert_eq!(
+            ErrorKind::InvalidData,
+            items.next().unwrap().unwrap_err().kind()
+        );
+        assert!(items.next().is_none());
+
+        // a header too short to hold a length spans into the next frame
+        let short = frame(FirstHeaderPointer::ByteIndex(36), vec![0; DATA_FIELD_LEN]);
+        let items: Vec<_> = short.packets().map(Result::unwrap).collect();
+        assert_eq!(
+            vec![
+                TmPacket::Continuation(&[0; 36]),
+                TmPacket::Spanning(&[0; 4])
+            ],
+            items
+        );
+    }
+}
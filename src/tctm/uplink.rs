@@ -0,0 +1,471 @@
+//! Encoding of [SpacePacket]s all the way to CLTUs ready for the uplink.
+//!
+//! The [UplinkPipeline] applies every stage in the order required by CCSDS 231.0-B-4
+//! and 232.0-B-4: packets are blocked or segmented into TC Transfer Frames,
+//! the Frame Error Control Field (FECF) is appended, then the frame is randomized
+//! and finally BCH encoded into a CLTU.
+
+use std::io::{Error, ErrorKind};
+
+#[cfg(feature = "crc")]
+use crc::{Crc, CRC_16_IBM_3740};
+
+use crate::{
+    tctm::{
+        channel::ChannelId,
+        cltu::{self, EncodingScheme},
+        tc::{
+            BypassFlag, ControlFlag, TCPrimaryHeader, TCSegmentHeader, TCTransferFrame,
+            TcFrameLimits,
+        },
+    },
+    GroupingFlag, SpacePacket,
+};
+
+#[cfg(doc)]
+use crate::tctm::tc::FrameTooLong;
+
+/// The CRC used for the TC Frame Error Control Field.
+#[cfg(feature = "crc")]
+const FECF_CRC: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+
+/// Encodes [SpacePacket]s into CLTUs of Type-BD TC Transfer Frames on a single virtual channel.
+///
+/// ```
+/// # use spacepacket::{tctm::{channel::ChannelId, cltu::EncodingScheme, uplink::UplinkPipeline}, GroupingFlag, PacketType, SpacePacket};
+/// let pipeline = UplinkPipeline::new(ChannelId::new(758, 0).unwrap(), EncodingScheme::BCHRandomized)
+///     .with_segment_header(2)
+///     .unwrap();
+///
+/// let packet = SpacePacket::new(0, PacketType::Command, 17, GroupingFlag::Unsegm, 0, false, vec![0x42; 10]);
+/// let cltu = pipeline.encode_command(packet).unwrap();
+/// assert_eq!(&[0xEB, 0x90], &cltu[..2]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UplinkPipeline {
+    header: TCPrimaryHeader,
+    map_id: Option<u8>,
+    #[cfg(feature = "crc")]
+    fecf: bool,
+    encoding: EncodingScheme,
+    limits: TcFrameLimits,
+}
+impl UplinkPipeline {
+    /// The longest TC Transfer Frame, including all headers and the FECF.
+    pub const MAX_FRAME_LEN: usize = 1024;
+
+    /// Create a pipeline for the `channel` without a Segment Header or FECF,
+    /// producing frames up to [Self::MAX_FRAME_LEN] bytes.
+    pub fn new(channel: ChannelId, encoding: EncodingScheme) -> Self {
+        Self {
+            header: TCPrimaryHeader {
+                tfvn: 0,
+                bypass_flag: BypassFlag::TypeB,
+                control_flag: ControlFlag::TypeD,
+                scid: channel.scid,
+                vcid: channel.vcid,
+                sequence_number: 0,
+            },
+            map_id: None,
+            #[cfg(feature = "crc")]
+            fecf: false,
+            encoding,
+            limits: TcFrameLimits::default(),
+        }
+    }
+
+    /// Start the payload of every frame with a [TCSegmentHeader] for the given MAP ID.
+    /// Packets longer than a single frame can only be segmented with a Segment Header.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `map_id` > 63.
+    pub fn with_segment_header(mut self, map_id: u8) -> Result<Self, Error> {
+        TCSegmentHeader {
+            sequence_flags: GroupingFlag::Unsegm,
+            map_id,
+        }
+        .validate()?;
+        self.map_id = Some(map_id);
+        self.check_data_len()?;
+        Ok(self)
+    }
+
+    /// Append the 2 byte Frame Error Control Field to every frame.
+    #[cfg(feature = "crc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+    pub fn with_fecf(mut self) -> Result<Self, Error> {
+        self.fecf = true;
+        self.check_data_len()?;
+        Ok(self)
+    }
+
+    /// Limit the length of the produced TC Transfer Frames, including all headers and the FECF.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - `max_frame_len` > [Self::MAX_FRAME_LEN]
+    ///  - `max_frame_len` leaves no room for data after the headers and FECF
+    pub fn with_max_frame_len(self, max_frame_len: usize) -> Result<Self, Error> {
+        self.with_frame_limits(TcFrameLimits::new(max_frame_len))
+    }
+
+    /// Produce frames within the mission `limits`, packets too long for a frame are
+    /// segmented by [Self::encode_commands] to fit them.
+    ///
+    /// # Errors
+    ///
+    /// This function errors under the following circumstances
+    ///  - `limits` fail [TcFrameLimits::validate]
+    ///  - `limits` leave no room for data after the headers and FECF
+    pub fn with_frame_limits(mut self, limits: TcFrameLimits) -> Result<Self, Error> {
+        limits.validate()?;
+        self.limits = limits;
+        self.check_data_len()?;
+        Ok(self)
+    }
+
+    /// The limits of the produced frames.
+    pub fn frame_limits(&self) -> &TcFrameLimits {
+        &self.limits
+    }
+
+    /// The number of bytes of frame overhead, excluding the packet data.
+    fn overhead_len(&self) -> usize {
+        let segment_header_len = match self.map_id {
+            Some(_) => 1,
+            None => 0,
+        };
+        #[cfg(feature = "crc")]
+        let fecf_len = match self.fecf {
+            true => 2,
+            false => 0,
+        };
+        #[cfg(not(feature = "crc"))]
+        let fecf_len = 0;
+
+        5 + segment_header_len + fecf_len
+    }
+
+    /// The maximum number of packet bytes in a single frame.
+    pub fn max_data_len(&self) -> usize {
+        self.limits
+            .max_frame_len
+            .saturating_sub(self.overhead_len())
+    }
+
+    fn check_data_len(&self) -> Result<(), Error> {
+        if self.max_data_len() == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Maximum frame length {} leaves no room for data after {} bytes of headers",
+                    self.limits.max_frame_len,
+                    self.overhead_len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Encode a single packet into the CLTU of a single frame.
+    ///
+    /// # Errors
+    ///
+    /// Errors with a [FrameTooLong] if the encoded packet is longer than [Self::max_data_len],
+    /// use [Self::encode_commands] to segment it across multiple frames.
+    pub fn encode_command(&self, packet: SpacePacket) -> Result<Vec<u8>, Error> {
+        let data = packet.encode();
+        self.limits
+            .check_frame_len(data.len() + self.overhead_len())?;
+        self.encode_frame(GroupingFlag::Unsegm, &data)
+    }
+
+    /// Encode packets into the CLTUs of as few frames as possible.
+    ///
+    /// Consecutive packets which fit into a single frame are blocked together,
+    /// packets longer than [Self::max_data_len] are segmented across frames.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a packet must be segmented but the pipeline has no Segment Header.
+    pub fn encode_commands(&self, packets: &[SpacePacket]) -> Result<Vec<Vec<u8>>, Error> {
+        let max_data_len = self.max_data_len();
+        let mut cltus = vec![];
+        let mut block = vec![];
+
+        for packet in packets {
+            let data = packet.encode();
+            if block.len() + data.len() > max_data_len && !block.is_empty() {
+                cltus.push(self.encode_frame(GroupingFlag::Unsegm, &block)?);
+                block.clear();
+            }
+
+            if data.len() <= max_data_len {
+                block.extend(data);
+                continue;
+            }
+
+            if self.map_id.is_none() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Packet of length {} must be segmented but no Segment Header is configured",
+                        data.len()
+                    ),
+                ));
+            }
+
+            let segments = data.chunks(max_data_len).count();
+            for (index, segment) in data.chunks(max_data_len).enumerate() {
+                let sequence_flags = match index {
+                    0 => GroupingFlag::First,
+                    index if index == segments - 1 => GroupingFlag::Last,
+                    _ => GroupingFlag::Interm,
+                };
+                cltus.push(self.encode_frame(sequence_flags, segment)?);
+            }
+        }
+
+        if !block.is_empty() {
+            cltus.push(self.encode_frame(GroupingFlag::Unsegm, &block)?);
+        }
+
+        Ok(cltus)
+    }
+
+    /// Wrap the `data` in a TC Transfer Frame, append the FECF and encode the CLTU.
+    fn encode_frame(&self, sequence_flags: GroupingFlag, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut payload = Vec::with_capacity(self.limits.max_frame_len);
+        if let Some(map_id) = self.map_id {
+            payload.push(
+                TCSegmentHeader {
+                    sequence_flags,
+                    map_id,
+                }
+                .encode(),
+            );
+        }
+        payload.extend_from_slice(data);
+
+        #[cfg(feature = "crc")]
+        if self.fecf {
+            // reserve the FECF so it is counted in the frame length
+            payload.extend([0_u8; 2]);
+        }
+
+        #[allow(unused_mut)]
+        let mut frame =
+            TCTransferFrame::new_with_limits(self.header, payload, &self.limits)?.encode();
+
+        #[cfg(feature = "crc")]
+        if self.fecf {
+            let fecf_start = frame.len() - 2;
+            let fecf = FECF_CRC.checksum(&frame[..fecf_start]);
+            frame[fecf_start..].copy_from_slice(&fecf.to_be_bytes());
+        }
+
+        Ok(cltu::generate_ctlu(frame, self.encoding))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{
+        tctm::{
+            cltu::test::{CLTU_01, CLTU_02, TC_FRAME_01, TC_FRAME_02},
+            randomizer::{apply_randomization, Randomization},
+            tc::FrameTooLong,
+        },
+        test_util::command,
+    };
+
+    use rstest::rstest;
+
+    /// The packet segmented into the SpacePyLibrary test frames.
+    fn spacepy_packet() -> SpacePacket {
+        // strip the primary header, segment header and FECF from both frames
+        let data = [
+            &TC_FRAME_01[6..TC_FRAME_01.len() - 2],
+            &TC_FRAME_02[6..TC_FRAME_02.len() - 2],
+        ]
+        .concat();
+        SpacePacket::decode(&mut data.as_slice()).unwrap()
+    }
+
+    fn spacepy_pipeline(encoding: EncodingScheme) -> UplinkPipeline {
+        UplinkPipeline::new(ChannelId::new(758, 0).unwrap(), encoding)
+            .with_segment_header(2)
+            .unwrap()
+            .with_fecf()
+            .unwrap()
+            .with_max_frame_len(TC_FRAME_01.len())
+            .unwrap()
+    }
+
+    #[test]
+    fn uplink_spacepy() {
+        let cltus = spacepy_pipeline(EncodingScheme::BCH)
+            .encode_commands(&[spacepy_packet()])
+            .unwrap();
+
+        assert_eq!(vec![CLTU_01.to_vec(), CLTU_02.to_vec()], cltus);
+    }
+
+    #[test]
+    fn uplink_spacepy_randomized() {
+        // FECF is computed before randomization, randomization before BCH
+        let expected: Vec<Vec<u8>> = [TC_FRAME_01, TC_FRAME_02]
+            .into_iter()
+            .map(|frame| {
+                cltu::generate_ctlu(
+                    apply_randomization(frame, Randomization::TC),
+                    EncodingScheme::BCH,
+                )
+            })
+            .collect();
+
+        let cltus = spacepy_pipeline(EncodingScheme::BCHRandomized)
+            .encode_commands(&[spacepy_packet()])
+            .unwrap();
+
+        assert_eq!(expected, cltus);
+    }
+
+    #[rstest]
+    #[case(None)]
+    #[case(Some(7))]
+    fn uplink_single(#[case] map_id: Option<u8>) {
+        let mut pipeline =
+            UplinkPipeline::new(ChannelId::new(758, 3).unwrap(), EncodingScheme::BCH);
+        if let Some(map_id) = map_id {
+            pipeline = pipeline.with_segment_header(map_id).unwrap();
+        }
+        let expected = command(17, 4, 20);
+
+        let mut payload = vec![];
+        if let Some(map_id) = map_id {
+            payload.push(0xC0 | map_id);
+        }
+        payload.extend(expected.encode());
+        let frame = TCTransferFrame::new(
+            TCPrimaryHeader {
+                tfvn: 0,
+                bypass_flag: BypassFlag::TypeB,
+                control_flag: ControlFlag::TypeD,
+                scid: 758,
+                vcid: 3,
+                sequence_number: 0,
+            },
+            payload,
+        )
+        .unwrap();
+
+        assert_eq!(
+            cltu::generate_ctlu(frame.encode(), EncodingScheme::BCH),
+            pipeline.encode_command(expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn uplink_blocking() {
+        let pipeline = UplinkPipeline::new(ChannelId::new(758, 3).unwrap(), EncodingScheme::BCH)
+            .with_max_frame_len(5 + 60)
+            .unwrap();
+
+        // two 26 byte packets share a frame, the third starts a new one
+        let packets = [command(17, 0, 20), command(17, 1, 20), command(17, 2, 20)];
+        let cltus = pipeline.encode_commands(&packets).unwrap();
+        assert_eq!(2, cltus.len());
+        assert_eq!(
+            cltus[1],
+            pipeline.encode_command(packets[2].clone()).unwrap()
+        );
+    }
+
+    #[test]
+    fn uplink_frame_limits() {
+        let limits = TcFrameLimits::new(256);
+        let pipeline = UplinkPipeline::new(ChannelId::new(758, 3).unwrap(), EncodingScheme::BCH)
+            .with_segment_header(1)
+            .unwrap()
+            .with_frame_limits(limits)
+            .unwrap();
+        assert_eq!(&limits, pipeline.frame_limits());
+        assert_eq!(250, pipeline.max_data_len());
+
+        let expected = command(17, 9, 600);
+
+        // a single frame is rejected naming the limit and the attempted length
+        let err = pipeline.encode_command(expected.clone()).unwrap_err();
+        assert_eq!(
+            Some(&FrameTooLong {
+                limit: 256,
+                len: 606 + 6
+            }),
+            err.get_ref().and_then(|inner| inner.downcast_ref())
+        );
+
+        // the segmenter splits the packet into frames within the same limits
+        let frames: Vec<TCTransferFrame> = pipeline
+            .encode_commands(std::slice::from_ref(&expected))
+            .unwrap()
+            .into_iter()
+            .map(|cltu| {
+                let frame = cltu::decode(&cltu, EncodingScheme::BCH).unwrap();
+                TCTransferFrame::decode(&mut frame.as_slice()).unwrap()
+            })
+            .collect();
+        assert_eq!(
+            vec![256, 256, 5 + 1 + 106],
+            frames
+                .iter()
+                .map(|frame| frame.encode().len())
+                .collect::<Vec<_>>()
+        );
+        let data: Vec<u8> = frames
+            .iter()
+            .flat_map(|frame| frame.payload()[1..].to_vec())
+            .collect();
+        assert_eq!(expected, SpacePacket::decode(&mut data.as_slice()).unwrap());
+
+        assert!(pipeline
+            .with_frame_limits(TcFrameLimits::new(1025))
+            .is_err());
+        assert!(TCTransferFrame::new_with_limits(
+            pipeline.header,
+            vec![0; 252],
+            pipeline.frame_limits()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn uplink_errors() {
+        let pipeline = UplinkPipeline::new(ChannelId::new(758, 3).unwrap(), EncodingScheme::BCH)
+            .with_max_frame_len(5 + 20)
+            .unwrap();
+
+        // too long for a single frame
+        assert!(pipeline.encode_command(command(17, 0, 20)).is_err());
+        // segmenting requires a segment header
+        assert!(pipeline.encode_commands(&[command(17, 0, 20)]).is_err());
+        assert_eq!(
+            2,
+            pipeline
+                .with_segment_header(0)
+                .unwrap()
+                .encode_commands(&[command(17, 0, 20)])
+                .unwrap()
+                .len()
+        );
+
+        assert!(pipeline.with_max_frame_len(1025).is_err());
+        assert!(pipeline.with_max_frame_len(5).is_err());
+        assert!(pipeline.with_max_frame_len(7).unwrap().with_fecf().is_err());
+        assert!(pipeline.with_segment_header(64).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+//! Fixtures shared by the unit tests, `tests/common` holds their counterparts for
+//! the integration tests.
+
+use crate::{GroupingFlag, PacketType, SpacePacket};
+
+/// An unsegmented telemetry packet carrying `payload_len` bytes counting up from 0.
+pub(crate) fn packet(apid: u16, sequence_count: u16, payload_len: usize) -> SpacePacket {
+    packet_with_payload(
+        apid,
+        sequence_count,
+        (0..payload_len).map(|val| val as u8).collect(),
+    )
+}
+
+/// An unsegmented telemetry packet carrying the `payload`.
+pub(crate) fn packet_with_payload(apid: u16, sequence_count: u16, payload: Vec<u8>) -> SpacePacket {
+    SpacePacket::new(
+        0,
+        PacketType::Telemetry,
+        apid,
+        GroupingFlag::Unsegm,
+        sequence_count,
+        false,
+        payload,
+    )
+}
+
+/// A [packet] of the command packet type.
+#[cfg(feature = "tctm")]
+pub(crate) fn command(apid: u16, sequence_count: u16, payload_len: usize) -> SpacePacket {
+    let mut command = packet(apid, sequence_count, payload_len);
+    command.primary_header.packet_type = PacketType::Command;
+    command
+}
@@ -0,0 +1,293 @@
+//! Packet Error Control fields appended to the end of a [SpacePacket].
+//!
+//! CCSDS 133.0-B-2 leaves the error control of a packet to the mission.
+//! Any check which fits in a fixed number of trailing bytes can implement [TrailerCheck]
+//! and be used with [SpacePacket::encode_with_trailer] and [SpacePacket::decode_with_trailer].
+//! With feature `crc` the CRC-16 used by [SpacePacket::encode_crc] and the CRC-32 used by
+//! [SpacePacket::encode_crc32] implement it for [Crc].
+//! Any lookup table size of [Crc] can be shared between framers and codecs as a [Crc16].
+//!
+//! ```
+//! # use spacepacket::{trailer::{CheckedPacket, TrailerCheck}, GroupingFlag, PacketType, SpacePacket};
+//! /// A one byte sum of every byte in the packet.
+//! struct Checksum;
+//! impl TrailerCheck for Checksum {
+//!     fn width(&self) -> usize {
+//!         1
+//!     }
+//!
+//!     fn compute(&self, data: &[u8], out: &mut [u8]) {
+//!         out[0] = data.iter().fold(0_u8, |sum, byte| sum.wrapping_add(*byte));
+//!     }
+//! }
+//!
+//! let packet = SpacePacket::new(0, PacketType::Command, 17, GroupingFlag::Unsegm, 0, false, vec![0x42]);
+//! let encoded = packet.encode_with_trailer(&Checksum).unwrap();
+//! assert_eq!(
+//!     CheckedPacket::Valid(packet),
+//!     SpacePacket::decode_with_trailer(&mut encoded.as_slice(), &Checksum).unwrap()
+//! );
+//! ```
+
+#[cfg(feature = "crc")]
+use {
+    crc::{Crc, NoTable, Table},
+    std::sync::Arc,
+};
+
+use crate::SpacePacket;
+
+/// An error control field of fixed width appended to an encoded packet.
+///
+/// The trailer counts towards the Packet Data Length of the packet.
+pub trait TrailerCheck {
+    /// The number of bytes in the trailer.
+    fn width(&self) -> usize;
+
+    /// Compute the trailer over the encoded packet `data` preceding it,
+    /// writing exactly [Self::width] bytes to `out`.
+    fn compute(&self, data: &[u8], out: &mut [u8]);
+
+    /// Whether the `trailer` received after the encoded packet `data` is valid.
+    ///
+    /// By default the trailer is recomputed and compared,
+    /// checks which can be verified more cheaply may override this.
+    fn verify(&self, data: &[u8], trailer: &[u8]) -> bool {
+        let mut computed = vec![0_u8; self.width()];
+        self.compute(data, &mut computed);
+        computed == trailer
+    }
+}
+
+#[cfg(feature = "crc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+/// The big-endian CRC-16 used by [SpacePacket::encode_crc].
+impl TrailerCheck for Crc<u16> {
+    fn width(&self) -> usize {
+        std::mem::size_of::<u16>()
+    }
+
+    fn compute(&self, data: &[u8], out: &mut [u8]) {
+        out.copy_from_slice(&self.checksum(data).to_be_bytes());
+    }
+
+    fn verify(&self, data: &[u8], trailer: &[u8]) -> bool {
+        self.checksum(data).to_be_bytes() == trailer
+    }
+}
+
+#[cfg(feature = "crc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+/// The big-endian CRC-32 used by [SpacePacket::encode_crc32].
+impl TrailerCheck for Crc<u32> {
+    fn width(&self) -> usize {
+        std::mem::size_of::<u32>()
+    }
+
+    fn compute(&self, data: &[u8], out: &mut [u8]) {
+        out.copy_from_slice(&self.checksum(data).to_be_bytes());
+    }
+
+    fn verify(&self, data: &[u8], trailer: &[u8]) -> bool {
+        self.checksum(data).to_be_bytes() == trailer
+    }
+}
+
+#[cfg(feature = "crc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+/// A CRC-16 over any lookup table size of the [crc] crate.
+///
+/// Framers and codecs hold a shared `Arc<dyn Crc16>`, so one large table
+/// can be built once and used by every connection.
+/// ```
+/// # use std::sync::Arc;
+/// # use crc::{Crc, Table, CRC_16_IBM_3740};
+/// # use spacepacket::{codec::SpacePacketCodec, trailer::Crc16};
+/// static CRC: Crc<u16, Table<16>> = Crc::<u16, Table<16>>::new(&CRC_16_IBM_3740);
+///
+/// let shared: Arc<dyn Crc16> = Arc::new(&CRC);
+/// let codec = SpacePacketCodec::new([0x1A, 0xCF]).with_shared_crc(shared.clone());
+/// ```
+pub trait Crc16: Send + Sync {
+    /// The CRC of `bytes`.
+    fn checksum(&self, bytes: &[u8]) -> u16;
+}
+
+#[cfg(feature = "crc")]
+impl Crc16 for Crc<u16, NoTable> {
+    fn checksum(&self, bytes: &[u8]) -> u16 {
+        Crc::<u16, NoTable>::checksum(self, bytes)
+    }
+}
+
+#[cfg(feature = "crc")]
+impl Crc16 for Crc<u16, Table<1>> {
+    fn checksum(&self, bytes: &[u8]) -> u16 {
+        Crc::<u16, Table<1>>::checksum(self, bytes)
+    }
+}
+
+#[cfg(feature = "crc")]
+impl Crc16 for Crc<u16, Table<16>> {
+    fn checksum(&self, bytes: &[u8]) -> u16 {
+        Crc::<u16, Table<16>>::checksum(self, bytes)
+    }
+}
+
+#[cfg(feature = "crc")]
+impl<C: Crc16 + ?Sized> Crc16 for &'static C {
+    fn checksum(&self, bytes: &[u8]) -> u16 {
+        (**self).checksum(bytes)
+    }
+}
+
+#[cfg(feature = "crc")]
+impl<C: Crc16 + ?Sized> Crc16 for Arc<C> {
+    fn checksum(&self, bytes: &[u8]) -> u16 {
+        (**self).checksum(bytes)
+    }
+}
+
+#[cfg(feature = "crc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+/// The big-endian CRC-16 used by [SpacePacket::encode_crc].
+impl TrailerCheck for dyn Crc16 + '_ {
+    fn width(&self) -> usize {
+        std::mem::size_of::<u16>()
+    }
+
+    fn compute(&self, data: &[u8], out: &mut [u8]) {
+        out.copy_from_slice(&self.checksum(data).to_be_bytes());
+    }
+
+    fn verify(&self, data: &[u8], trailer: &[u8]) -> bool {
+        self.checksum(data).to_be_bytes() == trailer
+    }
+}
+
+/// A packet decoded by [SpacePacket::decode_with_trailer].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckedPacket {
+    /// The packet with a valid trailer, which is stripped from the payload.
+    Valid(SpacePacket),
+    /// The trailer of the packet is invalid and the packet was discarded.
+    Invalid {
+        /// The trailer sent with the packet.
+        sent: Vec<u8>,
+        /// The trailer computed over the received packet.
+        computed: Vec<u8>,
+        /// The received primary header and payload the trailer was computed over,
+        /// retained for best-effort recovery.
+        data: Vec<u8>,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_util::packet;
+
+    #[cfg(feature = "crc")]
+    use crc::CRC_16_IBM_3740;
+    use rstest::rstest;
+
+    /// A 4 byte trailer repeating the XOR of all bytes.
+    struct XorCheck;
+    impl TrailerCheck for XorCheck {
+        fn width(&self) -> usize {
+            4
+        }
+
+        fn compute(&self, data: &[u8], out: &mut [u8]) {
+            out.fill(data.iter().fold(0, |acc, byte| acc ^ byte));
+        }
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(100)]
+    #[case(SpacePacket::MAX_PAYLOAD_LEN - 4)]
+    fn trailer_roundtrip(#[case] payload_len: usize) {
+        let expected = packet(17, 3, payload_len);
+        let encoded = expected.encode_with_trailer(&XorCheck).unwrap();
+        assert_eq!(6 + payload_len + 4, encoded.len());
+
+        assert_eq!(
+            CheckedPacket::Valid(expected),
+            SpacePacket::decode_with_trailer(&mut encoded.as_slice(), &XorCheck).unwrap()
+        );
+    }
+
+    #[test]
+    fn trailer_invalid() {
+        let mut encoded = packet(17, 3, 10).encode_with_trailer(&XorCheck).unwrap();
+        encoded[8] ^= 0x01;
+
+        let sent = encoded[encoded.len() - 4..].to_vec();
+        let computed = sent.iter().map(|byte| byte ^ 0x01).collect();
+        let data = encoded[..encoded.len() - 4].to_vec();
+        assert_eq!(
+            CheckedPacket::Invalid {
+                sent,
+                computed,
+                data
+            },
+            SpacePacket::decode_with_trailer(&mut encoded.as_slice(), &XorCheck).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(SpacePacket::MAX_PAYLOAD_LEN - 3)]
+    fn trailer_encode_invalid_len(#[case] payload_len: usize) {
+        assert!(packet(17, 3, payload_len)
+            .encode_with_trailer(&XorCheck)
+            .is_err());
+    }
+
+    #[test]
+    fn trailer_too_short() {
+        // a 4 byte payload holds nothing but the trailer
+        let encoded = packet(17, 3, 4).encode();
+        assert_eq!(
+            std::io::ErrorKind::InvalidData,
+            SpacePacket::decode_with_trailer(&mut encoded.as_slice(), &XorCheck)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "crc")]
+    fn trailer_crc() {
+        let crc = Crc::<u16>::new(&CRC_16_IBM_3740);
+        let expected = packet(17, 3, 10);
+
+        let encoded = expected.encode_with_trailer(&crc).unwrap();
+        assert_eq!(expected.encode_crc(&crc).unwrap(), encoded);
+        assert_eq!(&crc.checksum(&encoded[..16]).to_be_bytes(), &encoded[16..]);
+    }
+
+    #[test]
+    #[cfg(feature = "crc")]
+    fn trailer_crc16_tables() {
+        static SLICE16: Crc<u16, Table<16>> = Crc::<u16, Table<16>>::new(&CRC_16_IBM_3740);
+        let crc = Crc::<u16>::new(&CRC_16_IBM_3740);
+        let expected = packet(17, 3, 100).encode_with_trailer(&crc).unwrap();
+
+        let implementations: [Arc<dyn Crc16>; 3] = [
+            Arc::new(Crc::<u16, NoTable>::new(&CRC_16_IBM_3740)),
+            Arc::new(crc),
+            Arc::new(&SLICE16),
+        ];
+        for implementation in implementations {
+            assert_eq!(
+                expected,
+                packet(17, 3, 100)
+                    .encode_with_trailer(implementation.as_ref())
+                    .unwrap()
+            );
+        }
+    }
+}
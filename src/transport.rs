@@ -0,0 +1,389 @@
+//! Reassembly of frames forwarded over transports which fragment them into tagged chunks.
+//!
+//! Stations often forward frames over UDP split into chunks tagged with a frame id, the chunk
+//! index and the number of chunks. The [FrameReassembler] collects the chunks of every frame
+//! in any order and yields the complete frame, ready for a decoder such as
+//! `TMTransferFrame::decode`.
+//!
+//! ```
+//! # use std::time::Duration;
+//! # use spacepacket::transport::FrameReassembler;
+//! let mut reassembler = FrameReassembler::new(Duration::from_secs(2));
+//! let now = Duration::from_secs(10);
+//!
+//! assert_eq!(Ok(None), reassembler.push(7_u32, 1, 2, &[0x03, 0x04], now));
+//! assert_eq!(
+//!     Ok(Some(vec![0x01, 0x02, 0x03, 0x04])),
+//!     reassembler.push(7, 0, 2, &[0x01, 0x02], now)
+//! );
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    hash::Hash,
+    time::Duration,
+};
+
+use crate::limits::{DecodeLimits, Limit, ResourceLimit};
+
+/// A chunk was rejected by a [FrameReassembler].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReassemblyError {
+    /// The chunk index is not below the number of chunks, or the frame has no chunks.
+    InvalidIndex {
+        /// The index of the chunk.
+        index: usize,
+        /// The number of chunks the chunk declared.
+        total: usize,
+    },
+    /// The chunk declared a different number of chunks than the earlier chunks of its frame.
+    /// The partial frame is discarded.
+    TotalMismatch {
+        /// The number of chunks declared by the earlier chunks.
+        expected: usize,
+        /// The number of chunks declared by this chunk.
+        total: usize,
+    },
+    /// The frame grew beyond [DecodeLimits::max_item_len] bytes, or a single chunk does not fit
+    /// in [DecodeLimits::max_buffered]. The partial frame is discarded.
+    ResourceLimit(ResourceLimit),
+}
+impl Display for ReassemblyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidIndex { index, total } => {
+                write!(
+                    f,
+                    "Chunk index {index} is invalid for a frame of {total} chunks"
+                )
+            }
+            Self::TotalMismatch { expected, total } => write!(
+                f,
+                "Chunk declares a frame of {total} chunks but earlier chunks declared {expected}"
+            ),
+            Self::ResourceLimit(limit) => write!(f, "{limit}"),
+        }
+    }
+}
+impl std::error::Error for ReassemblyError {}
+impl From<ReassemblyError> for std::io::Error {
+    fn from(err: ReassemblyError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// The chunks received for one frame.
+#[derive(Debug, Clone)]
+struct PartialFrame {
+    total: usize,
+    chunks: BTreeMap<usize, Vec<u8>>,
+    len: usize,
+    first_seen: Duration,
+}
+
+/// Collects the chunks of frames keyed by a caller supplied id and yields every
+/// frame once all of its chunks arrived.
+///
+/// Chunks may arrive in any order and interleaved with the chunks of other frames.
+/// Repeated chunks are ignored. A frame still incomplete `timeout` after its first chunk
+/// arrived is discarded, time is only measured by the timestamps given to [Self::push] and
+/// [Self::expire]. When the chunks held would exceed [DecodeLimits::max_buffered] bytes the
+/// oldest partial frames are evicted.
+#[derive(Debug, Clone)]
+pub struct FrameReassembler<K> {
+    frames: HashMap<K, PartialFrame>,
+    timeout: Duration,
+    limits: DecodeLimits,
+    /// The number of bytes held across all partial frames.
+    buffered: usize,
+    duplicates: u64,
+    expired: u64,
+    evicted: u64,
+}
+impl<K: Eq + Hash + Clone> FrameReassembler<K> {
+    /// Create a reassembler discarding frames incomplete `timeout` after their first chunk.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            frames: HashMap::new(),
+            timeout,
+            limits: DecodeLimits::default(),
+            buffered: 0,
+            duplicates: 0,
+            expired: 0,
+            evicted: 0,
+        }
+    }
+
+    /// Cap the memory spent on partial frames, see [DecodeLimits].
+    ///
+    /// [DecodeLimits::max_item_len] bounds every frame and [DecodeLimits::max_buffered]
+    /// the chunks held across all frames.
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Add chunk `index` of the `total` chunks of frame `id`, received at time `now`.
+    ///
+    /// Returns the complete frame once its last missing chunk arrives.
+    ///
+    /// # Errors
+    ///
+    /// Errors with a [ReassemblyError] if the chunk is invalid or exceeds a limit.
+    pub fn push(
+        &mut self,
+        id: K,
+        index: usize,
+        total: usize,
+        bytes: &[u8],
+        now: Duration,
+    ) -> Result<Option<Vec<u8>>, ReassemblyError> {
+        self.expire(now);
+
+        if index >= total {
+            return Err(ReassemblyError::InvalidIndex { index, total });
+        }
+        if bytes.len() > self.limits.max_buffered() {
+            self.remove(&id);
+            return Err(ReassemblyError::ResourceLimit(ResourceLimit {
+                which: Limit::BufferedBytes,
+                limit: self.limits.max_buffered(),
+            }));
+        }
+
+        let frame = self.frames.entry(id.clone()).or_insert(PartialFrame {
+            total,
+            chunks: BTreeMap::new(),
+            len: 0,
+            first_seen: now,
+        });
+        if frame.total != total {
+            let expected = frame.total;
+            self.remove(&id);
+            return Err(ReassemblyError::TotalMismatch { expected, total });
+        }
+        if frame.chunks.contains_key(&index) {
+            self.duplicates += 1;
+            return Ok(None);
+        }
+        let len = frame.len + bytes.len();
+        if let Err(limit) = self.limits.check_item_len(len) {
+            self.remove(&id);
+            return Err(ReassemblyError::ResourceLimit(limit));
+        }
+
+        self.evict(&id, bytes.len());
+        let frame = match self.frames.get_mut(&id) {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+        frame.chunks.insert(index, bytes.to_vec());
+        frame.len = len;
+        self.buffered += bytes.len();
+
+        if frame.chunks.len() < frame.total {
+            return Ok(None);
+        }
+        Ok(self.remove(&id).map(|frame| {
+            let mut bytes = Vec::with_capacity(frame.len);
+            frame
+                .chunks
+                .into_values()
+                .for_each(|chunk| bytes.extend(chunk));
+            bytes
+        }))
+    }
+
+    /// Evict the oldest partial frames other than `keep` until `len` more bytes fit.
+    fn evict(&mut self, keep: &K, len: usize) {
+        while self.buffered + len > self.limits.max_buffered() {
+            let oldest = self
+                .frames
+                .iter()
+                .filter(|(id, _)| *id != keep)
+                .min_by_key(|(_, frame)| frame.first_seen)
+                .map(|(id, _)| id.clone());
+            // every other frame is gone, the frame being completed must make room itself
+            let oldest = oldest.unwrap_or_else(|| keep.clone());
+            self.remove(&oldest);
+            self.evicted += 1;
+        }
+    }
+
+    fn remove(&mut self, id: &K) -> Option<PartialFrame> {
+        let frame = self.frames.remove(id)?;
+        self.buffered -= frame.len;
+        Some(frame)
+    }
+
+    /// Discard every frame whose first chunk arrived more than the timeout before `now`,
+    /// returning the number of frames discarded.
+    pub fn expire(&mut self, now: Duration) -> usize {
+        let timeout = self.timeout;
+        let before = self.frames.len();
+        let mut released = 0;
+        self.frames.retain(|_, frame| {
+            let keep = now.saturating_sub(frame.first_seen) <= timeout;
+            if !keep {
+                released += frame.len;
+            }
+            keep
+        });
+        self.buffered -= released;
+        let expired = before - self.frames.len();
+        self.expired += expired as u64;
+        expired
+    }
+
+    /// The number of frames waiting for chunks.
+    pub fn pending_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The number of bytes held across all partial frames.
+    pub fn buffered_len(&self) -> usize {
+        self.buffered
+    }
+
+    /// The total number of repeated chunks ignored.
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    /// The total number of frames discarded by the timeout.
+    pub fn expired(&self) -> u64 {
+        self.expired
+    }
+
+    /// The total number of frames evicted to stay within [DecodeLimits::max_buffered].
+    pub fn evicted(&self) -> u64 {
+        self.evicted
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rstest::rstest;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn frame(len: usize) -> Vec<u8> {
+        (0..len).map(|val| val as u8).collect()
+    }
+
+    #[rstest]
+    #[case(vec![0, 1, 2, 3])]
+    #[case(vec![3, 2, 1, 0])]
+    #[case(vec![2, 0, 3, 1])]
+    // repeated chunks are ignored
+    #[case(vec![1, 1, 0, 3, 0, 2])]
+    fn reassembly_order(#[case] order: Vec<usize>) {
+        let expected = frame(5000);
+        let chunks: Vec<&[u8]> = expected.chunks(1400).collect();
+        let mut reassembler = FrameReassembler::new(SECOND);
+
+        let mut complete = vec![];
+        for index in &order {
+            if let Some(frame) = reassembler
+                .push("pass", *index, chunks.len(), chunks[*index], SECOND)
+                .unwrap()
+            {
+                complete.push(frame);
+            }
+        }
+
+        assert_eq!(vec![expected], complete);
+        assert_eq!(order.len() as u64 - 4, reassembler.duplicates());
+        assert_eq!(0, reassembler.pending_frames());
+        assert_eq!(0, reassembler.buffered_len());
+    }
+
+    #[test]
+    fn reassembly_interleaved() {
+        let mut reassembler = FrameReassembler::new(SECOND);
+        assert_eq!(Ok(None), reassembler.push(1, 0, 2, &[1, 1], SECOND));
+        assert_eq!(Ok(None), reassembler.push(2, 1, 2, &[2, 2], SECOND));
+        assert_eq!(
+            Ok(Some(vec![1, 1, 1])),
+            reassembler.push(1, 1, 2, &[1], SECOND)
+        );
+        assert_eq!(
+            Ok(Some(vec![2, 2, 2])),
+            reassembler.push(2, 0, 2, &[2], SECOND)
+        );
+        // a single chunk frame
+        assert_eq!(Ok(Some(vec![3])), reassembler.push(3, 0, 1, &[3], SECOND));
+    }
+
+    #[test]
+    fn reassembly_timeout() {
+        let mut reassembler = FrameReassembler::new(2 * SECOND);
+        reassembler.push(1, 0, 2, &[1], SECOND).unwrap();
+        reassembler.push(2, 0, 2, &[2], 2 * SECOND).unwrap();
+
+        assert_eq!(0, reassembler.expire(3 * SECOND));
+        assert_eq!(1, reassembler.expire(3 * SECOND + Duration::from_millis(1)));
+        // the late chunk starts a new frame
+        assert_eq!(Ok(None), reassembler.push(1, 1, 2, &[1], 4 * SECOND));
+        assert_eq!(
+            Ok(Some(vec![2, 2])),
+            reassembler.push(2, 1, 2, &[2], 4 * SECOND)
+        );
+        assert_eq!(1, reassembler.expired());
+        assert_eq!(1, reassembler.buffered_len());
+    }
+
+    #[test]
+    fn reassembly_invalid() {
+        let mut reassembler = FrameReassembler::new(SECOND);
+        assert_eq!(
+            Err(ReassemblyError::InvalidIndex { index: 2, total: 2 }),
+            reassembler.push(1, 2, 2, &[1], SECOND)
+        );
+        assert_eq!(
+            Err(ReassemblyError::InvalidIndex { index: 0, total: 0 }),
+            reassembler.push(1, 0, 0, &[1], SECOND)
+        );
+
+        reassembler.push(1, 0, 2, &[1], SECOND).unwrap();
+        assert_eq!(
+            Err(ReassemblyError::TotalMismatch {
+                expected: 2,
+                total: 3
+            }),
+            reassembler.push(1, 1, 3, &[1], SECOND)
+        );
+        assert_eq!(0, reassembler.pending_frames());
+    }
+
+    #[test]
+    fn reassembly_budgets() {
+        let limits = DecodeLimits::default()
+            .with_max_item_len(10)
+            .with_max_buffered(16);
+        let mut reassembler = FrameReassembler::new(SECOND).with_limits(limits);
+
+        // the frame grows beyond its budget
+        reassembler.push(1, 0, 3, &[1; 6], SECOND).unwrap();
+        assert_eq!(
+            Err(ReassemblyError::ResourceLimit(ResourceLimit {
+                which: Limit::ItemLen,
+                limit: 10
+            })),
+            reassembler.push(1, 1, 3, &[1; 6], SECOND)
+        );
+        assert_eq!(0, reassembler.buffered_len());
+
+        // the oldest frame is evicted to make room
+        reassembler.push(2, 0, 2, &[2; 6], SECOND / 2).unwrap();
+        reassembler.push(3, 0, 2, &[3; 6], SECOND).unwrap();
+        reassembler.push(4, 0, 2, &[4; 6], SECOND).unwrap();
+        assert_eq!(1, reassembler.evicted());
+        assert_eq!(2, reassembler.pending_frames());
+        assert_eq!(12, reassembler.buffered_len());
+        assert!(reassembler.buffered_len() <= limits.max_buffered());
+    }
+}
@@ -0,0 +1,420 @@
+//! Reduce a stream of [CompletePacket]s to the valid packets, counting the CRC failures.
+//!
+//! [ValidOnly] wraps an [Iterator], or with features `async-codec` or `tokio-codec`
+//! a Stream such as a Framed [SpacePacketCodec](crate::codec::SpacePacketCodec),
+//! yielding the valid [SpacePacket]s and handling invalid ones with an [InvalidPolicy].
+//!
+//! ```
+//! # use spacepacket::{valid::{InvalidPolicy, ValidOnly}, CompletePacket, SpacePacket};
+//! let packets = vec![
+//!     Ok(CompletePacket::Valid(SpacePacket::idle(4))),
+//!     Ok(CompletePacket::InvalidCRC(0x1234, 0x4321, vec![])),
+//!     Ok(CompletePacket::Valid(SpacePacket::idle(8))),
+//! ];
+//!
+//! let mut valid = ValidOnly::new(packets.into_iter(), InvalidPolicy::Silently);
+//! let stats = valid.stats();
+//! assert_eq!(2, valid.by_ref().count());
+//! assert_eq!((2, 1), (stats.valid(), stats.invalid()));
+//! ```
+use std::{
+    fmt::Debug,
+    io::{Error, ErrorKind},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use crate::{CompletePacket, SpacePacket};
+
+/// How [ValidOnly] handles a packet which failed its CRC.
+pub enum InvalidPolicy {
+    /// Count the packet and drop it.
+    Silently,
+    /// Count the packet and emit a warning with the CRC values through the [log] facade.
+    #[cfg(feature = "log")]
+    LogWarn,
+    /// Count the packet and call the function with the sent and computed CRC values.
+    Callback(Box<dyn FnMut(u16, u16) + Send>),
+    /// Count the packet and drop it, until this many consecutive packets fail.
+    /// Then yield an [ErrorKind::InvalidData] error and end, as a link with every packet failing
+    /// is most likely misconfigured. A limit of 0 aborts on the first failure like a limit of 1.
+    AbortAfter(usize),
+}
+impl Debug for InvalidPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Silently => f.write_str("Silently"),
+            #[cfg(feature = "log")]
+            Self::LogWarn => f.write_str("LogWarn"),
+            Self::Callback(_) => f.write_str("Callback(..)"),
+            Self::AbortAfter(count) => f.debug_tuple("AbortAfter").field(count).finish(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    valid: AtomicU64,
+    invalid: AtomicU64,
+    consecutive_invalid: AtomicU64,
+}
+
+/// Shared counts of the packets seen by one or more [ValidOnly] adapters.
+///
+/// Clones refer to the same counts, so a handle can be kept while the adapter
+/// is moved into a task.
+#[derive(Debug, Clone, Default)]
+pub struct CrcStats {
+    counts: Arc<Counts>,
+}
+impl CrcStats {
+    /// Initialize counts starting at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of packets which passed their CRC.
+    pub fn valid(&self) -> u64 {
+        self.counts.valid.load(Ordering::Relaxed)
+    }
+
+    /// The number of packets which failed their CRC.
+    pub fn invalid(&self) -> u64 {
+        self.counts.invalid.load(Ordering::Relaxed)
+    }
+
+    /// The number of packets which failed their CRC since the last valid packet.
+    pub fn consecutive_invalid(&self) -> u64 {
+        self.counts.consecutive_invalid.load(Ordering::Relaxed)
+    }
+
+    fn record_valid(&self) {
+        self.counts.valid.fetch_add(1, Ordering::Relaxed);
+        self.counts.consecutive_invalid.store(0, Ordering::Relaxed);
+    }
+
+    fn record_invalid(&self) {
+        self.counts.invalid.fetch_add(1, Ordering::Relaxed);
+        self.counts
+            .consecutive_invalid
+            .fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Yields the valid [SpacePacket]s of an inner iterator or stream of [CompletePacket]s,
+/// handling the invalid packets according to an [InvalidPolicy].
+///
+/// Errors of the inner iterator are passed through without affecting the counts.
+pub struct ValidOnly<I> {
+    inner: I,
+    policy: InvalidPolicy,
+    stats: CrcStats,
+    /// Consecutive failures seen by this adapter, the counts in `stats` may be shared.
+    consecutive_invalid: usize,
+    aborted: bool,
+}
+impl<I> ValidOnly<I> {
+    /// Wrap `inner`, handling invalid packets with `policy`.
+    pub fn new(inner: I, policy: InvalidPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            stats: CrcStats::new(),
+            consecutive_invalid: 0,
+            aborted: false,
+        }
+    }
+
+    /// Record counts in `stats`, which may be shared with other adapters.
+    pub fn with_stats(mut self, stats: CrcStats) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// A handle to the counts of this adapter.
+    pub fn stats(&self) -> CrcStats {
+        self.stats.clone()
+    }
+
+    /// Whether the adapter ended after [InvalidPolicy::AbortAfter] consecutive failures.
+    pub fn is_aborted(&self) -> bool {
+        self.aborted
+    }
+
+    /// Unwrap the inner iterator or stream.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+
+    /// Handle one item of the inner iterator, returning None if it is dropped.
+    fn filter(
+        &mut self,
+        item: Result<CompletePacket, Error>,
+    ) -> Option<Result<SpacePacket, Error>> {
+        let (sent, computed) = match item {
+            Ok(CompletePacket::Valid(packet)) => {
+                self.stats.record_valid();
+                self.consecutive_invalid = 0;
+                return Some(Ok(packet));
+            }
+            Ok(CompletePacket::InvalidCRC(sent, computed, _)) => (sent, computed),
+            Err(err) => return Some(Err(err)),
+        };
+
+        self.stats.record_invalid();
+        self.consecutive_invalid += 1;
+        match &mut self.policy {
+            InvalidPolicy::Silently => None,
+            #[cfg(feature = "log")]
+            InvalidPolicy::LogWarn => {
+                log::warn!("Dropped packet with CRC {sent:#06X}, computed {computed:#06X}");
+                None
+            }
+            InvalidPolicy::Callback(callback) => {
+                callback(sent, computed);
+                None
+            }
+            InvalidPolicy::AbortAfter(limit) if self.consecutive_invalid >= *limit => {
+                self.aborted = true;
+                Some(Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "{} consecutive packets failed their CRC, the link may be misconfigured",
+                        self.consecutive_invalid
+                    ),
+                )))
+            }
+            InvalidPolicy::AbortAfter(_) => None,
+        }
+    }
+}
+
+impl<I> Iterator for ValidOnly<I>
+where
+    I: Iterator<Item = Result<CompletePacket, Error>>,
+{
+    type Item = Result<SpacePacket, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.aborted {
+            let item = self.inner.next()?;
+            if let Some(item) = self.filter(item) {
+                return Some(item);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(any(feature = "async-codec", feature = "tokio-codec"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "async-codec", feature = "tokio-codec")))
+)]
+impl<S> futures_core::Stream for ValidOnly<S>
+where
+    S: futures_core::Stream<Item = Result<CompletePacket, Error>> + Unpin,
+{
+    type Item = Result<SpacePacket, Error>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        use std::task::Poll;
+
+        let this = self.get_mut();
+        while !this.aborted {
+            let item = match std::pin::Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(item)) => item,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            if let Some(item) = this.filter(item) {
+                return Poll::Ready(Some(item));
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    use rstest::rstest;
+
+    fn valid(len: usize) -> Result<CompletePacket, Error> {
+        Ok(CompletePacket::Valid(SpacePacket::idle(len)))
+    }
+
+    fn invalid() -> Result<CompletePacket, Error> {
+        Ok(CompletePacket::InvalidCRC(0x1234, 0x4321, vec![]))
+    }
+
+    #[rstest]
+    #[case(InvalidPolicy::Silently)]
+    #[case(InvalidPolicy::AbortAfter(4))]
+    fn valid_only_counts(#[case] policy: InvalidPolicy) {
+        let items = vec![valid(1), invalid(), invalid(), valid(2), invalid()];
+        let mut adapter = ValidOnly::new(items.into_iter(), policy);
+        let stats = adapter.stats();
+
+        let packets: Vec<_> = adapter.by_ref().map(Result::unwrap).collect();
+        assert_eq!(vec![SpacePacket::idle(1), SpacePacket::idle(2)], packets);
+        assert_eq!(2, stats.valid());
+        assert_eq!(3, stats.invalid());
+        assert_eq!(1, stats.consecutive_invalid());
+        assert!(!adapter.is_aborted());
+    }
+
+    #[test]
+    fn valid_only_callback() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let callback = {
+            let seen = seen.clone();
+            InvalidPolicy::Callback(Box::new(move |sent, computed| {
+                seen.lock().unwrap().push((sent, computed))
+            }))
+        };
+
+        let items = vec![invalid(), valid(1), invalid()];
+        assert_eq!(1, ValidOnly::new(items.into_iter(), callback).count());
+        assert_eq!(vec![(0x1234, 0x4321); 2], *seen.lock().unwrap());
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn valid_only_log_warn() {
+        struct Recorder(Mutex<Vec<String>>);
+        impl log::Log for Recorder {
+            fn enabled(&self, metadata: &log::Metadata) -> bool {
+                metadata.level() <= log::Level::Warn
+            }
+
+            fn log(&self, record: &log::Record) {
+                if self.enabled(record.metadata()) {
+                    let line = format!("{} {}", record.level(), record.args());
+                    self.0.lock().unwrap().push(line);
+                }
+            }
+
+            fn flush(&self) {}
+        }
+        static RECORDER: Recorder = Recorder(Mutex::new(vec![]));
+        log::set_logger(&RECORDER).unwrap();
+        log::set_max_level(log::LevelFilter::Warn);
+
+        let items = vec![invalid(), valid(1)];
+        let adapter = ValidOnly::new(items.into_iter(), InvalidPolicy::LogWarn);
+        assert_eq!(1, adapter.count());
+        assert_eq!(
+            vec!["WARN Dropped packet with CRC 0x1234, computed 0x4321"],
+            *RECORDER.0.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn valid_only_passes_errors() {
+        let items = vec![
+            invalid(),
+            Err(Error::new(ErrorKind::UnexpectedEof, "eof")),
+            invalid(),
+            valid(1),
+        ];
+        let mut adapter = ValidOnly::new(items.into_iter(), InvalidPolicy::AbortAfter(3));
+
+        // errors neither count as failures nor reset the consecutive count
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            adapter.next().unwrap().unwrap_err().kind()
+        );
+        assert_eq!(SpacePacket::idle(1), adapter.next().unwrap().unwrap());
+        assert!(adapter.next().is_none());
+        assert_eq!(0, adapter.stats().consecutive_invalid());
+    }
+
+    #[rstest]
+    // the limit is reached, later packets are never read
+    #[case(2, vec![valid(1), invalid(), invalid(), valid(2)], 1, true, 1)]
+    // a valid packet resets the consecutive count
+    #[case(2, vec![invalid(), valid(1), invalid(), valid(2)], 2, false, 0)]
+    // the limit is reached by the last packet
+    #[case(3, vec![invalid(), invalid(), invalid()], 0, true, 0)]
+    // a limit of 1 aborts on the first failure
+    #[case(1, vec![valid(1), invalid(), valid(2)], 1, true, 1)]
+    fn valid_only_abort_after(
+        #[case] limit: usize,
+        #[case] items: Vec<Result<CompletePacket, Error>>,
+        #[case] valid_count: usize,
+        #[case] aborted: bool,
+        #[case] remaining: usize,
+    ) {
+        let mut items = items.into_iter();
+        let mut adapter = ValidOnly::new(items.by_ref(), InvalidPolicy::AbortAfter(limit));
+
+        let results: Vec<_> = adapter.by_ref().collect();
+        let packets = results.iter().filter(|result| result.is_ok()).count();
+        assert_eq!(valid_count, packets);
+        assert_eq!(aborted, adapter.is_aborted());
+        if aborted {
+            let error = results.last().unwrap().as_ref().unwrap_err();
+            assert_eq!(ErrorKind::InvalidData, error.kind());
+            // the adapter stays ended
+            assert!(adapter.next().is_none());
+        }
+        // nothing after the aborting packet is consumed
+        assert_eq!(remaining, adapter.into_inner().count());
+    }
+
+    #[test]
+    fn valid_only_shared_stats() {
+        let stats = CrcStats::new();
+        let first = ValidOnly::new(
+            vec![valid(1), invalid()].into_iter(),
+            InvalidPolicy::Silently,
+        )
+        .with_stats(stats.clone());
+        let second = ValidOnly::new(vec![valid(2)].into_iter(), InvalidPolicy::Silently)
+            .with_stats(stats.clone());
+
+        assert_eq!(2, first.chain(second).count());
+        assert_eq!((2, 1), (stats.valid(), stats.invalid()));
+    }
+
+    #[cfg(feature = "async-codec")]
+    #[test]
+    fn valid_only_stream() {
+        use asynchronous_codec::FramedRead;
+        use crc::{Crc, CRC_16_IBM_3740};
+        use futures::{executor, io::Cursor, TryStreamExt};
+
+        use crate::codec::SpacePacketCodec;
+
+        let crc = Crc::<u16>::new(&CRC_16_IBM_3740);
+        let mut stream = vec![];
+        for (index, len) in [4, 5, 6, 7].into_iter().enumerate() {
+            let mut encoded = SpacePacket::idle(len).encode_crc(&crc).unwrap();
+            if index % 2 == 1 {
+                *encoded.last_mut().unwrap() ^= 0xFF;
+            }
+            stream.extend([0xAA, 0xBB]);
+            stream.extend(encoded);
+        }
+
+        let framed = FramedRead::new(
+            Cursor::new(stream),
+            SpacePacketCodec::new([0xAA, 0xBB]).with_crc(crc),
+        );
+        let adapter = ValidOnly::new(framed, InvalidPolicy::AbortAfter(2));
+        let stats = adapter.stats();
+
+        let packets: Vec<SpacePacket> = executor::block_on(adapter.try_collect()).unwrap();
+        assert_eq!(vec![SpacePacket::idle(4), SpacePacket::idle(6)], packets);
+        assert_eq!((2, 2), (stats.valid(), stats.invalid()));
+    }
+}
@@ -0,0 +1,145 @@
+//! A synthetic pass corrupted in known ways must be fully explained by the anomaly log.
+mod common;
+
+use asynchronous_codec::{BytesMut, Decoder};
+use spacepacket::{
+    anomaly::{Anomaly, AnomalyKind, AnomalyLog, AnomalySummary, Layer},
+    codec::SpacePacketCodec,
+    consts::ASM,
+    continuity::ContinuityChecker,
+    crc::{Crc, CRC_16_IBM_3740},
+    tctm::{
+        extractor::PacketExtractor,
+        tm::{CollectFrames, TMFramePacker, TMPrimaryHeader},
+    },
+    CompletePacket,
+};
+
+use common::packet_with_payload;
+
+const CRC: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+
+#[test]
+fn anomaly_log_tells_the_story() {
+    let log = AnomalyLog::new();
+
+    // the codec pass, every packet is 6 + 20 + 2 bytes after its marker
+    let mut stream = vec![];
+    let mut offsets = vec![];
+    for count in 0..10 {
+        match count {
+            // five bytes of noise
+            2 => stream.extend([0x11; 5]),
+            // the packet is lost entirely
+            7 => continue,
+            _ => (),
+        }
+        stream.extend(ASM);
+        offsets.push(stream.len() as u64);
+        let mut encoded = packet_with_payload(0x42, count, vec![count as u8; 20])
+            .encode_crc(&CRC)
+            .unwrap();
+        if count == 4 {
+            encoded[10] ^= 0x80;
+        }
+        stream.extend(encoded);
+    }
+
+    let mut codec = SpacePacketCodec::new(ASM)
+        .with_crc(CRC)
+        .with_anomaly_log(log.clone());
+    let mut checker = ContinuityChecker::new().with_anomaly_log(log.clone());
+    let mut buffer = BytesMut::new();
+    let mut decoded = 0;
+    for chunk in stream.chunks(16) {
+        buffer.extend_from_slice(chunk);
+        while let Some(packet) = codec.decode(&mut buffer).unwrap() {
+            decoded += 1;
+            if let CompletePacket::Valid(packet) = packet {
+                checker.observe(&packet.primary_header, codec.last_packet_offset().unwrap());
+            }
+        }
+    }
+    assert_eq!(9, decoded);
+
+    // the TM pass of another APID loses its third frame
+    let packer =
+        TMFramePacker::new(TMPrimaryHeader::builder().scid(758).build().unwrap(), 40).unwrap();
+    let mut frames = (0..6)
+        .map(|count| packet_with_payload(0x43, count, vec![count as u8; 20]))
+        .collect_frames(packer)
+        .unwrap();
+    frames.remove(2);
+    let mut extractor = PacketExtractor::new().with_anomaly_log(log.clone());
+    for frame in &frames {
+        for packet in extractor.push(frame) {
+            checker.observe(&packet.primary_header, 0);
+        }
+    }
+
+    let corrupted = &stream[offsets[4] as usize..][..28];
+    assert_eq!(
+        vec![
+            Anomaly {
+                offset: offsets[2] - 4 - 5,
+                layer: Layer::Codec,
+                kind: AnomalyKind::Unsynchronized { skipped: 5 },
+            },
+            Anomaly {
+                offset: offsets[4],
+                layer: Layer::Codec,
+                kind: AnomalyKind::CrcMismatch {
+                    sent: u16::from_be_bytes([corrupted[26], corrupted[27]]),
+                    computed: CRC.checksum(&corrupted[..26]),
+                },
+            },
+            // the packet failing its CRC is not observed
+            Anomaly {
+                offset: offsets[5],
+                layer: Layer::Continuity,
+                kind: AnomalyKind::SequenceGap {
+                    apid: 0x42,
+                    expected: 4,
+                    received: 5,
+                },
+            },
+            Anomaly {
+                offset: offsets[7],
+                layer: Layer::Continuity,
+                kind: AnomalyKind::SequenceGap {
+                    apid: 0x42,
+                    expected: 7,
+                    received: 8,
+                },
+            },
+            Anomaly {
+                offset: 80,
+                layer: Layer::Extractor,
+                kind: AnomalyKind::FramesLost { count: 1 },
+            },
+            // the packet spanning into the lost frame and the packet starting inside it
+            Anomaly {
+                offset: 0,
+                layer: Layer::Continuity,
+                kind: AnomalyKind::SequenceGap {
+                    apid: 0x43,
+                    expected: 3,
+                    received: 5,
+                },
+            },
+        ],
+        log.events()
+    );
+
+    assert_eq!(
+        AnomalySummary {
+            resyncs: 1,
+            unsynchronized_bytes: 5,
+            crc_mismatches: 1,
+            lost_frames: 1,
+            sequence_gaps: 3,
+            ..Default::default()
+        },
+        log.summary()
+    );
+}
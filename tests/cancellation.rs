@@ -0,0 +1,130 @@
+//! Cancellation safety of codec backed streams.
+//!
+//! A read which is still pending when the future polling the stream is dropped,
+//! as happens to the losing branch of a `select!`, must lose no packets and yield none twice.
+mod common;
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use asynchronous_codec::FramedRead;
+use futures::{
+    executor,
+    future::{self, Either},
+    AsyncRead, StreamExt,
+};
+use rstest::rstest;
+
+use spacepacket::{
+    codec::SpacePacketCodec, consts::ASM as SYNC_MARKER, CompletePacket, SpacePacket, IDLE_APID,
+};
+
+use common::packet;
+
+/// A reader handing out at most `chunk` bytes per read, pending before every read.
+struct Trickle {
+    data: Vec<u8>,
+    position: usize,
+    chunk: usize,
+    ready: bool,
+}
+impl AsyncRead for Trickle {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.ready {
+            self.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        self.ready = false;
+
+        let len = self
+            .chunk
+            .min(buf.len())
+            .min(self.data.len() - self.position);
+        buf[..len].copy_from_slice(&self.data[self.position..self.position + len]);
+        self.position += len;
+        Poll::Ready(Ok(len))
+    }
+}
+
+/// A timeout which expires after being polled `remaining` times.
+struct Countdown(usize);
+impl Future for Countdown {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 == 0 {
+            return Poll::Ready(());
+        }
+        self.0 -= 1;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// A stream of packets interleaved with idle packets and the offset of every packet.
+fn stream(count: usize) -> (Vec<u8>, Vec<u64>) {
+    let mut data = vec![];
+    let mut offsets = vec![];
+    for index in 0..count {
+        data.extend(SYNC_MARKER);
+        offsets.push(data.len() as u64);
+        data.extend(packet(0x42, index as u16, 1 + index * 13).encode());
+        if index % 3 == 0 {
+            data.extend(SYNC_MARKER);
+            data.extend(SpacePacket::idle_with_apid(IDLE_APID, 1 + index).encode());
+        }
+    }
+    (data, offsets)
+}
+
+#[rstest]
+#[case(1, 1)]
+#[case(3, 2)]
+#[case(7, 5)]
+#[case(64, 3)]
+fn cancelled_reads(#[case] chunk: usize, #[case] timeout: usize) {
+    let (data, offsets) = stream(20);
+    let reader = Trickle {
+        data,
+        position: 0,
+        chunk,
+        ready: false,
+    };
+    let codec = SpacePacketCodec::new(SYNC_MARKER).skip_idle(IDLE_APID);
+    let mut framed = FramedRead::new(reader, codec);
+
+    let mut received = vec![];
+    let mut cancelled = 0;
+    executor::block_on(async {
+        loop {
+            match future::select(framed.next(), Countdown(timeout)).await {
+                Either::Left((Some(packet), _)) => {
+                    received.push(packet.unwrap());
+                    assert_eq!(
+                        Some(offsets[received.len() - 1]),
+                        framed.decoder().last_packet_offset()
+                    );
+                }
+                Either::Left((None, _)) => break,
+                Either::Right(((), _)) => cancelled += 1,
+            }
+        }
+    });
+
+    assert!(cancelled > 0);
+    assert_eq!(
+        (0..20)
+            .map(|index| CompletePacket::Valid(packet(0x42, index as u16, 1 + index * 13)))
+            .collect::<Vec<_>>(),
+        received
+    );
+}
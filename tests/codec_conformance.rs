@@ -0,0 +1,218 @@
+//! Loop-back conformance of the codec across CRC configurations and codec crates.
+//!
+//! Every encoder and decoder implementation shares the same helpers,
+//! these tests pin down the behavior those helpers are documented to have.
+use asynchronous_codec::BytesMut;
+use crc::{Crc, CRC_16_IBM_3740};
+use rstest::rstest;
+
+use spacepacket::{codec::SpacePacketCodec, CompletePacket, GroupingFlag, PacketType, SpacePacket};
+
+const CRC_CCITT_FALSE: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+
+const SYNC_MARKER: [u8; 4] = [0x1A, 0xCF, 0xFC, 0x1D];
+
+/// The outcome of a single decode call, errors are compared by kind.
+type Outcome = Result<Option<CompletePacket>, std::io::ErrorKind>;
+
+fn corpus() -> Vec<SpacePacket> {
+    [1, 2, 3, 100, 1017]
+        .into_iter()
+        .enumerate()
+        .map(|(index, payload_len)| {
+            SpacePacket::new(
+                0,
+                PacketType::Telemetry,
+                17 + index as u16,
+                GroupingFlag::Unsegm,
+                index as u16,
+                index % 2 == 0,
+                (0..payload_len).map(|val| (val * 7) as u8).collect(),
+            )
+        })
+        .collect()
+}
+
+fn codec(sync_marker: &[u8], crc: Option<Crc<u16>>) -> SpacePacketCodec {
+    let codec = SpacePacketCodec::new(sync_marker);
+    match crc {
+        Some(crc) => codec.with_crc(crc),
+        None => codec,
+    }
+}
+
+fn encode_async(mut codec: SpacePacketCodec, packets: &[SpacePacket]) -> BytesMut {
+    let mut dst = BytesMut::new();
+    for packet in packets {
+        <SpacePacketCodec as asynchronous_codec::Encoder>::encode(
+            &mut codec,
+            packet.clone(),
+            &mut dst,
+        )
+        .unwrap();
+    }
+    dst
+}
+
+/// Decode until the codec asks for more data, continuing after errors.
+fn decode_async(mut codec: SpacePacketCodec, src: &[u8]) -> Vec<Outcome> {
+    let mut src = BytesMut::from(src);
+    let mut outcomes = vec![];
+    loop {
+        let outcome =
+            <SpacePacketCodec as asynchronous_codec::Decoder>::decode(&mut codec, &mut src)
+                .map_err(|err| err.kind());
+        let done = outcome == Ok(None);
+        outcomes.push(outcome);
+        if done {
+            return outcomes;
+        }
+    }
+}
+
+/// Take the encoded corpus and damage it in a variety of ways.
+fn corrupted(encoded: &[u8]) -> Vec<Vec<u8>> {
+    let mut flipped = encoded.to_vec();
+    flipped
+        .iter_mut()
+        .step_by(37)
+        .for_each(|byte| *byte ^= 0xFF);
+
+    let mut bad_length = encoded.to_vec();
+    // the length field of the first packet follows the marker and 4 header bytes
+    bad_length[SYNC_MARKER.len() + 4] = 0xFF;
+
+    vec![
+        flipped,
+        bad_length,
+        encoded[..encoded.len() / 2].to_vec(),
+        encoded[5..].to_vec(),
+        [&encoded[..20], &encoded[40..]].concat(),
+    ]
+}
+
+#[rstest]
+fn crc_encoded_without_crc(#[values(&[][..], &SYNC_MARKER[..])] sync_marker: &[u8]) {
+    let packets = corpus();
+    let encoded = encode_async(codec(sync_marker, Some(CRC_CCITT_FALSE)), &packets);
+
+    // the CRC is left at the end of the payload
+    let mut expected: Vec<Outcome> = packets
+        .iter()
+        .map(|packet| {
+            let mut packet = packet.clone();
+            packet.payload = packet.encode_crc(&CRC_CCITT_FALSE).unwrap()[6..].to_vec();
+            Ok(Some(CompletePacket::Valid(packet)))
+        })
+        .collect();
+    expected.push(Ok(None));
+
+    assert_eq!(expected, decode_async(codec(sync_marker, None), &encoded));
+}
+
+#[rstest]
+fn plain_encoded_with_crc(#[values(&[][..], &SYNC_MARKER[..])] sync_marker: &[u8]) {
+    let packets = corpus();
+    let encoded = encode_async(codec(sync_marker, None), &packets);
+
+    let outcomes = decode_async(codec(sync_marker, Some(CRC_CCITT_FALSE)), &encoded);
+
+    assert_eq!(packets.len() + 1, outcomes.len());
+    for (packet, outcome) in packets.iter().zip(&outcomes) {
+        match packet.payload.len() {
+            // too short to contain a CRC
+            1 | 2 => assert_eq!(&Err(std::io::ErrorKind::InvalidData), outcome),
+            // the last two bytes of the payload are taken as an invalid CRC
+            _ => assert!(
+                matches!(outcome, Ok(Some(CompletePacket::InvalidCRC(..)))),
+                "{outcome:?}"
+            ),
+        }
+    }
+    assert_eq!(Some(&Ok(None)), outcomes.last());
+}
+
+#[rstest]
+fn loopback(
+    #[values(&[][..], &SYNC_MARKER[..])] sync_marker: &[u8],
+    #[values(None, Some(CRC_CCITT_FALSE))] crc: Option<Crc<u16>>,
+) {
+    let packets = corpus();
+    let encoded = encode_async(codec(sync_marker, crc.clone()), &packets);
+
+    let mut expected: Vec<Outcome> = packets
+        .into_iter()
+        .map(|packet| Ok(Some(CompletePacket::Valid(packet))))
+        .collect();
+    expected.push(Ok(None));
+
+    assert_eq!(expected, decode_async(codec(sync_marker, crc), &encoded));
+}
+
+#[rstest]
+fn corrupted_input_is_deterministic(#[values(None, Some(CRC_CCITT_FALSE))] crc: Option<Crc<u16>>) {
+    let encoded = encode_async(codec(&SYNC_MARKER, crc.clone()), &corpus());
+
+    for input in corrupted(&encoded) {
+        let outcomes = decode_async(codec(&SYNC_MARKER, crc.clone()), &input);
+        // a fresh or cloned codec behaves identically on the same input
+        assert_eq!(
+            outcomes,
+            decode_async(codec(&SYNC_MARKER, crc.clone()).clone(), &input)
+        );
+        assert_eq!(Some(&Ok(None)), outcomes.last());
+    }
+}
+
+#[cfg(feature = "tokio-codec")]
+mod tokio_parity {
+    use super::*;
+
+    fn encode_tokio(mut codec: SpacePacketCodec, packets: &[SpacePacket]) -> BytesMut {
+        let mut dst = BytesMut::new();
+        for packet in packets {
+            <SpacePacketCodec as tokio_util::codec::Encoder<SpacePacket>>::encode(
+                &mut codec,
+                packet.clone(),
+                &mut dst,
+            )
+            .unwrap();
+        }
+        dst
+    }
+
+    fn decode_tokio(mut codec: SpacePacketCodec, src: &[u8]) -> Vec<Outcome> {
+        let mut src = BytesMut::from(src);
+        let mut outcomes = vec![];
+        loop {
+            let outcome =
+                <SpacePacketCodec as tokio_util::codec::Decoder>::decode(&mut codec, &mut src)
+                    .map_err(|err| err.kind());
+            let done = outcome == Ok(None);
+            outcomes.push(outcome);
+            if done {
+                return outcomes;
+            }
+        }
+    }
+
+    #[rstest]
+    fn tokio_matches_async(
+        #[values(&[][..], &SYNC_MARKER[..])] sync_marker: &[u8],
+        #[values(None, Some(CRC_CCITT_FALSE))] crc: Option<Crc<u16>>,
+    ) {
+        let packets = corpus();
+        let encoded = encode_async(codec(sync_marker, crc.clone()), &packets);
+        assert_eq!(
+            encoded,
+            encode_tokio(codec(sync_marker, crc.clone()), &packets)
+        );
+
+        for input in std::iter::once(encoded.to_vec()).chain(corrupted(&encoded)) {
+            assert_eq!(
+                decode_async(codec(sync_marker, crc.clone()), &input),
+                decode_tokio(codec(sync_marker, crc.clone()), &input)
+            );
+        }
+    }
+}
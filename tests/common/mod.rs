@@ -0,0 +1,27 @@
+//! Fixtures shared by the integration tests and benchmarks, mirroring the unit test fixtures.
+// every test crate uses a different part of the fixtures
+#![allow(dead_code)]
+
+use spacepacket::{GroupingFlag, PacketType, SpacePacket};
+
+/// An unsegmented telemetry packet carrying `payload_len` bytes counting up from 0.
+pub fn packet(apid: u16, sequence_count: u16, payload_len: usize) -> SpacePacket {
+    packet_with_payload(
+        apid,
+        sequence_count,
+        (0..payload_len).map(|val| val as u8).collect(),
+    )
+}
+
+/// An unsegmented telemetry packet carrying the `payload`.
+pub fn packet_with_payload(apid: u16, sequence_count: u16, payload: Vec<u8>) -> SpacePacket {
+    SpacePacket::new(
+        0,
+        PacketType::Telemetry,
+        apid,
+        GroupingFlag::Unsegm,
+        sequence_count,
+        false,
+        payload,
+    )
+}
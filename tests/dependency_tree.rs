@@ -0,0 +1,66 @@
+//! The packet types must build without any async or buffer dependency so flight software
+//! can depend on them while a sibling ground crate enables the codecs.
+use std::process::Command;
+
+/// The normal dependencies of this crate with the given features, one crate name per line.
+fn dependencies(features: &str) -> Vec<String> {
+    let output = Command::new(env!("CARGO"))
+        .args([
+            "tree",
+            "--manifest-path",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"),
+            "--edges",
+            "normal",
+            "--prefix",
+            "none",
+            "--format",
+            "{p}",
+            "--no-default-features",
+            "--features",
+            features,
+        ])
+        .output()
+        .expect("Unable to run cargo tree.");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn lightweight_features() {
+    let heavy = [
+        "bytes",
+        "tokio",
+        "tokio-util",
+        "futures-core",
+        "asynchronous-codec",
+    ];
+    for features in ["", "framer", "framer,crc,tctm"] {
+        let dependencies = dependencies(features);
+        for name in heavy {
+            assert!(
+                !dependencies.iter().any(|dependency| dependency == name),
+                "{name} is a dependency with features [{features}]"
+            );
+        }
+    }
+}
+
+#[test]
+fn codec_features() {
+    assert!(dependencies("tokio-codec")
+        .iter()
+        .any(|dependency| dependency == "tokio"));
+    assert!(dependencies("async-codec")
+        .iter()
+        .any(|dependency| dependency == "bytes"));
+}
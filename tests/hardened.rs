@@ -0,0 +1,196 @@
+//! Decoding hostile input with [DecodeLimits] keeps memory and work bounded.
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use asynchronous_codec::{BytesMut, Decoder};
+use spacepacket::{
+    codec::SpacePacketCodec,
+    consts::ASM,
+    framer::{DiscardReason, FramerEvent, PacketFramer},
+    limits::{DecodeLimits, Limit, ResourceLimit},
+    tctm::{
+        extractor::PacketExtractor,
+        tm::{TMPrimaryHeader, TMRandomization, TMTransferFrame},
+    },
+};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// The number of allocations performed on this thread while running `f`.
+fn allocations<T>(f: impl FnOnce() -> T) -> (usize, T) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let out = f();
+    (ALLOCATIONS.with(Cell::get) - before, out)
+}
+
+/// Pseudo-random bytes from a linear congruential generator.
+fn noise(len: usize, seed: u32) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 24) as u8
+        })
+        .collect()
+}
+
+/// Noise where every few bytes a false sync marker is followed by a random header.
+fn false_markers(len: usize, seed: u32) -> Vec<u8> {
+    let mut stream = noise(len, seed);
+    let mut index = 0;
+    while index + ASM.len() < stream.len() {
+        stream[index..index + ASM.len()].copy_from_slice(&ASM);
+        index += ASM.len() + 6 + usize::from(stream[index + ASM.len()] % 8);
+    }
+    stream
+}
+
+fn hardened() -> DecodeLimits {
+    DecodeLimits::hardened()
+        .with_max_item_len(1024)
+        .with_max_buffered(8 * 1024)
+}
+
+#[test]
+fn hardened_false_markers_allocations() {
+    const CHUNK_LEN: usize = 4096;
+    let stream = false_markers(1 << 20, 42);
+    let chunks = stream.len() / CHUNK_LEN;
+
+    let mut framer = PacketFramer::new(ASM).with_limits(hardened());
+    let (count, limits) = allocations(|| {
+        let mut limits = 0;
+        for chunk in stream.chunks(CHUNK_LEN) {
+            framer.push(chunk);
+            while let Some(event) = framer.next_event() {
+                match event {
+                    FramerEvent::NeedMore => break,
+                    FramerEvent::Discarded(DiscardReason::ResourceLimit(_)) => limits += 1,
+                    _ => (),
+                }
+            }
+        }
+        limits
+    });
+
+    // tens of thousands of false markers, but the resync limit cuts every chunk short
+    assert!(limits >= chunks, "{limits} limits hit in {chunks} chunks");
+    // the few packets which are decoded allocate, the markers do not
+    assert!(
+        count <= 3 * chunks,
+        "{count} allocations for {chunks} chunks"
+    );
+    assert!(framer.pending_len() <= hardened().max_buffered());
+}
+
+#[test]
+fn hardened_fuzz_bounded() {
+    let limits = hardened();
+    for seed in 0..64 {
+        let stream = match seed % 2 {
+            0 => noise(64 * 1024, seed),
+            _ => false_markers(64 * 1024, seed),
+        };
+        let mut framer = PacketFramer::new(ASM).with_limits(limits);
+        // pseudo-random chunk lengths up to 32 KB, some exceeding the buffered limit
+        let lengths = noise(64, seed);
+        let mut rest = stream.as_slice();
+        for length in lengths.iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (chunk, remainder) =
+                rest.split_at((usize::from(*length) * 128 + 1).min(rest.len()));
+            rest = remainder;
+
+            framer.push(chunk);
+            assert!(framer.pending_len() <= limits.max_buffered());
+            while let Some(event) = framer.next_event() {
+                match event {
+                    FramerEvent::NeedMore => break,
+                    FramerEvent::Packet(packet) => {
+                        assert!(packet.payload.len() + 6 <= limits.max_item_len())
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        // frames of random bytes never reassemble beyond the limits
+        let mut extractor = PacketExtractor::new().with_limits(limits);
+        for frame in stream.chunks(1115).filter(|frame| frame.len() == 1115) {
+            let frame = TMTransferFrame::decode_with_limits(
+                frame,
+                frame.len(),
+                TMRandomization::None,
+                &limits,
+            );
+            if let Ok(frame) = frame {
+                for packet in extractor.push(&frame) {
+                    assert!(packet.payload.len() + 6 <= limits.max_item_len());
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn hardened_codec_error() {
+    let mut codec = SpacePacketCodec::new(ASM).with_limits(hardened());
+    let mut buffer = BytesMut::from(&ASM[..]);
+    buffer.extend_from_slice(&[0x08, 0x42, 0xC0, 0x00, 0x10, 0x00, 0x00]);
+
+    let error = codec.decode(&mut buffer).unwrap_err();
+    assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+    assert_eq!(
+        Some(&ResourceLimit {
+            which: Limit::ItemLen,
+            limit: 1024
+        }),
+        error
+            .get_ref()
+            .and_then(|err| err.downcast_ref::<ResourceLimit>())
+    );
+}
+
+#[test]
+fn hardened_frame_length() {
+    let frame = TMTransferFrame {
+        primary_header: TMPrimaryHeader::builder().scid(758).build().unwrap(),
+        secondary_header: None,
+        data_field: vec![0x55; 2048],
+    }
+    .encode(TMRandomization::None);
+
+    let (count, result) = allocations(|| {
+        TMTransferFrame::decode_with_limits(
+            frame.as_slice(),
+            frame.len(),
+            TMRandomization::None,
+            &hardened(),
+        )
+    });
+    assert!(result.is_err());
+    // rejected before the frame is buffered, only the boxed error is allocated
+    assert!(count <= 2, "{count} allocations");
+}
@@ -0,0 +1,151 @@
+//! Chunked file ingestion and resuming from the returned offset.
+mod common;
+
+use std::{cell::Cell, path::PathBuf};
+
+use rstest::rstest;
+
+use spacepacket::{
+    consts::ASM,
+    framer::PacketFramer,
+    ingest::{process_file, IngestProgress, CHUNK_LEN},
+    SpacePacket,
+};
+
+use common::packet;
+
+/// A recording of `count` packets of `payload_len` bytes, each preceded by the ASM,
+/// after a few bytes of noise.
+fn recording(count: u16, payload_len: usize) -> (Vec<SpacePacket>, Vec<u8>) {
+    let packets: Vec<_> = (0..count)
+        .map(|count| packet(0x42, count, payload_len))
+        .collect();
+    let mut bytes = vec![0x00, 0x1A, 0xCF, 0x55];
+    for packet in &packets {
+        bytes.extend(ASM);
+        bytes.extend(packet.encode());
+    }
+    (packets, bytes)
+}
+
+/// Write `bytes` to a file unique to the `name`.
+fn write_file(name: &str, bytes: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "spacepacket-ingest-{}-{name}.bin",
+        std::process::id()
+    ));
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+fn ingest(path: &PathBuf, start_offset: u64) -> (Vec<SpacePacket>, u64, IngestProgress) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let mut packets = vec![];
+    let last = Cell::new(IngestProgress::default());
+    let offset = runtime
+        .block_on(process_file(
+            path,
+            PacketFramer::new(ASM),
+            start_offset,
+            |packet, _| packets.push(packet),
+            |progress| last.set(progress),
+        ))
+        .unwrap();
+    (packets, offset, last.get())
+}
+
+#[test]
+fn ingest_complete_file() {
+    let (expected, bytes) = recording(20, 100);
+    let path = write_file("complete", &bytes);
+
+    let (packets, offset, progress) = ingest(&path, 0);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(expected, packets);
+    assert_eq!(bytes.len() as u64, offset);
+    assert_eq!(
+        IngestProgress {
+            offset: bytes.len() as u64,
+            bytes_read: bytes.len() as u64,
+            packets: 20,
+            discarded: 1,
+        },
+        progress
+    );
+}
+
+#[test]
+fn ingest_packet_offsets() {
+    let (_, bytes) = recording(3, 10);
+    let path = write_file("offsets", &bytes);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let mut offsets = vec![];
+    runtime
+        .block_on(process_file(
+            &path,
+            PacketFramer::new(ASM),
+            0,
+            |_, offset| offsets.push(offset),
+            |_| {},
+        ))
+        .unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    // every packet is 4 bytes of ASM and 16 bytes of packet after the 4 bytes of noise
+    assert_eq!(vec![24, 44, 64], offsets);
+}
+
+#[test]
+fn ingest_across_chunks() {
+    // packets of 60006 bytes straddle the chunk boundaries
+    let (expected, bytes) = recording(40, 60000);
+    assert!(bytes.len() > 2 * CHUNK_LEN);
+    let path = write_file("chunks", &bytes);
+
+    let (packets, offset, progress) = ingest(&path, 0);
+
+    assert_eq!(expected, packets);
+    assert_eq!(bytes.len() as u64, offset);
+    assert_eq!(bytes.len() as u64, progress.bytes_read);
+
+    // resume from an offset which is not aligned to a chunk
+    let start = 4 + 17 * 60010;
+    let (packets, _, progress) = ingest(&path, start);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(expected[17..], packets);
+    assert_eq!(bytes.len() as u64 - start, progress.bytes_read);
+}
+
+#[rstest]
+#[case::noise(2)]
+#[case::partial_marker(4 + 2)]
+#[case::after_marker(4 + 4)]
+#[case::partial_header(4 + 4 + 3)]
+#[case::partial_payload(4 + 4 + 6 + 50)]
+#[case::packet_boundary(4 + 110)]
+#[case::second_marker(4 + 110 + 3)]
+#[case::second_payload(4 + 110 + 4 + 6 + 99)]
+fn ingest_resume(#[case] stop: usize) {
+    let (expected, bytes) = recording(5, 100);
+
+    // the recording stopped part way through
+    let path = write_file(&format!("resume-{stop}"), &bytes[..stop]);
+    let (mut packets, offset, _) = ingest(&path, 0);
+    assert!(offset <= stop as u64);
+
+    // the complete recording is resumed from the returned offset
+    std::fs::write(&path, &bytes).unwrap();
+    let (resumed, _, _) = ingest(&path, offset);
+    std::fs::remove_file(path).unwrap();
+
+    packets.extend(resumed);
+    assert_eq!(expected, packets);
+}
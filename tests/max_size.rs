@@ -0,0 +1,209 @@
+//! Maximum size Space Packets through every stage of the crate.
+//!
+//! Without a CRC a packet carries at most [SpacePacket::MAX_PAYLOAD_LEN] bytes,
+//! with a CRC counted in the Packet Data Length at most [SpacePacket::MAX_PAYLOAD_LEN_CRC].
+mod common;
+
+use asynchronous_codec::Framed;
+use crc::{Crc, CRC_16_IBM_3740};
+use futures::{executor, io::Cursor, SinkExt, TryStreamExt};
+use rstest::rstest;
+
+use spacepacket::{
+    codec::SpacePacketCodec,
+    tctm::{
+        extractor::PacketExtractor,
+        tm::{TMFramePacker, TMPrimaryHeader},
+    },
+    CompletePacket, LengthOutOfRange, PrimaryHeader, SpacePacket,
+};
+
+use common::packet;
+
+const CRC_CCITT_FALSE: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+
+fn codec(crc: Option<Crc<u16>>) -> SpacePacketCodec {
+    let codec = SpacePacketCodec::new([0xAA, 0xBB]);
+    match crc {
+        Some(crc) => codec.with_crc(crc),
+        None => codec,
+    }
+}
+
+#[test]
+fn max_size_limits() {
+    assert_eq!(65536, SpacePacket::MAX_PAYLOAD_LEN);
+    assert_eq!(65534, SpacePacket::MAX_PAYLOAD_LEN_CRC);
+}
+
+#[rstest]
+#[case(1)]
+#[case(SpacePacket::MAX_PAYLOAD_LEN)]
+fn max_size_encode(#[case] payload_len: usize) {
+    let expected = packet(0x42, 1234, payload_len);
+
+    let encoded = expected.encode();
+    assert_eq!(payload_len + 6, encoded.len());
+    assert_eq!(
+        expected,
+        SpacePacket::decode(&mut encoded.as_slice()).unwrap()
+    );
+
+    let mut out = vec![0_u8; payload_len + 6];
+    assert_eq!(
+        payload_len + 6,
+        expected.borrowed().encode_into(&mut out).unwrap()
+    );
+    assert_eq!(encoded, out);
+}
+
+#[test]
+#[should_panic]
+fn max_size_encode_too_long() {
+    packet(0x42, 1234, SpacePacket::MAX_PAYLOAD_LEN + 1).encode();
+}
+
+#[rstest]
+#[case(1)]
+#[case(SpacePacket::MAX_PAYLOAD_LEN_CRC)]
+fn max_size_encode_crc(#[case] payload_len: usize) {
+    let expected = packet(0x42, 1234, payload_len);
+
+    let encoded = expected.encode_crc(&CRC_CCITT_FALSE).unwrap();
+    assert_eq!(payload_len + 8, encoded.len());
+    assert_eq!(
+        CompletePacket::Valid(expected),
+        SpacePacket::decode_crc(&mut encoded.as_slice(), &CRC_CCITT_FALSE).unwrap()
+    );
+}
+
+#[rstest]
+#[case(0)]
+#[case(SpacePacket::MAX_PAYLOAD_LEN_CRC + 1)]
+#[case(SpacePacket::MAX_PAYLOAD_LEN)]
+fn max_size_encode_crc_too_long(#[case] payload_len: usize) {
+    let error = packet(0x42, 1234, payload_len)
+        .encode_crc(&CRC_CCITT_FALSE)
+        .unwrap_err();
+    assert_eq!(std::io::ErrorKind::InvalidInput, error.kind());
+}
+
+#[rstest]
+#[case(1, Some(0))]
+#[case(SpacePacket::MAX_PAYLOAD_LEN, Some(u16::MAX))]
+#[case(0, None)]
+#[case(SpacePacket::MAX_PAYLOAD_LEN + 1, None)]
+#[case(u32::MAX as usize, None)]
+#[case(usize::MAX, None)]
+fn max_size_data_length(#[case] data_len: usize, #[case] expected: Option<u16>) {
+    match PrimaryHeader::data_length(data_len) {
+        Ok(length) => assert_eq!(expected, Some(length)),
+        Err(err) => {
+            assert_eq!(None, expected);
+            assert_eq!(
+                LengthOutOfRange {
+                    len: data_len,
+                    min_len: 1,
+                    max_len: SpacePacket::MAX_PAYLOAD_LEN
+                },
+                err
+            );
+        }
+    }
+}
+
+#[rstest]
+#[case(SpacePacket::MAX_PAYLOAD_LEN_CRC, true)]
+#[case(SpacePacket::MAX_PAYLOAD_LEN_CRC + 1, false)]
+#[case(SpacePacket::MAX_PAYLOAD_LEN + 1, false)]
+fn max_size_vectored(#[case] payload_len: usize, #[case] fits: bool) {
+    let packet = packet(0x42, 1234, payload_len);
+    let vectored = packet.encode_vectored_crc(&CRC_CCITT_FALSE);
+    let trailered = packet.encode_with_trailer(&CRC_CCITT_FALSE);
+    match fits {
+        true => {
+            let vectored = vectored.unwrap();
+            let encoded = trailered.unwrap();
+            assert_eq!([0xFF, 0xFF], encoded[4..6]);
+            assert_eq!(vectored.header, encoded[..6]);
+        }
+        false => {
+            assert_eq!(
+                std::io::ErrorKind::InvalidInput,
+                vectored.unwrap_err().kind()
+            );
+            assert_eq!(
+                std::io::ErrorKind::InvalidInput,
+                trailered.unwrap_err().kind()
+            );
+        }
+    }
+}
+
+#[rstest]
+#[case(None, SpacePacket::MAX_PAYLOAD_LEN)]
+#[case(Some(CRC_CCITT_FALSE), SpacePacket::MAX_PAYLOAD_LEN_CRC)]
+fn max_size_codec(#[case] crc: Option<Crc<u16>>, #[case] payload_len: usize) {
+    let expected = packet(0x42, 1234, payload_len);
+    let codec = codec(crc);
+
+    let mut framed = Framed::new(Cursor::new(vec![]), codec.clone());
+    executor::block_on(framed.send(expected.clone())).unwrap();
+    executor::block_on(framed.send(packet(0x42, 1234, 10))).unwrap();
+
+    let mut cursor = framed.into_inner();
+    cursor.set_position(0);
+
+    let mut framed = Framed::new(cursor, codec);
+    assert_eq!(
+        Some(CompletePacket::Valid(expected)),
+        executor::block_on(framed.try_next()).unwrap()
+    );
+    assert_eq!(
+        Some(CompletePacket::Valid(packet(0x42, 1234, 10))),
+        executor::block_on(framed.try_next()).unwrap()
+    );
+}
+
+#[rstest]
+#[case(None, SpacePacket::MAX_PAYLOAD_LEN + 1)]
+#[case(Some(CRC_CCITT_FALSE), SpacePacket::MAX_PAYLOAD_LEN_CRC + 1)]
+fn max_size_codec_too_long(#[case] crc: Option<Crc<u16>>, #[case] payload_len: usize) {
+    let mut framed = Framed::new(Cursor::new(vec![]), codec(crc));
+    let error = executor::block_on(framed.send(packet(0x42, 1234, payload_len))).unwrap_err();
+    assert_eq!(std::io::ErrorKind::InvalidInput, error.kind());
+    assert!(framed.into_inner().into_inner().is_empty());
+}
+
+#[test]
+fn max_size_frames() {
+    let expected = vec![
+        packet(0x42, 1234, SpacePacket::MAX_PAYLOAD_LEN),
+        packet(0x42, 1234, 10),
+    ];
+
+    let header = TMPrimaryHeader::builder()
+        .scid(758)
+        .vcid(3)
+        .build()
+        .unwrap();
+    let mut packer = TMFramePacker::new(header, 223).unwrap();
+    let mut extractor = PacketExtractor::new();
+
+    let mut frames = vec![];
+    for packet in &expected {
+        packer.push(packet).unwrap();
+        frames.extend(std::iter::from_fn(|| packer.pop_frame()));
+    }
+    frames.extend(packer.flush());
+    // the packet spans hundreds of frames and wraps the 8-bit frame counter
+    assert!(frames.len() > 256);
+
+    let recovered: Vec<SpacePacket> = frames
+        .iter()
+        .flat_map(|frame| extractor.push(frame))
+        .collect();
+
+    assert_eq!(0, extractor.lost_frames());
+    assert_eq!(expected, recovered);
+}
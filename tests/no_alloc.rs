@@ -0,0 +1,169 @@
+//! Check the `decode_into`/`encode_into` family performs no heap allocations.
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use spacepacket::{
+    tctm::{
+        cltu::{self, EncodingScheme},
+        tc::{BypassFlag, ControlFlag, TCPrimaryHeader, TCTransferFrame},
+        tm::{TMPrimaryHeader, TMRandomization, TMTransferFrame},
+    },
+    GroupingFlag, PacketType, SpacePacket,
+};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// The number of allocations performed on this thread while running `f`.
+fn allocations<T>(f: impl FnOnce() -> T) -> (usize, T) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let out = f();
+    (ALLOCATIONS.with(Cell::get) - before, out)
+}
+
+#[test]
+fn no_alloc_pipeline() {
+    // make sure the allocator is actually counting
+    let (count, _) = allocations(|| Vec::<u8>::with_capacity(16));
+    assert_eq!(1, count);
+
+    let packet = SpacePacket::new(
+        0,
+        PacketType::Telemetry,
+        1234,
+        GroupingFlag::Unsegm,
+        7,
+        false,
+        (0..100).collect(),
+    );
+    let tm_frame = TMTransferFrame {
+        primary_header: TMPrimaryHeader::builder()
+            .scid(758)
+            .vcid(3)
+            .build()
+            .unwrap(),
+        secondary_header: None,
+        data_field: packet.clone().encode(),
+    };
+    let tc_frame = TCTransferFrame::new(
+        TCPrimaryHeader {
+            tfvn: 0,
+            bypass_flag: BypassFlag::TypeB,
+            control_flag: ControlFlag::TypeD,
+            scid: 758,
+            vcid: 3,
+            sequence_number: 23,
+        },
+        packet.clone().encode(),
+    )
+    .unwrap();
+
+    let mut wire = [0_u8; 1024];
+    let mut scratch = [0_u8; 1024];
+    let mut payload = [0_u8; 1024];
+
+    // initialize the lazily computed randomization sequences before counting.
+    tm_frame
+        .encode_into(TMRandomization::Tm255, &mut wire)
+        .unwrap();
+    tm_frame
+        .encode_into(TMRandomization::Tm131071, &mut wire)
+        .unwrap();
+
+    let (count, _) = allocations(|| {
+        let len = packet.borrowed().encode_into(&mut wire).unwrap();
+        let decoded = SpacePacket::decode_into(&mut &wire[..len], &mut payload).unwrap();
+        assert_eq!(packet.borrowed(), decoded);
+    });
+    assert_eq!(0, count, "SpacePacket allocated");
+
+    // a reused payload with enough capacity is never reallocated
+    let mut reused = Vec::with_capacity(packet.payload.len());
+    let (count, _) = allocations(|| {
+        let len = packet.borrowed().encode_into(&mut wire).unwrap();
+        let header = SpacePacket::decode_into_vec(&mut &wire[..len], &mut reused).unwrap();
+        assert_eq!(packet.primary_header, header);
+    });
+    assert_eq!(0, count, "SpacePacket::decode_into_vec allocated");
+
+    let len = packet.borrowed().encode_into(&mut wire).unwrap();
+    let len = len + packet.borrowed().encode_into(&mut wire[len..]).unwrap();
+    let (count, _) = allocations(|| {
+        for decoded in SpacePacket::iter_refs(&wire[..len]) {
+            assert_eq!(packet.borrowed(), decoded.unwrap());
+        }
+    });
+    assert_eq!(0, count, "SpacePacket::iter_refs allocated");
+
+    let crc = spacepacket::crc::Crc::<u16>::new(&spacepacket::crc::CRC_16_IBM_3740);
+    let (count, _) = allocations(|| {
+        let vectored = packet.encode_vectored_crc(&crc).unwrap();
+        vectored.write_to(&mut &mut wire[..]).unwrap();
+    });
+    assert_eq!(0, count, "SpacePacket::encode_vectored allocated");
+    assert_eq!(packet.encode_crc(&crc).unwrap(), wire[..108]);
+
+    for randomization in [
+        TMRandomization::None,
+        TMRandomization::Tm255,
+        TMRandomization::Tm131071,
+    ] {
+        let (count, _) = allocations(|| {
+            let len = tm_frame.encode_into(randomization, &mut wire).unwrap();
+            let view =
+                TMTransferFrame::decode_into(&mut &wire[..len], len, randomization, &mut scratch)
+                    .unwrap();
+            assert_eq!(tm_frame.view(), view);
+
+            let decoded =
+                SpacePacket::decode_into(&mut &view.data_field[..], &mut payload).unwrap();
+            assert_eq!(packet.borrowed(), decoded);
+        });
+        assert_eq!(
+            0, count,
+            "TM Transfer Frame allocated with {randomization:?}"
+        );
+    }
+
+    let (count, _) = allocations(|| {
+        let len = tc_frame.encode_into(&mut wire).unwrap();
+        let view = TCTransferFrame::decode_into(&mut &wire[..len], &mut scratch).unwrap();
+        assert_eq!(tc_frame.view(), view);
+
+        let decoded = SpacePacket::decode_into(&mut &view.payload[..], &mut payload).unwrap();
+        assert_eq!(packet.borrowed(), decoded);
+    });
+    assert_eq!(0, count, "TC Transfer Frame allocated");
+
+    let len = tc_frame.encode_into(&mut wire).unwrap();
+    let mut cltu = Vec::with_capacity(2048);
+    for encoding in [EncodingScheme::BCH, EncodingScheme::BCHRandomized] {
+        // initialize the lazily computed tables before counting.
+        cltu::encode_into(&wire[..len], encoding, &mut cltu);
+
+        let (count, _) = allocations(|| {
+            cltu.clear();
+            cltu::encode_into(&wire[..len], encoding, &mut cltu);
+        });
+        assert_eq!(0, count, "CLTU allocated with {encoding:?}");
+    }
+}